@@ -0,0 +1,47 @@
+use langchain::agents::error::AgentError;
+use langchain::agents::guardrails::{AgentGuardrail, FunctionGuardrail, GuardrailResult};
+use langchain::agents::libs::Agent;
+use serde_json::{json, Value};
+use env_logger::Env;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let mut homework_checker = Agent::new(
+        "HomeworkChecker".to_string(),
+        "Check if the user is asking you to do their math homework.".to_string(),
+    ).await;
+    homework_checker.model = Some("gpt-4o-mini".to_string());
+
+    let length_guardrail = FunctionGuardrail::new("max_length", |text: String| async move {
+        if text.len() > 2000 {
+            GuardrailResult::trip(json!({"length": text.len()}))
+        } else {
+            GuardrailResult::pass(Value::Null)
+        }
+    });
+
+    let mut agent = Agent::new(
+        "CustomerSupportAgent".to_string(),
+        "You are a customer support agent. You help customers with their questions.".to_string(),
+    ).await;
+    agent.model = Some("gpt-4o".to_string());
+
+    let agent = agent
+        .with_input_guardrail(AgentGuardrail::new(homework_checker))
+        .with_output_guardrail(length_guardrail);
+
+    match agent.run("Hello, can you help me solve for x: 2x + 3 = 11?").await {
+        Ok(response) => println!("{}", response),
+        Err(AgentError::InputGuardrailTripwireTriggered(result)) => {
+            println!("Input blocked by {}: {}", result.guardrail_name, result.output_info);
+        }
+        Err(AgentError::OutputGuardrailTripwireTriggered(result)) => {
+            println!("Output blocked by {}: {}", result.guardrail_name, result.output_info);
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use langchain::agents::financials_agents::create_research_agent;
+use langchain::agents::hooks::AgentHooks;
+use langchain::agents::tools::FunctionTool;
+use langchain::llm::libs::{ChatMessage, ToolCall};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use env_logger::Env;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SearchArgs {
+    /// The search term
+    query: String,
+}
+
+#[derive(Debug)]
+struct ProgressHooks;
+
+#[async_trait]
+impl AgentHooks for ProgressHooks {
+    async fn on_agent_start(&self, agent_name: &str) {
+        println!("> {} is working", agent_name);
+    }
+
+    async fn on_tool_start(&self, agent_name: &str, tool_call: &ToolCall) {
+        println!("> {} calls {} with {}", agent_name, tool_call.name, tool_call.arguments);
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    // Replace with a real search API
+    let web_search = FunctionTool::new(
+        "web_search",
+        "Searches the web and returns the most relevant snippets.",
+        |args: SearchArgs| async move {
+            Ok::<_, String>(format!(
+                "No live results for '{}'. Tesla delivered 336,681 vehicles in Q1 2025, \
+                down 13% year over year.",
+                args.query
+            ))
+        },
+    );
+
+    let research_agent = create_research_agent(vec![web_search]).await
+        .with_hooks(ProgressHooks);
+
+    let result = research_agent
+        .run_messages(vec![ChatMessage::user("Analyze Tesla's most recent quarter")])
+        .await?;
+
+    println!("Agents: {}", result.agents.join(" -> "));
+    println!("Turns: {}\n", result.turns);
+    println!("{}\n", result.final_output);
+    println!("{}", result.trace);
+
+    Ok(())
+}
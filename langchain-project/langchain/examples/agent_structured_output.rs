@@ -0,0 +1,39 @@
+use langchain::agents::libs::Agent;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use env_logger::Env;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct CalendarEvent {
+    /// Name of the event
+    name: String,
+    /// Day of the event, e.g. Friday
+    date: String,
+    /// People attending the event
+    participants: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let mut agent = Agent::new(
+        "CalendarExtractor".to_string(),
+        "Extract calendar events from text.".to_string(),
+    ).await;
+    agent.model = Some("gpt-4o-mini".to_string());
+
+    let agent = agent
+        .with_output_type::<CalendarEvent>()
+        .with_max_output_retries(2);
+
+    let event = agent
+        .run("Alice and Bob are going to a science fair on Friday.")
+        .await?;
+
+    println!("Event: {}", event.name);
+    println!("Date: {}", event.date);
+    println!("Participants: {}", event.participants.join(", "));
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use langchain::agents::libs::{Agent, AgentType, ToolUseBehavior};
+use langchain::agents::tools::FunctionTool;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use env_logger::Env;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct StockArgs {
+    /// The stock ticker symbol, e.g. AAPL for Apple Inc.
+    ticker: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ConvertArgs {
+    /// Amount in USD
+    amount: f64,
+    /// Target currency code, e.g. EUR
+    currency: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let stock_tool = FunctionTool::new(
+        "get_stock_price",
+        "Retrieves the latest trade price in USD for a given ticker symbol.",
+        |args: StockArgs| async move {
+            match args.ticker.as_str() {
+                "TSLA" => Ok(248.50),
+                "AAPL" => Ok(213.32),
+                ticker => Err(format!("Unknown ticker {}", ticker)),
+            }
+        },
+    );
+
+    let convert_tool = FunctionTool::new(
+        "convert_currency",
+        "Converts an amount in USD to another currency.",
+        |args: ConvertArgs| async move {
+            let rate = match args.currency.as_str() {
+                "EUR" => 0.92,
+                "GBP" => 0.79,
+                _ => return Err("Unsupported currency".to_string()),
+            };
+            Ok(format!("{:.2} {}", args.amount * rate, args.currency))
+        },
+    );
+
+    let mut agent = Agent::new(
+        "StockAgent".to_string(),
+        "You are a stock market assistant. Use the tools to answer.".to_string(),
+    ).await;
+    agent.agent_type = AgentType::Anthropic;
+    agent.model = Some("claude-3-5-haiku-20241022".to_string());
+
+    let agent = agent
+        .with_tool(stock_tool)
+        .with_tool(convert_tool)
+        .with_tool_use_behavior(ToolUseBehavior::RunLLMAgain)
+        .with_max_turns(5);
+
+    let response = agent
+        .run("How much is one Tesla share in euros?")
+        .await?;
+
+    println!("{}", response);
+
+    Ok(())
+}
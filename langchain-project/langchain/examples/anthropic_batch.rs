@@ -0,0 +1,48 @@
+use langchain::anthropic::batch::AnthropicBatch;
+use langchain::anthropic::chat::ChatAnthropic;
+use env_logger::Env;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let reviews = [
+        "Great product, works as described.",
+        "It broke after a day.",
+        "Arrived on time, does the job.",
+    ];
+
+    let llm = ChatAnthropic::new("claude-3-5-haiku-20241022")
+        .with_system_prompt("Classify the review as positive or negative. Answer with one word.")
+        .with_max_tokens(16);
+
+    let mut batch = AnthropicBatch::new();
+    for (index, review) in reviews.iter().enumerate() {
+        let custom_id = format!("review-{}", index);
+        batch = batch.with_request(&custom_id, llm.clone().with_prompt(review));
+    }
+
+    let message_batch = batch.create().await?;
+    println!("Batch {} created", message_batch.id);
+
+    let message_batch = batch.wait(&message_batch.id).await?;
+    let responses = batch.responses(&message_batch).await?;
+
+    for (index, review) in reviews.iter().enumerate() {
+        let custom_id = format!("review-{}", index);
+        match responses.get(&custom_id) {
+            Some(Ok(response)) => {
+                let label = response.content
+                    .as_ref()
+                    .and_then(|contents| contents.first())
+                    .and_then(|content| content.text.clone())
+                    .unwrap_or_default();
+                println!("{} -> {}", review, label);
+            }
+            Some(Err(e)) => println!("{} -> Error: {}", review, e),
+            None => println!("{} -> Missing result", review),
+        }
+    }
+
+    Ok(())
+}
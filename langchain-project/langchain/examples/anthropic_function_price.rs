@@ -42,7 +42,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     if let Some(contents) = &response.content {
         let function_content = &contents[1];
         assert_eq!(function_content.name, Some("get_stock_price".to_string()));
-        assert_eq!(function_content.content_type, Some("tool_use".to_string()));
+        assert_eq!(function_content.content_type, "tool_use");
         if let Some(input) = &function_content.input {
             assert_eq!(input["ticker"], "TSLA");
         } else {
@@ -33,7 +33,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     });
 
-    let tools = Some(vec![tool_data]);
+    let tools = vec![tool_data];
     let tool_choice = Some(json!({"type": "tool", "name": "get_weather"}));
     let prompt = "What is the weather like in San Francisco?";
 
@@ -28,8 +28,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let llm = ChatAnthropic::new("claude-3-5-sonnet-20241022");
 
     let response = llm
-        .with_image(&base64_string_01, mime_type_jpeg)
-        .with_image(&base64_string_02, mime_type_png)
+        .with_image_base64(&base64_string_01, mime_type_jpeg)
+        .with_image_base64(&base64_string_02, mime_type_png)
         .invoke("Compare the two pictures provided")
         .await?;
 
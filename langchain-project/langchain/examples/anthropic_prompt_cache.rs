@@ -0,0 +1,42 @@
+use langchain::anthropic::chat::ChatAnthropic;
+use langchain::anthropic::libs::{CacheControl, CacheTtl, SystemBlock};
+use env_logger::Env;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    // Only prompts above 1024 tokens are cached
+    let style_guide = "Answer in plain English, without jargon. ".repeat(200);
+
+    let llm = ChatAnthropic::new("claude-3-7-sonnet-20250219")
+        .with_system_blocks(vec![
+            SystemBlock::text("You are a financial assistant."),
+            SystemBlock::text(&style_guide)
+                .with_cache_control(CacheControl::ephemeral().with_ttl(CacheTtl::OneHour)),
+        ]);
+
+    let questions = ["What is a bond?", "What is a stock split?"];
+
+    for question in questions {
+        let response = llm.clone().invoke(question).await?;
+
+        for content in response.content.unwrap_or_default() {
+            if let Some(text) = content.text {
+                println!("{}\n", text);
+            }
+        }
+
+        if let Some(usage) = response.usage {
+            let usage = usage.token_usage();
+            println!(
+                "Input tokens: {} (cache write: {}, cache read: {})\n",
+                usage.input_tokens,
+                usage.cache_creation_input_tokens,
+                usage.cache_read_input_tokens,
+            );
+        }
+    }
+
+    Ok(())
+}
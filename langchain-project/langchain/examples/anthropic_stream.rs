@@ -0,0 +1,34 @@
+use langchain::anthropic::chat::ChatAnthropic;
+use langchain::anthropic::libs::{ContentDelta, StreamEvent};
+use futures::StreamExt;
+use futures::pin_mut;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let llm = ChatAnthropic::new("claude-3-5-haiku-20241022")
+        .with_max_tokens(1024);
+
+    let prompt = String::from("Tell me how the internet works, but pretend I'm a puppy who only understands squeaky toys.");
+
+    let stream = llm.stream_response(prompt);
+
+    pin_mut!(stream);
+
+    println!("\n");
+
+    while let Some(event) = stream.next().await {
+        match event? {
+            StreamEvent::ContentBlockDelta { delta: ContentDelta::TextDelta { text }, .. } => {
+                print!("{}", text);
+            }
+            StreamEvent::MessageDelta { usage: Some(usage), .. } => {
+                println!("\n\nOutput tokens: {:?}", usage.output_tokens);
+            }
+            _ => (),
+        }
+    }
+
+    println!("\n\n");
+
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use langchain::anthropic::chat::ChatAnthropic;
+use env_logger::Env;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let llm = ChatAnthropic::new("claude-3-7-sonnet-20250219")
+        .with_max_tokens(4096)
+        .with_thinking(2048);
+
+    let prompt = "Are there an infinite number of prime numbers such that n mod 4 == 3?";
+
+    let response = llm.invoke(prompt).await?;
+
+    for content in response.content.unwrap_or_default() {
+        match content.content_type.as_str() {
+            "thinking" => println!("Thinking:\n{}\n", content.thinking.unwrap_or_default()),
+            "redacted_thinking" => println!("Redacted thinking block\n"),
+            "text" => println!("Answer:\n{}", content.text.unwrap_or_default()),
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use langchain::anthropic::chat::ChatAnthropic;
+use langchain::gemini::chat::ChatGemini;
+use langchain::llm::chat::ChatModel;
+use langchain::llm::libs::{ChatMessage, ChatOptions};
+use langchain::openai::chat::ChatOpenAI;
+use env_logger::Env;
+
+async fn ask(llm: &dyn ChatModel, question: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let messages = vec![
+        ChatMessage::system("You are a helpful assistant. Answer in one sentence."),
+        ChatMessage::user(question),
+    ];
+
+    let options = ChatOptions::new()
+        .with_temperature(0.2)
+        .with_max_tokens(256);
+
+    let response = llm.generate(&messages, &options).await?;
+
+    println!("[{:?} - {}] {}", llm.provider(), llm.model_name(), response.text());
+    if let Some(usage) = response.usage {
+        println!("Tokens: {} in / {} out", usage.input_tokens, usage.output_tokens);
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let models: Vec<Box<dyn ChatModel>> = vec![
+        Box::new(ChatGemini::new("gemini-2.0-flash")),
+        Box::new(ChatAnthropic::new("claude-3-5-haiku-20241022")),
+        Box::new(ChatOpenAI::new("gpt-4o-mini")),
+    ];
+
+    for llm in models.iter() {
+        ask(llm.as_ref(), "Why is the sky blue?").await?;
+    }
+
+    Ok(())
+}
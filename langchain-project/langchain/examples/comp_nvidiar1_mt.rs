@@ -62,6 +62,13 @@ pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let mut output = String::new();
             
             while let Some(stream_response) = stream.next().await {
+                let stream_response = match stream_response {
+                    Ok(stream_response) => stream_response,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        break;
+                    }
+                };
                 if let Some(choices) = stream_response.choices {
                     for choice in choices {
                         if let Some(delta) = choice.delta {
@@ -7,7 +7,6 @@ use serde_json::{json, Value};
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let endpoint_url = "https://api.replicate.com/v1";
     let model = "models/meta/meta-llama-3-70b-instruct/predictions";
-    let prefer = "wait";
 
     let llm = ChatCompatible::new(endpoint_url, model);
 
@@ -31,7 +30,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_max_retries(0)
         .with_input_replicate(
             input_data,
-            prefer,
         )
         .await?;
 
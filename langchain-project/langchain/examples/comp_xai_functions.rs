@@ -5,13 +5,13 @@ use langchain::compatible::libs::ChatResponse;
 use serde_json::{json, Value, Map};
 use env_logger::Env;
 
-const endpoint_url: &str = "https://api.open-meteo.com/v1";
+const WEATHER_URL: &str = "https://api.open-meteo.com/v1";
 
 async fn get_weather(latitude: f64, longitude: f64) -> Result<Value, Box<dyn std::error::Error>> {
     // Build the URL with formatted parameters
     let url = format!(
         "{}/forecast?latitude={}&longitude={}&current=temperature_2m,wind_speed_10m&hourly=temperature_2m,relative_humidity_2m,wind_speed_10m",
-        endpoint_url,
+        WEATHER_URL,
         latitude, 
         longitude,
     );
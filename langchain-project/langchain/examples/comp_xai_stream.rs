@@ -19,6 +19,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("\n");
 
     while let Some(stream_response) = stream.next().await { 
+        let stream_response = stream_response?;
         if let Some(choices) = stream_response.choices {
             for choice in choices {
                 if let Some(delta) = choice.delta {
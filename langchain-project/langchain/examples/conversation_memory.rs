@@ -0,0 +1,37 @@
+use langchain::chains::conversation::ConversationChain;
+use langchain::gemini::chat::ChatGemini;
+use langchain::memory::libs::SummaryMemory;
+use langchain::memory::stores::JsonFileChatStore;
+use env_logger::Env;
+use std::sync::Arc;
+
+const FILE_PATH: &str = "tests/output/conversation_memory.json";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let chat = Arc::new(ChatGemini::new("gemini-2.0-flash"));
+
+    // Older turns are summarized once the history passes 1000 tokens
+    let store = Arc::new(JsonFileChatStore::new(FILE_PATH));
+    let memory = Arc::new(SummaryMemory::new(store, chat.clone(), 1000));
+
+    let chain = ConversationChain::new(chat, memory)
+        .with_system_prompt("You are a patient history tutor. Keep answers short.");
+
+    // Run it twice: the second run remembers the first one
+    let questions = [
+        "Hi, I'm Ada and I'm studying the Apollo program.",
+        "Which mission first landed on the Moon?",
+        "What is my name and what am I studying?",
+    ];
+    for question in questions {
+        let response = chain.invoke("student-1", question).await?;
+        println!("User: {}\nAssistant: {}\n", question, response.text());
+    }
+
+    println!("History: {} messages in {}", chain.history("student-1").await?.len(), FILE_PATH);
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use langchain::anthropic::chat::ChatAnthropic;
+use langchain::gemini::chat::ChatGemini;
+use langchain::llm::chat::ChatModel;
+use langchain::llm::cost::{CostTracker, ModelPrice, PriceTable};
+use langchain::openai::chat::ChatOpenAI;
+use env_logger::Env;
+use std::sync::Arc;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    // USD per million tokens; check the provider pages for current prices
+    let prices = PriceTable::new()
+        .with_price("gpt-4o-mini", ModelPrice::new(0.15, 0.6).with_cache(0.15, 0.075))
+        .with_price("claude-3-5-haiku", ModelPrice::new(0.8, 4.0).with_cache(1.0, 0.08))
+        .with_price("gemini-2.0-flash", ModelPrice::new(0.1, 0.4).with_audio(0.7, 0.4));
+    let tracker = CostTracker::new(prices);
+
+    let models: Vec<Arc<dyn ChatModel>> = vec![
+        Arc::new(ChatOpenAI::new("gpt-4o-mini")),
+        Arc::new(ChatAnthropic::new("claude-3-5-haiku-latest")),
+        Arc::new(ChatGemini::new("gemini-2.0-flash")),
+    ];
+
+    for (tenant, question) in [
+        ("acme", "Summarize the plot of Hamlet in one sentence."),
+        ("globex", "Name three prime numbers greater than 100."),
+    ] {
+        for llm in &models {
+            let tracked = tracker.track(llm.clone(), tenant);
+            match tracked.generate_text(question).await {
+                Ok(answer) => println!("[{}] {}: {}", tenant, llm.model_name(), answer.trim()),
+                Err(e) => println!("[{}] {}: {}", tenant, llm.model_name(), e),
+            }
+        }
+    }
+
+    for tenant in ["acme", "globex"] {
+        let summary = tracker.session(tenant);
+        println!(
+            "{}: {} requests, {} tokens, ${:.6}",
+            tenant, summary.requests, summary.usage.total_tokens, summary.cost.total,
+        );
+    }
+
+    tracker.export_json("tests/output/cost_report.json")?;
+    println!("Report written to tests/output/cost_report.json");
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use langchain::documents::libs::contents;
+use langchain::documents::loaders::{DocumentLoader, MarkdownLoader, PdfLoader};
+use langchain::documents::splitters::{MarkdownHeaderTextSplitter, TokenTextSplitter};
+use langchain::openai::embed::EmbedOpenAI;
+use env_logger::Env;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    // One document per page, split in chunks of about 256 tokens
+    let splitter = TokenTextSplitter::new(256, 32);
+    let mut chunks = PdfLoader::new("tests/files/sample_marketing_campaign.pdf")
+        .load_and_split(&splitter)
+        .await?;
+
+    // Markdown sections keep their headers as metadata
+    let splitter = MarkdownHeaderTextSplitter::default().with_chunk_size(1000, 100);
+    chunks.extend(
+        MarkdownLoader::new("tests/files/rag_guide.md")
+            .load_and_split(&splitter)
+            .await?,
+    );
+
+    for chunk in &chunks {
+        println!("{:?}", chunk.metadata);
+    }
+
+    let embed = EmbedOpenAI::new("text-embedding-3-small");
+    let embeddings = embed.embed_documents(contents(&chunks)).await?;
+    println!("Embedded {} chunks of {} dimensions", embeddings.len(), embeddings[0].len());
+
+    Ok(())
+}
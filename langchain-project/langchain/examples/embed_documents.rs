@@ -0,0 +1,31 @@
+use langchain::gemini::embed::EmbedGemini;
+use langchain::gemini::libs::TaskType;
+use langchain::openai::embed::EmbedOpenAI;
+use env_logger::Env;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let documents: Vec<String> = (1..=250)
+        .map(|index| format!("Quarterly report number {} of the company", index))
+        .collect();
+
+    // 100 texts per batchEmbedContents call, 4 calls at a time
+    let gemini = EmbedGemini::new("text-embedding-004")
+        .with_task_type(TaskType::RetrievalDocument)
+        .with_max_concurrency(4);
+
+    let vectors = gemini.embed_documents(documents.clone()).await?;
+    println!("Gemini: {} vectors of {} dimensions", vectors.len(), vectors[0].len());
+
+    // Smaller requests than the 2048 inputs allowed, to stay under rate limits
+    let openai = EmbedOpenAI::new("text-embedding-3-small")
+        .with_dimensions(256)
+        .with_batch_size(100, 50_000);
+
+    let vectors = openai.embed_documents(documents).await?;
+    println!("OpenAI: {} vectors of {} dimensions", vectors.len(), vectors[0].len());
+
+    Ok(())
+}
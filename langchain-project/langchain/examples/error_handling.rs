@@ -0,0 +1,36 @@
+use langchain::anthropic::chat::ChatAnthropic;
+use langchain::llm::chat::ChatModel;
+use langchain::llm::error::LlmError;
+use langchain::llm::libs::{ChatMessage, ChatOptions};
+use env_logger::Env;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let llm = ChatAnthropic::new("claude-3-5-haiku-latest");
+    let messages = vec![ChatMessage::user("Summarize the plot of Hamlet in one sentence.")];
+
+    match llm.generate(&messages, &ChatOptions::new().with_max_tokens(200)).await {
+        Ok(response) => println!("{}", response.text()),
+        Err(LlmError::Authentication(e)) => {
+            println!("Check ANTHROPIC_API_KEY: {}", e.message);
+        }
+        Err(LlmError::ContextLengthExceeded(e)) => {
+            println!("Shorten the conversation: {}", e.message);
+        }
+        Err(e) if e.is_retryable() => {
+            let request_id = e.api_error().and_then(|api_error| api_error.request_id.clone());
+            println!("Temporary failure, try again later ({:?}): {}", request_id, e);
+        }
+        Err(e) => {
+            if let Some(api_error) = e.api_error() {
+                println!("Status: {:?}, code: {:?}", api_error.status, api_error.code);
+                println!("Body: {}", api_error.body);
+            }
+            return Err(e.into());
+        }
+    }
+
+    Ok(())
+}
@@ -16,26 +16,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     match type_file {
         "image" => {
             let file_path = "tests/files/image01.jpg";
-            llm = llm.media_upload(file_path, "auto").await?;
+            llm = llm.media_upload(Some(file_path), None, "file", "auto").await?;
         },
         "video" => {
             let file_path = "tests/files/sample.mp4";
-            llm = llm.media_upload(file_path, "auto").await?;
+            llm = llm.media_upload(Some(file_path), None, "file", "auto").await?;
             prompt = "Describe this video clip";
         },
         "pdf" => {
             let file_path = "tests/files/test.pdf";
-            llm = llm.media_upload(file_path, "auto").await?;
+            llm = llm.media_upload(Some(file_path), None, "file", "auto").await?;
             prompt = "Summarize this document";
         },
         "audio" => {
             let file_path = "tests/files/sample.mp3";
-            llm = llm.media_upload(file_path, "auto").await?;
+            llm = llm.media_upload(Some(file_path), None, "file", "auto").await?;
             prompt = "Summarize in a few lines this audio clip";
         },
         _ => {
             let file_path = "tests/files/sample.csv";
-            llm = llm.media_upload(file_path, "auto").await?;
+            llm = llm.media_upload(Some(file_path), None, "file", "auto").await?;
             prompt = "Summarize this document";
         }
     }
@@ -17,6 +17,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     pin_mut!(stream);
 
     while let Some(stream_response) = stream.next().await { 
+        let stream_response = stream_response?;
         if let Some(candidates) = stream_response.candidates {
             for candidate in candidates {
                 if let Some(content) = candidate.content {
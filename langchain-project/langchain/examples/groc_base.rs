@@ -15,7 +15,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let start = Instant::now();
 
-    let response: ChatResponse = llm.
+    let response: ChatResponse = llm
         .with_temperature(0.9)
         .with_max_tokens(2048)
         .with_timeout_sec(30)
@@ -29,9 +29,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     match response.choices {
         Some(candidates) => {
             for candidate in candidates {
-                #[allow(irrefutable_let_patterns)]
-                if let message = candidate.message {
-                    println!("{}", message.content);
+                if let Some(message) = candidate.message {
+                    println!("{}", message.content.unwrap_or_default());
                 }
             }
         }
@@ -81,9 +81,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         match response.choices {
             Some(candidates) => {
                 for candidate in candidates {
-                    #[allow(irrefutable_let_patterns)]
-                    if let message = candidate.message {
-                        let json_str = message.content.lines()
+                    if let Some(message) = candidate.message {
+                        let content = message.content.unwrap_or_default();
+                        let json_str = content.lines()
                             .skip(1) // Skip ```json
                             .take_while(|line| !line.starts_with("```")) // Take until closing ```
                             .collect::<Vec<&str>>()
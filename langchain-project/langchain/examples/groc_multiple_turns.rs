@@ -29,9 +29,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     match response.choices {
         Some(candidates) => {
             for candidate in candidates {
-                #[allow(irrefutable_let_patterns)]
-                if let message = candidate.message {
-                    message_assistant = message.content;
+                if let Some(message) = candidate.message {
+                    message_assistant = message.content.unwrap_or_default();
                     println!("{}", message_assistant);
                 }
             }
@@ -59,9 +58,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     match response.choices {
         Some(candidates) => {
             for candidate in candidates {
-                #[allow(irrefutable_let_patterns)]
-                if let message = candidate.message {
-                    println!("{}", message.content);
+                if let Some(message) = candidate.message {
+                    println!("{}", message.content.unwrap_or_default());
                 }
             }
         }
@@ -0,0 +1,44 @@
+use langchain::anthropic::chat::ChatAnthropic;
+use langchain::llm::chat::ChatModel;
+use langchain::llm::http::{HttpConfig, WithHttpClient};
+use langchain::openai::chat::ChatOpenAI;
+use env_logger::Env;
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    // Build the client once, e.g. outside the Lambda handler, so warm
+    // invocations reuse open connections instead of a new TLS handshake
+    let mut config = HttpConfig::new()
+        .with_user_agent("capitals-job/1.0")
+        .with_connect_timeout(Duration::from_secs(5))
+        .with_pool_max_idle_per_host(16)
+        .with_header("x-team", "research");
+    if let Ok(proxy) = std::env::var("LLM_PROXY_URL") {
+        config = config.with_proxy(&proxy);
+    }
+    let client = config.build_client()?;
+
+    let openai = ChatOpenAI::new("gpt-4o-mini").with_http_client(client.clone());
+    let anthropic = ChatAnthropic::new("claude-3-5-haiku-latest").with_http_client(client);
+
+    for country in ["France", "Japan", "Brazil"] {
+        let prompt = format!("What is the capital of {}? Answer with one word.", country);
+        let openai_answer = openai.generate_text(&prompt).await?;
+        let anthropic_answer = anthropic.generate_text(&prompt).await?;
+        println!("{}: {} / {}", country, openai_answer.trim(), anthropic_answer.trim());
+    }
+
+    // An OpenAI-compatible gateway: the config also replaces the endpoint
+    if let Ok(gateway_url) = std::env::var("LLM_GATEWAY_URL") {
+        let gateway = HttpConfig::new()
+            .with_header("x-gateway-key", &std::env::var("LLM_GATEWAY_KEY").unwrap_or_default())
+            .with_base_url(&format!("{}/v1/chat/completions", gateway_url));
+        let llm = ChatOpenAI::new("gpt-4o-mini").with_http_config(&gateway)?;
+        println!("Gateway: {}", llm.generate_text("Say hello").await?.trim());
+    }
+
+    Ok(())
+}
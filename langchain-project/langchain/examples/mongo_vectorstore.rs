@@ -0,0 +1,58 @@
+use langchain::documents::libs::Document;
+use langchain::llm::embed::Embeddings;
+use langchain::openai::embed::EmbedOpenAI;
+use langchain::vectorstores::libs::{Filter, SearchOptions, VectorStore};
+use langchain::vectorstores::mongo::MongoVectorStore;
+use env_logger::Env;
+use std::env;
+use std::sync::Arc;
+
+// cargo run --example mongo_vectorstore --features mongodb
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    // Atlas connection string, vector search is not available on a local mongod
+    let uri = env::var("MONGODB_URI").expect("MONGODB_URI must be set");
+
+    let embeddings: Arc<dyn Embeddings> = Arc::new(
+        EmbedOpenAI::new("text-embedding-3-small").with_dimensions(512)
+    );
+
+    let store = MongoVectorStore::from_uri(&uri, "news", "news_data", embeddings)
+        .await?
+        .with_text_key("short_description")
+        .with_embedding_key("news_embedding")
+        .with_filter_fields(&["category", "year"]);
+
+    // Creates the index once and waits until it is queryable
+    store.create_index(512).await?;
+
+    let documents = vec![
+        Document::new("The national team wins the World Cup final on penalties.")
+            .with_id("news-1")
+            .with_metadata("category", "SPORTS")
+            .with_metadata("year", 2022),
+        Document::new("The central bank raises interest rates by half a point.")
+            .with_id("news-2")
+            .with_metadata("category", "BUSINESS")
+            .with_metadata("year", 2022),
+        Document::new("A new stadium opens in the city before the season starts.")
+            .with_id("news-3")
+            .with_metadata("category", "SPORTS")
+            .with_metadata("year", 2023),
+    ];
+    store.add_documents(documents).await?;
+
+    let options = SearchOptions::new(2).with_filter(Filter::and(vec![
+        Filter::eq("category", "SPORTS"),
+        Filter::gte("year", 2022),
+    ]));
+
+    let results = store.similarity_search("Who won the football final?", &options).await?;
+    for result in results {
+        println!("{:.3} {}", result.score, result.document.content);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use langchain::openai::batch::OpenAIBatch;
+use langchain::openai::chat::ChatOpenAI;
+use env_logger::Env;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let reviews = [
+        "Great product, works as described.",
+        "It broke after a day.",
+        "Arrived on time, does the job.",
+    ];
+
+    let llm = ChatOpenAI::new("gpt-4o-mini")
+        .with_system_prompt("Classify the review as positive or negative. Answer with one word.")
+        .with_max_tokens(16);
+
+    let mut batch = OpenAIBatch::new();
+    for (index, review) in reviews.iter().enumerate() {
+        let custom_id = format!("review-{}", index);
+        batch = batch.with_chat_request(&custom_id, llm.clone().with_prompt(review));
+    }
+
+    let created = batch.create().await?;
+    println!("Batch {} created", created.id);
+
+    let finished = batch.wait(&created.id).await?;
+    let responses = batch.chat_responses(&finished).await?;
+
+    for (index, review) in reviews.iter().enumerate() {
+        let custom_id = format!("review-{}", index);
+        match responses.get(&custom_id) {
+            Some(Ok(response)) => {
+                let label = response.choices
+                    .as_ref()
+                    .and_then(|choices| choices.first())
+                    .and_then(|choice| choice.message.as_ref())
+                    .and_then(|message| message.content.clone())
+                    .unwrap_or_default();
+                println!("{} -> {}", review, label);
+            }
+            Some(Err(e)) => println!("{} -> Error: {}", review, e),
+            None => println!("{} -> Missing result", review),
+        }
+    }
+
+    // The input and output files stay in the account until deleted
+    batch.files.delete(&finished.input_file_id).await?;
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+// https://platform.openai.com/docs/api-reference/responses-streaming
+use langchain::openai::response::ChatOpenAI;
+use langchain::openai::lib_response::ResponseStreamEvent;
+use futures::StreamExt;
+use futures::pin_mut;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let llm = ChatOpenAI::new("o4-mini")
+        .with_reasoning("low")
+        .with_reasoning_summary("auto")
+        .with_prompt("Are semicolons optional in Rust?");
+
+    let stream = llm.stream_response();
+
+    pin_mut!(stream);
+
+    while let Some(event) = stream.next().await {
+        match event? {
+            ResponseStreamEvent::ReasoningSummaryTextDelta { delta, .. } => {
+                print!("{}", delta);
+            }
+            ResponseStreamEvent::ReasoningSummaryTextDone { .. } => {
+                println!("\n\n---\n");
+            }
+            ResponseStreamEvent::OutputTextDelta { delta, .. } => {
+                print!("{}", delta);
+            }
+            ResponseStreamEvent::Completed { response } => {
+                if let Some(usage) = response.usage {
+                    println!("\n\nTotal tokens: {:?}", usage.total_tokens);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use langchain::llm::chat::ChatModel;
+use langchain::llm::libs::{ChatOptions, Role};
+use langchain::openai::chat::ChatOpenAI;
+use langchain::prompts::chat::ChatPromptTemplate;
+use langchain::prompts::template::PromptTemplate;
+use env_logger::Env;
+use serde_json::json;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let llm = ChatOpenAI::new("gpt-4o-mini");
+
+    // Few-shot chat prompt written in code
+    let example_prompt = ChatPromptTemplate::from_messages(&[
+        (Role::User, "{word}"),
+        (Role::Assistant, "{antonym}"),
+    ])?;
+    let prompt = ChatPromptTemplate::from_messages(&[
+        (Role::System, "Answer with the antonym of the word, in {language}."),
+    ])?
+    .with_few_shot(
+        vec![
+            json!({"word": "happy", "antonym": "sad"}),
+            json!({"word": "tall", "antonym": "short"}),
+        ],
+        example_prompt,
+    )
+    .with_message(Role::User, PromptTemplate::new("{word}")?)
+    .with_partial("language", "English");
+
+    let messages = prompt.format_messages(&json!({"word": "fast"}))?;
+    let response = llm.generate(&messages, &ChatOptions::default()).await?;
+    println!("Antonym: {}", response.text());
+
+    // Prompt pulled from the LangSmith hub, needs LANGSMITH_API_KEY
+    let rag_prompt = ChatPromptTemplate::pull("rlm", "rag-prompt", "latest").await?;
+    println!("Hub prompt variables: {:?}", rag_prompt.input_variables());
+
+    let messages = rag_prompt.format_messages(&json!({
+        "question": "When did Apollo 11 land?",
+        "context": "Apollo 11 landed on the Moon on July 20, 1969.",
+    }))?;
+    let response = llm.generate(&messages, &ChatOptions::default()).await?;
+    println!("Answer: {}", response.text());
+
+    Ok(())
+}
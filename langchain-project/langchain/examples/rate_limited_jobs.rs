@@ -0,0 +1,42 @@
+use langchain::llm::chat::ChatModel;
+use langchain::llm::retry::{RateLimiter, RetryPolicy};
+use langchain::openai::chat::ChatOpenAI;
+use env_logger::Env;
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    // One limiter for the whole account, shared by every clone of the client
+    let limiter = RateLimiter::new()
+        .with_requests_per_minute(60)
+        .with_tokens_per_minute(40_000);
+
+    let llm = ChatOpenAI::new("gpt-4o-mini")
+        .with_rate_limiter(limiter)
+        .with_retry_policy(
+            RetryPolicy::new(5)
+                .with_base_delay(Duration::from_millis(500))
+                .with_max_delay(Duration::from_secs(30)),
+        );
+
+    let countries = ["France", "Japan", "Brazil", "Kenya", "Canada", "India", "Norway", "Chile"];
+    let tasks: Vec<_> = countries
+        .iter()
+        .map(|country| {
+            let llm = llm.clone();
+            let prompt = format!("What is the capital of {}? Answer with one word.", country);
+            tokio::spawn(async move { llm.generate_text(&prompt).await })
+        })
+        .collect();
+
+    for (country, task) in countries.iter().zip(tasks) {
+        match task.await? {
+            Ok(answer) => println!("{}: {}", country, answer.trim()),
+            Err(e) => println!("{}: failed after retries: {}", country, e),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use langchain::anthropic::embed::EmbedRankVoyage;
+use langchain::chains::retrieval_qa::RetrievalQA;
+use langchain::documents::loaders::{DocumentLoader, TextLoader};
+use langchain::documents::splitters::RecursiveCharacterTextSplitter;
+use langchain::llm::embed::Embeddings;
+use langchain::openai::chat::ChatOpenAI;
+use langchain::openai::embed::EmbedOpenAI;
+use langchain::vectorstores::libs::{SearchOptions, VectorStore};
+use langchain::vectorstores::memory::InMemoryVectorStore;
+use env_logger::Env;
+use std::sync::Arc;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let embeddings: Arc<dyn Embeddings> = Arc::new(EmbedOpenAI::new("text-embedding-3-small"));
+    let store = Arc::new(InMemoryVectorStore::new(embeddings));
+
+    let splitter = RecursiveCharacterTextSplitter::new(800, 100);
+    let chunks = TextLoader::new("tests/files/apolo11.txt")
+        .load_and_split(&splitter)
+        .await?;
+    store.add_documents(chunks).await?;
+
+    // 20 candidates from the store, the 4 best after reranking
+    let qa = RetrievalQA::new(store, Arc::new(ChatOpenAI::new("gpt-4o-mini")))
+        .with_reranker(Arc::new(EmbedRankVoyage::new("rerank-2-lite")))
+        .with_search_options(SearchOptions::new(4).with_fetch_k(20))
+        .with_max_context_tokens(2000);
+
+    let response = qa.invoke("Who stayed in lunar orbit during the landing?").await?;
+    println!("{}\n", response.answer);
+
+    for (index, source) in response.sources.iter().enumerate() {
+        println!("Source {} ({:.3}): {:.80}", index + 1, source.score, source.document.content);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use langchain::documents::libs::Document;
+use langchain::llm::embed::Embeddings;
+use langchain::openai::embed::EmbedOpenAI;
+use langchain::vectorstores::libs::{Filter, SearchOptions, VectorStore};
+use langchain::vectorstores::memory::InMemoryVectorStore;
+use env_logger::Env;
+use std::path::Path;
+use std::sync::Arc;
+
+static STORE_PATH: &str = "tests/output/vectorstore.json";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    // Any client works: EmbedGemini, EmbedOpenAI or EmbedVoyage
+    let embeddings: Arc<dyn Embeddings> = Arc::new(
+        EmbedOpenAI::new("text-embedding-3-small").with_dimensions(256)
+    );
+
+    // Embed the corpus once, then reuse the file
+    let store = if Path::new(STORE_PATH).exists() {
+        InMemoryVectorStore::load(STORE_PATH, embeddings).await?
+    } else {
+        let store = InMemoryVectorStore::new(embeddings);
+        let documents = vec![
+            Document::new("Apollo 11 landed on the Moon on July 20, 1969.")
+                .with_metadata("topic", "space")
+                .with_metadata("year", 1969),
+            Document::new("Apollo 13 suffered an oxygen tank explosion and returned safely.")
+                .with_metadata("topic", "space")
+                .with_metadata("year", 1970),
+            Document::new("The first crewed Moon landing was Apollo 11.")
+                .with_metadata("topic", "space")
+                .with_metadata("year", 1969),
+            Document::new("Croissants are made from a laminated yeast dough.")
+                .with_metadata("topic", "food"),
+        ];
+        store.add_documents(documents).await?;
+        store.save(STORE_PATH).await?;
+        store
+    };
+
+    let query = "Who landed on the Moon first?";
+
+    println!("Similarity search:");
+    let options = SearchOptions::new(2).with_filter(Filter::eq("topic", "space"));
+    for result in store.similarity_search(query, &options).await? {
+        println!("  {:.3} {}", result.score, result.document.content);
+    }
+
+    // MMR drops the near duplicate Apollo 11 sentence
+    println!("Max marginal relevance search:");
+    let options = SearchOptions::new(2).with_lambda_mult(0.3);
+    for result in store.max_marginal_relevance_search(query, &options).await? {
+        println!("  {:.3} {}", result.score, result.document.content);
+    }
+
+    Ok(())
+}
@@ -1,2 +1,7 @@
 pub mod libs;
+pub mod error;
+pub mod guardrails;
+pub mod hooks;
+pub mod tools;
+pub mod trace;
 pub mod financials_agents;
@@ -0,0 +1,27 @@
+use crate::agents::guardrails::GuardrailResult;
+use crate::llm::error::LlmError;
+
+#[allow(dead_code)]
+#[derive(Debug, thiserror::Error)]
+pub enum AgentError {
+    #[error("Model error: {0}")]
+    LlmError(#[from] LlmError),
+
+    #[error("Max turns exceeded: the agent did not produce a final output after {0} turns")]
+    MaxTurnsExceeded(u32),
+
+    #[error("Input guardrail {} tripped: {}", .0.guardrail_name, .0.output_info)]
+    InputGuardrailTripwireTriggered(GuardrailResult),
+
+    #[error("Output guardrail {} tripped: {}", .0.guardrail_name, .0.output_info)]
+    OutputGuardrailTripwireTriggered(GuardrailResult),
+
+    #[error("Guardrail error: {0}")]
+    GuardrailError(String),
+
+    #[error("Invalid output: {0}")]
+    InvalidOutput(String),
+
+    #[error("Error in converting to json {0}")]
+    JsonError(#[from] serde_json::Error),
+}
@@ -1,4 +1,5 @@
 use crate::agents::libs::Agent;
+use crate::agents::tools::FunctionTool;
 
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ FinancialAdvisorAgent ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
 
@@ -12,6 +13,7 @@ pub async fn create_advisor_agent() -> Agent {
         "FinancialAdvisorAgent".to_string(),
         ADVISOR_PROMPT.to_string(),
     ).await
+    .with_handoff_description("Writes the final analysis of the company's recent performance.")
 }
 
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ FundamentalsAnalystAgent ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
@@ -27,6 +29,7 @@ pub async fn create_financials_agent() -> Agent {
         "FundamentalsAnalystAgent".to_string(),
         FINANCIALS_PROMPT.to_string(),
     ).await
+    .with_handoff_description("Analyzes revenue, profit, margins and growth from the search results.")
 }
 
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ FinancialPlannerAgent ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
@@ -42,6 +45,7 @@ pub async fn create_planner_agent() -> Agent {
         "FinancialPlannerAgent".to_string(),
         PLANNER_PROMPT.to_string(),
     ).await
+    .with_handoff_description("Plans the web searches needed for a financial analysis.")
 }
 
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ RiskAnalystAgent ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
@@ -56,6 +60,7 @@ pub async fn create_risk_agent() -> Agent {
         "RiskAnalystAgent".to_string(),
         RISK_PROMPT.to_string(),
     ).await
+    .with_handoff_description("Looks for red flags such as competitive, regulatory or supply chain risks.")
 }
 
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ FinancialSearchAgent ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
@@ -71,4 +76,64 @@ pub async fn create_search_agent() -> Agent {
         "FinancialSearchAgent".to_string(),
         SEARCH_PROMPT.to_string(),
     ).await
-}
\ No newline at end of file
+    .with_handoff_description("Runs the planned web searches and summarizes the results.")
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ FinancialResearchFlow ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+const PLANNER_HANDOFF: &str =
+    "When the search terms are ready, transfer to the FinancialSearchAgent with them.";
+
+const SEARCH_HANDOFF: &str =
+    "Run a search for each term. When every summary is written, transfer to the \
+    FundamentalsAnalystAgent.";
+
+const FINANCIALS_HANDOFF: &str =
+    "When your analysis is written, transfer to the RiskAnalystAgent.";
+
+const RISK_HANDOFF: &str =
+    "When your analysis is written, transfer to the FinancialAdvisorAgent.";
+
+const ADVISOR_FINAL: &str =
+    "Use the whole conversation, including the fundamentals and risk analyses, \
+    and answer the original request. This is the final report.";
+
+/// Creates the financial research flow and returns its entry agent
+///
+/// The agents hand off to each other in order: planner, search, fundamentals
+/// analyst, risk analyst and advisor, which writes the final report. The
+/// search agent receives `search_tools` to retrieve up-to-date context.
+///
+/// # Example
+/// ```ignore
+/// let research_agent = create_research_agent(vec![web_search_tool]).await;
+/// let result = research_agent
+///     .run_messages(vec![ChatMessage::user("Analyze Tesla's last quarter")])
+///     .await?;
+/// println!("{:?}: {}", result.agents, result.final_output);
+/// ```
+pub async fn create_research_agent(search_tools: Vec<FunctionTool>) -> Agent {
+    let mut advisor_agent = create_advisor_agent().await;
+    advisor_agent.instructions = format!("{} {}", advisor_agent.instructions, ADVISOR_FINAL);
+
+    let mut risk_agent = create_risk_agent().await;
+    risk_agent.instructions = format!("{} {}", risk_agent.instructions, RISK_HANDOFF);
+    let risk_agent = risk_agent.with_handoff(advisor_agent);
+
+    let mut financials_agent = create_financials_agent().await;
+    financials_agent.instructions = format!("{} {}", financials_agent.instructions, FINANCIALS_HANDOFF);
+    let financials_agent = financials_agent.with_handoff(risk_agent);
+
+    let mut search_agent = create_search_agent().await;
+    search_agent.instructions = format!("{} {}", search_agent.instructions, SEARCH_HANDOFF);
+    let mut search_agent = search_agent.with_handoff(financials_agent);
+    for tool in search_tools {
+        search_agent = search_agent.with_tool(tool);
+    }
+
+    let mut planner_agent = create_planner_agent().await;
+    planner_agent.instructions = format!("{} {}", planner_agent.instructions, PLANNER_HANDOFF);
+    planner_agent
+        .with_handoff(search_agent)
+        .with_max_turns(30)
+}
@@ -0,0 +1,213 @@
+use crate::agents::error::AgentError;
+use crate::agents::libs::{extract_json, Agent};
+use async_trait::async_trait;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+const AGENT_GUARDRAIL_PROMPT: &str =
+    "Answer only with a JSON object with two keys: \"tripwire_triggered\", a boolean \
+    that is true when the text must be blocked, and \"reasoning\", a short explanation.";
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Guardrail ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Outcome of a guardrail check
+///
+/// # Fields
+/// * `guardrail_name` - Name of the guardrail that produced the result
+/// * `tripwire_triggered` - Whether the run must be aborted
+/// * `output_info` - Details about the check, e.g. the reasoning of a guardrail agent
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GuardrailResult {
+    pub guardrail_name: String,
+    pub tripwire_triggered: bool,
+    pub output_info: Value,
+}
+
+#[allow(dead_code)]
+impl GuardrailResult {
+    /// The check passed
+    pub fn pass(output_info: Value) -> Self {
+        Self {
+            guardrail_name: String::new(),
+            tripwire_triggered: false,
+            output_info,
+        }
+    }
+
+    /// The check failed and the run must be aborted
+    pub fn trip(output_info: Value) -> Self {
+        Self {
+            guardrail_name: String::new(),
+            tripwire_triggered: true,
+            output_info,
+        }
+    }
+}
+
+/// A check that runs on the input of an agent run or on its final output
+///
+/// Input guardrails get the text of the input messages and run in parallel
+/// with the first model call. Output guardrails get the final output. When
+/// a check trips the tripwire the run is aborted with
+/// `AgentError::InputGuardrailTripwireTriggered` or
+/// `AgentError::OutputGuardrailTripwireTriggered`.
+#[async_trait]
+pub trait Guardrail: Send + Sync + fmt::Debug {
+    fn name(&self) -> String;
+
+    async fn check(&self, agent_name: &str, text: &str) -> Result<GuardrailResult, AgentError>;
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ FunctionGuardrail ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+type GuardrailFuture = Pin<Box<dyn Future<Output = GuardrailResult> + Send>>;
+type GuardrailHandler = Arc<dyn Fn(String) -> GuardrailFuture + Send + Sync>;
+
+/// A guardrail backed by a Rust async function
+///
+/// # Example
+/// ```ignore
+/// let guardrail = FunctionGuardrail::new("no_homework", |text: String| async move {
+///     if text.contains("homework") {
+///         GuardrailResult::trip(json!({"reason": "Homework request"}))
+///     } else {
+///         GuardrailResult::pass(Value::Null)
+///     }
+/// });
+/// ```
+#[derive(Clone)]
+pub struct FunctionGuardrail {
+    pub name: String,
+    handler: GuardrailHandler,
+}
+
+#[allow(dead_code)]
+impl FunctionGuardrail {
+    pub fn new<F, Fut>(name: &str, function: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = GuardrailResult> + Send + 'static,
+    {
+        let handler: GuardrailHandler = Arc::new(move |text: String| -> GuardrailFuture {
+            Box::pin(function(text))
+        });
+
+        Self {
+            name: name.to_string(),
+            handler,
+        }
+    }
+}
+
+impl fmt::Debug for FunctionGuardrail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionGuardrail")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Guardrail for FunctionGuardrail {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn check(&self, _agent_name: &str, text: &str) -> Result<GuardrailResult, AgentError> {
+        Ok((self.handler)(text.to_string()).await)
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ AgentGuardrail ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// A guardrail that asks another agent whether the text must be blocked
+///
+/// The agent instructions describe the policy; the agent is asked to answer
+/// with `{"tripwire_triggered": bool, "reasoning": "..."}`, which becomes the
+/// `output_info` of the result. A cheap, fast model is usually enough.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct AgentGuardrail {
+    pub agent: Agent,
+}
+
+#[allow(dead_code)]
+impl AgentGuardrail {
+    pub fn new(agent: Agent) -> Self {
+        Self { agent }
+    }
+}
+
+#[async_trait]
+impl Guardrail for AgentGuardrail {
+    fn name(&self) -> String {
+        self.agent.name.clone()
+    }
+
+    async fn check(&self, _agent_name: &str, text: &str) -> Result<GuardrailResult, AgentError> {
+        let mut agent = self.agent.clone();
+        agent.instructions = format!("{}\n\n{}", agent.instructions, AGENT_GUARDRAIL_PROMPT);
+
+        let output = agent.run(text).await?;
+
+        let output_info: Value = match serde_json::from_str(extract_json(&output)) {
+            Ok(value) => value,
+            Err(_) => {
+                return Err(AgentError::GuardrailError(format!(
+                    "Guardrail agent {} did not answer with JSON: {}",
+                    self.agent.name, output
+                )));
+            }
+        };
+
+        match output_info["tripwire_triggered"].as_bool() {
+            Some(true) => Ok(GuardrailResult::trip(output_info)),
+            Some(false) => Ok(GuardrailResult::pass(output_info)),
+            None => Err(AgentError::GuardrailError(format!(
+                "Guardrail agent {} did not return tripwire_triggered: {}",
+                self.agent.name, output
+            ))),
+        }
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Runner ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Runs the guardrails concurrently and returns as soon as one trips
+///
+/// # Errors
+/// * `tripped` - Built from the first result with `tripwire_triggered`
+/// * Any error returned by a guardrail
+pub async fn run_guardrails(
+    guardrails: &[Arc<dyn Guardrail>],
+    agent_name: &str,
+    text: &str,
+    tripped: fn(GuardrailResult) -> AgentError,
+) -> Result<Vec<GuardrailResult>, AgentError> {
+    let mut checks: FuturesUnordered<_> = guardrails
+        .iter()
+        .map(|guardrail| async move {
+            let mut result = guardrail.check(agent_name, text).await?;
+            result.guardrail_name = guardrail.name();
+            Ok::<_, AgentError>(result)
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    while let Some(result) = checks.next().await {
+        let result = result?;
+        if result.tripwire_triggered {
+            return Err(tripped(result));
+        }
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
@@ -0,0 +1,143 @@
+use crate::agents::guardrails::GuardrailResult;
+use crate::agents::tools::ToolResult;
+use crate::llm::libs::{ChatMessage, ModelResponse, ToolCall};
+use async_trait::async_trait;
+use std::fmt;
+use std::sync::Arc;
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ AgentHooks ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Callbacks on the lifecycle events of an agent run
+///
+/// Every method has an empty default, so an implementation only overrides
+/// the events it cares about. Hooks set on the agent that starts the run are
+/// called for every step, including the steps of the agents it hands off to.
+///
+/// # Example
+/// ```ignore
+/// #[derive(Debug)]
+/// struct LogHooks;
+///
+/// #[async_trait]
+/// impl AgentHooks for LogHooks {
+///     async fn on_tool_start(&self, agent_name: &str, tool_call: &ToolCall) {
+///         println!("{} calls {} with {}", agent_name, tool_call.name, tool_call.arguments);
+///     }
+/// }
+///
+/// let agent = agent.with_hooks(LogHooks);
+/// ```
+#[async_trait]
+pub trait AgentHooks: Send + Sync + fmt::Debug {
+    /// An agent starts, at the beginning of the run or after a handoff
+    async fn on_agent_start(&self, _agent_name: &str) {}
+
+    /// The last agent produced the final output
+    async fn on_agent_end(&self, _agent_name: &str, _output: &str) {}
+
+    /// A request is about to be sent to the model
+    async fn on_llm_request(&self, _agent_name: &str, _messages: &[ChatMessage]) {}
+
+    /// The model answered
+    async fn on_llm_response(&self, _agent_name: &str, _response: &ModelResponse) {}
+
+    /// A tool is about to be called
+    async fn on_tool_start(&self, _agent_name: &str, _tool_call: &ToolCall) {}
+
+    /// A tool returned, or failed
+    async fn on_tool_end(&self, _agent_name: &str, _result: &ToolResult) {}
+
+    /// The conversation is transferred to another agent
+    async fn on_handoff(&self, _from_agent: &str, _to_agent: &str) {}
+
+    /// An input or output guardrail tripped and the run is aborted
+    async fn on_guardrail_tripped(&self, _agent_name: &str, _result: &GuardrailResult) {}
+}
+
+/// Calls every hook of the list in order
+#[async_trait]
+impl AgentHooks for [Arc<dyn AgentHooks>] {
+    async fn on_agent_start(&self, agent_name: &str) {
+        for hooks in self {
+            hooks.on_agent_start(agent_name).await;
+        }
+    }
+
+    async fn on_agent_end(&self, agent_name: &str, output: &str) {
+        for hooks in self {
+            hooks.on_agent_end(agent_name, output).await;
+        }
+    }
+
+    async fn on_llm_request(&self, agent_name: &str, messages: &[ChatMessage]) {
+        for hooks in self {
+            hooks.on_llm_request(agent_name, messages).await;
+        }
+    }
+
+    async fn on_llm_response(&self, agent_name: &str, response: &ModelResponse) {
+        for hooks in self {
+            hooks.on_llm_response(agent_name, response).await;
+        }
+    }
+
+    async fn on_tool_start(&self, agent_name: &str, tool_call: &ToolCall) {
+        for hooks in self {
+            hooks.on_tool_start(agent_name, tool_call).await;
+        }
+    }
+
+    async fn on_tool_end(&self, agent_name: &str, result: &ToolResult) {
+        for hooks in self {
+            hooks.on_tool_end(agent_name, result).await;
+        }
+    }
+
+    async fn on_handoff(&self, from_agent: &str, to_agent: &str) {
+        for hooks in self {
+            hooks.on_handoff(from_agent, to_agent).await;
+        }
+    }
+
+    async fn on_guardrail_tripped(&self, agent_name: &str, result: &GuardrailResult) {
+        for hooks in self {
+            hooks.on_guardrail_tripped(agent_name, result).await;
+        }
+    }
+}
+
+/// Lets the caller keep a handle on hooks that collect data during the run
+#[async_trait]
+impl<T: AgentHooks + ?Sized> AgentHooks for Arc<T> {
+    async fn on_agent_start(&self, agent_name: &str) {
+        (**self).on_agent_start(agent_name).await;
+    }
+
+    async fn on_agent_end(&self, agent_name: &str, output: &str) {
+        (**self).on_agent_end(agent_name, output).await;
+    }
+
+    async fn on_llm_request(&self, agent_name: &str, messages: &[ChatMessage]) {
+        (**self).on_llm_request(agent_name, messages).await;
+    }
+
+    async fn on_llm_response(&self, agent_name: &str, response: &ModelResponse) {
+        (**self).on_llm_response(agent_name, response).await;
+    }
+
+    async fn on_tool_start(&self, agent_name: &str, tool_call: &ToolCall) {
+        (**self).on_tool_start(agent_name, tool_call).await;
+    }
+
+    async fn on_tool_end(&self, agent_name: &str, result: &ToolResult) {
+        (**self).on_tool_end(agent_name, result).await;
+    }
+
+    async fn on_handoff(&self, from_agent: &str, to_agent: &str) {
+        (**self).on_handoff(from_agent, to_agent).await;
+    }
+
+    async fn on_guardrail_tripped(&self, agent_name: &str, result: &GuardrailResult) {
+        (**self).on_guardrail_tripped(agent_name, result).await;
+    }
+}
@@ -1,8 +1,34 @@
+use crate::agents::error::AgentError;
+use crate::agents::guardrails::{run_guardrails, Guardrail, GuardrailResult};
+use crate::agents::hooks::AgentHooks;
+use crate::agents::tools::{run_tool_call, FunctionTool, ToolResult};
+use crate::agents::trace::{RunTrace, SpanKind};
 use crate::anthropic::chat::ChatAnthropic;
+use crate::anthropic::libs::CacheControl;
+use crate::gemini::chat::ChatGemini;
+use crate::llm::chat::ChatModel;
+use crate::llm::libs::{
+    ChatMessage, ChatOptions, ContentPart, OutputSchema, Role, TokenUsage, ToolCall, ToolDefinition,
+};
 use crate::openai::chat::ChatOpenAI;
+use crate::openai::utils::generate_schema;
+use futures::future::{join_all, select, Either};
+use log::info;
+use schemars::gen::SchemaSettings;
+use schemars::schema::InstanceType;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 const DEFAULT_OPENAI_MODEL: &str = "gpt-4.5-preview";
 const DEFAULT_ANTHROPIC_MODEL: &str = "claude-3-7-sonnet-20250219";
+const DEFAULT_GEMINI_MODEL: &str = "gemini-2.0-flash";
+const DEFAULT_MAX_TURNS: u32 = 10;
+const DEFAULT_MAX_OUTPUT_RETRIES: u32 = 2;
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
@@ -12,6 +38,9 @@ pub enum AgentType {
     
     /// An agent that uses the Anthropic API.
     Anthropic,
+
+    /// An agent that uses the Gemini API.
+    Gemini,
 }
 
 /// The behavior of the agent when it uses tools. This determines how the agent interacts with
@@ -33,10 +62,49 @@ pub enum ToolUseBehavior {
     /// process the result of the tool call.
     StopAtTools(Vec<String>),
     
-    /// If you pass a function, it will be called with the run context and the list of
-    /// tool results. It must return a `ToolToFinalOutputResult`, which determines whether the tool
+    /// If you pass a function, it will be called with the list of tool results of the turn.
+    /// It must return a `ToolToFinalOutputResult`, which determines whether the tool
     /// calls result in a final output.
-    CustomFunction(String),
+    CustomFunction(ToolsToFinalOutputFunction),
+}
+
+#[allow(dead_code)]
+impl ToolUseBehavior {
+    /// Creates a `CustomFunction` behavior from a closure
+    pub fn custom<F>(function: F) -> Self
+    where
+        F: Fn(&[ToolResult]) -> ToolToFinalOutputResult + Send + Sync + 'static,
+    {
+        Self::CustomFunction(ToolsToFinalOutputFunction(Arc::new(function)))
+    }
+
+    /// Decides whether the tool results of a turn end the run
+    pub fn final_output(&self, results: &[ToolResult]) -> ToolToFinalOutputResult {
+        let result = match self {
+            Self::RunLLMAgain => None,
+            Self::StopOnFirstTool => results.first(),
+            Self::StopAtTools(names) => results.iter().find(|result| names.contains(&result.name)),
+            Self::CustomFunction(function) => return (function.0)(results),
+        };
+
+        match result {
+            Some(result) if result.is_error => ToolToFinalOutputResult::new_error(result.output.clone()),
+            Some(result) => ToolToFinalOutputResult::new_success(result.output.clone()),
+            None => ToolToFinalOutputResult::Ignore,
+        }
+    }
+}
+
+type ToolsToFinalOutputFn = dyn Fn(&[ToolResult]) -> ToolToFinalOutputResult + Send + Sync;
+
+/// Function used by `ToolUseBehavior::CustomFunction`
+#[derive(Clone)]
+pub struct ToolsToFinalOutputFunction(pub Arc<ToolsToFinalOutputFn>);
+
+impl fmt::Debug for ToolsToFinalOutputFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ToolsToFinalOutputFunction")
+    }
 }
 
 #[allow(dead_code)]
@@ -55,26 +123,38 @@ pub struct ModelSettings {
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub enum Thinking {
-    /// The agent is thinking. This means that the agent is currently processing the input
-    /// and generating a response.
+    /// The model reasons before answering, with a budget of tokens to think. Anthropic models
+    /// get it as `budget_tokens`; OpenAI models as a reasoning effort, `low` under 4096 tokens,
+    /// `medium` under 16384 and `high` above.
     Enabled(i32),
     
-    /// The agent is not thinking. This means that the agent is not currently processing the input
-    /// and generating a response.
+    /// The model answers without extended thinking.
     Disabled,
 }
 
+#[allow(dead_code)]
+impl Thinking {
+    /// OpenAI reasoning effort matching the thinking budget
+    pub fn reasoning_effort(&self) -> Option<&'static str> {
+        match self {
+            Self::Enabled(budget_tokens) if *budget_tokens < 4096 => Some("low"),
+            Self::Enabled(budget_tokens) if *budget_tokens < 16384 => Some("medium"),
+            Self::Enabled(_) => Some("high"),
+            Self::Disabled => None,
+        }
+    }
+}
+
 /// An agent is an AI model configured with instructions, tools, guardrails, handoffs and more.
 ///
 /// We strongly recommend passing `instructions`, which is the "system prompt" for the agent. In
 /// addition, you can pass `handoff_description`, which is a human-readable description of the
 /// agent, used when the agent is used inside tools/handoffs.
-
-/// Agents are generic on the context type. The context is a (mutable) object you create. It is
-/// passed to tool functions, handoffs, guardrails, etc.
+///
+/// Agents are generic on the output type. `Agent` answers with text, while an agent built with
+/// `with_output_type::<T>()` asks the model for JSON matching the schema of `T` and returns a `T`.
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
-pub struct Agent {
+pub struct Agent<O = String> {
     /// The name of the agent.
     pub name: String,
     
@@ -92,7 +172,7 @@ pub struct Agent {
     /// Handoffs are sub-agents that the agent can delegate to. You can provide a list of handoffs,
     /// and the agent can choose to delegate to them if relevant. Allows for separation of concerns and
     /// modularity.
-    pub handoffs: Option<Vec<String>>,
+    pub handoffs: Option<Vec<Agent>>,
    
     /// The model implementation to use when invoking the LLM.
     pub model: Option<String>,
@@ -101,27 +181,117 @@ pub struct Agent {
     pub model_settings: Option<ModelSettings>,
 
     /// A list of tools that the agent can use.
-    pub tools: Option<Vec<String>>,
+    pub tools: Option<Vec<FunctionTool>>,
 
     /// A list of checks that run in parallel to the agent's execution, before generating a
     /// response. Runs only if the agent is the first agent in the chain.
-    pub input_guardrails: Option<Vec<String>>,
+    pub input_guardrails: Option<Vec<Arc<dyn Guardrail>>>,
 
     /// A list of checks that run on the final output of the agent, after generating a response.
     /// Runs only if the agent produces a final output.
-    pub output_guardrails: Option<Vec<String>>,
+    pub output_guardrails: Option<Vec<Arc<dyn Guardrail>>>,
 
-    ///The type of the output object. If not provided, the output will be <str>.
-    pub output_type: Option<String>,
+    /// The type of the output object. With `String` the answer is returned as is, any other
+    /// type is requested as structured output and deserialized from the JSON answer.
+    pub output_type: PhantomData<fn() -> O>,
 
-    /// A class that receives callbacks on various lifecycle events for this agent.
-    pub hooks: Option<Vec<String>>,
+    /// How many times the model is asked again when its answer does not match the output
+    /// type (default: 2).
+    pub max_output_retries: Option<u32>,
+
+    /// Callbacks on the lifecycle events of a run started by this agent, including the steps
+    /// of the agents it hands off to.
+    pub hooks: Option<Vec<Arc<dyn AgentHooks>>>,
 
     /// A list of tools that the agent can use. These are the tools that the agent can use to
     /// perform actions. The agent can choose to use these tools, and the results of the tools
     /// will be passed to the agent as input.
-    pub tool_use_behavior: Option<ToolUseBehavior>,    
+    pub tool_use_behavior: Option<ToolUseBehavior>,
+
+    /// The maximum number of model calls in a single run (default: 10).
+    pub max_turns: Option<u32>,
+
+    /// Overrides the endpoint of the provider selected by `agent_type`.
+    pub base_url: Option<String>,
 
+    /// Cache breakpoints on the instructions, the tools and the conversation so far, so every
+    /// turn reads the previous prompt from the cache. Only used by Anthropic agents.
+    pub prompt_cache: Option<CacheControl>,
+}
+
+impl<O> Clone for Agent<O> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            instructions: self.instructions.clone(),
+            agent_type: self.agent_type.clone(),
+            handoff_description: self.handoff_description.clone(),
+            handoffs: self.handoffs.clone(),
+            model: self.model.clone(),
+            model_settings: self.model_settings.clone(),
+            tools: self.tools.clone(),
+            input_guardrails: self.input_guardrails.clone(),
+            output_guardrails: self.output_guardrails.clone(),
+            output_type: PhantomData,
+            max_output_retries: self.max_output_retries,
+            hooks: self.hooks.clone(),
+            tool_use_behavior: self.tool_use_behavior.clone(),
+            max_turns: self.max_turns,
+            base_url: self.base_url.clone(),
+            prompt_cache: self.prompt_cache.clone(),
+        }
+    }
+}
+
+impl<O> fmt::Debug for Agent<O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Agent")
+            .field("name", &self.name)
+            .field("instructions", &self.instructions)
+            .field("agent_type", &self.agent_type)
+            .field("handoff_description", &self.handoff_description)
+            .field("handoffs", &self.handoffs)
+            .field("model", &self.model)
+            .field("model_settings", &self.model_settings)
+            .field("tools", &self.tools)
+            .field("input_guardrails", &self.input_guardrails)
+            .field("output_guardrails", &self.output_guardrails)
+            .field("output_type", &std::any::type_name::<O>())
+            .field("max_output_retries", &self.max_output_retries)
+            .field("hooks", &self.hooks)
+            .field("tool_use_behavior", &self.tool_use_behavior)
+            .field("max_turns", &self.max_turns)
+            .field("base_url", &self.base_url)
+            .field("prompt_cache", &self.prompt_cache)
+            .finish()
+    }
+}
+
+/// The result of an agent run
+///
+/// # Fields
+/// * `final_output` - The answer of the model, or the tool output that ended the run,
+///   deserialized into the output type of the agent
+/// * `messages` - The whole conversation, including tool calls and tool results
+/// * `turns` - Number of model calls made
+/// * `usage` - Token usage added over every turn
+/// * `agents` - Names of the agents that ran, in handoff order
+/// * `last_agent` - Name of the agent that produced the final output
+/// * `input_guardrail_results` - Results of the input guardrails of the first agent
+/// * `output_guardrail_results` - Results of the output guardrails of the last agent
+/// * `trace` - Span tree of the run, with timings and token usage for every step
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct RunResult<O = String> {
+    pub final_output: O,
+    pub messages: Vec<ChatMessage>,
+    pub turns: u32,
+    pub usage: TokenUsage,
+    pub agents: Vec<String>,
+    pub last_agent: String,
+    pub input_guardrail_results: Vec<GuardrailResult>,
+    pub output_guardrail_results: Vec<GuardrailResult>,
+    pub trace: RunTrace,
 }
 
 impl Agent {
@@ -138,11 +308,110 @@ impl Agent {
             tools: None,
             input_guardrails: None,
             output_guardrails: None,
-            output_type: None,
+            output_type: PhantomData,
+            max_output_retries: None,
             hooks: None,
             tool_use_behavior: None,
+            max_turns: None,
+            base_url: None,
+            prompt_cache: None,
         }
     }
+}
+
+impl<O> Agent<O> {
+    /// Sets the type the final output is deserialized into
+    ///
+    /// # Example
+    /// ```ignore
+    /// #[derive(Debug, Deserialize, JsonSchema)]
+    /// struct Weather {
+    ///     city: String,
+    ///     temperature: f64,
+    /// }
+    ///
+    /// let agent = Agent::new(name, instructions).await.with_output_type::<Weather>();
+    /// let weather: Weather = agent.run("What is the weather like in Paris?").await?;
+    /// ```
+    pub fn with_output_type<T>(self) -> Agent<T> {
+        Agent {
+            name: self.name,
+            instructions: self.instructions,
+            agent_type: self.agent_type,
+            handoff_description: self.handoff_description,
+            handoffs: self.handoffs,
+            model: self.model,
+            model_settings: self.model_settings,
+            tools: self.tools,
+            input_guardrails: self.input_guardrails,
+            output_guardrails: self.output_guardrails,
+            output_type: PhantomData,
+            max_output_retries: self.max_output_retries,
+            hooks: self.hooks,
+            tool_use_behavior: self.tool_use_behavior,
+            max_turns: self.max_turns,
+            base_url: self.base_url,
+            prompt_cache: self.prompt_cache,
+        }
+    }
+
+    pub fn with_max_output_retries(mut self, max_output_retries: u32) -> Self {
+        self.max_output_retries = Some(max_output_retries);
+        self
+    }
+
+    pub fn with_tool(mut self, tool: FunctionTool) -> Self {
+        self.tools.get_or_insert_with(Vec::new).push(tool);
+        self
+    }
+
+    pub fn with_tool_use_behavior(mut self, tool_use_behavior: ToolUseBehavior) -> Self {
+        self.tool_use_behavior = Some(tool_use_behavior);
+        self
+    }
+
+    /// Adds a sub-agent the model can transfer the conversation to
+    ///
+    /// The output of the run keeps the output type of the first agent.
+    pub fn with_handoff<T>(mut self, agent: Agent<T>) -> Self {
+        self.handoffs.get_or_insert_with(Vec::new).push(agent.with_output_type());
+        self
+    }
+
+    pub fn with_handoff_description(mut self, handoff_description: &str) -> Self {
+        self.handoff_description = Some(handoff_description.to_string());
+        self
+    }
+
+    pub fn with_input_guardrail<G: Guardrail + 'static>(mut self, guardrail: G) -> Self {
+        self.input_guardrails.get_or_insert_with(Vec::new).push(Arc::new(guardrail));
+        self
+    }
+
+    pub fn with_output_guardrail<G: Guardrail + 'static>(mut self, guardrail: G) -> Self {
+        self.output_guardrails.get_or_insert_with(Vec::new).push(Arc::new(guardrail));
+        self
+    }
+
+    pub fn with_hooks<H: AgentHooks + 'static>(mut self, hooks: H) -> Self {
+        self.hooks.get_or_insert_with(Vec::new).push(Arc::new(hooks));
+        self
+    }
+
+    pub fn with_max_turns(mut self, max_turns: u32) -> Self {
+        self.max_turns = Some(max_turns);
+        self
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Some(base_url.to_string());
+        self
+    }
+
+    pub fn with_prompt_cache(mut self, cache_control: CacheControl) -> Self {
+        self.prompt_cache = Some(cache_control);
+        self
+    }
 
     pub async fn model_settings(
         &mut self,
@@ -158,62 +427,462 @@ impl Agent {
         self
     }
 
-    pub async fn run(&self, prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
-        let mut result = String::new();
+    /// Builds the chat model selected by `agent_type` and `model`, with the thinking
+    /// settings of `model_settings` and the prompt cache.
+    pub fn chat_model(&self) -> Box<dyn ChatModel> {
+        let thinking = self.model_settings.as_ref().and_then(|settings| settings.thinking.as_ref());
+
         match self.agent_type {
             AgentType::OpenAI => {
                 let model = self.model.clone().unwrap_or(DEFAULT_OPENAI_MODEL.to_string());
                 let mut llm = ChatOpenAI::new(&model);
-                llm = llm.with_system_prompt(&self.instructions)
-                    .with_temperature(self.model_settings.as_ref().map_or(1.0, |s| s.temperature))
-                    .with_max_tokens(self.model_settings.as_ref().map_or(4096, |s| s.max_tokens));
-
-                // if let Some(thinking) = &self.model_settings {
-                //     if let Some(Thinking::Enabled(_)) = thinking.thinking {
-                //         llm = llm.with_thinking(thinking.thinking.clone().unwrap());
-                //     }
-                // }
-
-                let response = llm.invoke(prompt).await?;
-
-                match response.choices {
-                    Some(candidates) => {
-                        candidates.iter()
-                            .filter_map(|candidate| candidate
-                                .message.as_ref()?
-                                .content.as_ref()
-                            ).for_each(|content| {
-                                result.push_str(content);
-                            });
-                    }
-                    None => result = "No response choices available".to_string(),
-                };
+                if let Some(base_url) = &self.base_url {
+                    llm = llm.with_base_url(base_url);
+                }
+                if let Some(reasoning_effort) = thinking.and_then(|thinking| thinking.reasoning_effort()) {
+                    llm = llm.with_reasoning_effort(reasoning_effort);
+                }
+                Box::new(llm)
             }
             AgentType::Anthropic => {
                 let model = self.model.clone().unwrap_or(DEFAULT_ANTHROPIC_MODEL.to_string());
                 let mut llm = ChatAnthropic::new(&model);
-                llm = llm.with_system_prompt(&self.instructions)
-                    .with_temperature(self.model_settings.as_ref().map_or(1.0, |s| s.temperature))
-                    .with_max_tokens(self.model_settings.as_ref().map_or(4096, |s| s.max_tokens));
-
-                let response = llm.invoke(prompt).await?;
-                
-                if let Some(candidates) = response.content {
-                    candidates.iter()
-                        .filter_map(|c| c.text.as_ref())
-                        .for_each(|text| result.push_str(text));
-                } else {
-                    result = "No response choices available".to_string();
+                if let Some(base_url) = &self.base_url {
+                    llm = llm.with_base_url(base_url);
+                }
+                if let Some(Thinking::Enabled(budget_tokens)) = thinking {
+                    llm = llm.with_thinking((*budget_tokens).max(0) as u32);
+                }
+                if let Some(cache_control) = &self.prompt_cache {
+                    llm = llm.with_prompt_cache(cache_control.clone());
+                }
+                Box::new(llm)
+            }
+            AgentType::Gemini => {
+                let model = self.model.clone().unwrap_or(DEFAULT_GEMINI_MODEL.to_string());
+                let mut llm = ChatGemini::new(&model);
+                if let Some(base_url) = &self.base_url {
+                    llm = llm.with_base_url(base_url);
+                }
+                Box::new(llm)
+            }
+        }
+    }
+
+    /// Name of the synthetic tool used to hand off to this agent,
+    /// e.g. `FinancialSearchAgent` becomes `transfer_to_financial_search_agent`
+    pub fn handoff_tool_name(&self) -> String {
+        let mut name = String::new();
+        let mut previous_lower = false;
+        for c in self.name.chars() {
+            if c.is_ascii_alphanumeric() {
+                if c.is_ascii_uppercase() && previous_lower {
+                    name.push('_');
+                }
+                previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+                name.push(c.to_ascii_lowercase());
+            } else if !name.ends_with('_') {
+                name.push('_');
+                previous_lower = false;
+            }
+        }
+        format!("transfer_to_{}", name.trim_matches('_'))
+    }
+
+    /// Tool definition the parent agent receives for this handoff
+    pub fn handoff_definition(&self) -> ToolDefinition {
+        let mut description = format!(
+            "Handoff to the {} agent to handle the request.",
+            self.name
+        );
+        if let Some(handoff_description) = &self.handoff_description {
+            description.push(' ');
+            description.push_str(handoff_description);
+        }
+
+        ToolDefinition {
+            name: self.handoff_tool_name(),
+            description,
+            parameters: json!({"type": "object", "properties": {}}),
+        }
+    }
+
+    fn find_handoff(&self, tool_name: &str) -> Option<&Agent> {
+        self.handoffs
+            .as_ref()?
+            .iter()
+            .find(|agent| agent.handoff_tool_name() == tool_name)
+    }
+
+    fn chat_options(&self) -> ChatOptions {
+        let mut options = ChatOptions::new()
+            .with_temperature(self.model_settings.as_ref().map_or(1.0, |s| s.temperature))
+            .with_max_tokens(self.model_settings.as_ref().map_or(4096, |s| s.max_tokens));
+
+        let mut definitions: Vec<ToolDefinition> = self.tools
+            .iter()
+            .flatten()
+            .map(|tool| tool.definition())
+            .collect();
+        definitions.extend(self.handoffs.iter().flatten().map(|agent| agent.handoff_definition()));
+
+        if !definitions.is_empty() {
+            options = options.with_tools(definitions);
+        }
+        options
+    }
+}
+
+impl<O: JsonSchema + DeserializeOwned> Agent<O> {
+    /// Runs the agent on a single prompt and returns the final output
+    pub async fn run(&self, prompt: &str) -> Result<O, AgentError> {
+        let messages = vec![ChatMessage::user(prompt)];
+        let result = self.run_messages(messages).await?;
+        Ok(result.final_output)
+    }
+
+    /// Runs the agent loop on a conversation
+    ///
+    /// The model is called with the agent instructions, tools, one
+    /// `transfer_to_<agent>` tool per handoff and, unless the output type is
+    /// `String`, the JSON schema of the output type. When it requests tool calls
+    /// they are run, their results are appended to the conversation and
+    /// `tool_use_behavior` decides whether the run stops with a tool output or
+    /// the model is called again. A handoff call switches to the sub-agent's
+    /// instructions, model and tools and carries the conversation forward.
+    /// The run ends when the model answers without tool calls. An answer that
+    /// does not deserialize into the output type is sent back to the model
+    /// with the error, up to `max_output_retries` times.
+    ///
+    /// The input guardrails of this agent run in parallel with the first
+    /// model call, and the output guardrails of the last agent check the
+    /// final output.
+    ///
+    /// # Errors
+    /// * `AgentError::MaxTurnsExceeded` - No final output after `max_turns` model calls
+    /// * `AgentError::InputGuardrailTripwireTriggered` - An input guardrail blocked the run
+    /// * `AgentError::OutputGuardrailTripwireTriggered` - An output guardrail blocked the final output
+    /// * `AgentError::InvalidOutput` - The final output does not match the output type
+    /// * `AgentError::LlmError` - The model request failed
+    pub async fn run_messages(&self, messages: Vec<ChatMessage>) -> Result<RunResult<O>, AgentError> {
+        let max_turns = self.max_turns.unwrap_or(DEFAULT_MAX_TURNS);
+        let max_output_retries = self.max_output_retries.unwrap_or(DEFAULT_MAX_OUTPUT_RETRIES);
+        let output_schema = output_schema::<O>()?;
+        let mut output_retries = 0;
+
+        // Handoff agents answer with text, the output is parsed into `O` at the end
+        let mut agent: Agent = self.clone().with_output_type();
+        let mut agents = vec![agent.name.clone()];
+        let mut usage = TokenUsage::default();
+        let mut final_output = None;
+        let mut turns = 0;
+
+        let hooks = self.hooks.clone().unwrap_or_default();
+        let mut trace = RunTrace::new();
+        trace.start_agent(&agent.name);
+        hooks.on_agent_start(&agent.name).await;
+
+        let input_guardrails = self.input_guardrails.clone().unwrap_or_default();
+        let mut input_guardrail_results = Vec::new();
+        let input_text = messages
+            .iter()
+            .map(|message| message.text())
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let mut history = messages;
+        // Messages before this index were written for an earlier agent
+        let mut agent_start = 0;
+
+        for turn in 1..=max_turns {
+            turns = turn;
+            let llm = agent.chat_model();
+            let tools = agent.tools.clone().unwrap_or_default();
+            let mut options = agent.chat_options();
+            if let Some(output_schema) = &output_schema {
+                options = options.with_output_schema(output_schema.clone());
+            }
+
+            // Earlier agents' tool turns refer to tools this agent may not define
+            let mut request = vec![ChatMessage::system(&agent.instructions)];
+            request.extend(history[..agent_start].iter().map(flatten_tool_turn));
+            request.extend(history[agent_start..].iter().cloned());
+
+            hooks.on_llm_request(&agent.name, &request).await;
+            let started = Instant::now();
+
+            let response = if turn == 1 && !input_guardrails.is_empty() {
+                // A tripped guardrail drops the pending model call
+                let guardrails = Box::pin(async {
+                    let started = Instant::now();
+                    let results = run_guardrails(
+                        &input_guardrails,
+                        &self.name,
+                        &input_text,
+                        AgentError::InputGuardrailTripwireTriggered,
+                    ).await;
+                    (results, started)
+                });
+
+                match select(guardrails, llm.generate(&request, &options)).await {
+                    Either::Left((checked, generate)) => {
+                        input_guardrail_results = guardrails_checked(
+                            &hooks, &mut trace, &self.name, "input_guardrails", checked,
+                        ).await?;
+                        generate.await?
+                    }
+                    Either::Right((response, guardrails)) => {
+                        let response = response?;
+                        input_guardrail_results = guardrails_checked(
+                            &hooks, &mut trace, &self.name, "input_guardrails", guardrails.await,
+                        ).await?;
+                        response
+                    }
+                }
+            } else {
+                llm.generate(&request, &options).await?
+            };
+
+            let turn_usage = response.usage.unwrap_or_default();
+            usage.add(&turn_usage);
+            trace.record(SpanKind::Generation, &llm.model_name(), started, started.elapsed(), turn_usage, None);
+            hooks.on_llm_response(&agent.name, &response).await;
+            history.push(response.message.clone());
+
+            if !response.has_tool_calls() {
+                let text = response.text();
+                match parse_output::<O>(&text, output_schema.is_some()) {
+                    Ok(output) => {
+                        final_output = Some((text, output));
+                        break;
+                    }
+                    Err(e) if output_retries < max_output_retries => {
+                        info!("Agent {} answered with an invalid output: {}", agent.name, e);
+                        output_retries += 1;
+                        history.push(ChatMessage::user(&format!(
+                            "Your answer does not match the expected JSON schema: {}. \
+                            Answer again with only the corrected JSON object.",
+                            e
+                        )));
+                        continue;
+                    }
+                    Err(e) => return Err(AgentError::InvalidOutput(e)),
+                }
+            }
+
+            let (handoff_calls, tool_calls): (Vec<ToolCall>, Vec<ToolCall>) = response
+                .tool_calls()
+                .iter()
+                .cloned()
+                .partition(|tool_call| agent.find_handoff(&tool_call.name).is_some());
+
+            let calls = tool_calls.iter().map(|tool_call| {
+                let (hooks, agent_name, tools) = (&hooks, &agent.name, &tools);
+                async move {
+                    hooks.on_tool_start(agent_name, tool_call).await;
+                    let started = Instant::now();
+                    let result = run_tool_call(tools, tool_call).await;
+                    let elapsed = started.elapsed();
+                    hooks.on_tool_end(agent_name, &result).await;
+                    (result, started, elapsed)
+                }
+            });
+
+            let mut results = Vec::new();
+            for (result, started, elapsed) in join_all(calls).await {
+                info!("Agent {} called tool {}", agent.name, result.name);
+                let error = result.is_error.then(|| result.output.clone());
+                trace.record(SpanKind::Tool, &result.name, started, elapsed, TokenUsage::default(), error);
+                history.push(ChatMessage::tool_result(
+                    &result.tool_call_id,
+                    &result.name,
+                    &result.output,
+                ));
+                results.push(result);
+            }
+
+            if let Some(handoff_call) = handoff_calls.first() {
+                let next_agent = match agent.find_handoff(&handoff_call.name) {
+                    Some(next_agent) => next_agent.clone(),
+                    None => continue,
+                };
+
+                // Every call must be answered, only the first handoff is followed
+                for tool_call in &handoff_calls {
+                    let output = if tool_call.id == handoff_call.id {
+                        json!({"assistant": next_agent.name}).to_string()
+                    } else {
+                        "Multiple handoffs detected, ignoring this one.".to_string()
+                    };
+                    history.push(ChatMessage::tool_result(&tool_call.id, &tool_call.name, &output));
+                }
+
+                info!("Agent {} handed off to {}", agent.name, next_agent.name);
+                trace.record(SpanKind::Handoff, &next_agent.name, Instant::now(), Duration::ZERO, TokenUsage::default(), None);
+                hooks.on_handoff(&agent.name, &next_agent.name).await;
+                trace.end_agent();
+
+                agents.push(next_agent.name.clone());
+                agent = next_agent;
+                agent_start = history.len();
+                trace.start_agent(&agent.name);
+                hooks.on_agent_start(&agent.name).await;
+                continue;
+            }
+
+            let behavior = agent.tool_use_behavior.clone().unwrap_or(ToolUseBehavior::RunLLMAgain);
+            match behavior.final_output(&results) {
+                ToolToFinalOutputResult::Success(text) | ToolToFinalOutputResult::Error(text) => {
+                    let output = parse_output::<O>(&text, output_schema.is_some())
+                        .map_err(AgentError::InvalidOutput)?;
+                    final_output = Some((text, output));
+                    break;
                 }
+                ToolToFinalOutputResult::Ignore => (),
             }
         }
-        Ok(result)
+
+        let (text, final_output) = match final_output {
+            Some(final_output) => final_output,
+            None => return Err(AgentError::MaxTurnsExceeded(max_turns)),
+        };
+
+        let output_guardrails = agent.output_guardrails.clone().unwrap_or_default();
+        let mut output_guardrail_results = Vec::new();
+        if !output_guardrails.is_empty() {
+            let started = Instant::now();
+            let results = run_guardrails(
+                &output_guardrails,
+                &agent.name,
+                &text,
+                AgentError::OutputGuardrailTripwireTriggered,
+            ).await;
+            output_guardrail_results = guardrails_checked(
+                &hooks, &mut trace, &agent.name, "output_guardrails", (results, started),
+            ).await?;
+        }
+
+        hooks.on_agent_end(&agent.name, &text).await;
+        trace.end_agent();
+
+        Ok(RunResult {
+            final_output,
+            messages: history,
+            turns,
+            usage,
+            agents,
+            last_agent: agent.name,
+            input_guardrail_results,
+            output_guardrail_results,
+            trace,
+        })
     }
 }
 
+/// Records a guardrail check in the trace and reports a tripwire to the hooks
+async fn guardrails_checked(
+    hooks: &[Arc<dyn AgentHooks>],
+    trace: &mut RunTrace,
+    agent_name: &str,
+    name: &str,
+    (results, started): (Result<Vec<GuardrailResult>, AgentError>, Instant),
+) -> Result<Vec<GuardrailResult>, AgentError> {
+    let tripped = match &results {
+        Err(AgentError::InputGuardrailTripwireTriggered(result))
+        | Err(AgentError::OutputGuardrailTripwireTriggered(result)) => Some(result),
+        _ => None,
+    };
+
+    let error = results.as_ref().err().map(|e| e.to_string());
+    trace.record(SpanKind::Guardrail, name, started, started.elapsed(), TokenUsage::default(), error);
+    if let Some(result) = tripped {
+        hooks.on_guardrail_tripped(agent_name, result).await;
+    }
+    results
+}
+
+/// Rewrites the tool calls and results of a message as text, which every
+/// provider accepts without the tool definitions
+fn flatten_tool_turn(message: &ChatMessage) -> ChatMessage {
+    if message.role == Role::Tool {
+        let name = message.name.as_deref().unwrap_or("tool");
+        return ChatMessage::user(&format!("Result of {}: {}", name, message.text()));
+    }
+
+    let mut message = message.clone();
+    for tool_call in std::mem::take(&mut message.tool_calls) {
+        message.content.push(ContentPart::Text {
+            text: format!("Called {} with {}", tool_call.name, tool_call.arguments),
+        });
+    }
+    message
+}
+
+/// Structured output requested for `O`, or `None` when `O` is a plain string
+fn output_schema<O: JsonSchema>() -> Result<Option<OutputSchema>, AgentError> {
+    let settings = SchemaSettings::draft07().with(|settings| {
+        settings.inline_subschemas = true;
+        settings.option_add_null_type = false;
+    });
+    let schema = settings.into_generator().into_root_schema_for::<O>();
+    if schema.schema.instance_type == Some(InstanceType::String.into()) {
+        return Ok(None);
+    }
+
+    // Schema names may only contain letters, digits, `_` and `-`
+    let name: String = O::schema_name()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+
+    let json_schema = generate_schema(schema, &name, false, false, false)
+        .map_err(|e| AgentError::InvalidOutput(e.to_string()))?;
+    Ok(Some(serde_json::from_value(json_schema)?))
+}
+
+/// Deserializes the final output, from JSON when structured output was requested
+fn parse_output<O: DeserializeOwned>(text: &str, structured: bool) -> Result<O, String> {
+    if !structured {
+        return serde_json::from_value(serde_json::Value::String(text.to_string()))
+            .map_err(|e| e.to_string());
+    }
+
+    serde_json::from_str(extract_json(text)).map_err(|e| e.to_string())
+}
+
+/// The JSON object or array of a model answer, without the markdown code
+/// block or the text models sometimes put around it
+///
+/// Returns the trimmed text when no bracketed span is valid JSON, so the
+/// parse error points at the answer.
+pub fn extract_json(text: &str) -> &str {
+    let mut text = text.trim();
+    if let Some(start) = text.find("```") {
+        // Skips the language tag of the fence, e.g. ```json
+        let fenced = &text[start + 3..];
+        let fenced = &fenced[fenced.find('\n').map_or(0, |i| i + 1)..];
+        text = fenced.split("```").next().unwrap_or(fenced).trim();
+    }
+
+    let mut spans: Vec<(usize, &str)> = [('{', '}'), ('[', ']')]
+        .into_iter()
+        .filter_map(|(open, close)| match (text.find(open), text.rfind(close)) {
+            (Some(start), Some(end)) if start < end => Some((start, &text[start..=end])),
+            _ => None,
+        })
+        .collect();
+    spans.sort_by_key(|(start, _)| *start);
+
+    spans
+        .into_iter()
+        .map(|(_, span)| span)
+        .find(|span| serde_json::from_str::<serde_json::Value>(span).is_ok())
+        .unwrap_or(text)
+}
+
 /// A struct that represents the result of a tool call. This is used to determine whether the
 /// tool call should be used as the final output or not. The result can be one of the following:
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ToolToFinalOutputResult {
     /// The tool call was successful, and the result should be used as the final output.
     Success(String),
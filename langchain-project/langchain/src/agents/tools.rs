@@ -0,0 +1,172 @@
+use crate::llm::libs::{ToolCall, ToolDefinition};
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+type ToolFuture = Pin<Box<dyn Future<Output = Result<String, String>> + Send>>;
+type ToolHandler = Arc<dyn Fn(Value) -> ToolFuture + Send + Sync>;
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ FunctionTool ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// A Rust async function exposed to the model as a tool
+///
+/// The JSON schema of the arguments is generated from the argument type with
+/// `schemars`, and the model's arguments are deserialized into it before the
+/// function is called. The output is sent back to the model as text: strings
+/// as is, any other value serialized to JSON.
+///
+/// # Example
+/// ```ignore
+/// #[derive(Deserialize, JsonSchema)]
+/// struct WeatherArgs {
+///     /// The city and state, e.g. San Francisco, CA
+///     location: String,
+/// }
+///
+/// let tool = FunctionTool::new(
+///     "get_current_weather",
+///     "Get the current weather in a given location",
+///     |args: WeatherArgs| async move {
+///         Ok::<_, String>(format!("22 degrees and sunny in {}", args.location))
+///     },
+/// );
+/// ```
+#[derive(Clone)]
+pub struct FunctionTool {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+    handler: ToolHandler,
+}
+
+#[allow(dead_code)]
+impl FunctionTool {
+    pub fn new<A, R, E, F, Fut>(name: &str, description: &str, function: F) -> Self
+    where
+        A: DeserializeOwned + JsonSchema + Send + 'static,
+        R: Serialize,
+        E: fmt::Display,
+        F: Fn(A) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, E>> + Send + 'static,
+    {
+        let handler: ToolHandler = Arc::new(move |arguments: Value| -> ToolFuture {
+            match serde_json::from_value::<A>(arguments) {
+                Ok(arguments) => {
+                    let future = function(arguments);
+                    Box::pin(async move {
+                        match future.await {
+                            Ok(output) => Ok(output_to_string(output)),
+                            Err(e) => Err(e.to_string()),
+                        }
+                    })
+                }
+                Err(e) => {
+                    let message = format!("Invalid arguments: {}", e);
+                    Box::pin(async move { Err(message) })
+                }
+            }
+        });
+
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters: parameters_schema::<A>(),
+            handler,
+        }
+    }
+
+    /// Definition sent to the model in `ChatOptions`
+    pub fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            parameters: self.parameters.clone(),
+        }
+    }
+
+    /// Runs the tool with the JSON arguments chosen by the model
+    pub async fn call(&self, arguments: Value) -> Result<String, String> {
+        (self.handler)(arguments).await
+    }
+}
+
+impl fmt::Debug for FunctionTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionTool")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("parameters", &self.parameters)
+            .finish()
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ ToolResult ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Output of a tool call made during an agent run
+///
+/// # Fields
+/// * `tool_call_id` - Id of the call answered
+/// * `name` - Tool that was called
+/// * `output` - Text sent back to the model, or the error message
+/// * `is_error` - Whether the tool failed, was unknown or got invalid arguments
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolResult {
+    pub tool_call_id: String,
+    pub name: String,
+    pub output: String,
+    pub is_error: bool,
+}
+
+/// Runs a tool call with the matching tool of the list
+pub async fn run_tool_call(tools: &[FunctionTool], tool_call: &ToolCall) -> ToolResult {
+    let result = match tools.iter().find(|tool| tool.name == tool_call.name) {
+        Some(tool) => tool.call(tool_call.arguments.clone()).await,
+        None => Err(format!("Tool {} not found", tool_call.name)),
+    };
+
+    let (output, is_error) = match result {
+        Ok(output) => (output, false),
+        Err(message) => (format!("Error: {}", message), true),
+    };
+
+    ToolResult {
+        tool_call_id: tool_call.id.clone(),
+        name: tool_call.name.clone(),
+        output,
+        is_error,
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Helpers ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// JSON schema of the tool arguments, without the `$schema` and `title`
+/// keys and with sub schemas inlined so every provider accepts it
+pub fn parameters_schema<A: JsonSchema>() -> Value {
+    let settings = SchemaSettings::draft07().with(|settings| {
+        settings.inline_subschemas = true;
+        settings.option_add_null_type = false;
+    });
+    let schema = settings.into_generator().into_root_schema_for::<A>();
+
+    let mut parameters = serde_json::to_value(schema).unwrap_or_default();
+    if let Some(object) = parameters.as_object_mut() {
+        object.remove("$schema");
+        object.remove("title");
+    }
+    parameters
+}
+
+fn output_to_string<R: Serialize>(output: R) -> String {
+    match serde_json::to_value(&output) {
+        Ok(Value::String(text)) => text,
+        Ok(value) => value.to_string(),
+        Err(e) => format!("Error: {}", e),
+    }
+}
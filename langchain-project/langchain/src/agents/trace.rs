@@ -0,0 +1,202 @@
+use crate::llm::libs::TokenUsage;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Span ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Step of an agent run recorded in a `RunTrace`
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpanKind {
+    /// An agent of the run, parent of the steps it made
+    Agent,
+    /// A model call
+    Generation,
+    /// A tool call
+    Tool,
+    /// A transfer to another agent
+    Handoff,
+    /// The input or output guardrails of the run
+    Guardrail,
+}
+
+/// A timed step of an agent run
+///
+/// # Fields
+/// * `kind` - What the step did
+/// * `name` - Agent, model, tool or guardrail name
+/// * `start` - Offset from the start of the run
+/// * `duration` - How long the step took
+/// * `usage` - Tokens used by the step; for an agent, by all its model calls
+/// * `error` - Error message when a tool failed or a guardrail tripped
+/// * `children` - Steps made by an agent, in order
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Span {
+    pub kind: SpanKind,
+    pub name: String,
+    pub start: Duration,
+    pub duration: Duration,
+    pub usage: TokenUsage,
+    pub error: Option<String>,
+    pub children: Vec<Span>,
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ RunTrace ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Span tree of an agent run
+///
+/// The root spans are the agents of the run, in handoff order. Their
+/// children are the guardrail checks, model calls, tool calls and handoffs
+/// they made. `Display` prints the tree with timings and token usage.
+///
+/// # Fields
+/// * `duration` - Duration of the whole run
+/// * `usage` - Token usage added over every model call
+/// * `spans` - One span per agent
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct RunTrace {
+    pub duration: Duration,
+    pub usage: TokenUsage,
+    pub spans: Vec<Span>,
+    started: Instant,
+}
+
+impl Default for RunTrace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[allow(dead_code)]
+impl RunTrace {
+    pub fn new() -> Self {
+        Self {
+            duration: Duration::ZERO,
+            usage: TokenUsage::default(),
+            spans: Vec::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Opens the span of an agent; the steps recorded next belong to it
+    pub fn start_agent(&mut self, name: &str) {
+        self.spans.push(Span {
+            kind: SpanKind::Agent,
+            name: name.to_string(),
+            start: self.started.elapsed(),
+            duration: Duration::ZERO,
+            usage: TokenUsage::default(),
+            error: None,
+            children: Vec::new(),
+        });
+    }
+
+    /// Closes the span of the current agent
+    pub fn end_agent(&mut self) {
+        let elapsed = self.started.elapsed();
+        if let Some(agent) = self.spans.last_mut() {
+            agent.duration = elapsed.saturating_sub(agent.start);
+        }
+        self.duration = elapsed;
+    }
+
+    /// Records a step of the current agent that started at `started` and
+    /// took `duration`, measured by the caller when the step ended
+    pub fn record(
+        &mut self,
+        kind: SpanKind,
+        name: &str,
+        started: Instant,
+        duration: Duration,
+        usage: TokenUsage,
+        error: Option<String>,
+    ) {
+        let span = Span {
+            kind,
+            name: name.to_string(),
+            start: started.saturating_duration_since(self.started),
+            duration,
+            usage,
+            error,
+            children: Vec::new(),
+        };
+
+        self.usage.add(&usage);
+        if let Some(agent) = self.spans.last_mut() {
+            agent.usage.add(&usage);
+            agent.children.push(span);
+        }
+    }
+
+    /// Every span of the tree, depth first
+    pub fn all_spans(&self) -> Vec<&Span> {
+        fn visit<'a>(span: &'a Span, spans: &mut Vec<&'a Span>) {
+            spans.push(span);
+            for child in &span.children {
+                visit(child, spans);
+            }
+        }
+
+        let mut spans = Vec::new();
+        for span in &self.spans {
+            visit(span, &mut spans);
+        }
+        spans
+    }
+
+    /// Spans of the given kind, depth first
+    pub fn find(&self, kind: SpanKind) -> Vec<&Span> {
+        self.all_spans()
+            .into_iter()
+            .filter(|span| span.kind == kind)
+            .collect()
+    }
+}
+
+impl fmt::Display for RunTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_span(f: &mut fmt::Formatter<'_>, span: &Span, depth: usize) -> fmt::Result {
+            write!(
+                f,
+                "{}{:?} {} [{:.3}s +{:.3}s]",
+                "  ".repeat(depth),
+                span.kind,
+                span.name,
+                span.start.as_secs_f64(),
+                span.duration.as_secs_f64(),
+            )?;
+            if span.usage.total_tokens > 0 {
+                write!(
+                    f,
+                    " tokens: {} in / {} out",
+                    span.usage.input_tokens, span.usage.output_tokens
+                )?;
+            }
+            if let Some(error) = &span.error {
+                write!(f, " error: {}", error)?;
+            }
+            writeln!(f)?;
+
+            for child in &span.children {
+                write_span(f, child, depth + 1)?;
+            }
+            Ok(())
+        }
+
+        writeln!(
+            f,
+            "Run [{:.3}s] tokens: {} in / {} out",
+            self.duration.as_secs_f64(),
+            self.usage.input_tokens,
+            self.usage.output_tokens
+        )?;
+        for span in &self.spans {
+            write_span(f, span, 1)?;
+        }
+        Ok(())
+    }
+}
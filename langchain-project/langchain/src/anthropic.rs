@@ -1,5 +1,4 @@
-use std::time::Duration;
-
+pub mod batch;
 pub mod chat;
 pub mod embed;
 pub mod error;
@@ -8,12 +7,14 @@ pub mod utils;
 pub mod requests;
 
 pub static ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1/messages";
+pub static ANTHROPIC_BATCH_URL: &str = "https://api.anthropic.com/v1/messages/batches";
 pub static ANTHROPIC_EMBED_URL: &str = "https://api.voyageai.com/v1/embeddings";
 pub static ANTHROPIC_EMBEDMUL_URL: &str = "https://api.voyageai.com/v1/multimodalembeddings";
 pub static ANTHROPIC_EMBEDRANK_URL: &str = "https://api.voyageai.com/v1/rerank";
 pub static ANTHROPIC_VERSION: &str = "2023-06-01";
 
-pub const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+/// Smallest extended thinking budget the API accepts
+pub const ANTHROPIC_MIN_THINKING_BUDGET: u32 = 1024;
 
 pub const DEBUG_PRE: bool = false;
 pub const DEBUG_POST: bool = false;
\ No newline at end of file
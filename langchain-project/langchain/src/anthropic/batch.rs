@@ -0,0 +1,237 @@
+use crate::anthropic::chat::ChatAnthropic;
+use crate::anthropic::error::AnthropicError;
+use crate::anthropic::libs::{
+    BatchRequest, BatchResult, ChatResponse, CreateBatchRequest, MessageBatch,
+};
+use crate::anthropic::requests::{get_request, post_request, stream_lines};
+use crate::anthropic::utils::GetApiKey;
+use crate::anthropic::ANTHROPIC_BATCH_URL;
+use async_stream::stream;
+use futures::{pin_mut, Stream, StreamExt};
+use log::{info, warn};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::sleep;
+use crate::llm::http::{default_client, WithHttpClient};
+use reqwest::Client;
+
+/// Client of the Message Batches API
+///
+/// Requests are processed asynchronously, usually within an hour, at half
+/// the price of the Messages API. A batch holds up to 100,000 requests.
+///
+/// # Example
+/// ```ignore
+/// let llm = ChatAnthropic::new("claude-3-5-haiku-20241022").with_max_tokens(16);
+///
+/// let batch = AnthropicBatch::new()
+///     .with_request("review-1", llm.clone().with_prompt("Classify: great product"))
+///     .with_request("review-2", llm.with_prompt("Classify: broke after a day"));
+///
+/// let message_batch = batch.create().await?;
+/// let message_batch = batch.wait(&message_batch.id).await?;
+/// let responses = batch.responses(&message_batch).await?;
+/// ```
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct AnthropicBatch {
+    pub api_key: String,
+    pub base_url: String,
+    pub requests: Vec<BatchRequest>,
+    pub timeout: Duration,
+    pub poll_interval: Duration,
+    pub max_poll_interval: Duration,
+    pub client: Client,
+}
+
+#[allow(dead_code)]
+impl AnthropicBatch {
+    pub fn new() -> Self {
+        let api_key: String = match Self::get_api_key() {
+            Ok(api_key) => api_key,
+            Err(_) => "not_key".to_string()
+        };
+
+        Self {
+            api_key,
+            base_url: ANTHROPIC_BATCH_URL.to_string(),
+            requests: Vec::new(),
+            timeout: Duration::from_secs(300), // default: 5 minutes
+            poll_interval: Duration::from_secs(10),
+            max_poll_interval: Duration::from_secs(300),
+            client: default_client(),
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = api_key.to_string();
+        self
+    }
+
+    /// Overrides the batches endpoint (default: `ANTHROPIC_BATCH_URL`)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_timeout_sec(mut self, timeout: u64) -> Self {
+        self.timeout = Duration::from_secs(timeout);
+        self
+    }
+
+    /// First and longest wait between two status checks in `wait`; the wait
+    /// doubles after every check (default: 10 seconds, up to 5 minutes)
+    pub fn with_poll_interval(mut self, poll_interval: Duration, max_poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self.max_poll_interval = max_poll_interval.max(poll_interval);
+        self
+    }
+
+    /// Adds the request `llm` would send, identified by `custom_id`
+    pub fn with_request(mut self, custom_id: &str, llm: ChatAnthropic) -> Self {
+        let mut params = llm.request;
+        params.stream = false;
+
+        self.requests.push(BatchRequest {
+            custom_id: custom_id.to_string(),
+            params,
+        });
+        self
+    }
+
+    /// Submits the requests added with `with_request` as a new batch
+    pub async fn create(&self) -> Result<MessageBatch, AnthropicError> {
+        let request = CreateBatchRequest {
+            requests: self.requests.clone(),
+        };
+        let request_body = serde_json::to_vec(&request)?;
+
+        let response = post_request(&self.client, &self.base_url, &self.api_key, &request_body, self.timeout).await?;
+        let message_batch: MessageBatch = serde_json::from_value(response)?;
+
+        info!("Created batch {} with {} requests", message_batch.id, self.requests.len());
+        Ok(message_batch)
+    }
+
+    /// Current status and request counts of a batch
+    pub async fn retrieve(&self, batch_id: &str) -> Result<MessageBatch, AnthropicError> {
+        let url = format!("{}/{}", self.base_url, batch_id);
+        let response = get_request(&self.client, &url, &self.api_key).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Cancels a batch; requests already being processed still complete
+    pub async fn cancel(&self, batch_id: &str) -> Result<MessageBatch, AnthropicError> {
+        let url = format!("{}/{}/cancel", self.base_url, batch_id);
+        let response = post_request(&self.client, &url, &self.api_key, b"{}", self.timeout).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Polls the batch until its processing ends, waiting longer after
+    /// every check
+    pub async fn wait(&self, batch_id: &str) -> Result<MessageBatch, AnthropicError> {
+        let mut poll_interval = self.poll_interval;
+
+        loop {
+            let message_batch = self.retrieve(batch_id).await?;
+            if message_batch.is_ended() {
+                return Ok(message_batch);
+            }
+
+            let counts = &message_batch.request_counts;
+            info!(
+                "Batch {} is {}: {} processing, {} succeeded, {} errored",
+                batch_id,
+                message_batch.processing_status,
+                counts.processing,
+                counts.succeeded,
+                counts.errored,
+            );
+
+            sleep(poll_interval).await;
+            poll_interval = (poll_interval * 2).min(self.max_poll_interval);
+        }
+    }
+
+    /// Streams the results of an ended batch, in no particular order
+    ///
+    /// # Errors
+    /// * `BatchNotEnded` - The batch has no `results_url` yet
+    /// * Request errors end the stream; a line that cannot be parsed is
+    ///   yielded as `JsonError` and the stream continues
+    pub fn results(
+        &self,
+        message_batch: &MessageBatch,
+    ) -> impl Stream<Item = Result<BatchResult, AnthropicError>> {
+        let results_url = message_batch.results_url.clone();
+        let batch_id = message_batch.id.clone();
+        let api_key = self.api_key.clone();
+        let client = self.client.clone();
+
+        stream! {
+            let results_url = match results_url {
+                Some(results_url) => results_url,
+                None => {
+                    yield Err(AnthropicError::BatchNotEnded(batch_id));
+                    return;
+                }
+            };
+
+            let lines = stream_lines(client, results_url, api_key);
+            pin_mut!(lines);
+
+            while let Some(line) = lines.next().await {
+                match line {
+                    Ok(line) => match serde_json::from_str::<BatchResult>(&line) {
+                        Ok(result) => yield Ok(result),
+                        Err(e) => {
+                            warn!("Error parsing batch result: {}", e);
+                            yield Err(AnthropicError::JsonError(e));
+                        }
+                    },
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Collects the results of an ended batch by `custom_id`, each one the
+    /// message of the request or the reason it failed
+    pub async fn responses(
+        &self,
+        message_batch: &MessageBatch,
+    ) -> Result<HashMap<String, Result<ChatResponse, AnthropicError>>, AnthropicError> {
+        let results = self.results(message_batch);
+        pin_mut!(results);
+
+        let mut responses = HashMap::new();
+        while let Some(result) = results.next().await {
+            let result = result?;
+            responses.insert(result.custom_id.clone(), result.response());
+        }
+
+        Ok(responses)
+    }
+}
+
+impl WithHttpClient for AnthropicBatch {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn with_config_base_url(self, base_url: &str) -> Self {
+        self.with_base_url(base_url)
+    }
+}
+
+impl Default for AnthropicBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GetApiKey for AnthropicBatch {}
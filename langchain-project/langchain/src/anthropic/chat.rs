@@ -1,23 +1,41 @@
 use crate::anthropic::libs::{
-    ChatRequest, Content, Message, ChatResponse,
-    Source,
+    CacheControl, ChatRequest, Content, Message, ChatResponse,
+    Source, StreamEvent, SystemBlock, SystemPrompt, ThinkingConfig,
 };
 use crate::anthropic::utils::{
-    GetApiKey, read_file_data,
+    GetApiKey, read_file_data, to_anthropic_messages, to_anthropic_tools,
+    to_anthropic_tool_choice, to_model_response, StreamAssembler,
 };
-use crate::anthropic::requests::request_chat;
+use crate::llm::chat::ChatModel;
+use crate::llm::error::{ApiError, LlmError};
+use crate::llm::retry::{RateLimiter, RetryPolicy};
+use crate::llm::libs::{
+    ChatMessage, ChatOptions, ModelResponse, Provider,
+};
+use async_trait::async_trait;
+use crate::anthropic::requests::{request_chat, strem_chat};
+use async_stream::stream;
+use futures::pin_mut;
+use futures::StreamExt;
 use crate::anthropic::error::AnthropicError;
+use crate::anthropic::{ANTHROPIC_BASE_URL, ANTHROPIC_MIN_THINKING_BUDGET};
 use serde_json::Value;
 use std::time::Duration;
 use log::error;
+use crate::llm::http::{default_client, WithHttpClient};
+use reqwest::Client;
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct ChatAnthropic {
     pub api_key: String,
+    pub base_url: String,
     pub request: ChatRequest,
     pub timeout: Duration,
-    pub max_retries: u32,
+    pub retry_policy: RetryPolicy,
+    pub rate_limiter: Option<RateLimiter>,
+    pub prompt_cache: Option<CacheControl>,
+    pub client: Client,
 }
 
 #[allow(dead_code)]
@@ -36,22 +54,34 @@ impl ChatAnthropic {
             max_tokens: Some(1024),
             tools: None,
             tool_choice: None,
+            thinking: None,
             stream: false,
         };
         
         Self {
             api_key: api_key,
+            base_url: ANTHROPIC_BASE_URL.to_string(),
             request: request,
             timeout: Duration::from_secs(300), // default: 5 minutes
-            max_retries: 3,         // default: 3 times
+            retry_policy: RetryPolicy::default(), // default: 3 retries
+            rate_limiter: None,
+            prompt_cache: None,
+            client: default_client(),
         }
     }
 
     pub async fn invoke(
-        mut self,
+        self,
         prompt: &str,
     ) -> Result<ChatResponse, AnthropicError> {
+        match self.with_prompt(prompt).send_request().await {
+            Ok(response) => Ok(response),
+            Err(e) => Err(e),
+        }
+    }
 
+    /// Adds a user message without sending the request, e.g. to build a batch
+    pub fn with_prompt(mut self, prompt: &str) -> Self {
         let content = vec![Content {
             content_type: "text".to_string(),
             text: Some(prompt.to_string()),
@@ -63,6 +93,10 @@ impl ChatAnthropic {
             input: None,
             content: None,
             tool_use_id: None,
+            thinking: None,
+            signature: None,
+            data: None,
+            cache_control: None,
         }];
 
         let new_message = Message {
@@ -76,10 +110,7 @@ impl ChatAnthropic {
             self.request.messages = Some(vec![new_message]);
         }
 
-        match self.send_request().await {
-            Ok(response) => Ok(response),
-            Err(e) => Err(e),
-        }
+        self
     }
 
     pub async fn with_tool_result(
@@ -98,6 +129,10 @@ impl ChatAnthropic {
             input: None,
             content: Some(content.to_string()),
             tool_use_id: Some(tool_id.to_string()),
+            thinking: None,
+            signature: None,
+            data: None,
+            cache_control: None,
         }];
 
         let new_message = Message {
@@ -118,16 +153,23 @@ impl ChatAnthropic {
     }
 
     pub async fn send_request(self) -> Result<ChatResponse, AnthropicError> {
+        if self.request.stream {
+            return self.send_stream_request().await;
+        }
+
         let response: String = match request_chat(
+            &self.client,
             &self.request,
+            &self.base_url,
             &self.api_key,
             self.timeout,
-            self.max_retries,
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
         ).await {
             Ok(response) => response,
             Err(e) => {
                 error!("Error {:?}", e);
-                return Err(AnthropicError::ResponseContentError);
+                return Err(e);
             }
         };
 
@@ -135,13 +177,13 @@ impl ChatAnthropic {
             Ok(response_form) => response_form,
             Err(e) => {
                 error!("Error {:?}", e);
-                return Err(AnthropicError::ResponseContentError);
+                return Err(e.into());
             }
         };
 
         if let Some(error) = chat_response.error {
             error!("Error {}", error.message);
-            return Err(AnthropicError::ResponseContentError);
+            Err(AnthropicError::ApiError(ApiError::from_body(Provider::Anthropic, &response)))
         } else {
             let format_response: ChatResponse = ChatResponse {
                 id: chat_response.id,
@@ -160,6 +202,96 @@ impl ChatAnthropic {
         }
     }
 
+    /// Streams the answer to `prompt` as typed server-sent events
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let stream = ChatAnthropic::new("claude-3-5-haiku-20241022")
+    ///     .stream_response("Tell me a story".to_string());
+    /// pin_mut!(stream);
+    ///
+    /// while let Some(event) = stream.next().await {
+    ///     if let StreamEvent::ContentBlockDelta {
+    ///         delta: ContentDelta::TextDelta { text }, ..
+    ///     } = event? {
+    ///         print!("{}", text);
+    ///     }
+    /// }
+    /// ```
+    pub fn stream_response(
+        mut self,
+        prompt: String,  // Don't change type for stream
+    ) -> impl futures::Stream<Item = Result<StreamEvent, AnthropicError>> {
+        stream! {
+            let content = vec![Content {
+                content_type: "text".to_string(),
+                text: Some(prompt),
+                ..Default::default()
+            }];
+
+            let new_message = Message {
+                role: "user".to_string(),
+                content,
+            };
+
+            if let Some(messages) = &mut self.request.messages {
+                messages.push(new_message);
+            } else {
+                self.request.messages = Some(vec![new_message]);
+            }
+
+            self.request.stream = true;
+
+            let stream = strem_chat(
+                self.client.clone(),
+                self.request.clone(),
+                self.base_url.clone(),
+                self.api_key.clone(),
+                self.retry_policy.clone(),
+                self.rate_limiter.clone(),
+            );
+
+            pin_mut!(stream);
+
+            while let Some(event) = stream.next().await {
+                yield event;
+            }
+        }
+    }
+
+    /// Same as `invoke`, but receives the answer as a stream and assembles
+    /// the events into a `ChatResponse`
+    pub async fn invoke_stream(
+        mut self,
+        prompt: &str,
+    ) -> Result<ChatResponse, AnthropicError> {
+        self.request.stream = true;
+        self.invoke(prompt).await
+    }
+
+    async fn send_stream_request(self) -> Result<ChatResponse, AnthropicError> {
+        let stream = strem_chat(
+            self.client.clone(),
+            self.request.clone(),
+            self.base_url.clone(),
+            self.api_key.clone(),
+            self.retry_policy.clone(),
+            self.rate_limiter.clone(),
+        );
+
+        pin_mut!(stream);
+
+        let mut assembler = StreamAssembler::new();
+        while let Some(event) = stream.next().await {
+            assembler.push(event?);
+        }
+
+        let mut chat_response = assembler.finish()?;
+        chat_response.chat_history = self.request.messages;
+        Ok(chat_response)
+    }
+
     pub fn with_timeout_sec(mut self, timeout: u64) -> Self {
         self.timeout = Duration::from_secs(timeout);
         self
@@ -175,11 +307,43 @@ impl ChatAnthropic {
         self
     }
 
+    /// Sets the maximum tokens to generate; with thinking enabled, it is
+    /// raised above the thinking budget
     pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
         self.request.max_tokens = Some(max_tokens);
+        self.fit_max_tokens();
+        self
+    }
+
+    /// Enables extended thinking with up to `budget_tokens` tokens to think
+    ///
+    /// Budgets below the API minimum are raised to `ANTHROPIC_MIN_THINKING_BUDGET`.
+    /// Thinking tokens count toward `max_tokens`, so `max_tokens` is raised
+    /// above the budget when needed. The `thinking` blocks of the response
+    /// must be sent back unchanged with `with_assistant_content` when the
+    /// conversation continues after a tool call.
+    pub fn with_thinking(mut self, budget_tokens: u32) -> Self {
+        self.request.thinking = Some(ThinkingConfig {
+            thinking_type: "enabled".to_string(),
+            budget_tokens: Some(budget_tokens.max(ANTHROPIC_MIN_THINKING_BUDGET)),
+        });
+        self.fit_max_tokens();
         self
     }
 
+    /// Keeps room for the answer when `max_tokens` is not above the thinking budget
+    fn fit_max_tokens(&mut self) {
+        let budget_tokens = match &self.request.thinking {
+            Some(ThinkingConfig { budget_tokens: Some(budget_tokens), .. }) => *budget_tokens,
+            _ => return,
+        };
+
+        let max_tokens = self.request.max_tokens.unwrap_or(1024);
+        if max_tokens <= budget_tokens {
+            self.request.max_tokens = Some(budget_tokens.saturating_add(max_tokens));
+        }
+    }
+
     pub fn with_tools(mut self, tools: Vec<Value>, tool_choice: Option<Value>) -> Self {
         // https://docs.anthropic.com/en/docs/build-with-claude/tool-use#controlling-claudes-output
         self.request.tools = Some(tools);
@@ -188,12 +352,64 @@ impl ChatAnthropic {
     }
 
     pub fn with_system_prompt(mut self, system_prompt: &str) -> Self {
-        self.request.system = Some(system_prompt.to_string());
+        self.request.system = Some(SystemPrompt::Text(system_prompt.to_string()));
+        self
+    }
+
+    pub fn with_system_blocks(mut self, system_blocks: Vec<SystemBlock>) -> Self {
+        self.request.system = Some(SystemPrompt::Blocks(system_blocks));
+        self
+    }
+
+    /// System prompt with a cache breakpoint at its end
+    pub fn with_cached_system_prompt(
+        mut self,
+        system_prompt: &str,
+        cache_control: CacheControl,
+    ) -> Self {
+        let block = SystemBlock::text(system_prompt).with_cache_control(cache_control);
+        self.request.system = Some(SystemPrompt::Blocks(vec![block]));
+        self
+    }
+
+    /// Cache breakpoint on the last tool, so the tool definitions are cached.
+    /// Call it after `with_tools`.
+    pub fn with_tools_cache(mut self, cache_control: CacheControl) -> Self {
+        if let Some(tool) = self.request.tools.as_mut().and_then(|tools| tools.last_mut()) {
+            set_tool_cache_control(tool, &cache_control);
+        }
+        self
+    }
+
+    /// Cache breakpoint on the last content block of the conversation so far
+    pub fn with_cache_breakpoint(mut self, cache_control: CacheControl) -> Self {
+        set_message_cache_control(&mut self.request.messages, &cache_control);
+        self
+    }
+
+    /// Cache breakpoints set by `generate` on the system prompt, the last tool
+    /// and the last message, so each turn of a conversation reads the previous
+    /// one from the cache
+    pub fn with_prompt_cache(mut self, cache_control: CacheControl) -> Self {
+        self.prompt_cache = Some(cache_control);
         self
     }
 
     pub fn with_max_retries(mut self, retry: u32) -> Self {
-        self.max_retries = retry;
+        self.retry_policy.max_retries = retry;
+        self
+    }
+
+    /// Replaces the retry policy, e.g. to change the backoff delays
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limits the requests and tokens per minute; clone the same limiter
+    /// into every client that shares the account
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
         self
     }
 
@@ -224,6 +440,10 @@ impl ChatAnthropic {
             input: None,
             content: None,
             tool_use_id: None,
+            thinking: None,
+            signature: None,
+            data: None,
+            cache_control: None,
         }];
 
         let new_message = Message {
@@ -266,6 +486,10 @@ impl ChatAnthropic {
             input: None,
             content: None,
             tool_use_id: None,
+            thinking: None,
+            signature: None,
+            data: None,
+            cache_control: None,
         }];
 
         let new_message = Message {
@@ -287,6 +511,12 @@ impl ChatAnthropic {
         self
     }
 
+    /// Overrides the messages endpoint (default: `ANTHROPIC_BASE_URL`)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.to_string();
+        self
+    }
+
     pub fn with_image_file(
         mut self, 
         file_path: &str, 
@@ -316,6 +546,10 @@ impl ChatAnthropic {
             input: None,
             content: None,
             tool_use_id: None,
+            thinking: None,
+            signature: None,
+            data: None,
+            cache_control: None,
         }];
 
         let new_message = Message {
@@ -333,4 +567,99 @@ impl ChatAnthropic {
     }
 }
 
-impl GetApiKey for ChatAnthropic {}
\ No newline at end of file
+impl WithHttpClient for ChatAnthropic {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn with_config_base_url(self, base_url: &str) -> Self {
+        self.with_base_url(base_url)
+    }
+}
+
+impl GetApiKey for ChatAnthropic {}
+
+#[async_trait]
+impl ChatModel for ChatAnthropic {
+    fn provider(&self) -> Provider {
+        Provider::Anthropic
+    }
+
+    fn model_name(&self) -> String {
+        self.request.model.clone()
+    }
+
+    async fn generate(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatOptions,
+    ) -> Result<ModelResponse, LlmError> {
+        let mut llm = self.clone();
+
+        // No structured output mode, the schema goes in the system prompt
+        let mut messages = messages.to_vec();
+        if let Some(output_schema) = &options.output_schema {
+            messages.push(ChatMessage::system(&output_schema.instructions()));
+        }
+        let (system, anthropic_messages) = to_anthropic_messages(&messages);
+
+        if let Some(system) = system {
+            llm.request.system = Some(SystemPrompt::Text(system));
+        }
+        llm.request.messages = Some(anthropic_messages);
+
+        if !options.tools.is_empty() {
+            llm.request.tools = Some(to_anthropic_tools(&options.tools));
+        }
+        if let Some(tool_choice) = &options.tool_choice {
+            llm.request.tool_choice = Some(to_anthropic_tool_choice(tool_choice));
+        }
+        if let Some(temperature) = options.temperature {
+            llm.request.temperature = Some(temperature);
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            llm.request.max_tokens = Some(max_tokens);
+        }
+
+        // Thinking does not accept temperature changes
+        if llm.request.thinking.is_some() {
+            llm.request.temperature = None;
+            llm.fit_max_tokens();
+        }
+
+        if let Some(cache_control) = &llm.prompt_cache {
+            llm.request.system = llm.request.system
+                .take()
+                .map(|system| system.with_cache_control(cache_control.clone()));
+            if let Some(tool) = llm.request.tools.as_mut().and_then(|tools| tools.last_mut()) {
+                set_tool_cache_control(tool, cache_control);
+            }
+            set_message_cache_control(&mut llm.request.messages, cache_control);
+        }
+
+        let response = llm.send_request().await?;
+        Ok(to_model_response(response))
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Prompt caching ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+fn set_tool_cache_control(tool: &mut Value, cache_control: &CacheControl) {
+    if let Some(tool) = tool.as_object_mut() {
+        tool.insert(
+            "cache_control".to_string(),
+            serde_json::to_value(cache_control).unwrap_or_default(),
+        );
+    }
+}
+
+fn set_message_cache_control(messages: &mut Option<Vec<Message>>, cache_control: &CacheControl) {
+    let content = messages
+        .as_mut()
+        .and_then(|messages| messages.last_mut())
+        .and_then(|message| message.content.last_mut());
+    if let Some(content) = content {
+        content.cache_control = Some(cache_control.clone());
+    }
+}
\ No newline at end of file
@@ -2,19 +2,40 @@ use crate::anthropic::requests::request_embed;
 use crate::anthropic::utils::GetApiKeyVoyage;
 use crate::anthropic::error::AnthropicError;
 use crate::anthropic::libs::{
-    EmbedRequest, Content, InputEmbed, EmbedContent,
-    EmbedResponse, AnthropicEmbedEndpoint
+    EmbedRequest, Content, InputEmbed, EmbedContent, EmbedResponse,
 };
+use crate::anthropic::{ANTHROPIC_EMBED_URL, ANTHROPIC_EMBEDMUL_URL, ANTHROPIC_EMBEDRANK_URL};
+use crate::llm::embed::{embed_batches, Embeddings, Reranker};
+use crate::llm::error::LlmError;
+use crate::llm::libs::Provider;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt, TryStreamExt};
 use log::error;
+use crate::llm::http::{default_client, WithHttpClient};
+use crate::llm::retry::{RateLimiter, RetryPolicy};
+use reqwest::Client;
 
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Embed Voyage ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
 
+/// Inputs per request accepted by the Voyage embeddings endpoint
+pub const VOYAGE_EMBED_MAX_INPUTS: usize = 1000;
+
+/// Tokens per request accepted by voyage-3 and voyage-3-large, all inputs added
+pub const VOYAGE_EMBED_MAX_TOKENS: usize = 120_000;
+
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct EmbedVoyage {
     pub model: String,
+    pub base_url: String,
     pub request: EmbedRequest,
     pub api_key: String,
+    pub batch_size: usize,
+    pub max_batch_tokens: usize,
+    pub max_concurrency: usize,
+    pub retry_policy: RetryPolicy,
+    pub rate_limiter: Option<RateLimiter>,
+    pub client: Client,
 }
 
 #[allow(dead_code)]
@@ -42,8 +63,15 @@ impl EmbedVoyage {
 
         Self {
             model: model.to_string(),
+            base_url: ANTHROPIC_EMBED_URL.to_string(),
             request: request,
             api_key: api_key,
+            batch_size: VOYAGE_EMBED_MAX_INPUTS,
+            max_batch_tokens: VOYAGE_EMBED_MAX_TOKENS,
+            max_concurrency: 4,
+            retry_policy: RetryPolicy::default(), // default: 3 retries
+            rate_limiter: None,
+            client: default_client(),
         }
     }
 
@@ -53,12 +81,13 @@ impl EmbedVoyage {
     ) -> Result<EmbedResponse, AnthropicError> {
         self.request.input = Some(input);
 
-        let endpoint = AnthropicEmbedEndpoint::Embed;
-
         let response: String = match request_embed(
+            &self.client,
             &self.request,
+            &self.base_url,
             &self.api_key,
-            endpoint
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
         ).await {
             Ok(response) => response,
             Err(error) => {
@@ -82,16 +111,145 @@ impl EmbedVoyage {
         }
     }
 
+    /// Embeds many texts and returns their vectors in input order
+    ///
+    /// The texts are sent as array inputs, split by `batch_size` and
+    /// `max_batch_tokens`, with up to `max_concurrency` requests at a time.
+    ///
+    /// # Errors
+    /// The first failed request; the vectors already computed are dropped.
+    pub async fn embed_documents(&self, documents: Vec<String>) -> Result<Vec<Vec<f32>>, AnthropicError> {
+        let batches = embed_batches(&documents, self.batch_size, self.max_batch_tokens);
+
+        let requests = batches.into_iter().map(|range| {
+            let inputs = documents[range].to_vec();
+            async move { self.embed_batch(inputs).await }
+        });
+
+        let batches: Vec<Vec<Vec<f32>>> = stream::iter(requests)
+            .buffered(self.max_concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(batches.into_iter().flatten().collect())
+    }
+
+    async fn embed_batch(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, AnthropicError> {
+        let expected = inputs.len();
+        let response = self.clone().embed_content(InputEmbed::Array(inputs)).await?;
+
+        let mut data = response.data.unwrap_or_default();
+        if data.len() != expected {
+            return Err(AnthropicError::VoyageError(format!(
+                "Expected {} embeddings, got {}",
+                expected,
+                data.len()
+            )));
+        }
+
+        data.sort_by_key(|embedding| embedding.index);
+        Ok(data
+            .into_iter()
+            .map(|embedding| {
+                embedding
+                    .embedding
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|value| value as f32)
+                    .collect()
+            })
+            .collect())
+    }
+
     pub fn with_dimensions(mut self, dimensions: u32) -> Self {
         // Only supported in text-embedding-3 and later models
         self.request.output_dimension = Some(dimensions);
         self
     }
 
+    /// Tells the model what the texts are used for: `"query"` or `"document"`
+    pub fn with_input_type(mut self, input_type: &str) -> Self {
+        self.request.input_type = Some(input_type.to_string());
+        self
+    }
+
     pub fn with_api_key(mut self, api_key: &str) -> Self {
         self.api_key = api_key.to_string();
         self
     }
+
+    /// Overrides the embeddings endpoint (default: `ANTHROPIC_EMBED_URL`)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.to_string();
+        self
+    }
+
+    /// Limits of one `embed_documents` request: inputs (default and maximum:
+    /// 1000) and estimated tokens (default: 120,000)
+    pub fn with_batch_size(mut self, batch_size: usize, max_batch_tokens: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, VOYAGE_EMBED_MAX_INPUTS);
+        self.max_batch_tokens = max_batch_tokens.max(1);
+        self
+    }
+
+    /// Requests sent at the same time by `embed_documents` (default: 4)
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Replaces the retry policy, e.g. to change the backoff delays
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limits the requests and tokens per minute; clone the same limiter
+    /// into every client that shares the account
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+}
+
+impl WithHttpClient for EmbedVoyage {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn with_config_base_url(self, base_url: &str) -> Self {
+        self.with_base_url(base_url)
+    }
+}
+
+#[async_trait]
+impl Embeddings for EmbedVoyage {
+    fn provider(&self) -> Provider {
+        Provider::Anthropic
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    async fn embed_documents(&self, documents: Vec<String>) -> Result<Vec<Vec<f32>>, LlmError> {
+        if self.request.input_type.is_none() {
+            let embed = self.clone().with_input_type("document");
+            return Ok(EmbedVoyage::embed_documents(&embed, documents).await?);
+        }
+        Ok(EmbedVoyage::embed_documents(self, documents).await?)
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>, LlmError> {
+        let mut embed = self.clone();
+        if matches!(embed.request.input_type.as_deref(), None | Some("document")) {
+            embed.request.input_type = Some("query".to_string());
+        }
+
+        let mut vectors = EmbedVoyage::embed_documents(&embed, vec![text.to_string()]).await?;
+        vectors.pop().ok_or(LlmError::EmptyResponse)
+    }
 }
 
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Embed Multi Voyage ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
@@ -102,6 +260,9 @@ pub struct EmbedMultiVoyage {
     pub model: String,
     pub request: EmbedRequest,
     pub api_key: String,
+    pub retry_policy: RetryPolicy,
+    pub rate_limiter: Option<RateLimiter>,
+    pub client: Client,
 }
 
 #[allow(dead_code)]
@@ -131,9 +292,25 @@ impl EmbedMultiVoyage {
             model: model.to_string(),
             request: request,
             api_key: api_key,
+            retry_policy: RetryPolicy::default(), // default: 3 retries
+            rate_limiter: None,
+            client: default_client(),
         }
     }
 
+    /// Replaces the retry policy, e.g. to change the backoff delays
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limits the requests and tokens per minute; clone the same limiter
+    /// into every client that shares the account
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
     pub async fn embed_content(
         mut self, 
         input_str: &str
@@ -149,6 +326,10 @@ impl EmbedMultiVoyage {
             input: None,
             content: None,
             tool_use_id: None,
+            thinking: None,
+            signature: None,
+            data: None,
+            cache_control: None,
         };
 
         let embed_content = EmbedContent {
@@ -161,12 +342,13 @@ impl EmbedMultiVoyage {
             self.request.inputs = Some(vec![embed_content]);
         }
         
-        let endpoint = AnthropicEmbedEndpoint::MultimodalEmbed;
-
         let response: String = match request_embed(
+            &self.client,
             &self.request,
+            ANTHROPIC_EMBEDMUL_URL,
             &self.api_key,
-            endpoint,
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
         ).await {
             Ok(response) => response,
             Err(e) => {
@@ -202,6 +384,10 @@ impl EmbedMultiVoyage {
             input: None,
             content: None,
             tool_use_id: None,
+            thinking: None,
+            signature: None,
+            data: None,
+            cache_control: None,
         };
 
         let embed_content = EmbedContent {
@@ -235,6 +421,10 @@ impl EmbedMultiVoyage {
             input: None,
             content: None,
             tool_use_id: None,
+            thinking: None,
+            signature: None,
+            data: None,
+            cache_control: None,
         };
 
         let embed_content = EmbedContent {
@@ -251,14 +441,25 @@ impl EmbedMultiVoyage {
     }
 }
 
+impl WithHttpClient for EmbedMultiVoyage {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Embed Rank Voyage ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct EmbedRankVoyage {
     pub model: String,
+    pub base_url: String,
     pub request: EmbedRequest,
     pub api_key: String,
+    pub retry_policy: RetryPolicy,
+    pub rate_limiter: Option<RateLimiter>,
+    pub client: Client,
 }
 
 #[allow(dead_code)]
@@ -286,8 +487,12 @@ impl EmbedRankVoyage {
 
         Self {
             model: model.to_string(),
+            base_url: ANTHROPIC_EMBEDRANK_URL.to_string(),
             request: request,
             api_key: api_key,
+            retry_policy: RetryPolicy::default(), // default: 3 retries
+            rate_limiter: None,
+            client: default_client(),
         }
     }
 
@@ -297,12 +502,13 @@ impl EmbedRankVoyage {
     ) -> Result<EmbedResponse, AnthropicError> {
         self.request.query = Some(input_str.to_string());
 
-        let endpoint = AnthropicEmbedEndpoint::Rerank;
-        
         let response: String = match request_embed(
+            &self.client,
             &self.request,
+            &self.base_url,
             &self.api_key,
-            endpoint,
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
         ).await {
             Ok(response) => response,
             Err(error) => {
@@ -326,10 +532,90 @@ impl EmbedRankVoyage {
         }
     }
 
+    /// Scores the documents against the query and returns their indexes
+    /// with the relevance scores, most relevant first
+    ///
+    /// `top_k` keeps only the best documents; `None` returns all of them.
+    pub async fn rerank(
+        &self,
+        query: &str,
+        documents: Vec<String>,
+        top_k: Option<usize>,
+    ) -> Result<Vec<(usize, f32)>, AnthropicError> {
+        let mut rank = self.clone().with_documents(documents);
+        rank.request.top_k = top_k.map(|top_k| top_k as u32);
+
+        let response = rank.embed_content(query).await?;
+        let mut ranked: Vec<(usize, f32)> = response
+            .data
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|data| Some((data.index?, data.relevance_score? as f32)))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(ranked)
+    }
+
     pub fn with_documents(mut self, documents: Vec<String>) -> Self {
         self.request.documents = Some(documents);
         self
     }
+
+    pub fn with_top_k(mut self, top_k: u32) -> Self {
+        self.request.top_k = Some(top_k);
+        self
+    }
+
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = api_key.to_string();
+        self
+    }
+
+    /// Overrides the rerank endpoint (default: `ANTHROPIC_EMBEDRANK_URL`)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.to_string();
+        self
+    }
+
+    /// Replaces the retry policy, e.g. to change the backoff delays
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limits the requests and tokens per minute; clone the same limiter
+    /// into every client that shares the account
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+}
+
+impl WithHttpClient for EmbedRankVoyage {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn with_config_base_url(self, base_url: &str) -> Self {
+        self.with_base_url(base_url)
+    }
+}
+
+#[async_trait]
+impl Reranker for EmbedRankVoyage {
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    async fn rerank(
+        &self,
+        query: &str,
+        documents: Vec<String>,
+        top_k: Option<usize>,
+    ) -> Result<Vec<(usize, f32)>, LlmError> {
+        Ok(EmbedRankVoyage::rerank(self, query, documents, top_k).await?)
+    }
 }
 
 impl GetApiKeyVoyage for EmbedVoyage {}
@@ -1,8 +1,13 @@
+use crate::llm::error::ApiError;
 use std::env;
 
 #[allow(dead_code)]
 #[derive(Debug, thiserror::Error)]
 pub enum AnthropicError {
+    /// An error answered by the API, with its status, code and body
+    #[error("{0}")]
+    ApiError(ApiError),
+
     #[error("API Connection Error: Issue connecting to services. Check network settings, proxy configuration, SSL certificates, or firewall rules. {0}")]
     APIConnectionError(String),
 
@@ -62,6 +67,9 @@ pub enum AnthropicError {
 
     #[error("Error in Voyage's API. {0}")]
     VoyageError(String),
+
+    #[error("Batch {0} has no results yet, wait until its processing ends")]
+    BatchNotEnded(String),
     
     #[error("{message}")]
     GenericError {
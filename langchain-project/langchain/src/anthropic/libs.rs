@@ -1,3 +1,6 @@
+use crate::anthropic::error::AnthropicError;
+use crate::anthropic::requests::error_from_details;
+use crate::llm::libs::TokenUsage;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -8,7 +11,7 @@ pub struct ChatRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub messages: Option<Vec<Message>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub system: Option<String>,
+    pub system: Option<SystemPrompt>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -17,9 +20,134 @@ pub struct ChatRequest {
     pub tools: Option<Vec<Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<ThinkingConfig>,
     pub stream: bool,
 }
 
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Prompt caching ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// How long a cached prompt prefix is kept after its last use
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CacheTtl {
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+}
+
+/// Cache breakpoint set on a system block, a tool or a content block
+///
+/// The prompt prefix up to and including the marked block (tools, then
+/// system, then messages) is cached, and later requests with the same
+/// prefix read it at a lower price. Up to 4 breakpoints per request.
+///
+/// # Fields
+/// * `cache_type` - Always `ephemeral`
+/// * `ttl` - Cache lifetime, 5 minutes when not set
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CacheControl {
+    #[serde(rename = "type")]
+    pub cache_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<CacheTtl>,
+}
+
+#[allow(dead_code)]
+impl CacheControl {
+    pub fn ephemeral() -> Self {
+        Self {
+            cache_type: "ephemeral".to_string(),
+            ttl: None,
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: CacheTtl) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+}
+
+/// Text block of a system prompt
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SystemBlock {
+    #[serde(rename = "type")]
+    pub block_type: String,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+}
+
+#[allow(dead_code)]
+impl SystemBlock {
+    pub fn text(text: &str) -> Self {
+        Self {
+            block_type: "text".to_string(),
+            text: text.to_string(),
+            cache_control: None,
+        }
+    }
+
+    pub fn with_cache_control(mut self, cache_control: CacheControl) -> Self {
+        self.cache_control = Some(cache_control);
+        self
+    }
+}
+
+/// System prompt, as plain text or as blocks that can carry cache breakpoints
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum SystemPrompt {
+    Text(String),
+    Blocks(Vec<SystemBlock>),
+}
+
+#[allow(dead_code)]
+impl SystemPrompt {
+    /// Text of the prompt, blocks joined with blank lines
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Blocks(blocks) => blocks
+                .iter()
+                .map(|block| block.text.as_str())
+                .collect::<Vec<&str>>()
+                .join("\n\n"),
+        }
+    }
+
+    /// Sets a cache breakpoint on the last block, turning text into a block
+    pub fn with_cache_control(self, cache_control: CacheControl) -> Self {
+        let mut blocks = match self {
+            Self::Text(text) => vec![SystemBlock::text(&text)],
+            Self::Blocks(blocks) => blocks,
+        };
+        if let Some(block) = blocks.last_mut() {
+            block.cache_control = Some(cache_control);
+        }
+        Self::Blocks(blocks)
+    }
+}
+
+/// Extended thinking settings of a request
+///
+/// # Fields
+/// * `thinking_type` - `enabled` or `disabled`
+/// * `budget_tokens` - Tokens Claude may use to think, at least 1024 and
+///   less than `max_tokens`
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ThinkingConfig {
+    #[serde(rename = "type")]
+    pub thinking_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget_tokens: Option<u32>,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(untagged)] // Allows for multiple types of input
@@ -98,7 +226,7 @@ pub struct Message {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Content {
     #[serde(rename = "type")]
     pub content_type: String,
@@ -120,6 +248,14 @@ pub struct Content {
     pub input: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
 }
 
 #[allow(dead_code)]
@@ -132,7 +268,7 @@ pub struct Source {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct ChatResponse {
     pub content: Option<Vec<Content>>,
     pub id: Option<String>,
@@ -159,6 +295,211 @@ pub struct Usage {
     pub text_tokens: Option<u32>,
 }
 
+#[allow(dead_code)]
+impl Usage {
+    /// Normalized usage; Anthropic counts cached prompt tokens apart from
+    /// `input_tokens`, they are added back here
+    pub fn token_usage(&self) -> TokenUsage {
+        let cache_creation = self.cache_creation_input_tokens.unwrap_or(0);
+        let cache_read = self.cache_read_input_tokens.unwrap_or(0);
+        let input_tokens = self.input_tokens.unwrap_or(0) + cache_creation + cache_read;
+
+        TokenUsage::new(input_tokens, self.output_tokens.unwrap_or(0))
+            .with_cache(cache_creation, cache_read)
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Streaming ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Server-sent event of a streamed Messages API response
+///
+/// A stream is made of one `MessageStart`, then for each content block a
+/// `ContentBlockStart`, any number of `ContentBlockDelta` and a
+/// `ContentBlockStop`, followed by `MessageDelta` and `MessageStop`. `Ping`
+/// events may appear anywhere. Event types added to the API later are
+/// decoded as `Unknown`.
+///
+/// # Variants
+/// * `MessageStart` - Message with empty `content` and the input usage
+/// * `ContentBlockStart` - A new `text`, `tool_use` or `thinking` block
+/// * `ContentBlockDelta` - Incremental content of the block at `index`
+/// * `ContentBlockStop` - The block at `index` is complete
+/// * `MessageDelta` - Stop reason and cumulative output usage
+/// * `Error` - Error sent after the stream started, e.g. `overloaded_error`
+///
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    MessageStart {
+        message: ChatResponse,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: Content,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: ContentDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageDelta {
+        delta: MessageDeltaInfo,
+        usage: Option<Usage>,
+    },
+    MessageStop,
+    Ping,
+    Error {
+        error: ErrorDetails,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// Incremental content of a content block
+///
+/// # Variants
+/// * `TextDelta` - Text appended to a `text` block
+/// * `InputJsonDelta` - Partial JSON of a `tool_use` input. The pieces are
+///   only valid JSON once concatenated
+/// * `ThinkingDelta` - Text appended to a `thinking` block
+/// * `SignatureDelta` - Signature of a `thinking` block, sent before its stop
+///
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentDelta {
+    TextDelta {
+        text: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    ThinkingDelta {
+        thinking: String,
+    },
+    SignatureDelta {
+        signature: String,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageDeltaInfo {
+    pub stop_reason: Option<String>,
+    pub stop_sequence: Option<String>,
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Message batches ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// One request of a message batch
+///
+/// # Fields
+/// * `custom_id` - Id chosen by the caller to match the result, unique in the batch
+/// * `params` - Messages API request, without streaming
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Clone)]
+pub struct BatchRequest {
+    pub custom_id: String,
+    pub params: ChatRequest,
+}
+
+/// Body of a create batch request
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Clone)]
+pub struct CreateBatchRequest {
+    pub requests: Vec<BatchRequest>,
+}
+
+/// A message batch as returned by the API
+///
+/// # Fields
+/// * `id` - Batch id, e.g. `msgbatch_013Zva2CMHLNnXjNJJKqJ2EF`
+/// * `processing_status` - `in_progress`, `canceling` or `ended`
+/// * `request_counts` - Requests per status
+/// * `results_url` - JSONL results file, set once processing ended
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageBatch {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub batch_type: String,
+    pub processing_status: String,
+    pub request_counts: RequestCounts,
+    pub ended_at: Option<String>,
+    pub created_at: String,
+    pub expires_at: String,
+    pub archived_at: Option<String>,
+    pub cancel_initiated_at: Option<String>,
+    pub results_url: Option<String>,
+}
+
+#[allow(dead_code)]
+impl MessageBatch {
+    /// Every request succeeded, errored, was canceled or expired
+    pub fn is_ended(&self) -> bool {
+        self.processing_status == "ended"
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RequestCounts {
+    pub processing: u32,
+    pub succeeded: u32,
+    pub errored: u32,
+    pub canceled: u32,
+    pub expired: u32,
+}
+
+/// One line of the results file of a batch
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct BatchResult {
+    pub custom_id: String,
+    pub result: BatchOutcome,
+}
+
+#[allow(dead_code)]
+impl BatchResult {
+    /// The message of a succeeded request
+    ///
+    /// # Errors
+    /// * The API error of an errored request
+    /// * `GenericError` for a canceled or expired request
+    pub fn response(self) -> Result<ChatResponse, AnthropicError> {
+        match self.result {
+            BatchOutcome::Succeeded { message } => Ok(*message),
+            BatchOutcome::Errored { error } => Err(error_from_details(error.error)),
+            BatchOutcome::Canceled => Err(AnthropicError::GenericError {
+                code: "canceled".to_string(),
+                message: format!("Batch request {} was canceled", self.custom_id),
+                detail: "ERROR-batch-0001".to_string(),
+            }),
+            BatchOutcome::Expired => Err(AnthropicError::GenericError {
+                code: "expired".to_string(),
+                message: format!("Batch request {} expired", self.custom_id),
+                detail: "ERROR-batch-0002".to_string(),
+            }),
+        }
+    }
+}
+
+/// Result of a batch request
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchOutcome {
+    Succeeded { message: Box<ChatResponse> },
+    Errored { error: ErrorResponse },
+    Canceled,
+    Expired,
+}
+
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Errors ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
 
 /// Represents an error response structure from the API
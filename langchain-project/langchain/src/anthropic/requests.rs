@@ -1,27 +1,35 @@
 use reqwest::{Client, Response};
 use log::{warn, error};
+use async_stream::stream;
+use futures::{pin_mut, StreamExt};
+use crate::llm::jsonl::json_lines;
+use crate::llm::sse::sse_events;
 use crate::anthropic::libs::{
-    ChatRequest, EmbedRequest, AnthropicEmbedEndpoint, 
-    ErrorResponse, VoyageError,
+    ChatRequest, EmbedRequest,
+    ErrorDetails, StreamEvent,
 };
 use crate::anthropic::utils::print_pre;
 use crate::anthropic::{
-    ANTHROPIC_VERSION, ANTHROPIC_BASE_URL, DEBUG_PRE, DEBUG_POST, RETRY_BASE_DELAY,
-    ANTHROPIC_EMBED_URL, ANTHROPIC_EMBEDMUL_URL, ANTHROPIC_EMBEDRANK_URL
+    ANTHROPIC_VERSION, DEBUG_PRE, DEBUG_POST,
 };
 use crate::anthropic::error::AnthropicError;
+use crate::llm::retry::{body_tokens, send_with_retry, RateLimiter, RetryPolicy};
+use crate::llm::error::ApiError;
+use crate::llm::libs::Provider;
 use std::time::Duration;
 use serde_json::Value;
-use tokio::time::sleep;
 
 /// Sends a chat request to the Anthropic API with retry functionality.
 ///
 /// # Arguments
 ///
+/// * `client` - Client shared by the requests of a provider struct
 /// * `request` - A reference to a `ChatRequest` struct containing the chat request details.
+/// * `url` - The messages endpoint URL (usually `ANTHROPIC_BASE_URL`).
 /// * `api_key` - A string slice containing the API key for authentication.
 /// * `timeout` - The timeout duration for each request attempt in seconds.
-/// * `max_retries` - The maximum number of retry attempts for failed requests.
+/// * `retry_policy` - When to retry failed requests and how long to wait.
+/// * `rate_limiter` - Optional limit of requests and tokens per minute.
 ///
 /// # Returns
 ///
@@ -30,45 +38,26 @@ use tokio::time::sleep;
 /// - `Err(AnthropicError)` if there's an error during the request or response processing.
 ///
 pub async fn request_chat(
+    client: &Client,
     request: &ChatRequest,
+    url: &str,
     api_key: &str,
     timeout: Duration,
-    max_retries: u32,
+    retry_policy: &RetryPolicy,
+    rate_limiter: Option<&RateLimiter>,
 ) -> Result<String, AnthropicError> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-
     print_pre(&request, DEBUG_PRE);
         
     // Serializes the request struct into a JSON byte vector
     let request_body = serde_json::to_vec(request)?;
 
-    let mut response: Response = make_request(
-        &client,
-        api_key, 
-        &request_body, 
-        timeout,
+    let response: Response = send_with_retry(
+        retry_policy,
+        rate_limiter,
+        body_tokens(&request_body),
+        || make_request(client, url, api_key, &request_body, timeout),
     ).await?;
 
-    for attempt in 1..=max_retries {
-        if response.status().is_success() {
-            break;
-        }
-
-        warn!("Server error (attempt {}/{}): {}", attempt, max_retries, response.status());
-
-        sleep(RETRY_BASE_DELAY).await;
-        
-        response = make_request(
-            &client,
-            api_key,
-            &request_body,
-            timeout,
-        ).await?;
-    }
-
     // Checks if the response status is not successful (i.e., not in the 200-299 range).
     if !response.status().is_success() {
         let anthropic_error: AnthropicError = manage_error(response).await;
@@ -82,13 +71,105 @@ pub async fn request_chat(
     Ok(response_string)
 }
 
-/// Sends an embedding request to the Anthropic API using the specified endpoint.
+/// Streams a chat request and yields the decoded server-sent events
 ///
 /// # Arguments
 ///
+/// * `client` - Client shared by the requests of a provider struct
+/// * `request` - The chat request, with `stream` set to `true`
+/// * `url` - The messages endpoint URL (usually `ANTHROPIC_BASE_URL`)
+/// * `api_key` - A string slice containing the API key for authentication
+/// * `retry_policy` - When to retry a failed request, before any chunk arrives
+/// * `rate_limiter` - Optional limit of requests and tokens per minute
+///
+/// # Errors
+///
+/// Failed requests, non-2xx statuses, transport errors and `error` events
+/// end the stream with an `Err` item. Events that cannot be parsed are
+/// yielded as `Err` and the stream continues.
+///
+pub fn strem_chat(
+    client: Client,
+    request: ChatRequest,
+    url: String,
+    api_key: String,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+) -> impl futures::Stream<Item = Result<StreamEvent, AnthropicError>> {
+    stream! {
+        print_pre(&request, DEBUG_PRE);
+
+        let request_body = match serde_json::to_vec(&request) {
+            Ok(request_body) => request_body,
+            Err(e) => {
+                yield Err(AnthropicError::JsonError(e));
+                return;
+            }
+        };
+
+        let response: Response = match send_with_retry(
+            &retry_policy,
+            rate_limiter.as_ref(),
+            body_tokens(&request_body),
+            || client
+                .post(&url)
+                .header("x-api-key", &api_key)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .header("Content-Type", "application/json")
+                .body(request_body.clone())
+                .send(),
+        ).await {
+                Ok(response) => response,
+                Err(e) => {
+                    error!("Error sending request: {}", e);
+                    yield Err(AnthropicError::RequestError(e));
+                    return;
+                }
+            };
+
+        if !response.status().is_success() {
+            yield Err(manage_error(response).await);
+            return;
+        }
+
+        let events = sse_events(response.bytes_stream());
+        pin_mut!(events);
+
+        while let Some(event) = events.next().await {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Error reading chunk: {}", e);
+                    yield Err(AnthropicError::RequestError(e));
+                    return;
+                }
+            };
+
+            match serde_json::from_str::<StreamEvent>(&event.data) {
+                Ok(StreamEvent::Error { .. }) => {
+                    yield Err(AnthropicError::ApiError(ApiError::from_body(Provider::Anthropic, &event.data)));
+                    return;
+                }
+                Ok(stream_event) => {
+                    yield Ok(stream_event);
+                }
+                Err(e) => {
+                    warn!("Error parsing event: {}", e);
+                    yield Err(AnthropicError::JsonError(e));
+                }
+            }
+        }
+    }
+}
+
+/// Sends an embedding request to the Voyage API.
+///
+/// # Arguments
+///
+/// * `client` - Client shared by the requests of a provider struct
 /// * `request` - A reference to an `EmbedRequest` struct containing the request details.
+/// * `url` - The endpoint URL (`ANTHROPIC_EMBED_URL`, `ANTHROPIC_EMBEDMUL_URL` or `ANTHROPIC_EMBEDRANK_URL`).
 /// * `api_key` - A string slice containing the API key for authentication.
-/// * `endpoint` - An `AnthropicEmbedEndpoint` enum specifying which embedding endpoint to use.
 ///
 /// # Returns
 ///
@@ -97,32 +178,23 @@ pub async fn request_chat(
 /// - `Err(AnthropicError)` if there's an error during the request or response processing.
 ///
 pub async fn request_embed(
+    client: &Client,
     request: &EmbedRequest,
+    url: &str,
     api_key: &str,
-    endpoint: AnthropicEmbedEndpoint
+    retry_policy: &RetryPolicy,
+    rate_limiter: Option<&RateLimiter>,
 ) -> Result<String, AnthropicError> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-    
-    // Determines the appropriate URL based on the specified endpoint.
-    let request_url = match endpoint {
-        AnthropicEmbedEndpoint::Embed => ANTHROPIC_EMBED_URL,
-        AnthropicEmbedEndpoint::MultimodalEmbed => ANTHROPIC_EMBEDMUL_URL,
-        AnthropicEmbedEndpoint::Rerank => ANTHROPIC_EMBEDRANK_URL,
-    };
-
     // Serializes the request struct into a JSON byte vector
     let request_body = serde_json::to_vec(request)?;
     
     print_pre(&request, DEBUG_PRE);
 
-    let response: Response = make_embed_request(
-        &client, 
-        &request_url,
-        api_key, 
-        &request_body, 
+    let response: Response = send_with_retry(
+        retry_policy,
+        rate_limiter,
+        body_tokens(&request_body),
+        || make_embed_request(client, url, api_key, &request_body),
     ).await?;
 
     // Checks if the response status is not successful (i.e., not in the 200-299 range).
@@ -142,6 +214,7 @@ pub async fn request_embed(
 ///
 /// # Arguments
 ///
+/// * `client` - Client shared by the requests of a provider struct
 /// * `url` - A string slice that holds the URL for the GET request.
 /// * `api_key` - A string slice containing the API key for authentication.
 ///
@@ -152,16 +225,12 @@ pub async fn request_embed(
 /// - `Err(AnthropicError)` if there's an error during the request or response processing.
 ///
 pub async fn get_request(
+    client: &Client,
     url: &str, 
     api_key: &str
 ) -> Result<Value, AnthropicError> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-
     let response: Response = make_get_request(
-        &client, 
+        client, 
         url, 
         api_key
     ).await?;
@@ -178,6 +247,98 @@ pub async fn get_request(
     Ok(response_data)
 }
 
+/// Sends a POST request with a JSON body and returns the JSON response,
+/// without retries
+///
+/// # Arguments
+///
+/// * `client` - Client shared by the requests of a provider struct
+/// * `url` - The endpoint URL, e.g. the cancel endpoint of a batch
+/// * `api_key` - A string slice containing the API key for authentication
+/// * `request_body` - The serialized JSON body
+/// * `timeout` - The timeout of the request
+///
+/// # Returns
+///
+/// A `Result` which is:
+/// - `Ok(Value)` containing the JSON response if the request is successful.
+/// - `Err(AnthropicError)` if there's an error during the request or response processing.
+///
+pub async fn post_request(
+    client: &Client,
+    url: &str,
+    api_key: &str,
+    request_body: &[u8],
+    timeout: Duration,
+) -> Result<Value, AnthropicError> {
+    let response: Response = make_request(
+        client,
+        url,
+        api_key,
+        request_body,
+        timeout,
+    ).await?;
+
+    if !response.status().is_success() {
+        let anthropic_error: AnthropicError = manage_error(response).await;
+        return Err(anthropic_error);
+    }
+
+    let response_data = response.json::<Value>().await?;
+
+    print_pre(&response_data, DEBUG_POST);
+
+    Ok(response_data)
+}
+
+/// Downloads a JSON Lines file and yields its lines as they arrive
+///
+/// # Errors
+///
+/// Failed requests, non-2xx statuses and transport errors end the stream
+/// with an `Err` item.
+///
+pub fn stream_lines(
+    client: Client,
+    url: String,
+    api_key: String,
+) -> impl futures::Stream<Item = Result<String, AnthropicError>> {
+    stream! {
+        let response: Response = match client
+            .get(&url)
+            .header("x-api-key", api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .send()
+            .await {
+                Ok(response) => response,
+                Err(e) => {
+                    error!("Error sending request: {}", e);
+                    yield Err(AnthropicError::RequestError(e));
+                    return;
+                }
+            };
+
+        if !response.status().is_success() {
+            yield Err(manage_error(response).await);
+            return;
+        }
+
+        let lines = json_lines(response.bytes_stream());
+        pin_mut!(lines);
+
+        while let Some(line) = lines.next().await {
+            match line {
+                Ok(line) => yield Ok(line),
+                Err(e) => {
+                    warn!("Error reading chunk: {}", e);
+                    yield Err(AnthropicError::RequestError(e));
+                    return;
+                }
+            }
+        }
+    }
+}
+
 /// Makes an HTTP POST request to the Anthropic API endpoint
 ///
 /// Sends a request with the specified parameters and handles authentication and headers
@@ -186,6 +347,7 @@ pub async fn get_request(
 /// # Arguments
 ///
 /// * `client` - The HTTP client instance used to make the request
+/// * `url` - The endpoint URL to send the POST request to
 /// * `api_key` - The authentication API key for the Anthropic service
 /// * `request_value` - The JSON payload to be sent in the request body
 /// * `timeout` - The request timeout duration in seconds
@@ -203,12 +365,13 @@ pub async fn get_request(
 ///
 pub async fn make_request(
     client: &Client,
+    url: &str,
     api_key: &str,
     request_body: &[u8],
     timeout: Duration,
 ) -> Result<Response, reqwest::Error> {
     Ok(client
-        .post(ANTHROPIC_BASE_URL)
+        .post(url)
         .timeout(timeout)
         .header("x-api-key", api_key)
         .header("anthropic-version", ANTHROPIC_VERSION)
@@ -293,72 +456,52 @@ pub async fn make_get_request(
         .await?)
 }
 
+/// Reads the status, request id and error body of a failed response
 pub async fn manage_error(
     response: Response,
 ) -> AnthropicError {
     error!("Response code: {}", response.status());
+    AnthropicError::ApiError(ApiError::read(Provider::Anthropic, response).await)
+}
 
-    match response.json::<ErrorResponse>().await {
-        Ok(error_detail) => {
-            match error_detail.error.error_type.as_str() {
-                "authentication_error" => AnthropicError::AuthenticationError(
-                    error_detail.error.message
-                ),
-                "invalid_request_error" => AnthropicError::BadRequestError(
-                    error_detail.error.message
-                ),
-                "permission_error" => AnthropicError::PermissionDeniedError(
-                    error_detail.error.message
-                ),
-                "not_found_error" => AnthropicError::NotFoundError(
-                    error_detail.error.message
-                ),
-                "request_too_large" => AnthropicError::RequestTooLarge(
-                    error_detail.error.message
-                ),
-                "rate_limit_error" => AnthropicError::RateLimitError(
-                    error_detail.error.message
-                ),
-                "api_error" => AnthropicError::APIConnectionError(
-                    error_detail.error.message
-                ),
-                "overloaded_error" => AnthropicError::OverloadedServerError(
-                    error_detail.error.message
-                ),
-                _ => AnthropicError::GenericError {
-                    code: error_detail.error.error_type,
-                    message: error_detail.error.message,
-                    detail: "ERROR-req-9822".to_string(),
-                },
-            }
-        }
-        Err(e) => {
-            AnthropicError::GenericError {
-                code: "None".to_string(),
-                message: format!("Error: {}", e),
-                detail: "ERROR-req-9823".to_string(),
-            }
-        }
+/// Maps the `error` object of an API error body or stream event to an
+/// `AnthropicError`
+pub fn error_from_details(error: ErrorDetails) -> AnthropicError {
+    match error.error_type.as_str() {
+        "authentication_error" => AnthropicError::AuthenticationError(
+            error.message
+        ),
+        "invalid_request_error" => AnthropicError::BadRequestError(
+            error.message
+        ),
+        "permission_error" => AnthropicError::PermissionDeniedError(
+            error.message
+        ),
+        "not_found_error" => AnthropicError::NotFoundError(
+            error.message
+        ),
+        "request_too_large" => AnthropicError::RequestTooLarge(
+            error.message
+        ),
+        "rate_limit_error" => AnthropicError::RateLimitError(
+            error.message
+        ),
+        "api_error" => AnthropicError::APIConnectionError(
+            error.message
+        ),
+        "overloaded_error" => AnthropicError::OverloadedServerError(
+            error.message
+        ),
+        _ => AnthropicError::ApiError(
+            ApiError::new(Provider::Anthropic, &error.message).with_code(&error.error_type)
+        ),
     }
 }
 
+/// Reads a failed Voyage response; its `detail` becomes the message
 pub async fn manage_voyage_error(
     response: Response,
 ) -> AnthropicError {
     error!("Response code: {}", response.status());
-
-    match response.json::<VoyageError>().await {
-        Ok(error) => {
-            AnthropicError::VoyageError(
-                error.detail,
-            )
-        }
-        Err(e) => {
-            AnthropicError::GenericError {
-                code: "None".to_string(),
-                message: format!("Error: {}", e),
-                detail: "ERROR-req-9853".to_string(),
-            }
-        }
-    }
+    AnthropicError::ApiError(ApiError::read(Provider::Anthropic, response).await)
 }
\ No newline at end of file
@@ -1,4 +1,12 @@
 use crate::anthropic::error::AnthropicError;
+use crate::anthropic::libs::{
+    ChatResponse, Content, ContentDelta, Message, Source, StreamEvent, Usage,
+};
+use crate::llm::libs::{
+    ChatMessage, ContentPart, ModelResponse, Role, StopReason,
+    ToolCall, ToolChoice, ToolDefinition,
+};
+use serde_json::{json, Value};
 use base64::{Engine as _, engine::general_purpose::STANDARD};
 use std::fs::File;
 use std::io::Read;
@@ -74,3 +82,329 @@ pub fn read_file_data(file_path: &str) -> Result<String, AnthropicError> {
     Ok(base64_encoded)
 }
 
+
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ ChatModel ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Converts normalized messages into Anthropic messages
+///
+/// # Returns
+/// * The system prompt built from all `Role::System` messages
+/// * The remaining conversation. Tool results are sent as `tool_result`
+///   blocks in a user message, grouped when they follow each other
+///
+pub fn to_anthropic_messages(messages: &[ChatMessage]) -> (Option<String>, Vec<Message>) {
+    let mut system_prompts: Vec<String> = Vec::new();
+    let mut anthropic_messages: Vec<Message> = Vec::new();
+
+    for message in messages {
+        match message.role {
+            Role::System => system_prompts.push(message.text()),
+            Role::User => {
+                anthropic_messages.push(Message {
+                    role: "user".to_string(),
+                    content: to_anthropic_content(&message.content),
+                });
+            }
+            Role::Assistant => {
+                let mut content = to_anthropic_content(&message.content);
+                for tool_call in &message.tool_calls {
+                    content.push(Content {
+                        content_type: "tool_use".to_string(),
+                        id: Some(tool_call.id.clone()),
+                        name: Some(tool_call.name.clone()),
+                        input: Some(tool_call.arguments.clone()),
+                        ..Default::default()
+                    });
+                }
+                anthropic_messages.push(Message {
+                    role: "assistant".to_string(),
+                    content,
+                });
+            }
+            Role::Tool => {
+                let tool_result = Content {
+                    content_type: "tool_result".to_string(),
+                    tool_use_id: message.tool_call_id.clone(),
+                    content: Some(message.text()),
+                    ..Default::default()
+                };
+
+                let is_tool_group = anthropic_messages.last().is_some_and(|last| {
+                    last.role == "user"
+                        && last.content.iter().all(|c| c.content_type == "tool_result")
+                });
+
+                match anthropic_messages.last_mut() {
+                    Some(last) if is_tool_group => last.content.push(tool_result),
+                    _ => anthropic_messages.push(Message {
+                        role: "user".to_string(),
+                        content: vec![tool_result],
+                    }),
+                }
+            }
+        }
+    }
+
+    let system = if system_prompts.is_empty() {
+        None
+    } else {
+        Some(system_prompts.join("\n\n"))
+    };
+
+    (system, anthropic_messages)
+}
+
+fn to_anthropic_content(content: &[ContentPart]) -> Vec<Content> {
+    content
+        .iter()
+        .map(|part| match part {
+            ContentPart::Text { text } => Content {
+                content_type: "text".to_string(),
+                text: Some(text.clone()),
+                ..Default::default()
+            },
+            ContentPart::Image { mime_type, data } => Content {
+                content_type: "image".to_string(),
+                source: Some(Source {
+                    source_type: "base64".to_string(),
+                    media_type: mime_type.clone(),
+                    data: data.clone(),
+                }),
+                ..Default::default()
+            },
+            ContentPart::Thinking { thinking, signature } => Content {
+                content_type: "thinking".to_string(),
+                thinking: Some(thinking.clone()),
+                signature: Some(signature.clone()),
+                ..Default::default()
+            },
+            ContentPart::RedactedThinking { data } => Content {
+                content_type: "redacted_thinking".to_string(),
+                data: Some(data.clone()),
+                ..Default::default()
+            },
+        })
+        .collect()
+}
+
+pub fn to_anthropic_tools(tools: &[ToolDefinition]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|tool| json!({
+            "name": tool.name,
+            "description": tool.description,
+            "input_schema": tool.parameters,
+        }))
+        .collect()
+}
+
+pub fn to_anthropic_tool_choice(tool_choice: &ToolChoice) -> Value {
+    match tool_choice {
+        ToolChoice::Auto => json!({ "type": "auto" }),
+        ToolChoice::Required => json!({ "type": "any" }),
+        ToolChoice::None => json!({ "type": "none" }),
+        ToolChoice::Tool(name) => json!({ "type": "tool", "name": name }),
+    }
+}
+
+/// Converts an Anthropic response into a `ModelResponse`
+pub fn to_model_response(response: ChatResponse) -> ModelResponse {
+    let mut content: Vec<ContentPart> = Vec::new();
+    let mut tool_calls: Vec<ToolCall> = Vec::new();
+
+    for block in response.content.unwrap_or_default() {
+        match block.content_type.as_str() {
+            "text" => {
+                if let Some(text) = block.text {
+                    content.push(ContentPart::Text { text });
+                }
+            }
+            // Kept so the blocks can be sent back on the next tool-use turn
+            "thinking" => {
+                content.push(ContentPart::Thinking {
+                    thinking: block.thinking.unwrap_or_default(),
+                    signature: block.signature.unwrap_or_default(),
+                });
+            }
+            "redacted_thinking" => {
+                content.push(ContentPart::RedactedThinking {
+                    data: block.data.unwrap_or_default(),
+                });
+            }
+            "tool_use" => {
+                tool_calls.push(ToolCall {
+                    id: block.id.unwrap_or_default(),
+                    name: block.name.unwrap_or_default(),
+                    arguments: block.input.unwrap_or(json!({})),
+                });
+            }
+            _ => (),
+        }
+    }
+
+    let stop_reason = response.stop_reason.map(|reason| match reason.as_str() {
+        "end_turn" => StopReason::EndTurn,
+        "max_tokens" => StopReason::MaxTokens,
+        "tool_use" => StopReason::ToolUse,
+        "stop_sequence" => StopReason::StopSequence,
+        _ => StopReason::Other(reason),
+    });
+
+    let usage = response.usage.map(|usage| usage.token_usage());
+
+    ModelResponse {
+        message: ChatMessage {
+            role: Role::Assistant,
+            content,
+            tool_calls,
+            tool_call_id: None,
+            name: None,
+        },
+        stop_reason,
+        usage,
+        model: response.model,
+        id: response.id,
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Streaming ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Rebuilds a `ChatResponse` from streamed `StreamEvent`s
+///
+/// Text and thinking deltas are appended to their block, `input_json_delta`
+/// pieces are concatenated and parsed as the `tool_use` input when the block
+/// stops, and `message_delta` sets the stop reason and output usage.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut assembler = StreamAssembler::new();
+/// while let Some(event) = stream.next().await {
+///     assembler.push(event?);
+/// }
+/// let response = assembler.finish()?;
+/// ```
+#[allow(dead_code)]
+#[derive(Debug, Default)]
+pub struct StreamAssembler {
+    message: Option<ChatResponse>,
+    blocks: Vec<Content>,
+    partial_json: Vec<String>,
+}
+
+#[allow(dead_code)]
+impl StreamAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: StreamEvent) {
+        match event {
+            StreamEvent::MessageStart { message } => {
+                self.message = Some(message);
+            }
+            StreamEvent::ContentBlockStart { index, content_block } => {
+                while self.blocks.len() <= index {
+                    self.blocks.push(Content::default());
+                    self.partial_json.push(String::new());
+                }
+                self.blocks[index] = content_block;
+            }
+            StreamEvent::ContentBlockDelta { index, delta } => {
+                let Some(block) = self.blocks.get_mut(index) else {
+                    return;
+                };
+                match delta {
+                    ContentDelta::TextDelta { text } => {
+                        block.text.get_or_insert_with(String::new).push_str(&text);
+                    }
+                    ContentDelta::ThinkingDelta { thinking } => {
+                        block.thinking.get_or_insert_with(String::new).push_str(&thinking);
+                    }
+                    ContentDelta::SignatureDelta { signature } => {
+                        block.signature = Some(signature);
+                    }
+                    ContentDelta::InputJsonDelta { partial_json } => {
+                        self.partial_json[index].push_str(&partial_json);
+                    }
+                    ContentDelta::Unknown => (),
+                }
+            }
+            StreamEvent::ContentBlockStop { index } => {
+                self.close_block(index);
+            }
+            StreamEvent::MessageDelta { delta, usage } => {
+                let Some(message) = &mut self.message else {
+                    return;
+                };
+                if delta.stop_reason.is_some() {
+                    message.stop_reason = delta.stop_reason;
+                }
+                if delta.stop_sequence.is_some() {
+                    message.stop_sequence = delta.stop_sequence;
+                }
+                if let Some(delta_usage) = usage {
+                    merge_usage(&mut message.usage, delta_usage);
+                }
+            }
+            StreamEvent::MessageStop
+            | StreamEvent::Ping
+            | StreamEvent::Error { .. }
+            | StreamEvent::Unknown => (),
+        }
+    }
+
+    /// Returns the assembled response, failing if no `message_start` was seen
+    pub fn finish(mut self) -> Result<ChatResponse, AnthropicError> {
+        for index in 0..self.blocks.len() {
+            self.close_block(index);
+        }
+
+        let mut message = match self.message {
+            Some(message) => message,
+            None => {
+                error!("Error Stream ended before message_start");
+                return Err(AnthropicError::ResponseContentError);
+            }
+        };
+
+        message.content = Some(self.blocks);
+        Ok(message)
+    }
+
+    fn close_block(&mut self, index: usize) {
+        let Some(partial_json) = self.partial_json.get_mut(index) else {
+            return;
+        };
+        if partial_json.is_empty() {
+            return;
+        }
+
+        let json_input = std::mem::take(partial_json);
+        match serde_json::from_str::<Value>(&json_input) {
+            Ok(input) => self.blocks[index].input = Some(input),
+            Err(e) => error!("Error parsing tool input {}: {:?}", json_input, e),
+        }
+    }
+}
+
+fn merge_usage(usage: &mut Option<Usage>, delta: Usage) {
+    let Some(usage) = usage else {
+        *usage = Some(delta);
+        return;
+    };
+
+    if delta.input_tokens.is_some() {
+        usage.input_tokens = delta.input_tokens;
+    }
+    if delta.output_tokens.is_some() {
+        usage.output_tokens = delta.output_tokens;
+    }
+    if delta.cache_creation_input_tokens.is_some() {
+        usage.cache_creation_input_tokens = delta.cache_creation_input_tokens;
+    }
+    if delta.cache_read_input_tokens.is_some() {
+        usage.cache_read_input_tokens = delta.cache_read_input_tokens;
+    }
+}
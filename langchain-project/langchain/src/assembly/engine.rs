@@ -11,6 +11,8 @@ use crate::assembly::error::AssemblyError;
 use std::time::Duration;
 use reqwest::Url;
 use log::error;
+use crate::llm::http::{default_client, WithHttpClient};
+use reqwest::Client;
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
@@ -19,6 +21,7 @@ pub struct TranscriptAssemblyAI {
     pub request: TranscriptRequest,
     pub timeout: Duration,
     pub max_retries: u32,
+    pub client: Client,
 }
 
 
@@ -37,6 +40,7 @@ impl TranscriptAssemblyAI {
             request: request,
             timeout: Duration::from_secs(900), // default: 15 minutes
             max_retries: 3,                    // default: 3
+            client: default_client(),
         }
     }
 
@@ -55,6 +59,7 @@ impl TranscriptAssemblyAI {
         };
 
         let upload_url = upload_media(
+            &self.client,
             &base_url,
             &self.api_key,
             &buffer,
@@ -79,6 +84,7 @@ impl TranscriptAssemblyAI {
         self.request.audio_url = Some(audio_url.to_string());
 
         let response = request_engine(
+            &self.client,
             &self.request,
             &base_url,
             &self.api_key,
@@ -112,6 +118,7 @@ impl TranscriptAssemblyAI {
         let base_url = format!("{}/transcript/{}", ASSEMBLYAI_BASE_URL, id);
 
         let response = get_engine(
+            &self.client,
             &base_url,
             &self.api_key,        
         ).await;
@@ -183,6 +190,7 @@ impl TranscriptAssemblyAI {
         };
         
         let response = get_engine(
+            &self.client,
             &base_url.to_string(),
             &self.api_key,        
         ).await;
@@ -246,4 +254,11 @@ impl TranscriptAssemblyAI {
     }
 }
 
+impl WithHttpClient for TranscriptAssemblyAI {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
 impl GetApiKey for TranscriptAssemblyAI {}
@@ -1,58 +1,35 @@
 use reqwest::{Client, Response};
-use log::{warn, error};
+use log::error;
 use crate::assembly::libs::{TranscriptRequest};
 use crate::assembly::utils::print_pre;
 use crate::assembly::{
-    DEBUG_PRE, DEBUG_POST, RETRY_BASE_DELAY,
+    DEBUG_PRE, DEBUG_POST,
 };
+use crate::llm::retry::{send_with_retry, RetryPolicy};
 use crate::assembly::error::AssemblyError;
 use std::time::Duration;
 use std::collections::HashMap;
-use tokio::time::sleep;
 
 pub async fn request_engine(
+    client: &Client,
     request: &TranscriptRequest,
     url: &str,
     api_key: &str,
     timeout: Duration,
     max_retries: u32,
 ) -> Result<String, AssemblyError> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-
     print_pre(&request, DEBUG_PRE);
         
     // Serializes the request struct into a JSON byte vector
     let request_body = serde_json::to_vec(request)?;
 
-    let mut response: Response = make_request(
-        &client,
-        url,
-        api_key, 
-        &request_body, 
-        timeout,
+    let response: Response = send_with_retry(
+        &RetryPolicy::new(max_retries),
+        None,
+        0,
+        || make_request(client, url, api_key, &request_body, timeout),
     ).await?;
 
-    for attempt in 1..=max_retries {
-        if response.status().is_success() {
-            break;
-        }
-
-        warn!("Server error (attempt {}/{}): {}", attempt, max_retries, response.status());
-
-        sleep(RETRY_BASE_DELAY).await;
-        
-        response = make_request(
-            &client,
-            url,
-            api_key,
-            &request_body,
-            timeout,
-        ).await?;
-    }
-
     // Checks if the response status is not successful (i.e., not in the 200-299 range).
     if !response.status().is_success() {
         let anthropic_error: AssemblyError = manage_error(response).await;
@@ -67,18 +44,14 @@ pub async fn request_engine(
 }
 
 pub async fn upload_media(
+    client: &Client,
     url: &str,
     api_key: &str,
     body: &[u8],
     timeout: Duration,
 ) -> Result<String, AssemblyError> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-
     let response = make_request(
-        &client,
+        client,
         url,
         api_key,
         body,
@@ -101,16 +74,12 @@ pub async fn upload_media(
 }
 
 pub async fn get_engine(
+    client: &Client,
     url: &str,
     api_key: &str,
 ) -> Result<String, AssemblyError> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-
     let response = make_get_request(
-        &client,
+        client,
         url,
         api_key,
     ).await?;
@@ -0,0 +1,3 @@
+pub mod conversation;
+pub mod error;
+pub mod retrieval_qa;
@@ -0,0 +1,102 @@
+use crate::chains::error::ChainError;
+use crate::llm::chat::ChatModel;
+use crate::llm::libs::{ChatMessage, ChatOptions, ModelResponse};
+use crate::memory::libs::ChatMemory;
+use std::fmt;
+use std::sync::Arc;
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ ConversationChain ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// A multi-turn chat with any `ChatModel`, remembered by a `ChatMemory`
+///
+/// Each call sends the system prompt, the history loaded from the memory
+/// and the new message, then saves the message and the answer. One chain
+/// serves many conversations, told apart by their session id, e.g. the
+/// Telegram chat id of a bot.
+///
+/// Tool calls are not run: use an `Agent` for tools.
+///
+/// # Example
+/// ```ignore
+/// let memory = Arc::new(WindowMemory::new(Arc::new(JsonFileChatStore::new("history.json")), 10));
+/// let chain = ConversationChain::new(Arc::new(ChatGemini::new("gemini-2.0-flash")), memory)
+///     .with_system_prompt("You are a patient tutor.");
+///
+/// let response = chain.invoke("chat-42", "Quiz me on US history").await?;
+/// ```
+pub struct ConversationChain {
+    chat: Arc<dyn ChatModel>,
+    memory: Arc<dyn ChatMemory>,
+    pub system_prompt: Option<String>,
+    pub chat_options: ChatOptions,
+}
+
+#[allow(dead_code)]
+impl ConversationChain {
+    pub fn new(chat: Arc<dyn ChatModel>, memory: Arc<dyn ChatMemory>) -> Self {
+        Self {
+            chat,
+            memory,
+            system_prompt: None,
+            chat_options: ChatOptions::default(),
+        }
+    }
+
+    pub fn with_system_prompt(mut self, system_prompt: &str) -> Self {
+        self.system_prompt = Some(system_prompt.to_string());
+        self
+    }
+
+    pub fn with_chat_options(mut self, chat_options: ChatOptions) -> Self {
+        self.chat_options = chat_options;
+        self
+    }
+
+    /// Sends a text message in the session and returns the answer
+    pub async fn invoke(&self, session_id: &str, input: &str) -> Result<ModelResponse, ChainError> {
+        self.invoke_message(session_id, ChatMessage::user(input)).await
+    }
+
+    /// Sends any user message, e.g. one with images
+    ///
+    /// The history is saved only when the model answers, so a failed call
+    /// can be retried.
+    ///
+    /// # Errors
+    /// * `MemoryError` - The history cannot be loaded or saved
+    /// * `LlmError` - The chat call failed
+    pub async fn invoke_message(&self, session_id: &str, message: ChatMessage) -> Result<ModelResponse, ChainError> {
+        let mut messages = Vec::new();
+        if let Some(system_prompt) = &self.system_prompt {
+            messages.push(ChatMessage::system(system_prompt));
+        }
+        messages.extend(self.memory.load_messages(session_id).await?);
+        messages.push(message.clone());
+
+        let response = self.chat.generate(&messages, &self.chat_options).await?;
+        self.memory
+            .save_messages(session_id, &[message, response.message.clone()])
+            .await?;
+
+        Ok(response)
+    }
+
+    /// Messages the next call sends before the new one
+    pub async fn history(&self, session_id: &str) -> Result<Vec<ChatMessage>, ChainError> {
+        Ok(self.memory.load_messages(session_id).await?)
+    }
+
+    /// Forgets the session
+    pub async fn clear(&self, session_id: &str) -> Result<(), ChainError> {
+        Ok(self.memory.clear(session_id).await?)
+    }
+}
+
+impl fmt::Debug for ConversationChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConversationChain")
+            .field("chat", &self.chat.model_name())
+            .field("system_prompt", &self.system_prompt)
+            .finish()
+    }
+}
@@ -0,0 +1,16 @@
+use crate::llm::error::LlmError;
+use crate::memory::error::MemoryError;
+use crate::vectorstores::error::VectorStoreError;
+
+#[allow(dead_code)]
+#[derive(Debug, thiserror::Error)]
+pub enum ChainError {
+    #[error("Model error: {0}")]
+    LlmError(#[from] LlmError),
+
+    #[error("Vector store error: {0}")]
+    VectorStoreError(#[from] VectorStoreError),
+
+    #[error("Memory error: {0}")]
+    MemoryError(#[from] MemoryError),
+}
@@ -0,0 +1,251 @@
+use crate::chains::error::ChainError;
+use crate::documents::libs::Document;
+use crate::llm::chat::ChatModel;
+use crate::llm::embed::{estimate_tokens, Reranker};
+use crate::llm::libs::{ChatMessage, ChatOptions, TokenUsage};
+use crate::vectorstores::libs::{ScoredDocument, SearchOptions, VectorStore};
+use log::debug;
+use std::fmt;
+use std::sync::Arc;
+
+/// Default instructions of `RetrievalQA`
+pub static DEFAULT_QA_PROMPT: &str = "Answer the question using only the numbered sources \
+provided by the user. Cite the sources you use with their number in square brackets, like [1] \
+or [2][3], right after the sentences they support. If the sources do not contain the answer, \
+say that you don't know and cite nothing.";
+
+/// Answer of a `RetrievalQA` chain
+///
+/// # Fields
+/// * `answer` - Text of the model, with `[n]` citations
+/// * `sources` - Documents cited in the answer, in order of first citation
+/// * `context` - Documents sent to the model; `[n]` refers to `context[n - 1]`
+/// * `usage` - Token usage of the chat call
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct QAResponse {
+    pub answer: String,
+    pub sources: Vec<ScoredDocument>,
+    pub context: Vec<ScoredDocument>,
+    pub usage: Option<TokenUsage>,
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ RetrievalQA ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Answers questions from the documents of a vector store
+///
+/// Each call searches the store, optionally reranks the candidates, packs
+/// the best documents as numbered sources within the token budget and asks
+/// the chat model to answer citing them.
+///
+/// With a reranker the store returns `fetch_k` candidates and the reranker
+/// keeps the `k` best; without it the store returns `k` documents, by
+/// similarity or by maximal marginal relevance with `with_mmr`.
+///
+/// # Example
+/// ```ignore
+/// let qa = RetrievalQA::new(store, Arc::new(ChatOpenAI::new("gpt-4o-mini")))
+///     .with_reranker(Arc::new(EmbedRankVoyage::new("rerank-2-lite")))
+///     .with_search_options(SearchOptions::new(4).with_fetch_k(20));
+///
+/// let response = qa.invoke("When did Apollo 11 land?").await?;
+/// println!("{}", response.answer);
+/// ```
+pub struct RetrievalQA {
+    store: Arc<dyn VectorStore>,
+    chat: Arc<dyn ChatModel>,
+    reranker: Option<Arc<dyn Reranker>>,
+    pub search_options: SearchOptions,
+    pub mmr: bool,
+    pub max_context_tokens: usize,
+    pub system_prompt: String,
+    pub chat_options: ChatOptions,
+}
+
+#[allow(dead_code)]
+impl RetrievalQA {
+    pub fn new(store: Arc<dyn VectorStore>, chat: Arc<dyn ChatModel>) -> Self {
+        Self {
+            store,
+            chat,
+            reranker: None,
+            search_options: SearchOptions::default(),
+            mmr: false,
+            max_context_tokens: 3000,
+            system_prompt: DEFAULT_QA_PROMPT.to_string(),
+            chat_options: ChatOptions::default(),
+        }
+    }
+
+    pub fn with_reranker(mut self, reranker: Arc<dyn Reranker>) -> Self {
+        self.reranker = Some(reranker);
+        self
+    }
+
+    pub fn with_search_options(mut self, search_options: SearchOptions) -> Self {
+        self.search_options = search_options;
+        self
+    }
+
+    /// Retrieves with maximal marginal relevance instead of similarity;
+    /// ignored when a reranker is set
+    pub fn with_mmr(mut self, mmr: bool) -> Self {
+        self.mmr = mmr;
+        self
+    }
+
+    /// Estimated tokens of the sources sent to the model (default: 3000)
+    pub fn with_max_context_tokens(mut self, max_context_tokens: usize) -> Self {
+        self.max_context_tokens = max_context_tokens;
+        self
+    }
+
+    /// Replaces `DEFAULT_QA_PROMPT`; keep asking for `[n]` citations, they
+    /// are how `sources` is filled
+    pub fn with_system_prompt(mut self, system_prompt: &str) -> Self {
+        self.system_prompt = system_prompt.to_string();
+        self
+    }
+
+    pub fn with_chat_options(mut self, chat_options: ChatOptions) -> Self {
+        self.chat_options = chat_options;
+        self
+    }
+
+    /// Answers the question from the retrieved documents
+    ///
+    /// # Errors
+    /// * `VectorStoreError` - The search failed
+    /// * `LlmError` - The rerank or chat call failed
+    pub async fn invoke(&self, question: &str) -> Result<QAResponse, ChainError> {
+        let documents = self.retrieve(question).await?;
+        let context = self.pack(documents);
+        debug!("RetrievalQA sends {} sources", context.len());
+
+        let messages = vec![
+            ChatMessage::system(&self.system_prompt),
+            ChatMessage::user(&qa_prompt(question, &context)),
+        ];
+        let response = self.chat.generate(&messages, &self.chat_options).await?;
+        let answer = response.text();
+
+        let sources = citations(&answer, context.len())
+            .into_iter()
+            .map(|number| context[number - 1].clone())
+            .collect();
+
+        Ok(QAResponse {
+            answer,
+            sources,
+            context,
+            usage: response.usage,
+        })
+    }
+
+    /// Documents for the question, most relevant first
+    pub async fn retrieve(&self, question: &str) -> Result<Vec<ScoredDocument>, ChainError> {
+        let Some(reranker) = &self.reranker else {
+            let documents = if self.mmr {
+                self.store.max_marginal_relevance_search(question, &self.search_options).await?
+            } else {
+                self.store.similarity_search(question, &self.search_options).await?
+            };
+            return Ok(documents);
+        };
+
+        let mut options = self.search_options.clone();
+        options.k = options.fetch_k.max(options.k);
+        let candidates = self.store.similarity_search(question, &options).await?;
+        if candidates.is_empty() {
+            return Ok(candidates);
+        }
+
+        let texts = candidates.iter().map(|candidate| candidate.document.content.clone()).collect();
+        let ranked = reranker.rerank(question, texts, Some(self.search_options.k)).await?;
+
+        Ok(ranked
+            .into_iter()
+            .filter(|(index, _)| *index < candidates.len())
+            .take(self.search_options.k)
+            .map(|(index, score)| ScoredDocument {
+                document: candidates[index].document.clone(),
+                score,
+            })
+            .collect())
+    }
+
+    /// Keeps the documents that fit in `max_context_tokens`, in order; a
+    /// document too long for the space left is skipped for the next ones
+    fn pack(&self, documents: Vec<ScoredDocument>) -> Vec<ScoredDocument> {
+        let mut budget = self.max_context_tokens;
+        documents
+            .into_iter()
+            .filter(|scored| {
+                let tokens = estimate_tokens(&scored.document.content);
+                if tokens > budget {
+                    return false;
+                }
+                budget -= tokens;
+                true
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for RetrievalQA {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetrievalQA")
+            .field("chat", &self.chat.model_name())
+            .field("reranker", &self.reranker.as_ref().map(|reranker| reranker.model_name()))
+            .field("search_options", &self.search_options)
+            .field("mmr", &self.mmr)
+            .field("max_context_tokens", &self.max_context_tokens)
+            .finish()
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Helpers ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// User message with the numbered sources and the question
+fn qa_prompt(question: &str, context: &[ScoredDocument]) -> String {
+    let mut prompt = String::from("Sources:\n\n");
+    for (index, scored) in context.iter().enumerate() {
+        prompt.push_str(&format!("{}\n\n", source_block(index + 1, &scored.document)));
+    }
+    prompt.push_str(&format!("Question: {}", question));
+    prompt
+}
+
+/// `[n] (source: file, page: 2)` followed by the content
+fn source_block(number: usize, document: &Document) -> String {
+    let labels: Vec<String> = ["source", "page", "title"]
+        .iter()
+        .filter_map(|key| {
+            let value = document.metadata.get(*key)?;
+            let value = value.as_str().map(String::from).unwrap_or_else(|| value.to_string());
+            Some(format!("{}: {}", key, value))
+        })
+        .collect();
+
+    if labels.is_empty() {
+        format!("[{}]\n{}", number, document.content)
+    } else {
+        format!("[{}] ({})\n{}", number, labels.join(", "), document.content)
+    }
+}
+
+/// Source numbers cited in the answer as `[2]` or `[1, 3]`, in order of
+/// first citation, ignoring numbers outside `1..=sources`
+pub fn citations(answer: &str, sources: usize) -> Vec<usize> {
+    let mut cited = Vec::new();
+    for part in answer.split('[').skip(1) {
+        let Some((inside, _)) = part.split_once(']') else { continue };
+        let numbers: Result<Vec<usize>, _> = inside.split(',').map(|number| number.trim().parse()).collect();
+        for number in numbers.unwrap_or_default() {
+            if (1..=sources).contains(&number) && !cited.contains(&number) {
+                cited.push(number);
+            }
+        }
+    }
+    cited
+}
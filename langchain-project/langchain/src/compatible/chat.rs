@@ -7,7 +7,17 @@ use crate::compatible::{
 use crate::compatible::requests::{
     request_chat, get_request, strem_chat,
 };
-use crate::compatible::utils::{GetApiKey, read_file_data};
+use crate::compatible::utils::{
+    GetApiKey, read_file_data, to_compatible_messages, to_model_response,
+};
+use crate::llm::chat::ChatModel;
+use crate::llm::error::{ApiError, LlmError};
+use crate::llm::retry::{RateLimiter, RetryPolicy};
+use crate::llm::libs::{
+    ChatMessage, ChatOptions, ModelResponse, Provider, Role,
+};
+use crate::openai::utils::{to_openai_tools, to_openai_tool_choice};
+use async_trait::async_trait;
 use crate::compatible::libs::{
     ChatRequest, Message, ChatResponse, ChatStreamResponse, 
     Content, ImageUrl,
@@ -17,6 +27,8 @@ use tokio::time::sleep;
 use std::time::Duration;
 use log::{info, error};
 use serde_json::{Value, json, from_str};
+use crate::llm::http::{default_client, WithHttpClient};
+use reqwest::Client;
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
@@ -24,9 +36,11 @@ pub struct ChatCompatible {
     pub api_key: String,
     pub request: ChatRequest,
     pub timeout: u64,
-    pub max_retries: i32,
+    pub retry_policy: RetryPolicy,
+    pub rate_limiter: Option<RateLimiter>,
     pub url: String,
     pub model: String,
+    pub client: Client,
 }
 
 #[allow(dead_code)]
@@ -61,9 +75,11 @@ impl ChatCompatible {
             api_key: api_key,
             request: request,
             timeout: 15 * 60, // default: 15 minutes
-            max_retries: 3,         // default: 3 times
+            retry_policy: RetryPolicy::default(), // default: 3 retries
+            rate_limiter: None,
             url: url.to_string(),
             model: model.to_string(),
+            client: default_client(),
         }
     }
 
@@ -89,6 +105,7 @@ impl ChatCompatible {
             role: Some("user".to_string()),
             content: content,
             tool_calls: None,
+            tool_call_id: None,
         };
 
         if let Some(messages) = &mut self.request.messages {
@@ -97,15 +114,21 @@ impl ChatCompatible {
             self.request.messages = Some(vec![new_message]);
         }
 
+        self.send_request().await
+    }
+
+    pub async fn send_request(mut self) -> Result<ChatResponse, CompatibleChatError> {
         self.request.model = Some(self.model.clone());
         let url = format!("{}/{}", self.url, CHAT_COMPLETION);
 
         let response = match request_chat(
+            &self.client,
             &url,
             &self.request,
             &self.api_key,
             self.timeout,
-            self.max_retries,
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
         ).await {
             Ok(response) => response,
             Err(error) => {
@@ -120,13 +143,13 @@ impl ChatCompatible {
             Ok(response_form) => response_form,
             Err(e) => {
                 error!("Error {:?}", e);
-                return Err(CompatibleChatError::ResponseContentError);
+                return Err(e.into());
             }
         };
 
         if let Some(error) = chat_response.error {
             error!("Error {}", error.message);
-            return Err(CompatibleChatError::ResponseContentError);
+            Err(CompatibleChatError::ApiError(ApiError::from_body(Provider::Compatible, &response_string)))
         } else {
             let format_response = ChatResponse {
                 choices: chat_response.choices,
@@ -158,16 +181,18 @@ impl ChatCompatible {
         self.request = request;
     
         let response: Value = match request_chat(
+            &self.client,
             &url_format,
             &self.request,
             &self.api_key,
             self.timeout,
-            self.max_retries,
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
         ).await {
             Ok(response) => response,
             Err(e) => {
                 error!("Error {:?}", e);
-                return Err(CompatibleChatError::ResponseContentError);
+                return Err(e);
             }
         };
 
@@ -180,11 +205,11 @@ impl ChatCompatible {
         let mut status = String::from("processing");
 
         while status == "processing" {
-            let response = get_request(url_str, &self.api_key)
+            let response = get_request(&self.client, url_str, &self.api_key)
                 .await
                 .map_err(|e| {
                     error!("Error fetching response: {:?}", e);
-                    CompatibleChatError::ResponseContentError
+                    e
                 })?;
     
             status = response
@@ -219,14 +244,12 @@ impl ChatCompatible {
     ///
     /// # Errors
     ///
-    /// Returns `CompatibleChatError::ResponseContentError` if:
-    /// - The API request fails
-    /// - The response cannot be parsed
-    /// - Network communication issues occur
+    /// * `ApiError` - The API answered an error, with its status and body
+    /// * `RequestError` - Network communication issues occur
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let response = ChatCompletion::new()
     ///     .with_api_key("your-api-key")
     ///     .baseten_invoke("What is the weather today?")
@@ -254,6 +277,7 @@ impl ChatCompatible {
             role: Some("user".to_string()),
             content: content,
             tool_calls: None,
+            tool_call_id: None,
         };
 
         if let Some(messages) = &mut self.request.messages {
@@ -268,16 +292,18 @@ impl ChatCompatible {
         let url = format!("{}/{}", self.url, CHAT_COMPLETION);
            
         let response: Value = match request_chat(
+            &self.client,
             &url,
             &self.request,
             &api_key_format,
             self.timeout,
-            self.max_retries,
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
         ).await {
             Ok(response) => response,
             Err(e) => {
                 error!("Error {:?}", e);
-                return Err(CompatibleChatError::ResponseContentError);
+                return Err(e);
             }
         };
         
@@ -297,20 +323,21 @@ impl ChatCompatible {
     /// 
     /// # Errors
     /// 
-    /// Returns `CompatibleChatError::ResponseContentError` if the request fails or response cannot be parsed
+    /// Returns `CompatibleChatError::ApiError` if the API answers an error
     ///
     pub async fn handle_get_request(
         self,
         url: &str
     ) -> Result<Value, CompatibleChatError> {
         let response: Value = match get_request(
+            &self.client,
             &url,
             &self.api_key,
         ).await {
             Ok(response) => response,
             Err(e) => {
                 error!("Error {:?}", e);
-                return Err(CompatibleChatError::ResponseContentError);
+                return Err(e);
             }
         };
 
@@ -356,7 +383,7 @@ impl ChatCompatible {
             Ok(response_form) => response_form,
             Err(e) => {
                 error!("Error {:?}", e);
-                return Err(CompatibleChatError::ResponseContentError);
+                return Err(e.into());
             }
         };
 
@@ -374,11 +401,12 @@ impl ChatCompatible {
     ///
     /// # Returns
     ///
-    /// * `impl futures::Stream<Item = ChatStreamResponse>` - A stream that yields chat response chunks
+    /// * `impl futures::Stream<Item = Result<ChatStreamResponse, CompatibleChatError>>` - A stream
+    ///   that yields chat response chunks, or the error that interrupted them
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// use futures::StreamExt;
     /// use futures::pin_mut;
     ///
@@ -388,6 +416,7 @@ impl ChatCompatible {
     /// pin_mut!(chat_stream);
     /// 
     /// while let Some(stream_response) = stream.next().await { 
+    ///     let stream_response = stream_response?;
     ///     if let Some(choices) = stream_response.choices {
     ///         for choice in choices {
     ///             if let Some(delta) = choice.delta {
@@ -430,8 +459,9 @@ impl ChatCompatible {
     ///      * content: The actual text fragment
     ///
     /// 5. Error Handling:
-    ///    - Network errors are propagated through the stream
-    ///    - Empty content chunks are filtered out
+    ///    - Network errors, non-2xx statuses and unparsable chunks are
+    ///      yielded as `Err` items
+    ///    - Events split across network chunks are buffered until complete
     ///    - Stream automatically closes when completion is finished
     ///
     /// 6. Resource Management:
@@ -445,7 +475,7 @@ impl ChatCompatible {
     pub fn stream_response(
         mut self,
         prompt: String,  // Don't change type for stream
-    ) -> impl futures::Stream<Item = ChatStreamResponse> {
+    ) -> impl futures::Stream<Item = Result<ChatStreamResponse, CompatibleChatError>> {
         stream! {            
             let content = vec![Content {
                 content_type: "text".to_string(),
@@ -464,6 +494,7 @@ impl ChatCompatible {
                 role: Some("user".to_string()),
                 content: content,
                 tool_calls: None,
+                tool_call_id: None,
             };
     
             if let Some(messages) = &mut self.request.messages {
@@ -477,9 +508,12 @@ impl ChatCompatible {
             let url = format!("{}/{}", self.url, CHAT_COMPLETION);
 
             let stream = strem_chat(
+                self.client.clone(),
                 url.clone(),
                 self.api_key.clone(),
                 self.request.clone(),
+                self.retry_policy.clone(),
+                self.rate_limiter.clone(),
             );
 
             pin_mut!(stream);
@@ -505,7 +539,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_temperature(0.7);
     /// ```
@@ -529,7 +563,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_max_tokens(150);
     /// ```
@@ -554,7 +588,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_frequency_penalty(0.5);
     /// ```
@@ -578,7 +612,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_timeout_sec(30);
     /// ```
@@ -603,7 +637,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_presence_penalty(0.5);
     /// ```
@@ -628,7 +662,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_top_p(0.9);
     /// ```
@@ -653,7 +687,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_min_p(0.05);
     /// ```
@@ -677,7 +711,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_n_completion(3);
     /// ```
@@ -701,7 +735,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_stop(vec!["END".to_string(), "STOP".to_string()]);
     /// ```
@@ -725,13 +759,26 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_max_retries(3);
     /// ```
     ///
     pub fn with_max_retries(mut self, max_retries: i32) -> Self {
-        self.max_retries = max_retries;
+        self.retry_policy.max_retries = u32::try_from(max_retries).unwrap_or(0);
+        self
+    }
+
+    /// Replaces the retry policy, e.g. to change the backoff delays
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limits the requests and tokens per minute; clone the same limiter
+    /// into every client that shares the account
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
         self
     }
 
@@ -751,7 +798,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_system_prompt("You are a helpful assistant that speaks in a friendly tone.");
     /// ```
@@ -775,6 +822,7 @@ impl ChatCompatible {
             role: Some("system".to_string()),
             content: content,
             tool_calls: None,
+            tool_call_id: None,
         };
 
         if let Some(messages) = &mut self.request.messages {
@@ -801,7 +849,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_assistant_response("Hello! How can I help you today?");
     /// ```
@@ -825,6 +873,7 @@ impl ChatCompatible {
             role: Some("assistant".to_string()),
             content: content,
             tool_calls: None,
+            tool_call_id: None,
         };
 
         if let Some(messages) = &mut self.request.messages {
@@ -851,7 +900,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let history = vec![
     ///     Message::new("user", "Hello"),
     ///     Message::new("assistant", "Hi there!")
@@ -880,7 +929,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let tools = vec![
     ///     json!({
     ///         "type": "function",
@@ -914,7 +963,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let tool_choice = json!({
     ///     "type": "function",
     ///     "function": {"name": "get_weather"}
@@ -962,7 +1011,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_image_url("https://example.com/image.jpg");
     /// ```
@@ -994,6 +1043,7 @@ impl ChatCompatible {
             role: Some("user".to_string()),
             content: content,
             tool_calls: None,
+            tool_call_id: None,
         };
 
         if let Some(messages) = &mut self.request.messages {
@@ -1021,7 +1071,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_image_base64("iVBORw0KGgoAAAANSUhE...", "image/png");
     /// ```
@@ -1056,6 +1106,7 @@ impl ChatCompatible {
             role: Some("user".to_string()),
             content: content,
             tool_calls: None,
+            tool_call_id: None,
         };
 
         if let Some(messages) = &mut self.request.messages {
@@ -1080,7 +1131,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_api_key("your-api-key-here");
     /// ```
@@ -1111,7 +1162,7 @@ impl ChatCompatible {
     ///
     /// # Example
     ///
-    /// ```rust
+    /// ```ignore
     /// let chat = ChatCompletion::new()
     ///     .with_image_file("path/to/image.jpg", "image/jpeg");
     /// ```
@@ -1154,6 +1205,7 @@ impl ChatCompatible {
             role: Some("user".to_string()),
             content: content,
             tool_calls: None,
+            tool_call_id: None,
         };
 
         if let Some(messages) = &mut self.request.messages {
@@ -1166,4 +1218,70 @@ impl ChatCompatible {
     }
 }
 
-impl GetApiKey for ChatCompatible {}
\ No newline at end of file
+impl WithHttpClient for ChatCompatible {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn with_config_base_url(mut self, base_url: &str) -> Self {
+        self.url = base_url.to_string();
+        self
+    }
+}
+
+impl GetApiKey for ChatCompatible {}
+
+#[async_trait]
+impl ChatModel for ChatCompatible {
+    fn provider(&self) -> Provider {
+        Provider::Compatible
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    async fn generate(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatOptions,
+    ) -> Result<ModelResponse, LlmError> {
+        let mut llm = self.clone();
+        let mut history = to_compatible_messages(messages);
+
+        // Keep the system prompt set with `with_system_prompt` unless the
+        // conversation brings its own
+        if !messages.iter().any(|message| message.role == Role::System) {
+            if let Some(current) = &self.request.messages {
+                let system: Vec<Message> = current
+                    .iter()
+                    .take_while(|message| message.role.as_deref() == Some("system"))
+                    .cloned()
+                    .collect();
+                history.splice(0..0, system);
+            }
+        }
+        if let Some(output_schema) = &options.output_schema {
+            let instructions = ChatMessage::system(&output_schema.instructions());
+            history.extend(to_compatible_messages(&[instructions]));
+        }
+        llm.request.messages = Some(history);
+
+        if !options.tools.is_empty() {
+            llm.request.tools = Some(to_openai_tools(&options.tools));
+        }
+        if let Some(tool_choice) = &options.tool_choice {
+            llm.request.tool_choice = Some(to_openai_tool_choice(tool_choice));
+        }
+        if let Some(temperature) = options.temperature {
+            llm.request.temperature = Some(temperature);
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            llm.request.max_tokens = Some(max_tokens);
+        }
+
+        let response = llm.send_request().await?;
+        to_model_response(response).ok_or(LlmError::EmptyResponse)
+    }
+}
\ No newline at end of file
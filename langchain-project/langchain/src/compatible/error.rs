@@ -1,8 +1,13 @@
+use crate::llm::error::ApiError;
 use std::env;
 
 #[allow(dead_code)]
 #[derive(Debug, thiserror::Error)]
 pub enum CompatibleChatError {
+    /// An error answered by the API, with its status, code and body
+    #[error("{0}")]
+    ApiError(ApiError),
+
     #[error("COMPATIBLE_API_KEY not found in environment variables")]
     ApiKeyNotFound,
     
@@ -1,3 +1,4 @@
+use crate::llm::libs::TokenUsage;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -67,13 +68,17 @@ pub struct ChatRequest {
 /// * `role` - Optional. The role of the message sender (e.g., "system", "user", "assistant")
 /// * `content` - Optional. The actual text content of the message
 /// * `tool_calls` - Optional. Array of tool calls made within this message
+/// * `tool_call_id` - Optional. Tool call answered by a "tool" message
 #[allow(dead_code)]
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Message {
     pub role: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub content: Vec<Content>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -237,6 +242,26 @@ pub struct Usage {
     pub prompt_tokens_details: Option<PromptTokensDetails>,
 }
 
+#[allow(dead_code)]
+impl Usage {
+    /// Normalized usage, with the cached, image and audio prompt tokens
+    pub fn token_usage(&self) -> TokenUsage {
+        let mut token_usage = TokenUsage::new(
+            self.prompt_tokens.unwrap_or(0),
+            self.completion_tokens.unwrap_or(0),
+        );
+        if let Some(total) = self.total_tokens {
+            token_usage.total_tokens = total;
+        }
+        if let Some(details) = &self.prompt_tokens_details {
+            token_usage = token_usage
+                .with_cache(0, details.cached_tokens.unwrap_or(0))
+                .with_media(details.image_tokens.unwrap_or(0), details.audio_tokens.unwrap_or(0), 0);
+        }
+        token_usage
+    }
+}
+
 /// Provides a detailed breakdown of token usage by content type
 ///
 /// # Fields
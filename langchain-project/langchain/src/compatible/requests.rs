@@ -1,58 +1,41 @@
 use reqwest::{Client, Response};
 use log::{warn, error};
 use async_stream::stream;
-use futures::StreamExt;
-use crate::compatible::{DEBUG_PRE, DEBUG_POST, RETRY_BASE_DELAY};
+use futures::{pin_mut, StreamExt};
+use crate::llm::sse::sse_events;
+use crate::compatible::{DEBUG_PRE, DEBUG_POST};
+use crate::llm::retry::{body_tokens, send_with_retry, RateLimiter, RetryPolicy};
 use crate::compatible::error::CompatibleChatError;
 use crate::compatible::libs::{
-    ChatRequest, ErrorResponse, ChatStreamResponse
+    ChatRequest, ChatStreamResponse
 };
 use crate::compatible::utils::print_pre;
+use crate::llm::error::ApiError;
+use crate::llm::libs::Provider;
 use std::time::Duration;
 use serde_json::Value;
-use tokio::time::sleep;
 
 pub async fn request_chat(
+    client: &Client,
     url: &str,
     request: &ChatRequest,
     api_key: &str,
     timeout: u64,
-    max_retries: i32,
+    retry_policy: &RetryPolicy,
+    rate_limiter: Option<&RateLimiter>,
 ) -> Result<serde_json::Value, CompatibleChatError> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-    
     print_pre(&request, DEBUG_PRE);
     
     // Serializes the request struct into a JSON byte vector
     let request_body = serde_json::to_vec(request)?;
 
-    let mut response: Response = make_request(
-        &client,
-        url,
-        api_key, 
-        &request_body, 
-        timeout,
+    let response: Response = send_with_retry(
+        retry_policy,
+        rate_limiter,
+        body_tokens(&request_body),
+        || make_request(client, url, api_key, &request_body, timeout),
     ).await?;
 
-    for attempt in 1..=max_retries {
-        if response.status().is_success() { break; }
-
-        warn!("Server error (attempt {}/{}): {}", attempt, max_retries, response.status());
-
-        sleep(RETRY_BASE_DELAY).await;
-        
-        response = make_request(
-            &client,
-            url,
-            api_key,
-            &request_body,
-            timeout,
-        ).await?;
-    }
-
     // Checks if the response status is not successful (i.e., not in the 200-299 range).
     if !response.status().is_success() {
         let comp_error: CompatibleChatError = manage_error(response).await;
@@ -66,14 +49,10 @@ pub async fn request_chat(
 }
 
 pub async fn get_request(
+    client: &Client,
     url: &str, 
     api_key: &str
 ) -> Result<Value, CompatibleChatError> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-
     let response = client
         .request(reqwest::Method::GET, url)
         .header("Authorization", format!("Bearer {}", api_key))
@@ -93,62 +72,93 @@ pub async fn get_request(
     Ok(response_data)
 }
 
+/// Streams a chat completion request and yields each decoded chunk
+///
+/// # Arguments
+///
+/// * `client` - Client shared by the requests of a provider struct
+/// * `url` - The chat completions endpoint URL
+/// * `api_key` - API key used for Bearer token authentication
+/// * `request` - The chat request, with `stream` enabled
+/// * `retry_policy` - When to retry a failed request, before any chunk arrives
+/// * `rate_limiter` - Optional limit of requests and tokens per minute
+///
+/// # Errors
+///
+/// Failed requests, non-2xx statuses, transport errors and chunks that
+/// cannot be parsed are yielded as `Err` items. A parse error does not end
+/// the stream.
 pub fn strem_chat(
+    client: Client,
     url: String,
     api_key: String,
     request: ChatRequest,
-) -> impl futures::Stream<Item = ChatStreamResponse> {
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+) -> impl futures::Stream<Item = Result<ChatStreamResponse, CompatibleChatError>> {
     stream! {
-        let client = Client::new();
-
-        let response: Response = match client
-            .post(url)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await {
+        let request_body = match serde_json::to_vec(&request) {
+            Ok(request_body) => request_body,
+            Err(e) => {
+                yield Err(CompatibleChatError::JsonError(e));
+                return;
+            }
+        };
+
+        let response: Response = match send_with_retry(
+            &retry_policy,
+            rate_limiter.as_ref(),
+            body_tokens(&request_body),
+            || client
+                .post(&url)
+                .header("Authorization", format!("Bearer {}", api_key))
+                .header("Content-Type", "application/json")
+                .body(request_body.clone())
+                .send(),
+        ).await {
                 Ok(response) => response,
                 Err(e) => {
                     error!("Error Error sending request: {}", e);
-                    return
+                    yield Err(CompatibleChatError::RequestError(e));
+                    return;
+                }
+            };
+
+        if !response.status().is_success() {
+            yield Err(manage_error(response).await);
+            return;
+        }
+
+        let events = sse_events(response.bytes_stream());
+        pin_mut!(events);
+
+        while let Some(event) = events.next().await {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Error reading chunk: {}", e);
+                    yield Err(CompatibleChatError::RequestError(e));
+                    return;
                 }
             };
 
-        if response.status().is_success() {
-            let mut stream = response.bytes_stream();
-
-            while let Some(chunk) = stream.next().await {
-                match chunk {
-                    Ok(bytes) => {
-                        let str_chunk = String::from_utf8_lossy(&bytes);
-                        let parts: Vec<&str> = str_chunk.split("\n\n").collect();
-                        for part in parts {
-                            if !part.is_empty() && part.ends_with("[DONE]") {
-                                break;
-                            }
-
-                            if !part.is_empty() && part.starts_with("data:") {
-                                let json_part = part.trim_start_matches("data:");
-                            
-                                match serde_json::from_str::<ChatStreamResponse>(json_part) {
-                                    Ok(stream_response) => {
-                                        yield stream_response;
-                                    },
-                                    Err(e) => {
-                                        warn!("Error parsing chunk: {}", e);
-                                    }
-                                }    
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        warn!("Error reading chunk: {}", e);
+            if event.is_done() {
+                break;
+            }
+
+            match serde_json::from_str::<ChatStreamResponse>(&event.data) {
+                Ok(stream_response) => {
+                    if stream_response.error.is_some() {
+                        yield Err(CompatibleChatError::ApiError(ApiError::from_body(Provider::Compatible, &event.data)));
+                    } else {
+                        yield Ok(stream_response);
                     }
+                },
+                Err(e) => {
+                    warn!("Error parsing chunk: {}", e);
+                    yield Err(CompatibleChatError::JsonError(e));
                 }
             }
-        } else {
-            error!("Error Request failed with status code: {}", response.status());
         }
     }
 }
@@ -192,35 +202,10 @@ pub async fn make_request(
         .await?)
 }
 
+/// Reads the status, request id and error body of a failed response
 pub async fn manage_error(
     response: Response,
 ) -> CompatibleChatError {
     error!("Response code: {}", response.status());
-
-    match response.json::<ErrorResponse>().await {
-        Ok(error) => {
-            if let Some(error) = error.error {
-                CompatibleChatError::GenericError {
-                    message: error.message,
-                    detail: "ERROR-req-9821".to_string(),
-                }
-            } else if let Some(error) = error.detail {
-                CompatibleChatError::GenericError {
-                    message: error,
-                    detail: "ERROR-req-9822".to_string(),
-                }
-            } else {
-                CompatibleChatError::GenericError {
-                    message: "Unknown error.".to_string(),
-                    detail: "ERROR-req-9823".to_string(),
-                }
-            }
-        },
-        Err(_) => {
-            CompatibleChatError::GenericError {
-                message: "Unknown error.".to_string(),
-                detail: "ERROR-req-9824".to_string(),
-            }
-        }
-    }
+    CompatibleChatError::ApiError(ApiError::read(Provider::Compatible, response).await)
 }
\ No newline at end of file
@@ -1,6 +1,13 @@
 use std::env;
 use log::{info, error};
 use crate::compatible::error::CompatibleChatError;
+use crate::compatible::libs::{ChatResponse, Content, ImageUrl, Message};
+use crate::llm::libs::{
+    ChatMessage, ContentPart, ModelResponse, Role,
+};
+use crate::openai::utils::{
+    from_openai_tool_calls, to_openai_tool_call, to_stop_reason,
+};
 use base64::{Engine as _, engine::general_purpose::STANDARD};
 use std::fs::File;
 use std::io::Read;
@@ -14,7 +21,7 @@ use std::io::Read;
 /// * If the COMPATIBLE_API_KEY environment variable is not set
 ///
 /// # Examples
-/// ```
+/// ```ignore
 /// let api_key = get_api_key();
 /// println!("API key: {}", api_key);
 /// ```
@@ -67,4 +74,88 @@ pub fn read_file_data(file_path: &str) -> Result<String, CompatibleChatError> {
     let base64_encoded = STANDARD.encode(&buffer);
     
     Ok(base64_encoded)
-}
\ No newline at end of file
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ ChatModel ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Converts normalized messages into OpenAI compatible messages
+pub fn to_compatible_messages(messages: &[ChatMessage]) -> Vec<Message> {
+    messages
+        .iter()
+        .map(|message| {
+            let role = match message.role {
+                Role::System => "system",
+                Role::User => "user",
+                Role::Assistant => "assistant",
+                Role::Tool => "tool",
+            };
+
+            let tool_calls = if message.tool_calls.is_empty() {
+                None
+            } else {
+                Some(message.tool_calls.iter().map(to_openai_tool_call).collect())
+            };
+
+            Message {
+                role: Some(role.to_string()),
+                content: to_compatible_content(&message.content),
+                tool_calls,
+                tool_call_id: message.tool_call_id.clone(),
+            }
+        })
+        .collect()
+}
+
+fn to_compatible_content(content: &[ContentPart]) -> Vec<Content> {
+    content
+        .iter()
+        .filter_map(|part| match part {
+            ContentPart::Text { text } => Some(Content {
+                content_type: "text".to_string(),
+                text: Some(text.clone()),
+                ..Default::default()
+            }),
+            ContentPart::Image { mime_type, data } => Some(Content {
+                content_type: "image_url".to_string(),
+                image_url: Some(ImageUrl {
+                    url: format!("data:{};base64,{}", mime_type, data),
+                    detail: "high".to_string(),
+                }),
+                ..Default::default()
+            }),
+            ContentPart::Thinking { .. } | ContentPart::RedactedThinking { .. } => None,
+        })
+        .collect()
+}
+
+/// Converts the first choice of a compatible response into a `ModelResponse`
+///
+/// # Returns
+/// * `None` if the response has no message
+///
+pub fn to_model_response(response: ChatResponse) -> Option<ModelResponse> {
+    let choice = response.choices?.into_iter().next()?;
+    let message = choice.message?;
+
+    let content = match message.content {
+        Some(text) if !text.is_empty() => vec![ContentPart::Text { text }],
+        _ => Vec::new(),
+    };
+    let tool_calls = from_openai_tool_calls(&message.tool_calls.unwrap_or_default());
+
+    let usage = response.usage.map(|usage| usage.token_usage());
+
+    Some(ModelResponse {
+        message: ChatMessage {
+            role: Role::Assistant,
+            content,
+            tool_calls,
+            tool_call_id: None,
+            name: None,
+        },
+        stop_reason: choice.finish_reason.map(to_stop_reason),
+        usage,
+        model: response.model,
+        id: response.id,
+    })
+}
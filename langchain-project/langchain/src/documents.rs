@@ -0,0 +1,4 @@
+pub mod error;
+pub mod libs;
+pub mod loaders;
+pub mod splitters;
@@ -0,0 +1,18 @@
+#[allow(dead_code)]
+#[derive(Debug, thiserror::Error)]
+pub enum DocumentError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("CSV error: {0}")]
+    CsvError(#[from] csv::Error),
+
+    #[error("Error in converting to json {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("PDF error: {0}")]
+    PdfError(#[from] lopdf::Error),
+
+    #[error("Field {field} not found in record {record}")]
+    MissingField { field: String, record: usize },
+}
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Document ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// A piece of text with its metadata, as produced by the loaders and
+/// splitters and stored in the vector stores
+///
+/// # Fields
+/// * `id` - Id in the store; generated by `add_documents` when missing
+/// * `content` - Text that is split, embedded and returned by searches
+/// * `metadata` - Arbitrary JSON values, e.g. the `source` file or `page`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub content: String,
+    #[serde(default)]
+    pub metadata: Map<String, Value>,
+}
+
+#[allow(dead_code)]
+impl Document {
+    pub fn new(content: &str) -> Self {
+        Self {
+            id: None,
+            content: content.to_string(),
+            metadata: Map::new(),
+        }
+    }
+
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    pub fn with_metadata(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.metadata.insert(key.to_string(), value.into());
+        self
+    }
+}
+
+/// Contents of the documents, in order, as expected by `Embeddings::embed_documents`
+pub fn contents(documents: &[Document]) -> Vec<String> {
+    documents.iter().map(|document| document.content.clone()).collect()
+}
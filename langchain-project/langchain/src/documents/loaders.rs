@@ -0,0 +1,372 @@
+use crate::documents::error::DocumentError;
+use crate::documents::libs::Document;
+use crate::documents::splitters::TextSplitter;
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// Common interface of the document loaders
+///
+/// Every loader sets the `source` metadata to the file path; the other
+/// metadata depends on the format (`row`, `page`, `title`...).
+#[async_trait]
+pub trait DocumentLoader: Send + Sync {
+    async fn load(&self) -> Result<Vec<Document>, DocumentError>;
+
+    /// Loads the documents and splits them into chunks
+    async fn load_and_split(&self, splitter: &dyn TextSplitter) -> Result<Vec<Document>, DocumentError> {
+        let documents = self.load().await?;
+        Ok(splitter.split_documents(&documents))
+    }
+}
+
+fn source_document(path: &Path, content: String) -> Document {
+    Document {
+        id: None,
+        content,
+        metadata: Map::new(),
+    }
+    .with_metadata("source", path.display().to_string())
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TextLoader ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Loads a text file as one document
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct TextLoader {
+    pub path: PathBuf,
+}
+
+#[allow(dead_code)]
+impl TextLoader {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+#[async_trait]
+impl DocumentLoader for TextLoader {
+    async fn load(&self) -> Result<Vec<Document>, DocumentError> {
+        let content = tokio::fs::read_to_string(&self.path).await?;
+        Ok(vec![source_document(&self.path, content)])
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ MarkdownLoader ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Loads a Markdown file as one document
+///
+/// The first `# ` header becomes the `title` metadata. A front matter block
+/// (`---` lines at the top) is removed from the content and its `key: value`
+/// lines are added to the metadata as strings.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct MarkdownLoader {
+    pub path: PathBuf,
+}
+
+#[allow(dead_code)]
+impl MarkdownLoader {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+#[async_trait]
+impl DocumentLoader for MarkdownLoader {
+    async fn load(&self) -> Result<Vec<Document>, DocumentError> {
+        let text = tokio::fs::read_to_string(&self.path).await?;
+        let (front_matter, body) = split_front_matter(&text);
+
+        let mut document = source_document(&self.path, body.trim_start().to_string());
+        for line in front_matter.lines() {
+            if let Some((key, value)) = line.split_once(':') {
+                let value = value.trim().trim_matches('"');
+                document = document.with_metadata(key.trim(), value);
+            }
+        }
+
+        let title = body
+            .lines()
+            .find_map(|line| line.strip_prefix("# "))
+            .map(|title| title.trim().to_string());
+        if let Some(title) = title {
+            document = document.with_metadata("title", title);
+        }
+
+        Ok(vec![document])
+    }
+}
+
+/// Splits `---\n...\n---` at the top of the text from the rest
+fn split_front_matter(text: &str) -> (&str, &str) {
+    let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+        return ("", text);
+    };
+    match rest.find("\n---") {
+        Some(end) => {
+            let body = &rest[end + 4..];
+            let body = body.split_once('\n').map(|(_, body)| body).unwrap_or("");
+            (&rest[..end], body)
+        }
+        None => ("", text),
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ CsvLoader ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Loads each row of a CSV file as a document
+///
+/// The content has one `column: value` line per content column, which
+/// embeds better than the raw row. The `row` metadata is the row number,
+/// from 0, without the header.
+///
+/// # Fields
+/// * `delimiter` - Field delimiter (default: `,`)
+/// * `content_columns` - Columns written to the content (default: all)
+/// * `metadata_columns` - Columns copied to the metadata
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct CsvLoader {
+    pub path: PathBuf,
+    pub delimiter: u8,
+    pub content_columns: Vec<String>,
+    pub metadata_columns: Vec<String>,
+}
+
+#[allow(dead_code)]
+impl CsvLoader {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            delimiter: b',',
+            content_columns: Vec::new(),
+            metadata_columns: Vec::new(),
+        }
+    }
+
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn with_content_columns(mut self, columns: &[&str]) -> Self {
+        self.content_columns = columns.iter().map(|column| column.to_string()).collect();
+        self
+    }
+
+    pub fn with_metadata_columns(mut self, columns: &[&str]) -> Self {
+        self.metadata_columns = columns.iter().map(|column| column.to_string()).collect();
+        self
+    }
+}
+
+#[async_trait]
+impl DocumentLoader for CsvLoader {
+    async fn load(&self) -> Result<Vec<Document>, DocumentError> {
+        let data = tokio::fs::read(&self.path).await?;
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .from_reader(data.as_slice());
+        let headers = reader.headers()?.clone();
+
+        let column = |name: &String, row: usize| -> Result<usize, DocumentError> {
+            headers
+                .iter()
+                .position(|header| header == name)
+                .ok_or_else(|| DocumentError::MissingField { field: name.clone(), record: row })
+        };
+
+        let mut documents = Vec::new();
+        for (row, record) in reader.records().enumerate() {
+            let record = record?;
+
+            let content_columns: Vec<usize> = if self.content_columns.is_empty() {
+                (0..headers.len()).collect()
+            } else {
+                self.content_columns
+                    .iter()
+                    .map(|name| column(name, row))
+                    .collect::<Result<_, _>>()?
+            };
+            let content = content_columns
+                .iter()
+                .map(|index| format!("{}: {}", &headers[*index], record.get(*index).unwrap_or_default()))
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            let mut document = source_document(&self.path, content).with_metadata("row", row);
+            for name in &self.metadata_columns {
+                let index = column(name, row)?;
+                document = document.with_metadata(name, record.get(index).unwrap_or_default());
+            }
+            documents.push(document);
+        }
+
+        Ok(documents)
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ JsonLoader ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Loads the records of a JSON or JSON Lines file as documents
+///
+/// A JSON array gives one document per item, any other JSON value a single
+/// document; a JSON Lines file (detected by the `.jsonl` extension) one per
+/// line. The `seq` metadata is the record number, from 0.
+///
+/// # Fields
+/// * `content_pointer` - JSON pointer of the content in a record, e.g. `/text`
+///   (default: the whole record). Strings are used as is, other values as JSON.
+/// * `metadata_pointers` - JSON pointers copied to the metadata under their last key
+/// * `json_lines` - Whether the file has one JSON record per line
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct JsonLoader {
+    pub path: PathBuf,
+    pub content_pointer: Option<String>,
+    pub metadata_pointers: Vec<String>,
+    pub json_lines: bool,
+}
+
+#[allow(dead_code)]
+impl JsonLoader {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let json_lines = path.extension().is_some_and(|extension| extension == "jsonl");
+        Self {
+            path,
+            content_pointer: None,
+            metadata_pointers: Vec::new(),
+            json_lines,
+        }
+    }
+
+    pub fn with_content_pointer(mut self, pointer: &str) -> Self {
+        self.content_pointer = Some(pointer.to_string());
+        self
+    }
+
+    pub fn with_metadata_pointers(mut self, pointers: &[&str]) -> Self {
+        self.metadata_pointers = pointers.iter().map(|pointer| pointer.to_string()).collect();
+        self
+    }
+
+    pub fn with_json_lines(mut self, json_lines: bool) -> Self {
+        self.json_lines = json_lines;
+        self
+    }
+
+    fn records(&self, text: &str) -> Result<Vec<Value>, DocumentError> {
+        if self.json_lines {
+            return text
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| Ok(serde_json::from_str(line)?))
+                .collect();
+        }
+
+        match serde_json::from_str(text)? {
+            Value::Array(records) => Ok(records),
+            record => Ok(vec![record]),
+        }
+    }
+}
+
+#[async_trait]
+impl DocumentLoader for JsonLoader {
+    async fn load(&self) -> Result<Vec<Document>, DocumentError> {
+        let text = tokio::fs::read_to_string(&self.path).await?;
+
+        let mut documents = Vec::new();
+        for (seq, record) in self.records(&text)?.into_iter().enumerate() {
+            let content = match &self.content_pointer {
+                Some(pointer) => record.pointer(pointer).ok_or_else(|| DocumentError::MissingField {
+                    field: pointer.clone(),
+                    record: seq,
+                })?,
+                None => &record,
+            };
+            let content = match content {
+                Value::String(text) => text.clone(),
+                value => value.to_string(),
+            };
+
+            let mut document = source_document(&self.path, content).with_metadata("seq", seq);
+            for pointer in &self.metadata_pointers {
+                if let Some(value) = record.pointer(pointer) {
+                    let key = pointer.rsplit('/').next().unwrap_or(pointer);
+                    document = document.with_metadata(key, value.clone());
+                }
+            }
+            documents.push(document);
+        }
+
+        Ok(documents)
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ PdfLoader ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Loads the text of a PDF file, one document per page with the `page`
+/// metadata (from 1), or a single document with `with_single_document`
+///
+/// Only the text layer is read: scanned pages give empty documents, which
+/// are skipped; send those files to a multimodal model instead.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct PdfLoader {
+    pub path: PathBuf,
+    pub single_document: bool,
+}
+
+#[allow(dead_code)]
+impl PdfLoader {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            single_document: false,
+        }
+    }
+
+    pub fn with_single_document(mut self, single_document: bool) -> Self {
+        self.single_document = single_document;
+        self
+    }
+}
+
+#[async_trait]
+impl DocumentLoader for PdfLoader {
+    async fn load(&self) -> Result<Vec<Document>, DocumentError> {
+        let data = tokio::fs::read(&self.path).await?;
+        let pdf = lopdf::Document::load_mem(&data)?;
+        let pages: Vec<u32> = pdf.get_pages().keys().copied().collect();
+        let total_pages = pages.len();
+
+        if self.single_document {
+            let content = pdf.extract_text(&pages)?;
+            let document = source_document(&self.path, content.trim().to_string())
+                .with_metadata("total_pages", total_pages);
+            return Ok(vec![document]);
+        }
+
+        let mut documents = Vec::new();
+        for page in pages {
+            let content = pdf.extract_text(&[page])?;
+            if content.trim().is_empty() {
+                continue;
+            }
+            let document = source_document(&self.path, content.trim().to_string())
+                .with_metadata("page", page)
+                .with_metadata("total_pages", total_pages);
+            documents.push(document);
+        }
+
+        Ok(documents)
+    }
+}
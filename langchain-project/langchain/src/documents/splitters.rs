@@ -0,0 +1,361 @@
+use crate::documents::libs::Document;
+use crate::llm::embed::estimate_tokens;
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+
+/// Measures the length of a chunk, in characters or tokens
+pub type LengthFunction = fn(&str) -> usize;
+
+/// Common interface of the text splitters
+///
+/// `split_documents` keeps the metadata of each document on its chunks, so
+/// a search result still points to its source file, page or row.
+pub trait TextSplitter: Send + Sync {
+    fn split_text(&self, text: &str) -> Vec<String>;
+
+    fn split_documents(&self, documents: &[Document]) -> Vec<Document> {
+        documents
+            .iter()
+            .flat_map(|document| {
+                self.split_text(&document.content)
+                    .into_iter()
+                    .map(|chunk| Document {
+                        id: None,
+                        content: chunk,
+                        metadata: document.metadata.clone(),
+                    })
+            })
+            .collect()
+    }
+}
+
+fn char_length(text: &str) -> usize {
+    text.chars().count()
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ RecursiveCharacterTextSplitter ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Splits on the first separator found in the text (paragraphs, then
+/// lines, then words, then characters) and merges the pieces back into
+/// chunks of up to `chunk_size`, repeating the last `chunk_overlap` of each
+/// chunk at the start of the next
+///
+/// # Fields
+/// * `chunk_size` - Maximum length of a chunk; a piece that cannot be split further may exceed it
+/// * `chunk_overlap` - Length shared by consecutive chunks, lower than `chunk_size`
+/// * `separators` - Tried in order; `""` splits between characters
+/// * `length_function` - Length of a text (default: characters)
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct RecursiveCharacterTextSplitter {
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    pub separators: Vec<String>,
+    pub length_function: LengthFunction,
+}
+
+#[allow(dead_code)]
+impl RecursiveCharacterTextSplitter {
+    pub fn new(chunk_size: usize, chunk_overlap: usize) -> Self {
+        let chunk_size = chunk_size.max(1);
+        Self {
+            chunk_size,
+            chunk_overlap: chunk_overlap.min(chunk_size - 1),
+            separators: ["\n\n", "\n", " ", ""].iter().map(|separator| separator.to_string()).collect(),
+            length_function: char_length,
+        }
+    }
+
+    pub fn with_separators(mut self, separators: &[&str]) -> Self {
+        self.separators = separators.iter().map(|separator| separator.to_string()).collect();
+        self
+    }
+
+    pub fn with_length_function(mut self, length_function: LengthFunction) -> Self {
+        self.length_function = length_function;
+        self
+    }
+
+    fn split_with(&self, text: &str, separators: &[String]) -> Vec<String> {
+        let position = separators
+            .iter()
+            .position(|separator| separator.is_empty() || text.contains(separator.as_str()));
+        let (separator, rest) = match position {
+            Some(index) => (separators[index].as_str(), &separators[index + 1..]),
+            None => ("", &separators[separators.len()..]),
+        };
+
+        let splits: Vec<String> = if separator.is_empty() {
+            text.chars().map(String::from).collect()
+        } else {
+            text.split(separator)
+                .filter(|split| !split.trim().is_empty())
+                .map(String::from)
+                .collect()
+        };
+
+        let mut chunks = Vec::new();
+        let mut fitting: Vec<String> = Vec::new();
+        for split in splits {
+            if (self.length_function)(&split) <= self.chunk_size {
+                fitting.push(split);
+                continue;
+            }
+
+            if !fitting.is_empty() {
+                chunks.extend(self.merge(&fitting, separator));
+                fitting.clear();
+            }
+            if rest.is_empty() {
+                chunks.push(split.trim().to_string());
+            } else {
+                chunks.extend(self.split_with(&split, rest));
+            }
+        }
+
+        if !fitting.is_empty() {
+            chunks.extend(self.merge(&fitting, separator));
+        }
+        chunks
+    }
+
+    /// Joins consecutive splits into chunks of up to `chunk_size`, starting
+    /// each chunk with the last splits of the previous one, up to `chunk_overlap`
+    fn merge(&self, splits: &[String], separator: &str) -> Vec<String> {
+        let separator_length = (self.length_function)(separator);
+        let mut chunks = Vec::new();
+        let mut current: VecDeque<&str> = VecDeque::new();
+        let mut total = 0;
+
+        for split in splits {
+            let length = (self.length_function)(split);
+            let joined = if current.is_empty() { 0 } else { separator_length };
+
+            if total + length + joined > self.chunk_size && !current.is_empty() {
+                push_chunk(&mut chunks, &current, separator);
+
+                while total > self.chunk_overlap
+                    || (total > 0 && total + length + separator_length > self.chunk_size)
+                {
+                    let Some(first) = current.pop_front() else { break };
+                    total -= (self.length_function)(first);
+                    if !current.is_empty() {
+                        total -= separator_length;
+                    }
+                }
+            }
+
+            if !current.is_empty() {
+                total += separator_length;
+            }
+            current.push_back(split);
+            total += length;
+        }
+
+        push_chunk(&mut chunks, &current, separator);
+        chunks
+    }
+}
+
+impl TextSplitter for RecursiveCharacterTextSplitter {
+    fn split_text(&self, text: &str) -> Vec<String> {
+        self.split_with(text, &self.separators)
+    }
+}
+
+fn push_chunk(chunks: &mut Vec<String>, splits: &VecDeque<&str>, separator: &str) {
+    let chunk = splits.iter().copied().collect::<Vec<&str>>().join(separator);
+    let chunk = chunk.trim();
+    if !chunk.is_empty() {
+        chunks.push(chunk.to_string());
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TokenTextSplitter ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Recursive splitter with sizes in tokens, to fit the input limit of an
+/// embedding model
+///
+/// Tokens are estimated at about 4 characters each, like the batching of
+/// `embed_documents`; `with_tokenizer` plugs in an exact count. Sentences
+/// are kept together before falling back to words.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct TokenTextSplitter {
+    pub splitter: RecursiveCharacterTextSplitter,
+}
+
+#[allow(dead_code)]
+impl TokenTextSplitter {
+    pub fn new(chunk_tokens: usize, overlap_tokens: usize) -> Self {
+        let splitter = RecursiveCharacterTextSplitter::new(chunk_tokens, overlap_tokens)
+            .with_separators(&["\n\n", "\n", ". ", " ", ""])
+            .with_length_function(estimate_tokens);
+        Self { splitter }
+    }
+
+    /// Counts tokens with the model tokenizer instead of the estimate
+    pub fn with_tokenizer(mut self, count_tokens: LengthFunction) -> Self {
+        self.splitter.length_function = count_tokens;
+        self
+    }
+}
+
+impl TextSplitter for TokenTextSplitter {
+    fn split_text(&self, text: &str) -> Vec<String> {
+        self.splitter.split_text(text)
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ MarkdownHeaderTextSplitter ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Splits Markdown into sections at the headers and records the enclosing
+/// headers in the metadata of each chunk, e.g. `{"h1": "Guide", "h2": "Setup"}`
+///
+/// Headers inside fenced code blocks are ignored. Sections longer than the
+/// chunk size set with `with_chunk_size` are split again, with overlap.
+///
+/// # Fields
+/// * `headers` - Header markers and their metadata keys (default: `#` to `###` as `h1` to `h3`)
+/// * `strip_headers` - Whether the header line is removed from the section (default: true)
+/// * `chunk_splitter` - Splitter for long sections
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct MarkdownHeaderTextSplitter {
+    pub headers: Vec<(String, String)>,
+    pub strip_headers: bool,
+    pub chunk_splitter: Option<RecursiveCharacterTextSplitter>,
+}
+
+impl Default for MarkdownHeaderTextSplitter {
+    fn default() -> Self {
+        Self::new(&[("#", "h1"), ("##", "h2"), ("###", "h3")])
+    }
+}
+
+#[allow(dead_code)]
+impl MarkdownHeaderTextSplitter {
+    pub fn new(headers: &[(&str, &str)]) -> Self {
+        let mut headers: Vec<(String, String)> = headers
+            .iter()
+            .map(|(marker, key)| (marker.to_string(), key.to_string()))
+            .collect();
+        // Longest markers first, so "##" is not taken for "#"
+        headers.sort_by_key(|(marker, _)| std::cmp::Reverse(marker.len()));
+
+        Self {
+            headers,
+            strip_headers: true,
+            chunk_splitter: None,
+        }
+    }
+
+    pub fn with_strip_headers(mut self, strip_headers: bool) -> Self {
+        self.strip_headers = strip_headers;
+        self
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize, chunk_overlap: usize) -> Self {
+        self.chunk_splitter = Some(RecursiveCharacterTextSplitter::new(chunk_size, chunk_overlap));
+        self
+    }
+
+    /// Sections of the text with the headers they are under
+    pub fn split_sections(&self, text: &str) -> Vec<(String, Map<String, Value>)> {
+        let mut sections = Vec::new();
+        // Open headers as (level, key, title), outermost first
+        let mut open: Vec<(usize, String, String)> = Vec::new();
+        let mut lines: Vec<&str> = Vec::new();
+        let mut fence: Option<&str> = None;
+
+        for line in text.lines() {
+            let trimmed = line.trim_start();
+
+            if let Some(marker) = fence {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+                lines.push(line);
+                continue;
+            }
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                fence = Some(&trimmed[..3]);
+                lines.push(line);
+                continue;
+            }
+
+            let header = self.headers.iter().find_map(|(marker, key)| {
+                let title = trimmed.strip_prefix(marker.as_str())?.strip_prefix(' ')?;
+                Some((marker.len(), key, title.trim()))
+            });
+
+            match header {
+                Some((level, key, title)) => {
+                    push_section(&mut sections, &lines, &open);
+                    lines.clear();
+
+                    open.retain(|(open_level, _, _)| *open_level < level);
+                    open.push((level, key.clone(), title.to_string()));
+                    if !self.strip_headers {
+                        lines.push(line);
+                    }
+                }
+                None => lines.push(line),
+            }
+        }
+
+        push_section(&mut sections, &lines, &open);
+        sections
+    }
+
+    fn split_section(&self, content: String) -> Vec<String> {
+        match &self.chunk_splitter {
+            Some(splitter) => splitter.split_text(&content),
+            None => vec![content],
+        }
+    }
+}
+
+impl TextSplitter for MarkdownHeaderTextSplitter {
+    fn split_text(&self, text: &str) -> Vec<String> {
+        self.split_sections(text)
+            .into_iter()
+            .flat_map(|(content, _)| self.split_section(content))
+            .collect()
+    }
+
+    fn split_documents(&self, documents: &[Document]) -> Vec<Document> {
+        let mut chunks = Vec::new();
+        for document in documents {
+            for (content, headers) in self.split_sections(&document.content) {
+                for chunk in self.split_section(content) {
+                    let mut metadata = document.metadata.clone();
+                    metadata.extend(headers.clone());
+                    chunks.push(Document {
+                        id: None,
+                        content: chunk,
+                        metadata,
+                    });
+                }
+            }
+        }
+        chunks
+    }
+}
+
+fn push_section(
+    sections: &mut Vec<(String, Map<String, Value>)>,
+    lines: &[&str],
+    open: &[(usize, String, String)],
+) {
+    let content = lines.join("\n").trim().to_string();
+    if content.is_empty() {
+        return;
+    }
+
+    let headers = open
+        .iter()
+        .map(|(_, key, title)| (key.clone(), Value::String(title.clone())))
+        .collect();
+    sections.push((content, headers));
+}
@@ -1,5 +1,3 @@
-use std::time::Duration;
-
 pub mod chat;
 pub mod embed;
 pub mod error;
@@ -7,8 +5,6 @@ pub mod libs;
 pub mod utils;
 pub mod requests;
 
-pub const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
-
 pub static GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
 pub static UPLOAD_BASE_URL: &str = "https://generativelanguage.googleapis.com/upload/v1beta";
 
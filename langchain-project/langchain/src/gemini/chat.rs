@@ -1,10 +1,19 @@
+use async_trait::async_trait;
 use futures::pin_mut;
 use futures::StreamExt;
 use log::error;
 use async_stream::stream;
 use crate::gemini::error::GeminiError;
 use crate::gemini::utils::{
-    GetApiKey, get_mime_type, get_base64_bytes_length
+    GetApiKey, get_mime_type, get_base64_bytes_length,
+    to_gemini_contents, to_gemini_tools, to_gemini_tool_config,
+    to_model_response,
+};
+use crate::llm::chat::ChatModel;
+use crate::llm::error::{ApiError, LlmError};
+use crate::llm::retry::{RateLimiter, RetryPolicy};
+use crate::llm::libs::{
+    ChatMessage, ChatOptions, ModelResponse, Provider,
 };
 use crate::gemini::libs::{
     ChatRequest, Content, Part, FileData, InlineData,
@@ -20,15 +29,21 @@ use std::io::Read;
 use base64::{Engine as _, engine::general_purpose::STANDARD};
 use std::time::Duration;
 use std::fs::metadata;
+use crate::llm::http::{default_client, WithHttpClient};
+use reqwest::Client;
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct ChatGemini {
     pub base_url: String,
+    pub api_url: String,
+    pub upload_url: String,
     pub model: String,
     pub request: ChatRequest,
     pub timeout: Duration,
-    pub max_retries: u32,
+    pub retry_policy: RetryPolicy,
+    pub rate_limiter: Option<RateLimiter>,
+    pub client: Client,
 }
 
 #[allow(dead_code)]
@@ -71,10 +86,14 @@ impl ChatGemini {
         
         Self {
             base_url: base_url,
+            api_url: GEMINI_BASE_URL.to_string(),
+            upload_url: UPLOAD_BASE_URL.to_string(),
             model: model.to_string(),
             request: request,
             timeout: Duration::from_secs(300), // default: 5 minutes
-            max_retries: 3,         // default: 3 times
+            retry_policy: RetryPolicy::default(), // default: 3 retries
+            rate_limiter: None,
+            client: default_client(),
         }
     }
 
@@ -99,17 +118,23 @@ impl ChatGemini {
         } else {
             self.request.contents = Some(vec![content]);
         }
-        
+
+        self.send_request().await
+    }
+
+    pub async fn send_request(self) -> Result<ChatResponse, GeminiError> {
         let response = match request_chat(
+            &self.client,
             &self.base_url,
             &self.request,
             self.timeout,
-            self.max_retries,
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
         ).await {
             Ok(response) => response,
             Err(e) => {
                 error!("Error {:?}", e);
-                return Err(GeminiError::RequestChatError);
+                return Err(e);
             }
         };
  
@@ -117,18 +142,19 @@ impl ChatGemini {
             Ok(response_form) => response_form,
             Err(e) => {
                 error!("Error {:?}", e);
-                return Err(GeminiError::ResponseContentError);
+                return Err(e.into());
             }
         };
 
         if let Some(error) = chat_response.error {
             error!("Error {:?}", error);
-            return Err(GeminiError::ResponseContentError);
+            Err(GeminiError::ApiError(ApiError::from_body(Provider::Gemini, &response)))
         } else {
             let format_response = ChatResponse {
                 candidates: chat_response.candidates,
                 model_version: chat_response.model_version,
                 usage_metadata: chat_response.usage_metadata,
+                prompt_feedback: chat_response.prompt_feedback,
                 chat_history: self.request.contents.clone(),
                 error: None,
             };
@@ -139,7 +165,7 @@ impl ChatGemini {
     pub fn stream_response(
         mut self,
         prompt: String,  // Don't change type for stream
-    ) -> impl futures::Stream<Item = ChatResponse> {
+    ) -> impl futures::Stream<Item = Result<ChatResponse, GeminiError>> {
         stream! {
             self.base_url = self.base_url
                 .replace("generateContent", "streamGenerateContent?alt=sse")
@@ -163,8 +189,11 @@ impl ChatGemini {
             }
 
             let stream = strem_chat(
+                self.client.clone(),
                 self.base_url.clone(),
                 self.request.clone(),
+                self.retry_policy.clone(),
+                self.rate_limiter.clone(),
             );
 
             pin_mut!(stream);
@@ -188,7 +217,7 @@ impl ChatGemini {
         
         let upload_url = format!(
             "{}/files?key={}",
-            UPLOAD_BASE_URL,
+            self.upload_url,
             api_key,
         );
                 
@@ -254,6 +283,7 @@ impl ChatGemini {
         } 
       
         let file_uri = match upload_media(
+            &self.client,
             &upload_url, 
             base64_string,
             display_name,
@@ -272,10 +302,14 @@ impl ChatGemini {
         Ok(
             Self{
                 base_url: self.base_url, 
+                api_url: self.api_url,
+                upload_url: self.upload_url,
                 model: self.model, 
                 request: self.request, 
                 timeout: self.timeout,
-                max_retries: self.max_retries,
+                retry_policy: self.retry_policy,
+                rate_limiter: self.rate_limiter,
+                client: self.client,
             }
         )
     }
@@ -290,11 +324,12 @@ impl ChatGemini {
         let api_key = Self::get_api_key()?;
         let url_cache = format!(
             "{}/cachedContents?key={}", 
-            GEMINI_BASE_URL,
+            self.api_url,
             api_key
         );
 
         let cache_name = match request_cache(
+            &self.client,
             url_cache,
             data,
             mime_type.to_string(),
@@ -423,7 +458,20 @@ impl ChatGemini {
     }
 
     pub fn with_max_retries(mut self, max_retries: u32) -> Self {
-        self.max_retries = max_retries;
+        self.retry_policy.max_retries = max_retries;
+        self
+    }
+
+    /// Replaces the retry policy, e.g. to change the backoff delays
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limits the requests and tokens per minute; clone the same limiter
+    /// into every client that shares the account
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
         self
     }
 
@@ -574,7 +622,7 @@ impl ChatGemini {
         self.model = model.to_string();
         self.base_url = format!(
             "{}/models/{}:generateContent?key={}",
-            GEMINI_BASE_URL,
+            self.api_url,
             model,
             api_key,
         );
@@ -585,13 +633,109 @@ impl ChatGemini {
     pub fn with_api_key(mut self, api_key: &str) -> Self {
         self.base_url = format!(
             "{}/models/{}:generateContent?key={}",
-            GEMINI_BASE_URL,
+            self.api_url,
             self.model,
             api_key,
         );
 
         self
     }
+
+    /// Overrides the API root (default: `GEMINI_BASE_URL`), e.g. to target
+    /// a proxy or a local mock server
+    pub fn with_base_url(mut self, api_url: &str) -> Self {
+        let api_url = api_url.trim_end_matches('/');
+        self.base_url = self.base_url.replacen(&self.api_url, api_url, 1);
+        self.api_url = api_url.to_string();
+        self
+    }
+
+    /// Overrides the media upload root (default: `UPLOAD_BASE_URL`)
+    pub fn with_upload_url(mut self, upload_url: &str) -> Self {
+        self.upload_url = upload_url.trim_end_matches('/').to_string();
+        self
+    }
+}
+
+impl WithHttpClient for ChatGemini {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn with_config_base_url(self, base_url: &str) -> Self {
+        self.with_base_url(base_url)
+    }
 }
 
-impl GetApiKey for ChatGemini {}
\ No newline at end of file
+impl GetApiKey for ChatGemini {}
+
+#[async_trait]
+impl ChatModel for ChatGemini {
+    fn provider(&self) -> Provider {
+        Provider::Gemini
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    async fn generate(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatOptions,
+    ) -> Result<ModelResponse, LlmError> {
+        let mut llm = self.clone();
+
+        // Gemini rejects a response schema together with function calling,
+        // so with tools the schema goes in the system instruction
+        let mut messages = messages.to_vec();
+        if let Some(output_schema) = &options.output_schema {
+            if options.tools.is_empty() {
+                let mut response_schema = output_schema.schema.clone();
+                if let Some(object) = response_schema.as_object_mut() {
+                    object.remove("additionalProperties");
+                }
+                llm = llm.with_response_schema(response_schema);
+            } else {
+                messages.push(ChatMessage::system(&output_schema.instructions()));
+            }
+        }
+        let (system_instruction, contents) = to_gemini_contents(&messages);
+
+        if system_instruction.is_some() {
+            llm.request.system_instruction = system_instruction;
+        }
+        llm.request.contents = Some(contents);
+
+        if !options.tools.is_empty() {
+            llm.request.tools = Some(to_gemini_tools(&options.tools));
+        }
+        if let Some(tool_choice) = &options.tool_choice {
+            llm.request.tool_config = Some(to_gemini_tool_config(tool_choice));
+        }
+        if let Some(temperature) = options.temperature {
+            llm = llm.with_temperature(temperature);
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            llm = llm.with_max_tokens(max_tokens);
+        }
+
+        let response = llm.send_request().await?;
+
+        let block_reason = response
+            .prompt_feedback
+            .as_ref()
+            .and_then(|feedback| feedback["blockReason"].as_str())
+            .map(str::to_string);
+        if let (None, Some(block_reason)) = (&response.candidates, block_reason) {
+            let feedback = response.prompt_feedback.unwrap_or_default();
+            let mut error = ApiError::new(Provider::Gemini, &format!("The prompt was blocked: {}", block_reason))
+                .with_code(&block_reason);
+            error.body = feedback.to_string();
+            return Err(LlmError::ContentFiltered(error));
+        }
+
+        to_model_response(response).ok_or(LlmError::EmptyResponse)
+    }
+}
\ No newline at end of file
@@ -1,22 +1,42 @@
 use crate::gemini::error::GeminiError;
 use crate::gemini::utils::GetApiKey;
 use crate::gemini::libs::{
-    Content, Part, EmbedResponse,EmbedRequest, TaskType
+    BatchEmbedRequest, BatchEmbedResponse, Content, Part, EmbedResponse, EmbedRequest, TaskType
 };
 use crate::gemini::requests::request_embed;
+use crate::llm::embed::{embed_batches, Embeddings};
+use crate::llm::error::LlmError;
+use crate::llm::libs::Provider;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt, TryStreamExt};
 use std::time::Duration;
 use log::error;
+use crate::llm::http::{default_client, WithHttpClient};
+use crate::llm::retry::{RateLimiter, RetryPolicy};
+use reqwest::Client;
 
 pub static GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
 
+/// Requests per `batchEmbedContents` call
+pub const GEMINI_EMBED_MAX_INPUTS: usize = 100;
+
+/// Estimated tokens per `batchEmbedContents` call, 2,048 per input
+pub const GEMINI_EMBED_MAX_TOKENS: usize = GEMINI_EMBED_MAX_INPUTS * 2048;
+
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct EmbedGemini {
     pub base_url: String,
+    pub api_url: String,
     pub model: String,
     pub request: EmbedRequest,
-    pub max_retries: u32,
+    pub retry_policy: RetryPolicy,
+    pub rate_limiter: Option<RateLimiter>,
     pub timeout: Duration,
+    pub batch_size: usize,
+    pub max_batch_tokens: usize,
+    pub max_concurrency: usize,
+    pub client: Client,
 }
 
 #[allow(dead_code)]
@@ -38,13 +58,7 @@ impl EmbedGemini {
             model: model.to_string(),
             content: Content {
                 role: "user".to_string(),
-                parts: vec![Part {
-                    text: Some("Init message.".to_string()),
-                    function_call: None,
-                    function_response: None,
-                    inline_data: None,
-                    file_data: None,
-                }],
+                parts: Vec::new(),
             },
             output_dimensionality: None,
             task_type: TaskType::Unspecified,
@@ -53,10 +67,16 @@ impl EmbedGemini {
         
         Self {
             base_url: base_url,
+            api_url: GEMINI_BASE_URL.to_string(),
             model: model.to_string(),
             request: request,
-            max_retries: 0,
+            retry_policy: RetryPolicy::default(), // default: 3 retries
+            rate_limiter: None,
             timeout: Duration::from_secs(300), // default: 5 minutes
+            batch_size: GEMINI_EMBED_MAX_INPUTS,
+            max_batch_tokens: GEMINI_EMBED_MAX_TOKENS,
+            max_concurrency: 4,
+            client: default_client(),
         }
     }
 
@@ -64,28 +84,15 @@ impl EmbedGemini {
         mut self, 
         input_str: &str
     ) -> Result<EmbedResponse, GeminiError> {
-
-        if self.request.content.parts[0].text == Some("Init message.".to_string()) {
-            self.request.content.parts[0].text = Some(input_str.to_string());
-        } else {
-            let content = Content {
-                role: "user".to_string(),
-                parts: vec![Part {
-                    text: Some(input_str.to_string()),
-                    function_call: None,
-                    function_response: None,
-                    inline_data: None,
-                    file_data: None,
-                }],
-            };
-            self.request.content = content;
-        }
+        self.request.content = text_content(input_str);
 
         let response: String = match request_embed(
+            &self.client,
             &self.base_url,
             self.request.clone(),
-            self.max_retries,
             self.timeout,
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
         ).await {
             Ok(response) => response,
             Err(e) => {
@@ -109,6 +116,72 @@ impl EmbedGemini {
         }
     }
 
+    /// Embeds many texts with `batchEmbedContents` and returns their vectors
+    /// in input order
+    ///
+    /// The texts are split by `batch_size` and `max_batch_tokens`, with up to
+    /// `max_concurrency` requests at a time. The task type, title and output
+    /// dimensionality apply to every text.
+    ///
+    /// # Errors
+    /// The first failed request; the vectors already computed are dropped.
+    pub async fn embed_documents(&self, documents: Vec<String>) -> Result<Vec<Vec<f32>>, GeminiError> {
+        let batches = embed_batches(&documents, self.batch_size, self.max_batch_tokens);
+        let batch_url = self.base_url.replacen(":embedContent", ":batchEmbedContents", 1);
+
+        let requests = batches.into_iter().map(|range| {
+            let request = BatchEmbedRequest {
+                requests: documents[range]
+                    .iter()
+                    .map(|document| EmbedRequest {
+                        model: format!("models/{}", self.model),
+                        content: text_content(document),
+                        ..self.request.clone()
+                    })
+                    .collect(),
+            };
+            let batch_url = batch_url.clone();
+            async move { self.embed_batch(&batch_url, request).await }
+        });
+
+        let batches: Vec<Vec<Vec<f32>>> = stream::iter(requests)
+            .buffered(self.max_concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(batches.into_iter().flatten().collect())
+    }
+
+    async fn embed_batch(
+        &self,
+        batch_url: &str,
+        request: BatchEmbedRequest,
+    ) -> Result<Vec<Vec<f32>>, GeminiError> {
+        let inputs = request.requests.len();
+        let response = request_embed(
+            &self.client,
+            batch_url,
+            request,
+            self.timeout,
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
+        ).await?;
+        let batch_response: BatchEmbedResponse = serde_json::from_str(&response)?;
+
+        if let Some(error) = batch_response.error {
+            error!("Error {:?}", error);
+            return Err(GeminiError::RequestEmbedError);
+        }
+
+        let embeddings = batch_response.embeddings.unwrap_or_default();
+        if embeddings.len() != inputs {
+            error!("Expected {} embeddings, got {}", inputs, embeddings.len());
+            return Err(GeminiError::ResponseContentError);
+        }
+
+        Ok(embeddings.into_iter().map(|embedding| embedding.values).collect())
+    }
+
     pub fn with_output_dimensionality(mut self, output_dimensionality: i32) -> Self {
         self.request.output_dimensionality = Some(output_dimensionality);
         self
@@ -125,7 +198,20 @@ impl EmbedGemini {
     }
 
     pub fn with_max_retries(mut self, max_retries: u32) -> Self {
-        self.max_retries = max_retries;
+        self.retry_policy.max_retries = max_retries;
+        self
+    }
+
+    /// Replaces the retry policy, e.g. to change the backoff delays
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limits the requests and tokens per minute; clone the same limiter
+    /// into every client that shares the account
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
         self
     }
     
@@ -137,13 +223,94 @@ impl EmbedGemini {
     pub fn with_api_key(mut self, api_key: &str) -> Self {
         self.base_url = format!(
             "{}/models/{}:embedContent?key={}",
-            GEMINI_BASE_URL,
+            self.api_url,
             self.model,
             api_key,
         );
 
         self
     }
+
+    /// Overrides the API root (default: `GEMINI_BASE_URL`), e.g. to target
+    /// a proxy or a local mock server
+    pub fn with_base_url(mut self, api_url: &str) -> Self {
+        let api_url = api_url.trim_end_matches('/');
+        self.base_url = self.base_url.replacen(&self.api_url, api_url, 1);
+        self.api_url = api_url.to_string();
+        self
+    }
+
+    /// Limits of one `embed_documents` request: inputs (default and maximum:
+    /// 100) and estimated tokens (default: 204,800)
+    pub fn with_batch_size(mut self, batch_size: usize, max_batch_tokens: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, GEMINI_EMBED_MAX_INPUTS);
+        self.max_batch_tokens = max_batch_tokens.max(1);
+        self
+    }
+
+    /// Requests sent at the same time by `embed_documents` (default: 4)
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+}
+
+impl WithHttpClient for EmbedGemini {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn with_config_base_url(self, base_url: &str) -> Self {
+        self.with_base_url(base_url)
+    }
+}
+
+impl GetApiKey for EmbedGemini {}
+
+#[async_trait]
+impl Embeddings for EmbedGemini {
+    fn provider(&self) -> Provider {
+        Provider::Gemini
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    async fn embed_documents(&self, documents: Vec<String>) -> Result<Vec<Vec<f32>>, LlmError> {
+        if matches!(self.request.task_type, TaskType::Unspecified) {
+            let embed = self.clone().with_task_type(TaskType::RetrievalDocument);
+            return Ok(EmbedGemini::embed_documents(&embed, documents).await?);
+        }
+        Ok(EmbedGemini::embed_documents(self, documents).await?)
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>, LlmError> {
+        // A title is only accepted with the RETRIEVAL_DOCUMENT task type
+        let mut embed = self.clone();
+        if matches!(embed.request.task_type, TaskType::Unspecified | TaskType::RetrievalDocument) {
+            embed.request.task_type = TaskType::RetrievalQuery;
+            embed.request.title = None;
+        }
+
+        let response = embed.embed_content(text).await?;
+        match response.embedding {
+            Some(embedding) => Ok(embedding.values),
+            None => Err(LlmError::EmptyResponse),
+        }
+    }
 }
 
-impl GetApiKey for EmbedGemini {}
\ No newline at end of file
+fn text_content(text: &str) -> Content {
+    Content {
+        role: "user".to_string(),
+        parts: vec![Part {
+            text: Some(text.to_string()),
+            function_call: None,
+            function_response: None,
+            inline_data: None,
+            file_data: None,
+        }],
+    }
+}
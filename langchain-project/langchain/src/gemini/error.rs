@@ -1,8 +1,13 @@
+use crate::llm::error::ApiError;
 use std::env;
 
 #[allow(dead_code)]
 #[derive(Debug, thiserror::Error)]
 pub enum GeminiError {
+    /// An error answered by the API, with its status, code and body
+    #[error("{0}")]
+    ApiError(ApiError),
+
     #[error("Gemini API key not found in environment variables")]
     ApiKeyNotFound,
     
@@ -1,3 +1,4 @@
+use crate::llm::libs::TokenUsage;
 use serde::{Deserialize, Serialize};
 
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Requests ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
@@ -32,7 +33,7 @@ pub struct Content {
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Part {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
@@ -169,6 +170,57 @@ pub struct UsageMetadata {
     pub rompt_token_count: Option<i32>,
     #[serde(rename = "totalTokenCount")]
     pub total_token_count: Option<i32>,
+    #[serde(rename = "cachedContentTokenCount")]
+    pub cached_content_token_count: Option<i32>,
+    #[serde(rename = "thoughtsTokenCount")]
+    pub thoughts_token_count: Option<i32>,
+    #[serde(rename = "promptTokensDetails")]
+    pub prompt_tokens_details: Option<Vec<ModalityTokenCount>>,
+    #[serde(rename = "candidatesTokensDetails")]
+    pub candidates_tokens_details: Option<Vec<ModalityTokenCount>>,
+}
+
+#[allow(dead_code)]
+impl UsageMetadata {
+    /// Normalized usage; Gemini counts thinking tokens apart from
+    /// `candidatesTokenCount`, they are added to the output tokens here
+    pub fn token_usage(&self) -> TokenUsage {
+        let count = |value: Option<i32>| value.unwrap_or(0).max(0) as u32;
+        let modality = |details: &Option<Vec<ModalityTokenCount>>, names: &[&str]| -> u32 {
+            details
+                .iter()
+                .flatten()
+                .filter(|detail| names.contains(&detail.modality.as_str()))
+                .map(|detail| count(detail.token_count))
+                .sum()
+        };
+
+        let thoughts = count(self.thoughts_token_count);
+        let mut token_usage = TokenUsage::new(
+            count(self.rompt_token_count),
+            count(self.candidates_token_count) + thoughts,
+        )
+        .with_cache(0, count(self.cached_content_token_count))
+        .with_reasoning(thoughts)
+        .with_media(
+            modality(&self.prompt_tokens_details, &["IMAGE", "VIDEO"]),
+            modality(&self.prompt_tokens_details, &["AUDIO"]),
+            modality(&self.candidates_tokens_details, &["AUDIO"]),
+        );
+        if let Some(total) = self.total_token_count {
+            token_usage.total_tokens = total.max(0) as u32;
+        }
+        token_usage
+    }
+}
+
+/// Tokens of one modality, e.g. `TEXT`, `IMAGE`, `VIDEO` or `AUDIO`
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModalityTokenCount {
+    pub modality: String,
+    #[serde(rename = "tokenCount")]
+    pub token_count: Option<i32>,
 }
 
 #[allow(dead_code)]
@@ -344,6 +396,9 @@ pub struct ChatResponse {
     pub model_version: Option<String>,
     #[serde(rename = "usageMetadata")]
     pub usage_metadata: Option<UsageMetadata>,
+    /// Why the prompt was blocked, in `blockReason`, when there are no candidates
+    #[serde(rename = "promptFeedback", skip_serializing_if = "Option::is_none")]
+    pub prompt_feedback: Option<serde_json::Value>,
     pub chat_history: Option<Vec<Content>>,
     pub error: Option<ErrorDetails>,
 }
@@ -362,6 +417,22 @@ pub struct EmbedResponse {
     pub error: Option<ErrorDetails>,
 }
 
+/// Body of a `batchEmbedContents` request; the `model` of every request
+/// must be `models/<model>`, the model of the endpoint
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchEmbedRequest {
+    pub requests: Vec<EmbedRequest>,
+}
+
+/// Embeddings of a `batchEmbedContents` request, in request order
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchEmbedResponse {
+    pub embeddings: Option<Vec<Embedding>>,
+    pub error: Option<ErrorDetails>,
+}
+
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Errors ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
 
 #[allow(dead_code)]
@@ -2,15 +2,20 @@ use reqwest::{Client, Response};
 use reqwest::{self, header::{HeaderMap, HeaderValue}};
 use log::{warn, error};
 use async_stream::stream;
-use futures::StreamExt;
+use futures::{pin_mut, StreamExt};
+use crate::llm::sse::sse_events;
 use crate::gemini::libs::{ChatRequest, Part, Content, ChatResponse};
-use crate::gemini::libs::{CacheRequest, InlineData, EmbedRequest};
+use crate::gemini::libs::{CacheRequest, InlineData};
 use crate::gemini::utils::print_pre;
-use crate::gemini::{DEBUG_PRE, DEBUG_POST, RETRY_BASE_DELAY};
+use crate::gemini::{DEBUG_PRE, DEBUG_POST};
+use crate::llm::retry::{body_tokens, send_with_retry, RateLimiter, RetryPolicy};
 use crate::gemini::error::GeminiError;
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
+use serde::Serialize;
 use serde_json::json;
+use crate::llm::error::ApiError;
+use crate::llm::libs::Provider;
 use std::time::Duration;
 use tokio::time::sleep;
 
@@ -20,10 +25,12 @@ use tokio::time::sleep;
 ///
 /// # Arguments
 ///
+/// * `client` - Client shared by the requests of a provider struct
 /// * `url` - The endpoint URL to send the chat request to
 /// * `request` - The chat request object containing the message payload
 /// * `timeout` - Request timeout duration in seconds
-/// * `max_retries` - The maximum number of retry attempts for failed requests.
+/// * `retry_policy` - When to retry failed requests and how long to wait
+/// * `rate_limiter` - Optional limit of requests and tokens per minute
 ///
 /// # Returns
 ///
@@ -33,49 +40,28 @@ use tokio::time::sleep;
 /// # Errors
 ///
 /// This function will return an error if:
-/// * The HTTP client cannot be built
 /// * The request fails to send
 /// * The response cannot be parsed as JSON
 /// * The request times out
 pub async fn request_chat(
+    client: &Client,
     url: &str, 
     request: &ChatRequest, 
     timeout: Duration,
-    max_retries: u32,
+    retry_policy: &RetryPolicy,
+    rate_limiter: Option<&RateLimiter>,
 ) -> Result<String, GeminiError> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-
     // Serializes the request struct into a JSON byte vector
     let request_body = serde_json::to_vec(request)?;
 
-    let mut response: Response = make_request(
-        &client,
-        url, 
-        &request_body, 
-        timeout,
-    ).await?;
-    
     print_pre(&request, DEBUG_PRE);
 
-    for attempt in 1..=max_retries {
-        if response.status().is_success() {
-            break;
-        }
-
-        warn!("Server error (attempt {}/{}): {}", attempt, max_retries, response.status());
-
-        sleep(RETRY_BASE_DELAY).await;
-
-        response = make_request(
-            &client,
-            url,
-            &request_body,
-            timeout,
-        ).await?;
-    }
+    let response: Response = send_with_retry(
+        retry_policy,
+        rate_limiter,
+        body_tokens(&request_body),
+        || make_request(client, url, &request_body, timeout),
+    ).await?;
 
     // Checks if the response status is not successful (i.e., not in the 200-299 range).
     if !response.status().is_success() {
@@ -93,17 +79,13 @@ pub async fn request_chat(
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Upload Media ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
 
 pub async fn upload_media(
+    client: &Client,
     url: &str,
     base64_string: String,
     display_name: &str,
     content_length: &str,
     mime_type: &str,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-
     let mut headers = HeaderMap::new();
     headers.insert("X-Goog-Upload-Protocol", HeaderValue::from_static("resumable"));
     headers.insert("X-Goog-Upload-Command", HeaderValue::from_static("start"));
@@ -137,10 +119,6 @@ pub async fn upload_media(
 
     let body_data = STANDARD.decode(base64_string).expect("Invalid base64 string");
 
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-
     let upload_resp = client
         .post(upload_url)
         .headers(upload_headers)
@@ -177,6 +155,7 @@ pub async fn upload_media(
 ///
 /// # Arguments
 ///
+/// * `client` - Client shared by the requests of a provider struct
 /// * `url` - The endpoint URL for the caching service
 /// * `data` - The data to be cached
 /// * `mime_type` - MIME type of the data being cached
@@ -200,11 +179,12 @@ pub async fn upload_media(
 /// # Errors
 ///
 /// This function will return an error if:
-/// * The HTTP client cannot be built
 /// * The cache request fails to send
 /// * The response cannot be parsed as JSON
 /// * The cache name is missing from the response
+#[allow(clippy::too_many_arguments)]
 pub async fn request_cache(
+    client: &Client,
     url: String,
     data: String,
     mime_type: String,
@@ -213,11 +193,6 @@ pub async fn request_cache(
     ttl: u32,
     timeout: Duration,
 ) -> Result<String, GeminiError> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-
     let mut headers = HeaderMap::new();
     headers.insert("Content-Type", HeaderValue::from_static("application/json"));
     let model_name = format!("models/{}", model);
@@ -260,7 +235,7 @@ pub async fn request_cache(
     let request_body = serde_json::to_vec(&request)?;
 
     let response: Response = make_request(
-        &client,
+        client,
         &url, 
         &request_body, 
         timeout,
@@ -293,9 +268,13 @@ pub async fn request_cache(
 ///
 /// # Arguments
 ///
+/// * `client` - Client shared by the requests of a provider struct
 /// * `url` - The endpoint URL for the embedding service
-/// * `request` - The embedding request containing the input text and model parameters
-/// * `max_retries` - Maximum number of retry attempts if the request fails
+/// * `request` - The embedding request containing the input text and model parameters,
+///   an `EmbedRequest` or a `BatchEmbedRequest`
+/// * `timeout` - The timeout of each attempt
+/// * `retry_policy` - When to retry failed requests and how long to wait
+/// * `rate_limiter` - Optional limit of requests and tokens per minute
 ///
 /// # Returns
 ///
@@ -314,49 +293,28 @@ pub async fn request_cache(
 /// # Errors
 ///
 /// This function will return an error if:
-/// * The HTTP client cannot be built
 /// * The request fails to send
 /// * The response cannot be parsed as JSON
-pub async fn request_embed(
+pub async fn request_embed<T: Serialize>(
+    client: &Client,
     url: &str,
-    request: EmbedRequest,
-    max_retries: u32,
+    request: T,
     timeout: Duration,
+    retry_policy: &RetryPolicy,
+    rate_limiter: Option<&RateLimiter>,
 ) -> Result<String, GeminiError> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
- 
     print_pre(&request, DEBUG_PRE);
 
     // Serializes the request struct into a JSON byte vector
     let request_body = serde_json::to_vec(&request)?;
     
-    let mut response: Response = make_request(
-        &client,
-        url, 
-        &request_body, 
-        timeout,
+    let response: Response = send_with_retry(
+        retry_policy,
+        rate_limiter,
+        body_tokens(&request_body),
+        || make_request(client, url, &request_body, timeout),
     ).await?;
 
-    for attempt in 1..=max_retries {
-        if response.status().is_success() {
-            break;
-        }
-
-        warn!("Server error (attempt {}/{}): {}", attempt, max_retries, response.status());
-
-        sleep(RETRY_BASE_DELAY).await;
-
-        response = make_request(
-            &client,
-            url, 
-            &request_body, 
-            timeout,
-        ).await?;
-    }
-
     // Checks if the response status is not successful (i.e., not in the 200-299 range).
     if !response.status().is_success() {
         let gemini_error: GeminiError = manage_error(response).await;
@@ -370,60 +328,90 @@ pub async fn request_embed(
     Ok(response_string)
 }
 
+/// Streams a chat request and yields each decoded `ChatResponse` chunk
+///
+/// # Arguments
+///
+/// * `client` - Client shared by the requests of a provider struct
+/// * `url` - The `streamGenerateContent?alt=sse` endpoint URL
+/// * `request` - The chat request object containing the message payload
+/// * `retry_policy` - When to retry a failed request, before any chunk arrives
+/// * `rate_limiter` - Optional limit of requests and tokens per minute
+///
+/// # Errors
+///
+/// Failed requests, non-2xx statuses, transport errors and chunks that
+/// cannot be parsed are yielded as `Err` items. A parse error does not end
+/// the stream.
 pub fn strem_chat(
+    client: Client,
     url: String,
     request: ChatRequest,
-) -> impl futures::Stream<Item = ChatResponse> {
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+) -> impl futures::Stream<Item = Result<ChatResponse, GeminiError>> {
     stream! {
-        let client = Client::new();
-
-        let response: Response = match client
-            .post(url)
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await {
+        let request_body = match serde_json::to_vec(&request) {
+            Ok(request_body) => request_body,
+            Err(e) => {
+                yield Err(GeminiError::JsonError(e));
+                return;
+            }
+        };
+
+        let response: Response = match send_with_retry(
+            &retry_policy,
+            rate_limiter.as_ref(),
+            body_tokens(&request_body),
+            || client
+                .post(&url)
+                .header("Content-Type", "application/json")
+                .body(request_body.clone())
+                .send(),
+        ).await {
                 Ok(response) => response,
                 Err(e) => {
                     error!("Error Error sending request: {}", e);
-                    return
+                    yield Err(GeminiError::RequestError(e));
+                    return;
                 }
             };
 
-        if response.status().is_success() {
-            let mut stream = response.bytes_stream();
-
-            while let Some(chunk) = stream.next().await {
-                match chunk {
-                    Ok(bytes) => {
-                        let str_chunk = String::from_utf8_lossy(&bytes);
-                        let parts: Vec<&str> = str_chunk.split("\n\n").collect();
-                        for part in parts {
-                            if !part.is_empty() && part.ends_with("[DONE]") {
-                                break;
-                            }
-                            
-                            if !part.is_empty() && part.starts_with("data:") {
-                                let json_part = part.trim_start_matches("data:");
-                            
-                                match serde_json::from_str::<ChatResponse>(json_part) {
-                                    Ok(stream_response) => {
-                                        yield stream_response;
-                                    },
-                                    Err(e) => {
-                                        warn!("Error Error parsing chunk: {}", e);
-                                    }
-                                }    
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        warn!("Error Error reading chunk: {}", e);
+        if !response.status().is_success() {
+            yield Err(manage_error(response).await);
+            return;
+        }
+
+        let events = sse_events(response.bytes_stream());
+        pin_mut!(events);
+
+        while let Some(event) = events.next().await {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Error Error reading chunk: {}", e);
+                    yield Err(GeminiError::RequestError(e));
+                    return;
+                }
+            };
+
+            if event.is_done() {
+                break;
+            }
+
+            match serde_json::from_str::<ChatResponse>(&event.data) {
+                Ok(stream_response) => {
+                    if stream_response.error.is_some() {
+                        yield Err(GeminiError::ApiError(ApiError::from_body(Provider::Gemini, &event.data)));
+                    } else {
+                        yield Ok(stream_response);
                     }
+                },
+                Err(e) => {
+                    warn!("Error Error parsing chunk: {}", e);
+                    yield Err(GeminiError::JsonError(e));
                 }
             }
-        } else {
-            error!("Error Request failed with status code: {}", response.status());
         }
     }
 }
@@ -465,31 +453,10 @@ pub async fn make_request(
         .await?)
 }
 
+/// Reads the status, request id and error body of a failed response
 pub async fn manage_error(
     response: Response,
 ) -> GeminiError {
     error!("Response code: {}", response.status());
-
-    match response.json::<ChatResponse>().await {
-        Ok(error_detail) => {
-            if let Some(error_message) = error_detail.error {
-                if let Some(message) = error_message.message {
-                    return GeminiError::GenericError {
-                        message,
-                        detail: "ERROR-req-9821".to_string(),
-                    }
-                };
-            }
-            GeminiError::GenericError {
-                message: "Unknown error".to_string(),
-                detail: "ERROR-req-9822".to_string(),
-            }
-        },
-        Err(e) => {
-            GeminiError::GenericError {
-                message: format!("Error: {}", e),
-                detail: "ERROR-req-9823".to_string(),
-            }
-        }
-    }
+    GeminiError::ApiError(ApiError::read(Provider::Gemini, response).await)
 }
\ No newline at end of file
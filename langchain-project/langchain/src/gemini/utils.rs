@@ -1,6 +1,13 @@
 use std::env;
 use crate::gemini::error::GeminiError;
-use crate::gemini::libs::Candidate;
+use crate::gemini::libs::{
+    Candidate, ChatResponse, Content, Part, InlineData, FinishReason,
+    FunctionCall, FunctionResponse, FunctionContent,
+};
+use crate::llm::libs::{
+    ChatMessage, ContentPart, ModelResponse, Role, StopReason,
+    ToolCall, ToolChoice, ToolDefinition,
+};
 use serde_json::{json, Value};
 use schemars::schema::RootSchema;
 use log::{info, error};
@@ -55,6 +62,8 @@ pub fn print_pre(request: &impl serde::Serialize, active: bool) {
 /// 
 /// # Examples
 /// ```
+/// use langchain::gemini::utils::get_mime_type;
+///
 /// let mime = get_mime_type("jpg");
 /// assert_eq!(mime, "image/jpeg");
 /// ```
@@ -208,4 +217,186 @@ pub fn generate_schema(
     }
 
     Ok(Value::Object(response))
-}
\ No newline at end of file
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ ChatModel ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Converts normalized messages into Gemini contents
+///
+/// # Returns
+/// * The system instruction built from all `Role::System` messages
+/// * The remaining conversation. Consecutive tool results are grouped in a
+///   single `function` content, as Gemini expects for parallel calls
+///
+pub fn to_gemini_contents(messages: &[ChatMessage]) -> (Option<Content>, Vec<Content>) {
+    let mut system_parts: Vec<Part> = Vec::new();
+    let mut contents: Vec<Content> = Vec::new();
+
+    for message in messages {
+        match message.role {
+            Role::System => {
+                system_parts.extend(to_gemini_parts(&message.content));
+            }
+            Role::User => {
+                contents.push(Content {
+                    role: "user".to_string(),
+                    parts: to_gemini_parts(&message.content),
+                });
+            }
+            Role::Assistant => {
+                let mut parts = to_gemini_parts(&message.content);
+                for tool_call in &message.tool_calls {
+                    parts.push(Part {
+                        function_call: Some(FunctionCall {
+                            name: tool_call.name.clone(),
+                            args: tool_call.arguments.clone(),
+                        }),
+                        ..Default::default()
+                    });
+                }
+                contents.push(Content {
+                    role: "model".to_string(),
+                    parts,
+                });
+            }
+            Role::Tool => {
+                let name = message.name.clone()
+                    .or_else(|| message.tool_call_id.clone())
+                    .unwrap_or_default();
+                let text = message.text();
+                let content = serde_json::from_str::<Value>(&text)
+                    .unwrap_or(Value::String(text));
+
+                let part = Part {
+                    function_response: Some(FunctionResponse {
+                        name: name.clone(),
+                        response: FunctionContent { name, content },
+                    }),
+                    ..Default::default()
+                };
+
+                match contents.last_mut() {
+                    Some(last) if last.role == "function" => last.parts.push(part),
+                    _ => contents.push(Content {
+                        role: "function".to_string(),
+                        parts: vec![part],
+                    }),
+                }
+            }
+        }
+    }
+
+    let system_instruction = if system_parts.is_empty() {
+        None
+    } else {
+        Some(Content {
+            role: "user".to_string(),
+            parts: system_parts,
+        })
+    };
+
+    (system_instruction, contents)
+}
+
+fn to_gemini_parts(content: &[ContentPart]) -> Vec<Part> {
+    content
+        .iter()
+        .filter_map(|part| match part {
+            ContentPart::Text { text } => Some(Part {
+                text: Some(text.clone()),
+                ..Default::default()
+            }),
+            ContentPart::Image { mime_type, data } => Some(Part {
+                inline_data: Some(InlineData {
+                    mime_type: mime_type.clone(),
+                    data: Some(data.clone()),
+                }),
+                ..Default::default()
+            }),
+            ContentPart::Thinking { .. } | ContentPart::RedactedThinking { .. } => None,
+        })
+        .collect()
+}
+
+/// Converts tool definitions into a Gemini `functionDeclarations` tool
+pub fn to_gemini_tools(tools: &[ToolDefinition]) -> Vec<Value> {
+    let declarations: Vec<Value> = tools
+        .iter()
+        .map(|tool| json!({
+            "name": tool.name,
+            "description": tool.description,
+            "parameters": tool.parameters,
+        }))
+        .collect();
+
+    vec![json!({ "functionDeclarations": declarations })]
+}
+
+pub fn to_gemini_tool_config(tool_choice: &ToolChoice) -> Value {
+    match tool_choice {
+        ToolChoice::Auto => json!({ "function_calling_config": { "mode": "AUTO" } }),
+        ToolChoice::Required => json!({ "function_calling_config": { "mode": "ANY" } }),
+        ToolChoice::None => json!({ "function_calling_config": { "mode": "NONE" } }),
+        ToolChoice::Tool(name) => json!({
+            "function_calling_config": {
+                "mode": "ANY",
+                "allowed_function_names": [name],
+            }
+        }),
+    }
+}
+
+/// Converts the first candidate of a Gemini response into a `ModelResponse`
+///
+/// # Returns
+/// * `None` if the response has no candidate content
+///
+pub fn to_model_response(response: ChatResponse) -> Option<ModelResponse> {
+    let candidate = response.candidates?.into_iter().next()?;
+    let mut content: Vec<ContentPart> = Vec::new();
+    let mut tool_calls: Vec<ToolCall> = Vec::new();
+
+    if let Some(candidate_content) = candidate.content {
+        for part in candidate_content.parts {
+            if let Some(text) = part.text {
+                content.push(ContentPart::Text { text });
+            }
+            if let Some(function_call) = part.function_call {
+                tool_calls.push(ToolCall {
+                    id: function_call.name.clone(),
+                    name: function_call.name,
+                    arguments: function_call.args,
+                });
+            }
+        }
+    }
+
+    let stop_reason = match candidate.finish_reason {
+        _ if !tool_calls.is_empty() => Some(StopReason::ToolUse),
+        Some(FinishReason::Stop) => Some(StopReason::EndTurn),
+        Some(FinishReason::MaxTokens) => Some(StopReason::MaxTokens),
+        Some(FinishReason::Safety)
+        | Some(FinishReason::Recitation)
+        | Some(FinishReason::Blocklist)
+        | Some(FinishReason::ProhibitedContent)
+        | Some(FinishReason::Spii) => Some(StopReason::ContentFilter),
+        Some(reason) => Some(StopReason::Other(format!("{:?}", reason))),
+        None => None,
+    };
+
+    let usage = response.usage_metadata.map(|usage| usage.token_usage());
+
+    Some(ModelResponse {
+        message: ChatMessage {
+            role: Role::Assistant,
+            content,
+            tool_calls,
+            tool_call_id: None,
+            name: None,
+        },
+        stop_reason,
+        usage,
+        model: response.model_version,
+        id: None,
+    })
+}
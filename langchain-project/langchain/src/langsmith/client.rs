@@ -5,6 +5,8 @@ use crate::langsmith::libs::{
 use crate::langsmith::utils::GetApiKey;
 use crate::langsmith::requests::request_langsmith;
 use crate::langsmith::error::LangsmithError;
+use crate::llm::http::{default_client, WithHttpClient};
+use reqwest::Client;
 use serde_json::Value;
 use log::error;
 
@@ -13,6 +15,7 @@ use log::error;
 pub struct LangsmithClient {
     pub request: LangsmithRequest,
     pub api_key: String,
+    pub client: Client,
 }
 
 #[allow(dead_code)]
@@ -23,12 +26,14 @@ impl LangsmithClient {
         Ok(Self {
             request: LangsmithRequest::Unknown,
             api_key: api_key,
+            client: default_client(),
         })
     }
 
     pub async fn invoke(self) -> Result<Value, LangsmithError> {
         
         let response: Value = match request_langsmith(
+            &self.client,
             &self.request,
             &self.api_key,
         ).await {
@@ -208,4 +213,11 @@ impl LangsmithClient {
 
 }
 
+impl WithHttpClient for LangsmithClient {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
 impl GetApiKey for LangsmithClient {}
\ No newline at end of file
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 
 #[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -219,8 +219,17 @@ pub struct Manifest {
 #[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ManifestKwargs {
+    #[serde(default)]
     pub messages: Vec<Message>,
+    #[serde(default)]
     pub input_variables: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial_variables: Option<Map<String, Value>>,
+    /// Set instead of `messages` when the commit is a plain `PromptTemplate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_format: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -236,7 +245,13 @@ pub struct Message {
 #[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MessageKwargs {
-    pub prompt: Prompt,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<Prompt>,
+    /// Set instead of `prompt` by a `MessagesPlaceholder`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variable_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optional: Option<bool>,
 }
 
 #[allow(dead_code)]
@@ -253,7 +268,9 @@ pub struct Prompt {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PromptKwargs {
     pub template: String,
+    #[serde(default)]
     pub input_variables: Vec<String>,
+    #[serde(default)]
     pub template_format: String,
 }
 
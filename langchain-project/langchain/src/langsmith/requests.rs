@@ -7,6 +7,7 @@ use crate::langsmith::error::LangsmithError;
 use serde_json::Value;
 
 pub async fn request_langsmith(
+    client: &Client,
     request: &LangsmithRequest,
     api_key: &str,
 ) -> Result<Value, Box<dyn std::error::Error>> {
@@ -17,6 +18,7 @@ pub async fn request_langsmith(
         LangsmithRequest::GetDataset(dataset_name) => {
             let url = format!("{}/datasets/?name={}", LANGSMITH_BASE_URL, dataset_name);
             response_value = get_request(
+                client,
                 &url,
                 api_key
             ).await?;            
@@ -25,6 +27,7 @@ pub async fn request_langsmith(
             let url = format!("{}/datasets", LANGSMITH_BASE_URL);
             let request_json = serde_json::to_value(&request_create_dataset)?;
             response_value = post_request(
+                client,
                 request_json, 
                 &url, 
                 api_key
@@ -34,6 +37,7 @@ pub async fn request_langsmith(
             let url = format!("{}/examples", LANGSMITH_BASE_URL);
             let request_json = serde_json::to_value(&request_create_example)?;
             response_value = post_request(
+                client,
                 request_json, 
                 &url, 
                 api_key
@@ -43,6 +47,7 @@ pub async fn request_langsmith(
             let url = format!("{}/model-price-map", LANGSMITH_BASE_URL);
             let request_json = serde_json::to_value(&request_model)?;
             response_value = post_request(
+                client,
                 request_json, 
                 &url, 
                 api_key
@@ -56,6 +61,7 @@ pub async fn request_langsmith(
                 request_repo.repo,
             );
             response_value = get_request(
+                client,
                 &url,
                 api_key
             ).await?;
@@ -69,6 +75,7 @@ pub async fn request_langsmith(
                 request_commit.commit,
             );
             response_value = get_request(
+                client,
                 &url,
                 api_key
             ).await?;
@@ -85,11 +92,7 @@ pub async fn request_langsmith(
     Ok(response_value)
 }
 
-pub async fn get_request(url: &str, api_key: &str) -> Result<Value, LangsmithError> {
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-
+pub async fn get_request(client: &Client, url: &str, api_key: &str) -> Result<Value, LangsmithError> {
     let response = client
         .request(reqwest::Method::GET, url)
         .header("X-API-Key", api_key)
@@ -113,11 +116,7 @@ pub async fn get_request(url: &str, api_key: &str) -> Result<Value, LangsmithErr
     Ok(response_data)
 }
 
-pub async fn post_request(request: Value, url: &str, api_key: &str) -> Result<Value, LangsmithError> {
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-
+pub async fn post_request(client: &Client, request: Value, url: &str, api_key: &str) -> Result<Value, LangsmithError> {
     print_pre(&request, DEBUG_PRE);
 
     let response = client
@@ -1,7 +1,13 @@
 pub mod anthropic;
 pub mod assembly;
+pub mod chains;
 pub mod compatible;
+pub mod documents;
 pub mod gemini;
 pub mod langsmith;
+pub mod llm;
+pub mod memory;
 pub mod openai;
+pub mod prompts;
 pub mod agents;
+pub mod vectorstores;
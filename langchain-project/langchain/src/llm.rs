@@ -0,0 +1,9 @@
+pub mod chat;
+pub mod cost;
+pub mod embed;
+pub mod error;
+pub mod http;
+pub mod jsonl;
+pub mod libs;
+pub mod retry;
+pub mod sse;
@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+use crate::llm::error::LlmError;
+use crate::llm::libs::{
+    ChatMessage, ChatOptions, ModelResponse, Provider,
+};
+
+/// Common interface implemented by every chat client
+///
+/// `ChatGemini`, `ChatAnthropic`, `ChatOpenAI` and `ChatCompatible` all
+/// implement this trait, so callers can hold a `Box<dyn ChatModel>` and swap
+/// providers without touching provider specific types.
+///
+/// The client configuration (api key, model, timeouts, builder settings) is
+/// kept as is; `generate` only replaces the conversation and applies the
+/// per-call `ChatOptions`.
+#[async_trait]
+pub trait ChatModel: Send + Sync {
+    fn provider(&self) -> Provider;
+
+    fn model_name(&self) -> String;
+
+    async fn generate(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatOptions,
+    ) -> Result<ModelResponse, LlmError>;
+
+    /// Sends a single user prompt and returns the text answer
+    async fn generate_text(&self, prompt: &str) -> Result<String, LlmError> {
+        let messages = vec![ChatMessage::user(prompt)];
+        let response = self.generate(&messages, &ChatOptions::default()).await?;
+        Ok(response.text())
+    }
+}
@@ -0,0 +1,494 @@
+use crate::llm::chat::ChatModel;
+use crate::llm::error::LlmError;
+use crate::llm::libs::{ChatMessage, ChatOptions, ModelResponse, Provider, TokenUsage};
+use async_trait::async_trait;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Prices are given per million tokens
+const TOKENS_PER_PRICE_UNIT: f64 = 1_000_000.0;
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ ModelPrice ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Price of a model, per million tokens
+///
+/// Each optional price applies to its part of the input or output tokens
+/// of a `TokenUsage`; without one, that part is billed as plain input or
+/// output.
+///
+/// # Fields
+/// * `input` - Prompt tokens
+/// * `output` - Generated tokens
+/// * `cache_write` - Prompt tokens written to the cache
+/// * `cache_read` - Prompt tokens read from the cache
+/// * `reasoning` - Output tokens spent thinking
+/// * `image_input` - Prompt tokens of images and videos
+/// * `audio_input` - Prompt tokens of audio
+/// * `audio_output` - Generated audio tokens
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_input: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_input: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_output: Option<f64>,
+}
+
+#[allow(dead_code)]
+impl ModelPrice {
+    pub fn new(input: f64, output: f64) -> Self {
+        Self {
+            input,
+            output,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_cache(mut self, cache_write: f64, cache_read: f64) -> Self {
+        self.cache_write = Some(cache_write);
+        self.cache_read = Some(cache_read);
+        self
+    }
+
+    pub fn with_reasoning(mut self, reasoning: f64) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
+    pub fn with_image_input(mut self, image_input: f64) -> Self {
+        self.image_input = Some(image_input);
+        self
+    }
+
+    pub fn with_audio(mut self, audio_input: f64, audio_output: f64) -> Self {
+        self.audio_input = Some(audio_input);
+        self.audio_output = Some(audio_output);
+        self
+    }
+
+    /// Cost of `usage` at this price
+    pub fn cost(&self, usage: &TokenUsage) -> Cost {
+        let price = |tokens: u32, price: f64| tokens as f64 * price / TOKENS_PER_PRICE_UNIT;
+
+        let plain_input = usage.input_tokens
+            .saturating_sub(usage.cache_creation_input_tokens)
+            .saturating_sub(usage.cache_read_input_tokens)
+            .saturating_sub(usage.image_input_tokens)
+            .saturating_sub(usage.audio_input_tokens);
+        let plain_output = usage.output_tokens
+            .saturating_sub(usage.reasoning_tokens)
+            .saturating_sub(usage.audio_output_tokens);
+
+        let input = price(plain_input, self.input)
+            + price(usage.image_input_tokens, self.image_input.unwrap_or(self.input))
+            + price(usage.audio_input_tokens, self.audio_input.unwrap_or(self.input));
+        let cache_write = price(usage.cache_creation_input_tokens, self.cache_write.unwrap_or(self.input));
+        let cache_read = price(usage.cache_read_input_tokens, self.cache_read.unwrap_or(self.input));
+        let output = price(plain_output, self.output)
+            + price(usage.reasoning_tokens, self.reasoning.unwrap_or(self.output))
+            + price(usage.audio_output_tokens, self.audio_output.unwrap_or(self.output));
+
+        Cost {
+            input,
+            cache_write,
+            cache_read,
+            output,
+            total: input + cache_write + cache_read + output,
+        }
+    }
+}
+
+/// Cost of one or more model calls, in the currency of the `PriceTable`
+///
+/// # Fields
+/// * `input` - Uncached prompt tokens, images and audio included
+/// * `cache_write` - Prompt tokens written to the cache
+/// * `cache_read` - Prompt tokens read from the cache
+/// * `output` - Generated tokens, reasoning and audio included
+/// * `total` - Sum of the other costs
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct Cost {
+    pub input: f64,
+    pub cache_write: f64,
+    pub cache_read: f64,
+    pub output: f64,
+    pub total: f64,
+}
+
+#[allow(dead_code)]
+impl Cost {
+    pub fn add(&mut self, other: &Cost) {
+        self.input += other.input;
+        self.cache_write += other.cache_write;
+        self.cache_read += other.cache_read;
+        self.output += other.output;
+        self.total += other.total;
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ PriceTable ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Prices by model name
+///
+/// A model is priced by its exact name, else by the longest name of the
+/// table it starts with, so `gpt-4o-mini` also prices the snapshot
+/// `gpt-4o-mini-2024-07-18`. No prices are built in: they change and
+/// depend on the contract, so load them from your own file.
+///
+/// # Example
+/// ```ignore
+/// // {"currency": "USD", "prices": {"gpt-4o-mini": {"input": 0.15, "output": 0.6, "cache_read": 0.075}}}
+/// let prices = PriceTable::from_file("prices.json")?
+///     .with_price("claude-3-5-haiku", ModelPrice::new(0.8, 4.0).with_cache(1.0, 0.08));
+/// ```
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PriceTable {
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    pub prices: BTreeMap<String, ModelPrice>,
+}
+
+fn default_currency() -> String {
+    "USD".to_string()
+}
+
+impl Default for PriceTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[allow(dead_code)]
+impl PriceTable {
+    pub fn new() -> Self {
+        Self {
+            currency: default_currency(),
+            prices: BTreeMap::new(),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(Self::from_json(&content)?)
+    }
+
+    pub fn with_currency(mut self, currency: &str) -> Self {
+        self.currency = currency.to_string();
+        self
+    }
+
+    pub fn with_price(mut self, model: &str, price: ModelPrice) -> Self {
+        self.prices.insert(model.to_string(), price);
+        self
+    }
+
+    /// Price of `model`, by exact name or longest matching prefix
+    pub fn price(&self, model: &str) -> Option<&ModelPrice> {
+        self.prices.get(model).or_else(|| {
+            self.prices
+                .iter()
+                .filter(|(name, _)| model.starts_with(name.as_str()))
+                .max_by_key(|(name, _)| name.len())
+                .map(|(_, price)| price)
+        })
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ CostTracker ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Tokens of many requests, with the fields of `TokenUsage`
+///
+/// The counters are 64 bits wide so that long-running totals do not
+/// saturate at the 32-bit limit of a single response.
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct UsageTotals {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    pub reasoning_tokens: u64,
+    pub image_input_tokens: u64,
+    pub audio_input_tokens: u64,
+    pub audio_output_tokens: u64,
+}
+
+#[allow(dead_code)]
+impl UsageTotals {
+    pub fn add(&mut self, other: &UsageTotals) {
+        self.input_tokens = self.input_tokens.saturating_add(other.input_tokens);
+        self.output_tokens = self.output_tokens.saturating_add(other.output_tokens);
+        self.total_tokens = self.total_tokens.saturating_add(other.total_tokens);
+        self.cache_creation_input_tokens = self.cache_creation_input_tokens.saturating_add(other.cache_creation_input_tokens);
+        self.cache_read_input_tokens = self.cache_read_input_tokens.saturating_add(other.cache_read_input_tokens);
+        self.reasoning_tokens = self.reasoning_tokens.saturating_add(other.reasoning_tokens);
+        self.image_input_tokens = self.image_input_tokens.saturating_add(other.image_input_tokens);
+        self.audio_input_tokens = self.audio_input_tokens.saturating_add(other.audio_input_tokens);
+        self.audio_output_tokens = self.audio_output_tokens.saturating_add(other.audio_output_tokens);
+    }
+}
+
+impl From<&TokenUsage> for UsageTotals {
+    fn from(usage: &TokenUsage) -> Self {
+        Self {
+            input_tokens: usage.input_tokens.into(),
+            output_tokens: usage.output_tokens.into(),
+            total_tokens: usage.total_tokens.into(),
+            cache_creation_input_tokens: usage.cache_creation_input_tokens.into(),
+            cache_read_input_tokens: usage.cache_read_input_tokens.into(),
+            reasoning_tokens: usage.reasoning_tokens.into(),
+            image_input_tokens: usage.image_input_tokens.into(),
+            audio_input_tokens: usage.audio_input_tokens.into(),
+            audio_output_tokens: usage.audio_output_tokens.into(),
+        }
+    }
+}
+
+/// Requests, tokens and cost of a session, a model or everything
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct UsageSummary {
+    pub requests: u64,
+    pub usage: UsageTotals,
+    pub cost: Cost,
+}
+
+#[allow(dead_code)]
+impl UsageSummary {
+    pub fn add(&mut self, other: &UsageSummary) {
+        self.requests += other.requests;
+        self.usage.add(&other.usage);
+        self.cost.add(&other.cost);
+    }
+}
+
+/// Usage of one session, by model
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SessionReport {
+    pub total: UsageSummary,
+    pub models: BTreeMap<String, UsageSummary>,
+}
+
+/// Everything a `CostTracker` recorded, e.g. for a chargeback per tenant
+///
+/// # Fields
+/// * `currency` - Currency of every cost
+/// * `total` - All sessions and models
+/// * `models` - All sessions, by model
+/// * `sessions` - By session, then by model
+/// * `unpriced_models` - Models missing from the price table; their tokens
+///   are counted at no cost
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CostReport {
+    pub currency: String,
+    pub total: UsageSummary,
+    pub models: BTreeMap<String, UsageSummary>,
+    pub sessions: BTreeMap<String, SessionReport>,
+    pub unpriced_models: Vec<String>,
+}
+
+/// Accumulates token usage and cost by session and by model
+///
+/// Clones share the records, so one tracker can be given to every client
+/// and task. A session is any key to bill by: a tenant, a user or a
+/// conversation.
+///
+/// # Example
+/// ```ignore
+/// let tracker = CostTracker::new(PriceTable::from_file("prices.json")?);
+/// let llm = tracker.track(Arc::new(ChatOpenAI::new("gpt-4o-mini")), "tenant-42");
+/// llm.generate_text("Hello").await?;
+/// tracker.export_json("usage.json")?;
+/// ```
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct CostTracker {
+    pub prices: PriceTable,
+    state: Arc<Mutex<TrackerState>>,
+}
+
+#[derive(Debug, Default)]
+struct TrackerState {
+    sessions: BTreeMap<String, BTreeMap<String, UsageSummary>>,
+    unpriced_models: BTreeSet<String>,
+}
+
+#[allow(dead_code)]
+impl CostTracker {
+    pub fn new(prices: PriceTable) -> Self {
+        Self {
+            prices,
+            state: Arc::default(),
+        }
+    }
+
+    /// Records one request of `model` and returns its cost
+    pub fn record(&self, session: &str, model: &str, usage: &TokenUsage) -> Cost {
+        let price = self.prices.price(model);
+        let cost = price.map(|price| price.cost(usage)).unwrap_or_default();
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if price.is_none() && state.unpriced_models.insert(model.to_string()) {
+            warn!("No price for model {}, its usage is counted at no cost", model);
+        }
+        state.sessions
+            .entry(session.to_string())
+            .or_default()
+            .entry(model.to_string())
+            .or_default()
+            .add(&UsageSummary { requests: 1, usage: usage.into(), cost });
+
+        cost
+    }
+
+    /// Records the usage of a response, if it has one
+    pub fn record_response(&self, session: &str, model: &str, response: &ModelResponse) -> Option<Cost> {
+        let model = response.model.as_deref().unwrap_or(model);
+        response.usage.as_ref().map(|usage| self.record(session, model, usage))
+    }
+
+    /// Wraps `llm` so that every `generate` call is recorded under `session`
+    pub fn track(&self, llm: Arc<dyn ChatModel>, session: &str) -> TrackedChatModel {
+        TrackedChatModel {
+            llm,
+            tracker: self.clone(),
+            session: session.to_string(),
+        }
+    }
+
+    pub fn session(&self, session: &str) -> UsageSummary {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut summary = UsageSummary::default();
+        for model in state.sessions.get(session).into_iter().flat_map(|models| models.values()) {
+            summary.add(model);
+        }
+        summary
+    }
+
+    pub fn model(&self, model: &str) -> UsageSummary {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut summary = UsageSummary::default();
+        for models in state.sessions.values() {
+            if let Some(usage) = models.get(model) {
+                summary.add(usage);
+            }
+        }
+        summary
+    }
+
+    pub fn total(&self) -> UsageSummary {
+        self.report().total
+    }
+
+    /// Forgets the records of `session`, e.g. once it has been billed
+    pub fn clear_session(&self, session: &str) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.sessions.remove(session);
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state = TrackerState::default();
+    }
+
+    pub fn report(&self) -> CostReport {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut report = CostReport {
+            currency: self.prices.currency.clone(),
+            total: UsageSummary::default(),
+            models: BTreeMap::new(),
+            sessions: BTreeMap::new(),
+            unpriced_models: state.unpriced_models.iter().cloned().collect(),
+        };
+
+        for (session, models) in &state.sessions {
+            let mut session_report = SessionReport::default();
+            for (model, summary) in models {
+                session_report.total.add(summary);
+                report.models.entry(model.clone()).or_default().add(summary);
+                session_report.models.insert(model.clone(), *summary);
+            }
+            report.total.add(&session_report.total);
+            report.sessions.insert(session.clone(), session_report);
+        }
+        report
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self.report()).unwrap_or_default()
+    }
+
+    /// Writes the report as pretty-printed JSON
+    pub fn export_json(&self, path: impl AsRef<Path>) -> Result<(), std::io::Error> {
+        let json = serde_json::to_vec_pretty(&self.report())?;
+        std::fs::write(path, json)
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TrackedChatModel ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// A `ChatModel` whose calls are recorded by a `CostTracker`, built with
+/// `CostTracker::track`
+///
+/// Responses are recorded under the model name they report, else the name
+/// of the wrapped model.
+#[allow(dead_code)]
+#[derive(Clone)]
+pub struct TrackedChatModel {
+    pub llm: Arc<dyn ChatModel>,
+    pub tracker: CostTracker,
+    pub session: String,
+}
+
+impl fmt::Debug for TrackedChatModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrackedChatModel")
+            .field("model", &self.llm.model_name())
+            .field("session", &self.session)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl ChatModel for TrackedChatModel {
+    fn provider(&self) -> Provider {
+        self.llm.provider()
+    }
+
+    fn model_name(&self) -> String {
+        self.llm.model_name()
+    }
+
+    async fn generate(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatOptions,
+    ) -> Result<ModelResponse, LlmError> {
+        let response = self.llm.generate(messages, options).await?;
+        self.tracker.record_response(&self.session, &self.llm.model_name(), &response);
+        Ok(response)
+    }
+}
@@ -0,0 +1,107 @@
+use async_trait::async_trait;
+use crate::llm::error::LlmError;
+use crate::llm::libs::Provider;
+use std::ops::Range;
+
+/// Rough token count of a text, about 4 characters per token for English
+///
+/// Used to keep embedding requests under the provider limits without a
+/// tokenizer; it overestimates rather than underestimates for short words.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4).max(1)
+}
+
+/// Splits the inputs into consecutive batches of at most `max_items` inputs
+/// and about `max_tokens` tokens
+///
+/// An input over `max_tokens` on its own gets a batch of its own; the
+/// provider truncates or rejects it.
+pub fn embed_batches(inputs: &[String], max_items: usize, max_tokens: usize) -> Vec<Range<usize>> {
+    let max_items = max_items.max(1);
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut tokens = 0;
+
+    for (index, input) in inputs.iter().enumerate() {
+        let input_tokens = estimate_tokens(input);
+        let full = index - start >= max_items || tokens + input_tokens > max_tokens;
+        if index > start && full {
+            batches.push(start..index);
+            start = index;
+            tokens = 0;
+        }
+        tokens += input_tokens;
+    }
+
+    if start < inputs.len() {
+        batches.push(start..inputs.len());
+    }
+    batches
+}
+
+/// Cosine similarity of two vectors, 0.0 when either is all zeros or the
+/// lengths differ
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    let mut dot = 0.0;
+    let mut norm_a = 0.0;
+    let mut norm_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Embeddings ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Common interface implemented by every embedding client
+///
+/// `EmbedGemini`, `EmbedOpenAI` and `EmbedVoyage` all implement this trait,
+/// so vector stores and apps can hold an `Arc<dyn Embeddings>` and swap
+/// providers. Clients that tell documents from queries (Gemini task types,
+/// Voyage input types) embed documents as documents when no type is set,
+/// and queries as queries unless a non-retrieval type is set.
+#[async_trait]
+pub trait Embeddings: Send + Sync {
+    fn provider(&self) -> Provider;
+
+    fn model_name(&self) -> String;
+
+    /// Embeds many texts and returns their vectors in input order
+    async fn embed_documents(&self, documents: Vec<String>) -> Result<Vec<Vec<f32>>, LlmError>;
+
+    /// Embeds a search query
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>, LlmError> {
+        let mut vectors = self.embed_documents(vec![text.to_string()]).await?;
+        vectors.pop().ok_or(LlmError::EmptyResponse)
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Reranker ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Common interface of the rerank models, implemented by `EmbedRankVoyage`
+///
+/// A reranker reads the query and each document together, so it orders the
+/// candidates of a vector search better than the embedding distance alone.
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    fn model_name(&self) -> String;
+
+    /// Indexes of the documents with their relevance scores, most relevant
+    /// first, keeping the `top_k` best when set
+    async fn rerank(
+        &self,
+        query: &str,
+        documents: Vec<String>,
+        top_k: Option<usize>,
+    ) -> Result<Vec<(usize, f32)>, LlmError>;
+}
@@ -0,0 +1,375 @@
+use crate::anthropic::error::AnthropicError;
+use crate::compatible::error::CompatibleChatError;
+use crate::gemini::error::GeminiError;
+use crate::llm::libs::Provider;
+use crate::openai::error::OpenAIError;
+use reqwest::header::HeaderMap;
+use reqwest::Response;
+use serde_json::Value;
+use std::fmt;
+
+/// Errors of the provider-neutral `ChatModel` and `Embeddings` traits
+///
+/// Errors answered by an API are sorted in the variants from
+/// `Authentication` to `Timeout`, whatever the provider, so callers can
+/// branch on them; each keeps the whole `ApiError`. Other provider errors,
+/// e.g. a missing API key, keep their provider type.
+///
+/// # Example
+/// ```ignore
+/// match llm.generate(&messages, &options).await {
+///     Err(LlmError::ContextLengthExceeded(_)) => retry_with_shorter_history().await,
+///     Err(LlmError::RateLimit(e)) => warn!("Rate limited, request id {:?}", e.request_id),
+///     other => other?,
+/// }
+/// ```
+#[allow(dead_code)]
+#[derive(Debug, thiserror::Error)]
+pub enum LlmError {
+    #[error("{0}")]
+    Authentication(ApiError),
+
+    #[error("{0}")]
+    RateLimit(ApiError),
+
+    #[error("{0}")]
+    ContextLengthExceeded(ApiError),
+
+    #[error("{0}")]
+    ContentFiltered(ApiError),
+
+    #[error("{0}")]
+    InvalidRequest(ApiError),
+
+    #[error("{0}")]
+    ServerError(ApiError),
+
+    #[error("{0}")]
+    Timeout(ApiError),
+
+    #[error("Gemini error: {0}")]
+    Gemini(GeminiError),
+
+    #[error("Anthropic error: {0}")]
+    Anthropic(AnthropicError),
+
+    #[error("OpenAI error: {0}")]
+    OpenAI(OpenAIError),
+
+    #[error("Compatible error: {0}")]
+    Compatible(CompatibleChatError),
+
+    #[error("Error in converting to json {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("The model returned no candidates or choices")]
+    EmptyResponse,
+}
+
+#[allow(dead_code)]
+impl LlmError {
+    /// Details of the API error, if the provider answered one
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            Self::Authentication(error)
+            | Self::RateLimit(error)
+            | Self::ContextLengthExceeded(error)
+            | Self::ContentFiltered(error)
+            | Self::InvalidRequest(error)
+            | Self::ServerError(error)
+            | Self::Timeout(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// HTTP status of the API error
+    pub fn status(&self) -> Option<u16> {
+        self.api_error().and_then(|error| error.status)
+    }
+
+    /// Whether the same request may succeed later
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::RateLimit(_) | Self::ServerError(_) | Self::Timeout(_))
+    }
+}
+
+impl From<ApiError> for LlmError {
+    fn from(error: ApiError) -> Self {
+        match error.kind() {
+            ApiErrorKind::Authentication => Self::Authentication(error),
+            ApiErrorKind::RateLimit => Self::RateLimit(error),
+            ApiErrorKind::ContextLengthExceeded => Self::ContextLengthExceeded(error),
+            ApiErrorKind::ContentFiltered => Self::ContentFiltered(error),
+            ApiErrorKind::InvalidRequest => Self::InvalidRequest(error),
+            ApiErrorKind::ServerError => Self::ServerError(error),
+            ApiErrorKind::Timeout => Self::Timeout(error),
+        }
+    }
+}
+
+impl From<GeminiError> for LlmError {
+    fn from(error: GeminiError) -> Self {
+        match error {
+            GeminiError::ApiError(error) => error.into(),
+            GeminiError::RequestError(e) if e.is_timeout() => ApiError::timeout(Provider::Gemini, &e).into(),
+            other => Self::Gemini(other),
+        }
+    }
+}
+
+impl From<AnthropicError> for LlmError {
+    fn from(error: AnthropicError) -> Self {
+        let (code, message) = match error {
+            AnthropicError::ApiError(error) => return error.into(),
+            AnthropicError::RequestError(e) if e.is_timeout() => {
+                return ApiError::timeout(Provider::Anthropic, &e).into();
+            }
+            AnthropicError::APITimeoutError(message) => ("timeout", message),
+            AnthropicError::AuthenticationError(message) => ("authentication_error", message),
+            AnthropicError::PermissionDeniedError(message) => ("permission_error", message),
+            AnthropicError::RateLimitError(message) => ("rate_limit_error", message),
+            AnthropicError::RequestTooLarge(message) => ("request_too_large", message),
+            AnthropicError::BadRequestError(message) => ("invalid_request_error", message),
+            AnthropicError::NotFoundError(message) => ("not_found_error", message),
+            AnthropicError::OverloadedServerError(message) => ("overloaded_error", message),
+            AnthropicError::APIConnectionError(message) => ("api_error", message),
+            other => return Self::Anthropic(other),
+        };
+        ApiError::new(Provider::Anthropic, &message).with_code(code).into()
+    }
+}
+
+impl From<OpenAIError> for LlmError {
+    fn from(error: OpenAIError) -> Self {
+        let (code, message) = match error {
+            OpenAIError::ApiError(error) => return error.into(),
+            OpenAIError::RequestError(e) if e.is_timeout() => {
+                return ApiError::timeout(Provider::OpenAI, &e).into();
+            }
+            OpenAIError::APITimeoutError(message) => ("timeout", message),
+            OpenAIError::AuthenticationError(message) => ("invalid_api_key", message),
+            OpenAIError::PermissionDeniedError(message) => ("permission_error", message),
+            OpenAIError::RateLimitError(message) => ("rate_limit_error", message),
+            OpenAIError::BadRequestError(message) => ("invalid_request_error", message),
+            OpenAIError::NotFoundError(message) => ("not_found_error", message),
+            OpenAIError::InternalServerError(message) => ("server_error", message),
+            other => return Self::OpenAI(other),
+        };
+        ApiError::new(Provider::OpenAI, &message).with_code(code).into()
+    }
+}
+
+impl From<CompatibleChatError> for LlmError {
+    fn from(error: CompatibleChatError) -> Self {
+        match error {
+            CompatibleChatError::ApiError(error) => error.into(),
+            CompatibleChatError::RequestError(e) if e.is_timeout() => {
+                ApiError::timeout(Provider::Compatible, &e).into()
+            }
+            other => Self::Compatible(other),
+        }
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ ApiError ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// An error answered by a provider API, as received
+///
+/// # Fields
+/// * `status` - HTTP status, `None` for errors inside a stream or a batch
+/// * `code` - Provider error code or type, e.g. `rate_limit_error`,
+///   `context_length_exceeded` or `INVALID_ARGUMENT`
+/// * `message` - Message of the provider
+/// * `request_id` - Id of the request, to quote to the provider support
+/// * `body` - Raw response body
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub provider: Provider,
+    pub status: Option<u16>,
+    pub code: Option<String>,
+    pub message: String,
+    pub request_id: Option<String>,
+    pub body: String,
+}
+
+/// Headers that carry the request id, by provider
+static REQUEST_ID_HEADERS: [&str; 3] = ["x-request-id", "request-id", "x-goog-request-id"];
+
+#[allow(dead_code)]
+impl ApiError {
+    pub fn new(provider: Provider, message: &str) -> Self {
+        Self {
+            provider,
+            status: None,
+            code: None,
+            message: message.to_string(),
+            request_id: None,
+            body: String::new(),
+        }
+    }
+
+    /// Reads the status, the request id and the error object of a failed
+    /// response; the body is kept as is, even if it is not JSON
+    ///
+    /// Understands the `{"error": {...}}` bodies of OpenAI, Anthropic and
+    /// Gemini, and the `{"detail": ...}` bodies of some compatible APIs.
+    pub fn from_response(provider: Provider, status: u16, headers: &HeaderMap, body: &str) -> Self {
+        let request_id = REQUEST_ID_HEADERS
+            .iter()
+            .find_map(|name| headers.get(*name).and_then(|value| value.to_str().ok()))
+            .map(str::to_string);
+
+        let mut error = Self::from_body(provider, body);
+        error.status = Some(status);
+        error.request_id = request_id.or(error.request_id);
+        if error.message.is_empty() {
+            error.message = format!("HTTP status {}", status);
+        }
+        error
+    }
+
+    /// Reads a failed response; a body that cannot be read is left empty
+    pub async fn read(provider: Provider, response: Response) -> Self {
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let body = response.text().await.unwrap_or_default();
+        Self::from_response(provider, status, &headers, &body)
+    }
+
+    /// Reads the error object of a JSON body, e.g. of a 200 response that
+    /// reports an error
+    pub fn from_body(provider: Provider, body: &str) -> Self {
+        let json: Value = serde_json::from_str(body).unwrap_or_default();
+        let details = match &json["error"] {
+            Value::Object(_) => &json["error"],
+            _ => &json,
+        };
+
+        let text = |value: &Value| match value {
+            Value::String(text) if !text.is_empty() => Some(text.clone()),
+            _ => None,
+        };
+        let message = text(&details["message"])
+            .or_else(|| text(&json["detail"]))
+            .or_else(|| text(&json["error"]))
+            .unwrap_or_else(|| body.trim().chars().take(500).collect());
+        let code = text(&details["code"])
+            .or_else(|| text(&details["type"]))
+            .or_else(|| text(&details["status"]));
+
+        Self {
+            provider,
+            status: None,
+            code,
+            message,
+            request_id: text(&json["request_id"]),
+            body: body.to_string(),
+        }
+    }
+
+    /// A request that got no answer in time
+    pub fn timeout(provider: Provider, error: &reqwest::Error) -> Self {
+        Self::new(provider, &error.to_string()).with_code("timeout")
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn with_code(mut self, code: &str) -> Self {
+        self.code = Some(code.to_string());
+        self
+    }
+
+    /// Sorts the error from its status, code and message
+    pub fn kind(&self) -> ApiErrorKind {
+        let code = self.code.as_deref().unwrap_or_default().to_lowercase();
+        let message = self.message.to_lowercase();
+        let status = self.status.unwrap_or_default();
+        let code_has = |words: &[&str]| words.iter().any(|word| code.contains(word));
+        let message_has = |words: &[&str]| words.iter().any(|word| message.contains(word));
+
+        if matches!(status, 401 | 403)
+            || code_has(&["api_key", "authentication", "permission", "unauthenticated", "unauthorized"])
+            || message_has(&["api key not valid", "invalid api key", "incorrect api key"])
+        {
+            ApiErrorKind::Authentication
+        } else if status == 429 || code_has(&["rate_limit", "tokens_exceeded", "resource_exhausted", "quota"]) {
+            ApiErrorKind::RateLimit
+        } else if status == 413
+            || code_has(&["context_length", "request_too_large"])
+            || message_has(&[
+                "context length",
+                "context window",
+                "maximum context",
+                "prompt is too long",
+                "input token count",
+            ])
+        {
+            ApiErrorKind::ContextLengthExceeded
+        } else if code_has(&["content_filter", "content_policy", "safety", "blocked"])
+            || message_has(&["content management policy", "content policy", "content filter"])
+        {
+            ApiErrorKind::ContentFiltered
+        } else if matches!(status, 408 | 504) || code_has(&["timeout", "deadline_exceeded"]) {
+            ApiErrorKind::Timeout
+        } else if status >= 500 || code_has(&["server_error", "overloaded", "api_error", "internal", "unavailable"]) {
+            ApiErrorKind::ServerError
+        } else {
+            ApiErrorKind::InvalidRequest
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind())?;
+        if let Some(status) = self.status {
+            write!(f, " ({})", status)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Kind of an `ApiError`, one per `LlmError` variant
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiErrorKind {
+    Authentication,
+    RateLimit,
+    ContextLengthExceeded,
+    ContentFiltered,
+    InvalidRequest,
+    ServerError,
+    Timeout,
+}
+
+impl fmt::Display for ApiErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Authentication => "Authentication Error",
+            Self::RateLimit => "Rate Limit Error",
+            Self::ContextLengthExceeded => "Context Length Exceeded Error",
+            Self::ContentFiltered => "Content Filtered Error",
+            Self::InvalidRequest => "Invalid Request Error",
+            Self::ServerError => "Server Error",
+            Self::Timeout => "Timeout Error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Errors building a client from an `HttpConfig`
+#[allow(dead_code)]
+#[derive(Debug, thiserror::Error)]
+pub enum HttpError {
+    #[error("Invalid header: {0}")]
+    InvalidHeader(String),
+
+    #[error("Client error: {0}")]
+    ClientError(#[from] reqwest::Error),
+}
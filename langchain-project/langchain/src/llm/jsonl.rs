@@ -0,0 +1,52 @@
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use futures::pin_mut;
+
+/// Decodes a byte stream (e.g. `reqwest::Response::bytes_stream`) into the
+/// lines of a JSON Lines file
+///
+/// Lines split across network chunks are joined before they are yielded;
+/// blank lines and trailing `\r` are dropped. Transport errors are passed
+/// through and end the stream.
+pub fn json_lines<S, B, E>(bytes: S) -> impl Stream<Item = Result<String, E>>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+{
+    stream! {
+        let mut buffer: Vec<u8> = Vec::new();
+        pin_mut!(bytes);
+
+        while let Some(chunk) = bytes.next().await {
+            match chunk {
+                Ok(chunk) => {
+                    buffer.extend_from_slice(chunk.as_ref());
+                    while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                        let line: Vec<u8> = buffer.drain(..=end).collect();
+                        if let Some(line) = decode_line(&line) {
+                            yield Ok(line);
+                        }
+                    }
+                }
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            }
+        }
+
+        if let Some(line) = decode_line(&buffer) {
+            yield Ok(line);
+        }
+    }
+}
+
+fn decode_line(line: &[u8]) -> Option<String> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim();
+    if line.is_empty() {
+        None
+    } else {
+        Some(line.to_string())
+    }
+}
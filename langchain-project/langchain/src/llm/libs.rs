@@ -0,0 +1,404 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Messages ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Provider that backs a `ChatModel`
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Gemini,
+    Anthropic,
+    OpenAI,
+    Compatible,
+}
+
+/// Author of a message in a normalized conversation
+///
+/// Providers map `System` to their own system field (`systemInstruction`,
+/// `system` or a developer message) and `Tool` to their tool result format.
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    Tool,
+}
+
+/// A single block of message content
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ContentPart {
+    Text {
+        text: String,
+    },
+    /// Base64 encoded image data
+    Image {
+        mime_type: String,
+        data: String,
+    },
+    /// Reasoning of the model before its answer, with the signature Anthropic
+    /// needs to accept it back in the following turns
+    ///
+    /// Thinking blocks can only be sent back to the provider that made them;
+    /// the other providers drop them, and `RedactedThinking` too, from requests.
+    Thinking {
+        thinking: String,
+        signature: String,
+    },
+    /// Thinking flagged by the safety systems, encrypted by the provider
+    #[serde(rename = "redacted_thinking")]
+    RedactedThinking {
+        data: String,
+    },
+}
+
+/// A tool invocation requested by the model
+///
+/// # Fields
+/// * `id` - Identifier used to match the tool result. Gemini does not return
+///   call ids, so the function name is used instead
+/// * `name` - Name of the tool to run
+/// * `arguments` - Parsed JSON arguments
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// Provider-agnostic chat message
+///
+/// # Fields
+/// * `role` - Author of the message
+/// * `content` - Text and image blocks
+/// * `tool_calls` - Tool calls requested by an assistant message
+/// * `tool_call_id` - Call answered by a `Role::Tool` message
+/// * `name` - Tool name answered by a `Role::Tool` message
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChatMessage {
+    pub role: Role,
+    #[serde(default)]
+    pub content: Vec<ContentPart>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[allow(dead_code)]
+impl ChatMessage {
+    pub fn new(role: Role, text: &str) -> Self {
+        Self {
+            role,
+            content: vec![ContentPart::Text { text: text.to_string() }],
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            name: None,
+        }
+    }
+
+    pub fn system(text: &str) -> Self {
+        Self::new(Role::System, text)
+    }
+
+    pub fn user(text: &str) -> Self {
+        Self::new(Role::User, text)
+    }
+
+    pub fn assistant(text: &str) -> Self {
+        Self::new(Role::Assistant, text)
+    }
+
+    /// Creates an assistant message that requests tool calls
+    pub fn assistant_tool_calls(text: Option<&str>, tool_calls: Vec<ToolCall>) -> Self {
+        let content = match text {
+            Some(text) if !text.is_empty() => vec![ContentPart::Text { text: text.to_string() }],
+            _ => Vec::new(),
+        };
+
+        Self {
+            role: Role::Assistant,
+            content,
+            tool_calls,
+            tool_call_id: None,
+            name: None,
+        }
+    }
+
+    /// Creates the answer to a tool call
+    pub fn tool_result(tool_call_id: &str, name: &str, content: &str) -> Self {
+        Self {
+            role: Role::Tool,
+            content: vec![ContentPart::Text { text: content.to_string() }],
+            tool_calls: Vec::new(),
+            tool_call_id: Some(tool_call_id.to_string()),
+            name: Some(name.to_string()),
+        }
+    }
+
+    pub fn with_image(mut self, data: &str, mime_type: &str) -> Self {
+        self.content.push(ContentPart::Image {
+            mime_type: mime_type.to_string(),
+            data: data.to_string(),
+        });
+        self
+    }
+
+    /// Concatenates all text blocks of the message
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<&str>>()
+            .join("")
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Options ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Tool exposed to the model
+///
+/// # Fields
+/// * `name` - Function name
+/// * `description` - What the tool does, used by the model to pick it
+/// * `parameters` - JSON schema of the arguments
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: Value,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum ToolChoice {
+    Auto,
+    Required,
+    None,
+    Tool(String),
+}
+
+/// JSON schema the answer of the model must follow
+///
+/// OpenAI sends it as a `json_schema` response format and Gemini as its
+/// response schema. Anthropic, compatible endpoints, and Gemini when tools
+/// are set get the schema in a system prompt instead.
+///
+/// # Fields
+/// * `name` - Name of the schema, letters, digits, `_` and `-` only
+/// * `schema` - JSON schema of the answer
+/// * `strict` - Whether OpenAI must enforce the schema exactly
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OutputSchema {
+    pub name: String,
+    pub schema: Value,
+    pub strict: bool,
+}
+
+#[allow(dead_code)]
+impl OutputSchema {
+    /// System prompt asking for a JSON answer that matches the schema
+    pub fn instructions(&self) -> String {
+        format!(
+            "Answer only with a JSON object that matches this JSON schema, without any other text:\n{}",
+            self.schema
+        )
+    }
+}
+
+/// Per-call settings shared by every provider
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct ChatOptions {
+    pub tools: Vec<ToolDefinition>,
+    pub tool_choice: Option<ToolChoice>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub output_schema: Option<OutputSchema>,
+}
+
+#[allow(dead_code)]
+impl ChatOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tools(mut self, tools: Vec<ToolDefinition>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_output_schema(mut self, output_schema: OutputSchema) -> Self {
+        self.output_schema = Some(output_schema);
+        self
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Response ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    EndTurn,
+    MaxTokens,
+    ToolUse,
+    StopSequence,
+    ContentFilter,
+    Other(String),
+}
+
+/// Tokens used by one or more model calls
+///
+/// The cache, reasoning, image and audio counts are parts of the input or
+/// output tokens, not additions to them.
+///
+/// # Fields
+/// * `input_tokens` - Every prompt token, read from the cache or not
+/// * `output_tokens` - Generated tokens, thinking included
+/// * `total_tokens` - Input plus output tokens
+/// * `cache_creation_input_tokens` - Prompt tokens written to the cache
+/// * `cache_read_input_tokens` - Prompt tokens read from the cache
+/// * `reasoning_tokens` - Output tokens spent thinking
+/// * `image_input_tokens` - Prompt tokens of images and videos
+/// * `audio_input_tokens` - Prompt tokens of audio
+/// * `audio_output_tokens` - Generated audio tokens
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct TokenUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub total_tokens: u32,
+    #[serde(default)]
+    pub cache_creation_input_tokens: u32,
+    #[serde(default)]
+    pub cache_read_input_tokens: u32,
+    #[serde(default)]
+    pub reasoning_tokens: u32,
+    #[serde(default)]
+    pub image_input_tokens: u32,
+    #[serde(default)]
+    pub audio_input_tokens: u32,
+    #[serde(default)]
+    pub audio_output_tokens: u32,
+}
+
+#[allow(dead_code)]
+impl TokenUsage {
+    pub fn new(input_tokens: u32, output_tokens: u32) -> Self {
+        Self {
+            input_tokens,
+            output_tokens,
+            total_tokens: input_tokens + output_tokens,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the cached part of `input_tokens`
+    pub fn with_cache(mut self, cache_creation_input_tokens: u32, cache_read_input_tokens: u32) -> Self {
+        self.cache_creation_input_tokens = cache_creation_input_tokens;
+        self.cache_read_input_tokens = cache_read_input_tokens;
+        self
+    }
+
+    /// Sets the thinking part of `output_tokens`
+    pub fn with_reasoning(mut self, reasoning_tokens: u32) -> Self {
+        self.reasoning_tokens = reasoning_tokens;
+        self
+    }
+
+    /// Sets the image and audio parts of the input and output tokens
+    pub fn with_media(mut self, image_input_tokens: u32, audio_input_tokens: u32, audio_output_tokens: u32) -> Self {
+        self.image_input_tokens = image_input_tokens;
+        self.audio_input_tokens = audio_input_tokens;
+        self.audio_output_tokens = audio_output_tokens;
+        self
+    }
+
+    /// Adds the counts of `other`; a count that would overflow stays at
+    /// `u32::MAX`
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.input_tokens = self.input_tokens.saturating_add(other.input_tokens);
+        self.output_tokens = self.output_tokens.saturating_add(other.output_tokens);
+        self.total_tokens = self.total_tokens.saturating_add(other.total_tokens);
+        self.cache_creation_input_tokens = self.cache_creation_input_tokens
+            .saturating_add(other.cache_creation_input_tokens);
+        self.cache_read_input_tokens = self.cache_read_input_tokens
+            .saturating_add(other.cache_read_input_tokens);
+        self.reasoning_tokens = self.reasoning_tokens.saturating_add(other.reasoning_tokens);
+        self.image_input_tokens = self.image_input_tokens.saturating_add(other.image_input_tokens);
+        self.audio_input_tokens = self.audio_input_tokens.saturating_add(other.audio_input_tokens);
+        self.audio_output_tokens = self.audio_output_tokens.saturating_add(other.audio_output_tokens);
+    }
+
+    /// Share of the input tokens read from the cache, between 0 and 1
+    pub fn cache_hit_rate(&self) -> f64 {
+        if self.input_tokens == 0 {
+            0.0
+        } else {
+            self.cache_read_input_tokens as f64 / self.input_tokens as f64
+        }
+    }
+}
+
+/// Normalized result of a `ChatModel::generate` call
+///
+/// # Fields
+/// * `message` - Assistant message, including any tool calls
+/// * `stop_reason` - Why the model stopped generating
+/// * `usage` - Token usage reported by the provider
+/// * `model` - Model that produced the answer
+/// * `id` - Provider response id
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModelResponse {
+    pub message: ChatMessage,
+    pub stop_reason: Option<StopReason>,
+    pub usage: Option<TokenUsage>,
+    pub model: Option<String>,
+    pub id: Option<String>,
+}
+
+#[allow(dead_code)]
+impl ModelResponse {
+    pub fn text(&self) -> String {
+        self.message.text()
+    }
+
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        &self.message.tool_calls
+    }
+
+    pub fn has_tool_calls(&self) -> bool {
+        !self.message.tool_calls.is_empty()
+    }
+}
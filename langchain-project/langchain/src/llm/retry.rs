@@ -0,0 +1,454 @@
+use log::warn;
+use reqwest::header::HeaderMap;
+use reqwest::{Response, StatusCode};
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::time::sleep;
+
+/// Default number of retries of a failed request
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Default longest delay before a retry
+pub const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(60);
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ RetryPolicy ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// When and how long to wait before sending a failed request again
+///
+/// Rate limits (429), timeouts (408), conflicts (409), server errors (5xx)
+/// and connection errors are retried; any other status is returned at once.
+/// The delay doubles on each attempt, from `base_delay` up to `max_delay`,
+/// unless the server asks for one with `Retry-After`, `retry-after-ms` or
+/// the `x-ratelimit-reset-*` headers.
+///
+/// # Fields
+/// * `max_retries` - Retries after the first attempt, 0 disables them
+/// * `base_delay` - Delay before the first retry
+/// * `max_delay` - Longest delay; a longer `Retry-After` ends the retries
+/// * `jitter` - Waits a random time between half and the whole delay, so
+///   parallel clients do not retry together
+///
+/// # Example
+/// ```ignore
+/// let llm = ChatOpenAI::new("gpt-4o-mini")
+///     .with_retry_policy(RetryPolicy::new(5).with_base_delay(Duration::from_millis(500)));
+/// ```
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_RETRIES)
+    }
+}
+
+#[allow(dead_code)]
+impl RetryPolicy {
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            base_delay: Duration::from_secs(1),
+            max_delay: DEFAULT_MAX_DELAY,
+            jitter: true,
+        }
+    }
+
+    /// A policy that sends each request once
+    pub fn none() -> Self {
+        Self::new(0)
+    }
+
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Whether a response with this status may succeed if sent again
+    pub fn is_retryable_status(&self, status: StatusCode) -> bool {
+        matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::REQUEST_TIMEOUT | StatusCode::CONFLICT
+        ) || status.is_server_error()
+    }
+
+    /// Whether a transport error may succeed if sent again
+    pub fn is_retryable_error(&self, error: &reqwest::Error) -> bool {
+        error.is_timeout() || error.is_connect()
+    }
+
+    /// Exponential delay before the retry `attempt`, counted from 1
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        if self.jitter {
+            delay / 2 + delay.mul_f64(random_fraction() / 2.0)
+        } else {
+            delay
+        }
+    }
+
+    /// Delay before the retry `attempt` of a response, the one asked by the
+    /// server if any; `None` if the server asks for more than `max_delay`
+    pub fn retry_delay(&self, attempt: u32, headers: &HeaderMap) -> Option<Duration> {
+        match retry_after(headers) {
+            Some(delay) if delay > self.max_delay => None,
+            Some(delay) => Some(delay),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+}
+
+/// Sends a request with `send` until it succeeds, fails with a fatal error
+/// or runs out of retries, and returns the last result
+///
+/// Each attempt first waits for the rate limiter, if any, which also learns
+/// the limits left from the headers of each response.
+///
+/// # Arguments
+/// * `policy` - When to retry and how long to wait
+/// * `rate_limiter` - Limiter shared by the clients of the same account
+/// * `tokens` - Estimated tokens of the request, for the tokens per minute
+/// * `send` - Sends the request once
+pub async fn send_with_retry<F, Fut>(
+    policy: &RetryPolicy,
+    rate_limiter: Option<&RateLimiter>,
+    tokens: u32,
+    send: F,
+) -> Result<Response, reqwest::Error>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<Response, reqwest::Error>>,
+{
+    let mut attempt = 0;
+    loop {
+        if let Some(rate_limiter) = rate_limiter {
+            rate_limiter.acquire(tokens).await;
+        }
+
+        let result = send().await;
+        let delay = match &result {
+            Ok(response) => {
+                if let Some(rate_limiter) = rate_limiter {
+                    rate_limiter.update_from_headers(response.headers());
+                }
+                if response.status().is_success() || !policy.is_retryable_status(response.status()) {
+                    return result;
+                }
+                policy.retry_delay(attempt + 1, response.headers())
+            }
+            Err(e) if policy.is_retryable_error(e) => Some(policy.backoff(attempt + 1)),
+            Err(_) => None,
+        };
+
+        attempt += 1;
+        let delay = match delay {
+            Some(delay) if attempt <= policy.max_retries => delay,
+            _ => return result,
+        };
+
+        match &result {
+            Ok(response) => warn!(
+                "Server error (attempt {}/{}): {}, retrying in {:?}",
+                attempt, policy.max_retries, response.status(), delay,
+            ),
+            Err(e) => warn!(
+                "Request error (attempt {}/{}): {}, retrying in {:?}",
+                attempt, policy.max_retries, e, delay,
+            ),
+        }
+        sleep(delay).await;
+    }
+}
+
+/// Estimated tokens of a JSON request body, for the tokens per minute
+///
+/// Counts a token per 4 bytes, like `estimate_tokens`; the JSON syntax
+/// makes it a slight overestimate of the prompt.
+pub fn body_tokens(body: &[u8]) -> u32 {
+    u32::try_from(body.len().div_ceil(4)).unwrap_or(u32::MAX)
+}
+
+/// Delay asked by the server, from `retry-after-ms`, `Retry-After` in
+/// seconds or as an HTTP date, or the reset time of an exhausted
+/// `x-ratelimit-*` limit
+///
+/// Values that are negative, not finite or too large for a `Duration` are
+/// ignored.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(millis) = header_str(headers, "retry-after-ms").and_then(|value| value.parse::<f64>().ok()) {
+        return seconds(millis / 1000.0);
+    }
+    if let Some(value) = header_str(headers, "retry-after") {
+        return match value.parse::<f64>() {
+            Ok(delay) => seconds(delay),
+            Err(_) => parse_http_date(value).map(|date| {
+                date.duration_since(SystemTime::now()).unwrap_or_default()
+            }),
+        };
+    }
+
+    ["requests", "tokens"]
+        .iter()
+        .filter(|limit| header_str(headers, &format!("x-ratelimit-remaining-{}", limit)) == Some("0"))
+        .filter_map(|limit| header_str(headers, &format!("x-ratelimit-reset-{}", limit)))
+        .filter_map(parse_reset)
+        .max()
+}
+
+/// Parses an HTTP date like `Wed, 21 Oct 2015 07:28:00 GMT`
+pub fn parse_http_date(value: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    let day: i64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|name| name == month)? as i64 + 1;
+    let year: i64 = year.parse().ok()?;
+    let time: Vec<i64> = time.split(':').map(|part| part.parse().ok()).collect::<Option<_>>()?;
+    let [hours, minutes, secs] = time.as_slice() else {
+        return None;
+    };
+    if !(1..=31).contains(&day) || *hours > 23 || *minutes > 59 || *secs > 60 {
+        return None;
+    }
+
+    // Days since 1970-01-01 of the proleptic Gregorian calendar
+    let (year, month) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let unix_seconds = days * 86_400 + hours * 3600 + minutes * 60 + secs;
+    UNIX_EPOCH.checked_add(Duration::from_secs(u64::try_from(unix_seconds).ok()?))
+}
+
+/// Parses a reset time like `1s`, `6m0s`, `20ms` or `1h2m3.5s`
+pub fn parse_reset(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(delay) = value.parse::<f64>() {
+        return seconds(delay);
+    }
+
+    let mut total = 0.0;
+    let mut number = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let unit = match (c, chars.peek()) {
+            ('m', Some('s')) => {
+                chars.next();
+                0.001
+            }
+            ('h', _) => 3600.0,
+            ('m', _) => 60.0,
+            ('s', _) => 1.0,
+            _ => return None,
+        };
+        total += number.parse::<f64>().ok()? * unit;
+        number.clear();
+    }
+
+    if number.is_empty() {
+        seconds(total)
+    } else {
+        None
+    }
+}
+
+/// A delay in seconds, `None` if it is not finite or does not fit a `Duration`
+fn seconds(value: f64) -> Option<Duration> {
+    if !value.is_finite() {
+        return None;
+    }
+    Duration::try_from_secs_f64(value.max(0.0)).ok()
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok()).map(str::trim)
+}
+
+/// A number in [0, 1) from the random keys of the standard library
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ RateLimiter ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Client-side limit of requests and tokens per minute
+///
+/// Token buckets that refill continuously; a request waits until both
+/// have room. Clones share the buckets, so give the same limiter to every
+/// client of an account, including clones used by parallel tasks. When a
+/// response reports an exhausted limit in its `x-ratelimit-*` headers, all
+/// requests wait for the reset.
+///
+/// # Example
+/// ```ignore
+/// let limiter = RateLimiter::new()
+///     .with_requests_per_minute(500)
+///     .with_tokens_per_minute(200_000);
+/// let llm = ChatOpenAI::new("gpt-4o-mini").with_rate_limiter(limiter.clone());
+/// ```
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u32>,
+    state: Arc<Mutex<LimiterState>>,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    requests: f64,
+    tokens: f64,
+    updated: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl Default for LimiterState {
+    fn default() -> Self {
+        Self {
+            requests: 0.0,
+            tokens: 0.0,
+            updated: Instant::now(),
+            paused_until: None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl RateLimiter {
+    /// A limiter without limits; set them with the `with_*` methods
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_requests_per_minute(mut self, requests_per_minute: u32) -> Self {
+        self.requests_per_minute = Some(requests_per_minute.max(1));
+        self.reset();
+        self
+    }
+
+    pub fn with_tokens_per_minute(mut self, tokens_per_minute: u32) -> Self {
+        self.tokens_per_minute = Some(tokens_per_minute.max(1));
+        self.reset();
+        self
+    }
+
+    /// Waits until a request of `tokens` estimated tokens fits in the limits
+    ///
+    /// A request larger than the tokens per minute waits for a full bucket.
+    pub async fn acquire(&self, tokens: u32) {
+        loop {
+            let wait = {
+                let mut state = self.lock();
+                let now = Instant::now();
+                match state.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        state.paused_until = None;
+                        self.refill(&mut state, now);
+                        match self.missing_time(&state, tokens) {
+                            Some(wait) => wait,
+                            None => {
+                                self.take(&mut state, tokens);
+                                return;
+                            }
+                        }
+                    }
+                }
+            };
+            sleep(wait).await;
+        }
+    }
+
+    /// Pauses the requests until the reset of a limit that a response
+    /// reports as exhausted, for at most `DEFAULT_MAX_DELAY`
+    pub fn update_from_headers(&self, headers: &HeaderMap) {
+        if let Some(reset) = retry_after(headers) {
+            let until = Instant::now() + reset.min(DEFAULT_MAX_DELAY);
+            let mut state = self.lock();
+            if state.paused_until.is_none_or(|paused| paused < until) {
+                state.paused_until = Some(until);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.state = Arc::new(Mutex::new(LimiterState {
+            requests: self.requests_per_minute.unwrap_or_default() as f64,
+            tokens: self.tokens_per_minute.unwrap_or_default() as f64,
+            ..LimiterState::default()
+        }));
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, LimiterState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn refill(&self, state: &mut LimiterState, now: Instant) {
+        let minutes = (now - state.updated).as_secs_f64() / 60.0;
+        state.updated = now;
+        if let Some(limit) = self.requests_per_minute {
+            state.requests = (state.requests + minutes * limit as f64).min(limit as f64);
+        }
+        if let Some(limit) = self.tokens_per_minute {
+            state.tokens = (state.tokens + minutes * limit as f64).min(limit as f64);
+        }
+    }
+
+    fn take(&self, state: &mut LimiterState, tokens: u32) {
+        if self.requests_per_minute.is_some() {
+            state.requests -= 1.0;
+        }
+        if let Some(limit) = self.tokens_per_minute {
+            state.tokens -= tokens.min(limit) as f64;
+        }
+    }
+
+    /// Time until both buckets have room, `None` if they have it now
+    fn missing_time(&self, state: &LimiterState, tokens: u32) -> Option<Duration> {
+        let mut wait: f64 = 0.0;
+        if let Some(limit) = self.requests_per_minute {
+            wait = wait.max((1.0 - state.requests) / limit as f64);
+        }
+        if let Some(limit) = self.tokens_per_minute {
+            let needed = tokens.min(limit) as f64;
+            wait = wait.max((needed - state.tokens) / limit as f64);
+        }
+        if wait > 0.0 {
+            Some(seconds(wait * 60.0).unwrap_or(DEFAULT_MAX_DELAY))
+        } else {
+            None
+        }
+    }
+}
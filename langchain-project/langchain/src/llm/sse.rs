@@ -0,0 +1,215 @@
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use futures::pin_mut;
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Events ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// A server-sent event
+///
+/// # Fields
+/// * `event` - Value of the `event:` field, `None` for the default `message` type
+/// * `data` - All `data:` lines of the event joined with `\n`
+/// * `id` - Last event id seen on the stream
+/// * `retry` - Reconnection time in milliseconds, if the server sent one
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+    pub id: Option<String>,
+    pub retry: Option<u64>,
+}
+
+#[allow(dead_code)]
+impl SseEvent {
+    /// OpenAI style end of stream marker (`data: [DONE]`)
+    pub fn is_done(&self) -> bool {
+        self.data.trim() == "[DONE]"
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Decoder ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Incremental `text/event-stream` decoder
+///
+/// Bytes are buffered until a full line is available, so events and UTF-8
+/// characters split across network chunks are decoded correctly. Lines may
+/// end in `\n`, `\r\n` or `\r`; comments, `event:`, `id:`, `retry:` and
+/// multi-line `data:` fields follow the WHATWG specification.
+///
+/// Unlike a browser, `finish` also dispatches a last event that is not
+/// followed by a blank line, since some providers close the stream right
+/// after the final `data:` line.
+#[allow(dead_code)]
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: String,
+    has_data: bool,
+    last_id: Option<String>,
+    retry: Option<u64>,
+    started: bool,
+}
+
+#[allow(dead_code)]
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a network chunk and returns the events it completed
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+
+        if !self.started {
+            // Wait for enough bytes to tell whether the stream starts with a BOM
+            if self.buffer.len() < 3 && b"\xEF\xBB\xBF".starts_with(&self.buffer) {
+                return Vec::new();
+            }
+            if self.buffer.starts_with(b"\xEF\xBB\xBF") {
+                self.buffer.drain(..3);
+            }
+            self.started = true;
+        }
+
+        let mut events = Vec::new();
+        let mut start = 0;
+        let mut index = 0;
+
+        while index < self.buffer.len() {
+            let line_end = match self.buffer[index] {
+                b'\n' => index + 1,
+                b'\r' => {
+                    // A trailing '\r' may be the first half of "\r\n"
+                    if index + 1 == self.buffer.len() {
+                        break;
+                    }
+                    if self.buffer[index + 1] == b'\n' {
+                        index + 2
+                    } else {
+                        index + 1
+                    }
+                }
+                _ => {
+                    index += 1;
+                    continue;
+                }
+            };
+
+            let line = String::from_utf8_lossy(&self.buffer[start..index]).to_string();
+            if let Some(event) = self.process_line(&line) {
+                events.push(event);
+            }
+
+            start = line_end;
+            index = line_end;
+        }
+
+        self.buffer.drain(..start);
+        events
+    }
+
+    /// Flushes the remaining bytes at the end of the stream
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let mut remaining = std::mem::take(&mut self.buffer);
+        if remaining.last() == Some(&b'\r') {
+            remaining.pop();
+        }
+
+        if !remaining.is_empty() {
+            let line = String::from_utf8_lossy(&remaining).to_string();
+            if let Some(event) = self.process_line(&line) {
+                return Some(event);
+            }
+        }
+
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => {
+                if self.has_data {
+                    self.data.push('\n');
+                }
+                self.data.push_str(value);
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(retry) = value.parse::<u64>() {
+                    self.retry = Some(retry);
+                }
+            }
+            _ => (),
+        }
+
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+
+        if !self.has_data {
+            return None;
+        }
+
+        self.has_data = false;
+        Some(SseEvent {
+            event: event.filter(|event| !event.is_empty()),
+            data: std::mem::take(&mut self.data),
+            id: self.last_id.clone(),
+            retry: self.retry,
+        })
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Stream ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Decodes a byte stream (e.g. `reqwest::Response::bytes_stream`) into
+/// server-sent events
+///
+/// Transport errors are passed through and end the stream.
+pub fn sse_events<S, B, E>(bytes: S) -> impl Stream<Item = Result<SseEvent, E>>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+{
+    stream! {
+        let mut decoder = SseDecoder::new();
+        pin_mut!(bytes);
+
+        while let Some(chunk) = bytes.next().await {
+            match chunk {
+                Ok(chunk) => {
+                    for event in decoder.push(chunk.as_ref()) {
+                        yield Ok(event);
+                    }
+                }
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            }
+        }
+
+        if let Some(event) = decoder.finish() {
+            yield Ok(event);
+        }
+    }
+}
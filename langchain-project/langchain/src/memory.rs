@@ -0,0 +1,5 @@
+pub mod error;
+pub mod libs;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod stores;
@@ -0,0 +1,22 @@
+use crate::llm::error::LlmError;
+
+#[allow(dead_code)]
+#[derive(Debug, thiserror::Error)]
+pub enum MemoryError {
+    #[error("Model error: {0}")]
+    LlmError(#[from] LlmError),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Error in converting to json {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+
+    #[cfg(feature = "sqlite")]
+    #[error("Database task failed: {0}")]
+    TaskError(#[from] tokio::task::JoinError),
+}
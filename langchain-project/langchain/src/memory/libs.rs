@@ -0,0 +1,318 @@
+use crate::llm::chat::ChatModel;
+use crate::llm::embed::estimate_tokens;
+use crate::llm::libs::{ChatMessage, Role};
+use crate::memory::error::MemoryError;
+use crate::memory::stores::ChatMessageStore;
+use async_trait::async_trait;
+use log::debug;
+use std::fmt;
+use std::sync::Arc;
+
+/// Name of the system message that holds the summary of `SummaryMemory`
+pub static SUMMARY_MESSAGE_NAME: &str = "conversation_summary";
+
+/// Start of the text of the summary message
+static SUMMARY_PREFIX: &str = "Summary of the earlier conversation:\n";
+
+/// Default instructions of `SummaryMemory`
+pub static DEFAULT_SUMMARY_PROMPT: &str = "Progressively summarize the lines of conversation \
+provided, adding onto the previous summary and returning a new summary. Keep the names, facts, \
+decisions and open questions the assistant needs to continue the conversation. Answer only \
+with the summary.";
+
+/// What a conversation remembers between turns
+///
+/// `load_messages` gives the history to send before the new input and
+/// `save_messages` records a turn, usually the user message and the answer
+/// of the model. The messages live in a `ChatMessageStore`, so the same
+/// session continues after a restart with a persistent store.
+#[async_trait]
+pub trait ChatMemory: Send + Sync {
+    async fn load_messages(&self, session_id: &str) -> Result<Vec<ChatMessage>, MemoryError>;
+
+    async fn save_messages(&self, session_id: &str, messages: &[ChatMessage]) -> Result<(), MemoryError>;
+
+    async fn clear(&self, session_id: &str) -> Result<(), MemoryError>;
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ BufferMemory ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Sends the whole history
+#[allow(dead_code)]
+pub struct BufferMemory {
+    store: Arc<dyn ChatMessageStore>,
+}
+
+#[allow(dead_code)]
+impl BufferMemory {
+    pub fn new(store: Arc<dyn ChatMessageStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl ChatMemory for BufferMemory {
+    async fn load_messages(&self, session_id: &str) -> Result<Vec<ChatMessage>, MemoryError> {
+        self.store.messages(session_id).await
+    }
+
+    async fn save_messages(&self, session_id: &str, messages: &[ChatMessage]) -> Result<(), MemoryError> {
+        self.store.add_messages(session_id, messages).await
+    }
+
+    async fn clear(&self, session_id: &str) -> Result<(), MemoryError> {
+        self.store.clear(session_id).await
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ WindowMemory ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Sends the last `turns` turns; a turn starts at a user message, so tool
+/// calls stay with their results
+///
+/// The store keeps the whole history.
+#[allow(dead_code)]
+pub struct WindowMemory {
+    store: Arc<dyn ChatMessageStore>,
+    pub turns: usize,
+}
+
+#[allow(dead_code)]
+impl WindowMemory {
+    pub fn new(store: Arc<dyn ChatMessageStore>, turns: usize) -> Self {
+        Self { store, turns }
+    }
+}
+
+#[async_trait]
+impl ChatMemory for WindowMemory {
+    async fn load_messages(&self, session_id: &str) -> Result<Vec<ChatMessage>, MemoryError> {
+        let messages = self.store.messages(session_id).await?;
+        let groups = turns(&messages);
+        let start = groups.len().saturating_sub(self.turns);
+        Ok(groups[start..].concat())
+    }
+
+    async fn save_messages(&self, session_id: &str, messages: &[ChatMessage]) -> Result<(), MemoryError> {
+        self.store.add_messages(session_id, messages).await
+    }
+
+    async fn clear(&self, session_id: &str) -> Result<(), MemoryError> {
+        self.store.clear(session_id).await
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ TokenBufferMemory ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Sends the latest whole turns that fit in `max_tokens`
+///
+/// Tokens are estimated like `embed_documents` does; the store keeps the
+/// whole history.
+#[allow(dead_code)]
+pub struct TokenBufferMemory {
+    store: Arc<dyn ChatMessageStore>,
+    pub max_tokens: usize,
+}
+
+#[allow(dead_code)]
+impl TokenBufferMemory {
+    pub fn new(store: Arc<dyn ChatMessageStore>, max_tokens: usize) -> Self {
+        Self { store, max_tokens }
+    }
+}
+
+#[async_trait]
+impl ChatMemory for TokenBufferMemory {
+    async fn load_messages(&self, session_id: &str) -> Result<Vec<ChatMessage>, MemoryError> {
+        let messages = self.store.messages(session_id).await?;
+        let groups = turns(&messages);
+        let start = groups.len() - latest_fitting(&groups, self.max_tokens);
+        Ok(groups[start..].concat())
+    }
+
+    async fn save_messages(&self, session_id: &str, messages: &[ChatMessage]) -> Result<(), MemoryError> {
+        self.store.add_messages(session_id, messages).await
+    }
+
+    async fn clear(&self, session_id: &str) -> Result<(), MemoryError> {
+        self.store.clear(session_id).await
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ SummaryMemory ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Keeps the latest turns verbatim and a rolling summary of the older ones
+///
+/// When the history grows over `max_tokens`, the oldest turns are
+/// summarized by the chat model, together with the previous summary, and
+/// replaced in the store by a system message named `SUMMARY_MESSAGE_NAME`.
+/// The latest turn is always kept verbatim.
+///
+/// # Fields
+/// * `max_tokens` - Estimated tokens of the history before summarizing
+/// * `summary_prompt` - Instructions given to the model with the turns to summarize
+#[allow(dead_code)]
+pub struct SummaryMemory {
+    store: Arc<dyn ChatMessageStore>,
+    chat: Arc<dyn ChatModel>,
+    pub max_tokens: usize,
+    pub summary_prompt: String,
+}
+
+#[allow(dead_code)]
+impl SummaryMemory {
+    pub fn new(store: Arc<dyn ChatMessageStore>, chat: Arc<dyn ChatModel>, max_tokens: usize) -> Self {
+        Self {
+            store,
+            chat,
+            max_tokens,
+            summary_prompt: DEFAULT_SUMMARY_PROMPT.to_string(),
+        }
+    }
+
+    pub fn with_summary_prompt(mut self, summary_prompt: &str) -> Self {
+        self.summary_prompt = summary_prompt.to_string();
+        self
+    }
+
+    /// Summary of the session, if the history was already compacted
+    pub async fn summary(&self, session_id: &str) -> Result<Option<String>, MemoryError> {
+        let messages = self.store.messages(session_id).await?;
+        Ok(messages.first().and_then(summary_text))
+    }
+
+    async fn summarize(&self, previous: Option<&str>, messages: &[ChatMessage]) -> Result<String, MemoryError> {
+        let prompt = format!(
+            "{}\n\nCurrent summary:\n{}\n\nNew lines of conversation:\n{}\n\nNew summary:",
+            self.summary_prompt,
+            previous.unwrap_or("(empty)"),
+            transcript(messages),
+        );
+        Ok(self.chat.generate_text(&prompt).await?.trim().to_string())
+    }
+}
+
+impl fmt::Debug for SummaryMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SummaryMemory")
+            .field("chat", &self.chat.model_name())
+            .field("max_tokens", &self.max_tokens)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl ChatMemory for SummaryMemory {
+    async fn load_messages(&self, session_id: &str) -> Result<Vec<ChatMessage>, MemoryError> {
+        self.store.messages(session_id).await
+    }
+
+    async fn save_messages(&self, session_id: &str, messages: &[ChatMessage]) -> Result<(), MemoryError> {
+        self.store.add_messages(session_id, messages).await?;
+
+        let history = self.store.messages(session_id).await?;
+        if history.iter().map(message_tokens).sum::<usize>() <= self.max_tokens {
+            return Ok(());
+        }
+
+        let previous = history.first().and_then(summary_text);
+        let rest = if previous.is_some() { &history[1..] } else { &history[..] };
+        let groups = turns(rest);
+        let keep = latest_fitting(&groups, self.max_tokens).max(1).min(groups.len());
+        let pruned = groups[..groups.len() - keep].concat();
+        if pruned.is_empty() {
+            return Ok(());
+        }
+
+        debug!("Summarizing {} messages of session {}", pruned.len(), session_id);
+        let summary = self.summarize(previous.as_deref(), &pruned).await?;
+
+        let mut compacted = vec![summary_message(&summary)];
+        compacted.extend(groups[groups.len() - keep..].concat());
+        self.store.replace_messages(session_id, &compacted).await
+    }
+
+    async fn clear(&self, session_id: &str) -> Result<(), MemoryError> {
+        self.store.clear(session_id).await
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Helpers ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Estimated tokens of a message, text and tool call arguments
+pub fn message_tokens(message: &ChatMessage) -> usize {
+    let arguments: usize = message
+        .tool_calls
+        .iter()
+        .map(|call| estimate_tokens(&call.arguments.to_string()))
+        .sum();
+    estimate_tokens(&message.text()) + arguments
+}
+
+/// Splits the history at each user message; messages before the first
+/// user message form their own group
+fn turns(messages: &[ChatMessage]) -> Vec<&[ChatMessage]> {
+    let mut groups = Vec::new();
+    let mut start = 0;
+    for (index, message) in messages.iter().enumerate() {
+        if message.role == Role::User && index > start {
+            groups.push(&messages[start..index]);
+            start = index;
+        }
+    }
+    if start < messages.len() {
+        groups.push(&messages[start..]);
+    }
+    groups
+}
+
+/// Number of groups, counted from the newest, that fit in `max_tokens`
+fn latest_fitting(groups: &[&[ChatMessage]], max_tokens: usize) -> usize {
+    let mut total = 0;
+    let mut count = 0;
+    for group in groups.iter().rev() {
+        total += group.iter().map(message_tokens).sum::<usize>();
+        if total > max_tokens {
+            break;
+        }
+        count += 1;
+    }
+    count
+}
+
+fn summary_message(summary: &str) -> ChatMessage {
+    let mut message = ChatMessage::system(&format!("{}{}", SUMMARY_PREFIX, summary));
+    message.name = Some(SUMMARY_MESSAGE_NAME.to_string());
+    message
+}
+
+fn summary_text(message: &ChatMessage) -> Option<String> {
+    if message.role != Role::System || message.name.as_deref() != Some(SUMMARY_MESSAGE_NAME) {
+        return None;
+    }
+    let text = message.text();
+    let summary = text.strip_prefix(SUMMARY_PREFIX).unwrap_or(&text);
+    Some(summary.to_string())
+}
+
+/// `User: ...` lines of the messages to summarize
+fn transcript(messages: &[ChatMessage]) -> String {
+    messages
+        .iter()
+        .filter_map(|message| {
+            let speaker = match message.role {
+                Role::User => "User",
+                Role::Assistant => "Assistant",
+                Role::Tool => "Tool",
+                Role::System => "System",
+            };
+            let text = message.text();
+            if text.is_empty() {
+                None
+            } else {
+                Some(format!("{}: {}", speaker, text))
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
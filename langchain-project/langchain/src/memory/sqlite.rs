@@ -0,0 +1,123 @@
+use crate::llm::libs::ChatMessage;
+use crate::memory::error::MemoryError;
+use crate::memory::stores::ChatMessageStore;
+use async_trait::async_trait;
+use rusqlite::{params, Connection};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+static CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS chat_messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    message TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS chat_messages_session ON chat_messages (session_id, id);";
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ SqliteChatStore ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Keeps the conversations in a SQLite database, one row per message
+///
+/// The messages are stored as JSON in the `chat_messages` table, created
+/// when the store is opened. Queries run on the blocking thread pool.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct SqliteChatStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+#[allow(dead_code)]
+impl SqliteChatStore {
+    /// Opens or creates the database file
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, MemoryError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// A database that lives as long as the store, for tests
+    pub fn open_in_memory() -> Result<Self, MemoryError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, MemoryError> {
+        connection.execute_batch(CREATE_TABLE)?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Runs a query on the blocking thread pool
+    async fn run<T, F>(&self, query: F) -> Result<T, MemoryError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, MemoryError> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            query(&mut connection)
+        })
+        .await?
+    }
+}
+
+fn insert_messages(connection: &Connection, session_id: &str, messages: &[String]) -> Result<(), MemoryError> {
+    let mut statement = connection.prepare_cached(
+        "INSERT INTO chat_messages (session_id, message) VALUES (?1, ?2)",
+    )?;
+    for message in messages {
+        statement.execute(params![session_id, message])?;
+    }
+    Ok(())
+}
+
+fn to_json(messages: &[ChatMessage]) -> Result<Vec<String>, MemoryError> {
+    messages
+        .iter()
+        .map(|message| Ok(serde_json::to_string(message)?))
+        .collect()
+}
+
+#[async_trait]
+impl ChatMessageStore for SqliteChatStore {
+    async fn messages(&self, session_id: &str) -> Result<Vec<ChatMessage>, MemoryError> {
+        let session_id = session_id.to_string();
+        self.run(move |connection| {
+            let mut statement = connection.prepare_cached(
+                "SELECT message FROM chat_messages WHERE session_id = ?1 ORDER BY id",
+            )?;
+            let rows = statement.query_map(params![session_id], |row| row.get::<_, String>(0))?;
+
+            let mut messages = Vec::new();
+            for row in rows {
+                messages.push(serde_json::from_str(&row?)?);
+            }
+            Ok(messages)
+        })
+        .await
+    }
+
+    async fn add_messages(&self, session_id: &str, messages: &[ChatMessage]) -> Result<(), MemoryError> {
+        let session_id = session_id.to_string();
+        let messages = to_json(messages)?;
+        self.run(move |connection| {
+            let transaction = connection.transaction()?;
+            insert_messages(&transaction, &session_id, &messages)?;
+            transaction.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn replace_messages(&self, session_id: &str, messages: &[ChatMessage]) -> Result<(), MemoryError> {
+        let session_id = session_id.to_string();
+        let messages = to_json(messages)?;
+        self.run(move |connection| {
+            let transaction = connection.transaction()?;
+            transaction.execute("DELETE FROM chat_messages WHERE session_id = ?1", params![session_id])?;
+            insert_messages(&transaction, &session_id, &messages)?;
+            transaction.commit()?;
+            Ok(())
+        })
+        .await
+    }
+}
@@ -0,0 +1,151 @@
+use crate::llm::libs::ChatMessage;
+use crate::memory::error::MemoryError;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use tokio::sync::Mutex;
+
+/// Persistence of the messages of each conversation, keyed by session id
+///
+/// Stores keep every message; the `ChatMemory` strategies decide which of
+/// them are sent to the model.
+#[async_trait]
+pub trait ChatMessageStore: Send + Sync {
+    /// Messages of the session, oldest first; empty for an unknown session
+    async fn messages(&self, session_id: &str) -> Result<Vec<ChatMessage>, MemoryError>;
+
+    async fn add_messages(&self, session_id: &str, messages: &[ChatMessage]) -> Result<(), MemoryError>;
+
+    /// Replaces the whole history of the session, e.g. to compact it
+    async fn replace_messages(&self, session_id: &str, messages: &[ChatMessage]) -> Result<(), MemoryError>;
+
+    async fn clear(&self, session_id: &str) -> Result<(), MemoryError> {
+        self.replace_messages(session_id, &[]).await
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ InMemoryChatStore ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Keeps the conversations in memory; they are lost when the process ends
+#[allow(dead_code)]
+#[derive(Debug, Default)]
+pub struct InMemoryChatStore {
+    sessions: RwLock<HashMap<String, Vec<ChatMessage>>>,
+}
+
+#[allow(dead_code)]
+impl InMemoryChatStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ids of the stored sessions
+    pub fn session_ids(&self) -> Vec<String> {
+        self.read().keys().cloned().collect()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<String, Vec<ChatMessage>>> {
+        self.sessions.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<String, Vec<ChatMessage>>> {
+        self.sessions.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl ChatMessageStore for InMemoryChatStore {
+    async fn messages(&self, session_id: &str) -> Result<Vec<ChatMessage>, MemoryError> {
+        Ok(self.read().get(session_id).cloned().unwrap_or_default())
+    }
+
+    async fn add_messages(&self, session_id: &str, messages: &[ChatMessage]) -> Result<(), MemoryError> {
+        self.write()
+            .entry(session_id.to_string())
+            .or_default()
+            .extend_from_slice(messages);
+        Ok(())
+    }
+
+    async fn replace_messages(&self, session_id: &str, messages: &[ChatMessage]) -> Result<(), MemoryError> {
+        let mut sessions = self.write();
+        if messages.is_empty() {
+            sessions.remove(session_id);
+        } else {
+            sessions.insert(session_id.to_string(), messages.to_vec());
+        }
+        Ok(())
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ JsonFileChatStore ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Keeps the conversations in a JSON file, `{"<session id>": [messages]}`
+///
+/// The file is read on every call and rewritten on every change, through a
+/// temporary file so a crash never leaves it half written. Good for bots
+/// with few users; use `SqliteChatStore` for more.
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct JsonFileChatStore {
+    pub path: PathBuf,
+    lock: Mutex<()>,
+}
+
+#[allow(dead_code)]
+impl JsonFileChatStore {
+    /// The file is created on the first write
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    async fn read_sessions(&self) -> Result<HashMap<String, Vec<ChatMessage>>, MemoryError> {
+        match tokio::fs::read(&self.path).await {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn write_sessions(&self, sessions: &HashMap<String, Vec<ChatMessage>>) -> Result<(), MemoryError> {
+        let data = serde_json::to_vec(sessions)?;
+        let temporary = self.path.with_extension("tmp");
+        tokio::fs::write(&temporary, data).await?;
+        tokio::fs::rename(&temporary, &self.path).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChatMessageStore for JsonFileChatStore {
+    async fn messages(&self, session_id: &str) -> Result<Vec<ChatMessage>, MemoryError> {
+        let _guard = self.lock.lock().await;
+        let mut sessions = self.read_sessions().await?;
+        Ok(sessions.remove(session_id).unwrap_or_default())
+    }
+
+    async fn add_messages(&self, session_id: &str, messages: &[ChatMessage]) -> Result<(), MemoryError> {
+        let _guard = self.lock.lock().await;
+        let mut sessions = self.read_sessions().await?;
+        sessions
+            .entry(session_id.to_string())
+            .or_default()
+            .extend_from_slice(messages);
+        self.write_sessions(&sessions).await
+    }
+
+    async fn replace_messages(&self, session_id: &str, messages: &[ChatMessage]) -> Result<(), MemoryError> {
+        let _guard = self.lock.lock().await;
+        let mut sessions = self.read_sessions().await?;
+        if messages.is_empty() {
+            sessions.remove(session_id);
+        } else {
+            sessions.insert(session_id.to_string(), messages.to_vec());
+        }
+        self.write_sessions(&sessions).await
+    }
+}
@@ -0,0 +1,332 @@
+use crate::openai::chat::ChatOpenAI;
+use crate::openai::embed::EmbedOpenAI;
+use crate::openai::error::OpenAIError;
+use crate::openai::files::OpenAIFiles;
+use crate::openai::libs::{
+    Batch, BatchRequest, BatchResult, ChatResponse, CreateBatchRequest, EmbedResponse,
+};
+use crate::openai::requests::{get_request, post_request};
+use crate::openai::utils::GetApiKey;
+use crate::openai::OPENAI_BATCHES_URL;
+use log::{info, warn};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::sleep;
+use crate::llm::http::{default_client, WithHttpClient};
+use reqwest::Client;
+
+pub static CHAT_COMPLETIONS_ENDPOINT: &str = "/v1/chat/completions";
+pub static EMBEDDINGS_ENDPOINT: &str = "/v1/embeddings";
+
+/// Client of the Batch API
+///
+/// The requests are written to a JSONL file, uploaded with the Files API and
+/// processed asynchronously within the completion window, at half the price
+/// of the synchronous endpoints. All requests of a batch go to the same
+/// endpoint.
+///
+/// # Example
+/// ```ignore
+/// let llm = ChatOpenAI::new("gpt-4o-mini").with_max_tokens(16);
+///
+/// let batch = OpenAIBatch::new()
+///     .with_chat_request("review-1", llm.clone().with_prompt("Classify: great product"))
+///     .with_chat_request("review-2", llm.with_prompt("Classify: broke after a day"));
+///
+/// let created = batch.create().await?;
+/// let finished = batch.wait(&created.id).await?;
+/// let responses = batch.chat_responses(&finished).await?;
+/// ```
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct OpenAIBatch {
+    pub api_key: String,
+    pub base_url: String,
+    pub files: OpenAIFiles,
+    pub requests: Vec<BatchRequest>,
+    pub invalid_requests: Vec<String>,
+    pub completion_window: String,
+    pub metadata: Option<Value>,
+    pub timeout: Duration,
+    pub poll_interval: Duration,
+    pub max_poll_interval: Duration,
+    pub client: Client,
+}
+
+#[allow(dead_code)]
+impl OpenAIBatch {
+    pub fn new() -> Self {
+        let api_key: String = match Self::get_api_key() {
+            Ok(api_key) => api_key,
+            Err(_) => "not_key".to_string()
+        };
+
+        Self {
+            api_key,
+            base_url: OPENAI_BATCHES_URL.to_string(),
+            files: OpenAIFiles::new(),
+            requests: Vec::new(),
+            invalid_requests: Vec::new(),
+            completion_window: "24h".to_string(),
+            metadata: None,
+            timeout: Duration::from_secs(300), // default: 5 minutes
+            poll_interval: Duration::from_secs(10),
+            max_poll_interval: Duration::from_secs(300),
+            client: default_client(),
+        }
+    }
+
+    /// Sets the API key of the batch and files requests
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = api_key.to_string();
+        self.files = self.files.with_api_key(api_key);
+        self
+    }
+
+    /// Overrides the batches endpoint (default: `OPENAI_BATCHES_URL`)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Overrides the files endpoint used for the input and output files
+    pub fn with_files_url(mut self, files_url: &str) -> Self {
+        self.files = self.files.with_base_url(files_url);
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// First and longest wait between two status checks in `wait`; the wait
+    /// doubles after every check (default: 10 seconds, up to 5 minutes)
+    pub fn with_poll_interval(mut self, poll_interval: Duration, max_poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self.max_poll_interval = max_poll_interval.max(poll_interval);
+        self
+    }
+
+    /// Adds the chat completion `llm` would send, identified by `custom_id`
+    ///
+    /// A request that cannot be serialized is kept out of the batch and
+    /// makes `create` fail.
+    pub fn with_chat_request(self, custom_id: &str, llm: ChatOpenAI) -> Self {
+        let mut request = llm.request;
+        request.stream = None;
+        let body = serde_json::to_value(request);
+        self.with_serialized_request(custom_id, CHAT_COMPLETIONS_ENDPOINT, body)
+    }
+
+    /// Adds the embedding request `embed` would send, identified by `custom_id`
+    ///
+    /// A request that cannot be serialized is kept out of the batch and
+    /// makes `create` fail.
+    pub fn with_embed_request(self, custom_id: &str, embed: EmbedOpenAI) -> Self {
+        let body = serde_json::to_value(embed.request);
+        self.with_serialized_request(custom_id, EMBEDDINGS_ENDPOINT, body)
+    }
+
+    fn with_serialized_request(
+        mut self,
+        custom_id: &str,
+        endpoint: &str,
+        body: Result<Value, serde_json::Error>,
+    ) -> Self {
+        match body {
+            Ok(body) => self.with_request(custom_id, endpoint, body),
+            Err(e) => {
+                warn!("Error serializing batch request {}: {}", custom_id, e);
+                self.invalid_requests.push(format!("{}: {}", custom_id, e));
+                self
+            }
+        }
+    }
+
+    /// Adds a request with a body built by hand
+    pub fn with_request(mut self, custom_id: &str, endpoint: &str, body: Value) -> Self {
+        self.requests.push(BatchRequest {
+            custom_id: custom_id.to_string(),
+            method: "POST".to_string(),
+            url: endpoint.to_string(),
+            body,
+        });
+        self
+    }
+
+    /// Input file of the batch, one request per line
+    pub fn to_jsonl(&self) -> Result<String, OpenAIError> {
+        let mut jsonl = String::new();
+        for request in &self.requests {
+            jsonl.push_str(&serde_json::to_string(request)?);
+            jsonl.push('\n');
+        }
+        Ok(jsonl)
+    }
+
+    /// Uploads the input file and creates the batch; the input file is
+    /// deleted again when the batch cannot be created
+    ///
+    /// # Errors
+    /// * `BatchError` - No requests, requests for different endpoints, or
+    ///   requests that could not be serialized when they were added
+    pub async fn create(&self) -> Result<Batch, OpenAIError> {
+        if !self.invalid_requests.is_empty() {
+            return Err(OpenAIError::BatchError(format!(
+                "Invalid batch requests: {}",
+                self.invalid_requests.join("; "),
+            )));
+        }
+
+        let endpoint = match self.requests.first() {
+            Some(request) => request.url.clone(),
+            None => return Err(OpenAIError::BatchError("The batch has no requests".to_string())),
+        };
+        if self.requests.iter().any(|request| request.url != endpoint) {
+            return Err(OpenAIError::BatchError(
+                "All requests of a batch must use the same endpoint".to_string(),
+            ));
+        }
+
+        let input_file = self.files
+            .upload("batch_input.jsonl", self.to_jsonl()?.into_bytes(), "batch")
+            .await?;
+
+        let request = CreateBatchRequest {
+            input_file_id: input_file.id.clone(),
+            endpoint,
+            completion_window: self.completion_window.clone(),
+            metadata: self.metadata.clone(),
+        };
+        let request_body = serde_json::to_vec(&request)?;
+
+        let response = match post_request(&self.client, &self.base_url, &self.api_key, &request_body, self.timeout).await {
+            Ok(response) => response,
+            Err(e) => {
+                if let Err(delete_error) = self.files.delete(&input_file.id).await {
+                    warn!("Error deleting batch input file {}: {}", input_file.id, delete_error);
+                }
+                return Err(e);
+            }
+        };
+        let batch: Batch = serde_json::from_value(response)?;
+
+        info!("Created batch {} with {} requests", batch.id, self.requests.len());
+        Ok(batch)
+    }
+
+    /// Current status and request counts of a batch
+    pub async fn retrieve(&self, batch_id: &str) -> Result<Batch, OpenAIError> {
+        let url = format!("{}/{}", self.base_url, batch_id);
+        let response = get_request(&self.client, &url, &self.api_key, self.timeout).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Cancels a batch; results of the requests already done are kept
+    pub async fn cancel(&self, batch_id: &str) -> Result<Batch, OpenAIError> {
+        let url = format!("{}/{}/cancel", self.base_url, batch_id);
+        let response = post_request(&self.client, &url, &self.api_key, b"{}", self.timeout).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Polls the batch until it is completed, failed, expired or cancelled,
+    /// waiting longer after every check
+    pub async fn wait(&self, batch_id: &str) -> Result<Batch, OpenAIError> {
+        let mut poll_interval = self.poll_interval;
+
+        loop {
+            let batch = self.retrieve(batch_id).await?;
+            if batch.is_terminal() {
+                return Ok(batch);
+            }
+
+            let counts = batch.request_counts.clone().unwrap_or_default();
+            info!(
+                "Batch {} is {}: {}/{} completed, {} failed",
+                batch_id, batch.status, counts.completed, counts.total, counts.failed,
+            );
+
+            sleep(poll_interval).await;
+            poll_interval = (poll_interval * 2).min(self.max_poll_interval);
+        }
+    }
+
+    /// Downloads and parses the output and error files of a finished batch
+    ///
+    /// A line that cannot be parsed is returned as a `JsonError`.
+    pub async fn results(&self, batch: &Batch) -> Result<Vec<Result<BatchResult, OpenAIError>>, OpenAIError> {
+        let mut results = Vec::new();
+
+        for file_id in [&batch.output_file_id, &batch.error_file_id].into_iter().flatten() {
+            let content = self.files.content(file_id).await?;
+            for line in String::from_utf8_lossy(&content).lines() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<BatchResult>(line) {
+                    Ok(result) => results.push(Ok(result)),
+                    Err(e) => {
+                        warn!("Error parsing batch result: {}", e);
+                        results.push(Err(OpenAIError::JsonError(e)));
+                    }
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Chat completions of a finished batch by `custom_id`, each one the
+    /// response of the request or the reason it failed
+    pub async fn chat_responses(
+        &self,
+        batch: &Batch,
+    ) -> Result<HashMap<String, Result<ChatResponse, OpenAIError>>, OpenAIError> {
+        self.typed_results(batch).await
+    }
+
+    /// Embeddings of a finished batch by `custom_id`, each one the response
+    /// of the request or the reason it failed
+    pub async fn embed_responses(
+        &self,
+        batch: &Batch,
+    ) -> Result<HashMap<String, Result<EmbedResponse, OpenAIError>>, OpenAIError> {
+        self.typed_results(batch).await
+    }
+
+    async fn typed_results<T: DeserializeOwned>(
+        &self,
+        batch: &Batch,
+    ) -> Result<HashMap<String, Result<T, OpenAIError>>, OpenAIError> {
+        let mut responses = HashMap::new();
+        for result in self.results(batch).await? {
+            let result = result?;
+            responses.insert(result.custom_id.clone(), result.body::<T>());
+        }
+        Ok(responses)
+    }
+}
+
+impl WithHttpClient for OpenAIBatch {
+    fn with_http_client(mut self, client: Client) -> Self {
+        // Uploads and downloads go through the same client
+        self.files = self.files.with_http_client(client.clone());
+        self.client = client;
+        self
+    }
+
+    fn with_config_base_url(self, base_url: &str) -> Self {
+        self.with_base_url(base_url)
+    }
+}
+
+impl Default for OpenAIBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GetApiKey for OpenAIBatch {}
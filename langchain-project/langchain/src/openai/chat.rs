@@ -2,7 +2,17 @@ use futures::pin_mut;
 use futures::StreamExt;
 use async_stream::stream;
 use crate::openai::requests::{request_chat, strem_chat};
-use crate::openai::utils::GetApiKey;
+use crate::openai::utils::{
+    GetApiKey, to_openai_messages, to_openai_tools, to_openai_tool_choice,
+    to_model_response,
+};
+use crate::llm::chat::ChatModel;
+use crate::llm::error::LlmError;
+use crate::llm::retry::{RateLimiter, RetryPolicy};
+use crate::llm::libs::{
+    ChatMessage, ChatOptions, ModelResponse, Provider, Role as LlmRole,
+};
+use async_trait::async_trait;
 use crate::openai::libs::{
     MainRequest, ChatRequest, InputContent, ResponseFormat,
     Message, Role, ChatResponse, ImageUrl,
@@ -11,15 +21,20 @@ use crate::openai::OPENAI_BASE_URL;
 use crate::openai::error::OpenAIError;
 use std::time::Duration;
 use log::error;
+use crate::llm::http::{default_client, WithHttpClient};
+use reqwest::Client;
 
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct ChatOpenAI {
     pub api_key: String,
+    pub base_url: String,
     pub request: ChatRequest,
     pub timeout: Duration,
-    pub max_retries: u32,
+    pub retry_policy: RetryPolicy,
+    pub rate_limiter: Option<RateLimiter>,
+    pub client: Client,
 }
 
 #[allow(dead_code)]
@@ -37,6 +52,7 @@ impl ChatOpenAI {
             tools: None,
             tool_choice: None,
             max_completion_tokens: None,
+            reasoning_effort: None,
             response_format: None,
             frequency_penalty: None,
             presence_penalty: None,
@@ -48,17 +64,24 @@ impl ChatOpenAI {
         
         Self {
             api_key: api_key,
+            base_url: OPENAI_BASE_URL.to_string(),
             request: request,
             timeout: Duration::from_secs(300), // default: 5 minutes
-            max_retries: 3,         // default: 3 times
+            retry_policy: RetryPolicy::default(), // default: 3 retries
+            rate_limiter: None,
+            client: default_client(),
         }
     }
 
     pub async fn invoke(
-        mut self,
+        self,
         prompt: &str,
     ) -> Result<ChatResponse, OpenAIError> {
-        
+        self.with_prompt(prompt).send_request().await
+    }
+
+    /// Adds a user message without sending the request, e.g. to build a batch
+    pub fn with_prompt(mut self, prompt: &str) -> Self {
         let content = vec![InputContent {
             content_type: "text".to_string(),
             text: Some(prompt.to_string()),
@@ -71,6 +94,8 @@ impl ChatOpenAI {
             content: content.clone(),
             recipient: None,
             end_turn: None,
+            tool_calls: None,
+            tool_call_id: None,
         };
 
         if let Some(messages) = &mut self.request.messages {
@@ -79,14 +104,20 @@ impl ChatOpenAI {
             self.request.messages = Some(vec![new_message]);
         }
 
+        self
+    }
+
+    pub async fn send_request(self) -> Result<ChatResponse, OpenAIError> {
         let body_request = MainRequest::Chat(self.request.clone());
 
         let response: String = match request_chat(
+            &self.client,
             &body_request,
-            OPENAI_BASE_URL,
+            &self.base_url,
             &self.api_key,
             self.timeout,
-            self.max_retries,
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
         ).await {
             Ok(response) => response,
             Err(openai_error) => {
@@ -97,8 +128,8 @@ impl ChatOpenAI {
         let chat_response: ChatResponse = match serde_json::from_str(&response) {
             Ok(response_form) => response_form,
             Err(e) => {
-                error!("Failed to parse response: {}. ERROR-req-0023", e);
-                return Err(OpenAIError::ResponseContentError);
+                error!("Failed to parse response: {}", e);
+                return Err(e.into());
             }
         };
 
@@ -120,7 +151,7 @@ impl ChatOpenAI {
     pub fn stream_response(
         mut self,
         prompt: String,  // Don't change type for stream
-    ) -> impl futures::Stream<Item = ChatResponse> {
+    ) -> impl futures::Stream<Item = Result<ChatResponse, OpenAIError>> {
         stream! {     
             
             let content = vec![InputContent {
@@ -135,6 +166,8 @@ impl ChatOpenAI {
                 content: content.clone(),
                 recipient: None,
                 end_turn: None,
+                tool_calls: None,
+                tool_call_id: None,
             };
     
             if let Some(messages) = &mut self.request.messages {
@@ -144,12 +177,15 @@ impl ChatOpenAI {
             }
 
             self.request.stream = Some(true);
-            let endpoint_string = OPENAI_BASE_URL.to_string();
+            let endpoint_string = self.base_url.clone();
 
             let stream = strem_chat(
+                self.client.clone(),
                 endpoint_string.clone(),
                 self.api_key.clone(),
                 self.request.clone(),
+                self.retry_policy.clone(),
+                self.rate_limiter.clone(),
             );
 
             pin_mut!(stream);
@@ -178,6 +214,12 @@ impl ChatOpenAI {
         self
     }
 
+    /// How hard o-series models reason before answering: `low`, `medium` or `high`
+    pub fn with_reasoning_effort(mut self, reasoning_effort: &str) -> Self {
+        self.request.reasoning_effort = Some(reasoning_effort.to_string());
+        self
+    }
+
     pub fn with_timeout_sec(mut self, timeout: u64) -> Self {
         self.timeout = Duration::from_secs(timeout);
         self
@@ -226,6 +268,8 @@ impl ChatOpenAI {
             content: content.clone(),
             recipient: None,
             end_turn: None,
+            tool_calls: None,
+            tool_call_id: None,
         };
 
         if let Some(messages) = &mut self.request.messages {
@@ -275,6 +319,8 @@ impl ChatOpenAI {
             content: content.clone(),
             recipient: None,
             end_turn: None,
+            tool_calls: None,
+            tool_call_id: None,
         };
 
         if let Some(messages) = &mut self.request.messages {
@@ -299,6 +345,8 @@ impl ChatOpenAI {
             content: content.clone(),
             recipient: None,
             end_turn: None,
+            tool_calls: None,
+            tool_call_id: None,
         };
 
         if let Some(messages) = &mut self.request.messages {
@@ -336,7 +384,20 @@ impl ChatOpenAI {
     }
 
     pub fn with_max_retries(mut self, max_retries: u32) -> Self {
-        self.max_retries = max_retries;
+        self.retry_policy.max_retries = max_retries;
+        self
+    }
+
+    /// Replaces the retry policy, e.g. to change the backoff delays
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limits the requests and tokens per minute; clone the same limiter
+    /// into every client that shares the account
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
         self
     }
 
@@ -344,6 +405,77 @@ impl ChatOpenAI {
         self.api_key = api_key.to_string();
         self
     }
+
+    /// Overrides the chat completions endpoint (default: `OPENAI_BASE_URL`)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.to_string();
+        self
+    }
 }
 
-impl GetApiKey for ChatOpenAI {}
\ No newline at end of file
+impl WithHttpClient for ChatOpenAI {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn with_config_base_url(self, base_url: &str) -> Self {
+        self.with_base_url(base_url)
+    }
+}
+
+impl GetApiKey for ChatOpenAI {}
+
+#[async_trait]
+impl ChatModel for ChatOpenAI {
+    fn provider(&self) -> Provider {
+        Provider::OpenAI
+    }
+
+    fn model_name(&self) -> String {
+        self.request.model.clone()
+    }
+
+    async fn generate(
+        &self,
+        messages: &[ChatMessage],
+        options: &ChatOptions,
+    ) -> Result<ModelResponse, LlmError> {
+        let mut llm = self.clone();
+        let mut history = to_openai_messages(messages, &llm.request.model);
+
+        // Keep the system prompt set with `with_system_prompt` unless the
+        // conversation brings its own
+        if !messages.iter().any(|message| message.role == LlmRole::System) {
+            if let Some(current) = &self.request.messages {
+                let system: Vec<Message> = current
+                    .iter()
+                    .take_while(|message| matches!(message.role, Role::Developer))
+                    .cloned()
+                    .collect();
+                history.splice(0..0, system);
+            }
+        }
+        llm.request.messages = Some(history);
+
+        if !options.tools.is_empty() {
+            llm.request.tools = Some(to_openai_tools(&options.tools));
+        }
+        if let Some(tool_choice) = &options.tool_choice {
+            llm.request.tool_choice = Some(to_openai_tool_choice(tool_choice));
+        }
+        if let Some(temperature) = options.temperature {
+            llm.request.temperature = Some(temperature);
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            llm.request.max_completion_tokens = Some(max_tokens);
+        }
+        if let Some(output_schema) = &options.output_schema {
+            let json_schema = serde_json::to_value(output_schema)?;
+            llm = llm.with_json_schema(json_schema);
+        }
+
+        let response = llm.send_request().await?;
+        to_model_response(response).ok_or(LlmError::EmptyResponse)
+    }
+}
\ No newline at end of file
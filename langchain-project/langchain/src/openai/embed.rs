@@ -1,17 +1,39 @@
+use crate::llm::embed::{embed_batches, Embeddings};
+use crate::llm::error::LlmError;
+use crate::llm::libs::Provider;
+use async_trait::async_trait;
 use crate::openai::requests::request_embed;
-use crate::openai::libs::{EmbedRequest, EmbedResponse};
+use crate::openai::libs::{EmbedInput, EmbedRequest, EmbedResponse};
 use crate::openai::utils::GetApiKey;
 use crate::openai::error::OpenAIError;
+use crate::openai::OPENAI_EMBED_URL;
+use futures::stream::{self, StreamExt, TryStreamExt};
 use std::time::Duration;
 use log::error;
+use crate::llm::http::{default_client, WithHttpClient};
+use crate::llm::retry::{RateLimiter, RetryPolicy};
+use reqwest::Client;
+
+/// Inputs per request accepted by the embeddings endpoint
+pub const OPENAI_EMBED_MAX_INPUTS: usize = 2048;
+
+/// Tokens per request accepted by the embeddings endpoint, all inputs added
+pub const OPENAI_EMBED_MAX_TOKENS: usize = 300_000;
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct EmbedOpenAI {
     pub model: String,
+    pub base_url: String,
     pub request: EmbedRequest,
     pub timeout: Duration,
     pub api_key: String,
+    pub batch_size: usize,
+    pub max_batch_tokens: usize,
+    pub max_concurrency: usize,
+    pub retry_policy: RetryPolicy,
+    pub rate_limiter: Option<RateLimiter>,
+    pub client: Client,
 }
 
 #[allow(dead_code)]
@@ -24,24 +46,36 @@ impl EmbedOpenAI {
 
         let request = EmbedRequest {
             model: model.to_string(),
-            input: "Init message.".to_string(),
+            input: EmbedInput::Text(String::new()),
             dimensions: None,
         };
 
         Self {
             model: model.to_string(),
+            base_url: OPENAI_EMBED_URL.to_string(),
             request: request,
             timeout: Duration::from_secs(300), // default: 5 minutes
             api_key: api_key,
+            batch_size: OPENAI_EMBED_MAX_INPUTS,
+            max_batch_tokens: OPENAI_EMBED_MAX_TOKENS,
+            max_concurrency: 4,
+            retry_policy: RetryPolicy::default(), // default: 3 retries
+            rate_limiter: None,
+            client: default_client(),
         }
     }
 
     pub async fn embed_content(mut self, input_str: &str) -> Result<EmbedResponse, OpenAIError> {
-        self.request.input = input_str.to_string();
+        self.request.input = EmbedInput::Text(input_str.to_string());
         
         let response: String = match request_embed(
+            &self.client,
             &self.request,
+            &self.base_url,
             &self.api_key,
+            self.timeout,
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
         ).await {
             Ok(response) => response,
             Err(e) => {
@@ -65,11 +99,70 @@ impl EmbedOpenAI {
         }    
     }
 
+    /// Embeds many texts and returns their vectors in input order
+    ///
+    /// The texts are sent as array inputs, split by `batch_size` and
+    /// `max_batch_tokens`, with up to `max_concurrency` requests at a time.
+    ///
+    /// # Errors
+    /// The first failed request; the vectors already computed are dropped.
+    pub async fn embed_documents(&self, documents: Vec<String>) -> Result<Vec<Vec<f32>>, OpenAIError> {
+        let batches = embed_batches(&documents, self.batch_size, self.max_batch_tokens);
+
+        let requests = batches.into_iter().map(|range| {
+            let mut request = self.request.clone();
+            request.input = EmbedInput::Texts(documents[range].to_vec());
+            async move { self.embed_batch(request).await }
+        });
+
+        let batches: Vec<Vec<Vec<f32>>> = stream::iter(requests)
+            .buffered(self.max_concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(batches.into_iter().flatten().collect())
+    }
+
+    async fn embed_batch(&self, request: EmbedRequest) -> Result<Vec<Vec<f32>>, OpenAIError> {
+        let inputs = match &request.input {
+            EmbedInput::Texts(texts) => texts.len(),
+            EmbedInput::Text(_) => 1,
+        };
+        let response = request_embed(
+            &self.client,
+            &request,
+            &self.base_url,
+            &self.api_key,
+            self.timeout,
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
+        ).await?;
+        let embed_response: EmbedResponse = serde_json::from_str(&response)?;
+
+        if let Some(error) = embed_response.error {
+            return Err(OpenAIError::BadRequestError(error.message));
+        }
+
+        let mut data = embed_response.data;
+        if data.len() != inputs {
+            error!("Expected {} embeddings, got {}", inputs, data.len());
+            return Err(OpenAIError::ResponseContentError);
+        }
+        data.sort_by_key(|embedding| embedding.index);
+        Ok(data.into_iter().map(|embedding| embedding.embedding).collect())
+    }
+
     pub fn with_timeout_sec(mut self, timeout: u64) -> Self {
         self.timeout = Duration::from_secs(timeout);
         self
     }
 
+    /// Sets the text to embed without sending the request, e.g. to build a batch
+    pub fn with_input(mut self, input: &str) -> Self {
+        self.request.input = EmbedInput::Text(input.to_string());
+        self
+    }
+
     pub fn with_dimensions(mut self, dimensions: u32) -> Self {
         // Only supported in text-embedding-3 and later models
         self.request.dimensions = Some(dimensions);
@@ -80,6 +173,65 @@ impl EmbedOpenAI {
         self.api_key = api_key.to_string();
         self
     }
+
+    /// Overrides the embeddings endpoint (default: `OPENAI_EMBED_URL`)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.to_string();
+        self
+    }
+
+    /// Limits of one `embed_documents` request: inputs (default and maximum:
+    /// 2048) and estimated tokens (default and maximum: 300,000)
+    pub fn with_batch_size(mut self, batch_size: usize, max_batch_tokens: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, OPENAI_EMBED_MAX_INPUTS);
+        self.max_batch_tokens = max_batch_tokens.clamp(1, OPENAI_EMBED_MAX_TOKENS);
+        self
+    }
+
+    /// Requests sent at the same time by `embed_documents` (default: 4)
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Replaces the retry policy, e.g. to change the backoff delays
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limits the requests and tokens per minute; clone the same limiter
+    /// into every client that shares the account
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+}
+
+impl WithHttpClient for EmbedOpenAI {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn with_config_base_url(self, base_url: &str) -> Self {
+        self.with_base_url(base_url)
+    }
 }
 
-impl GetApiKey for EmbedOpenAI {}
\ No newline at end of file
+impl GetApiKey for EmbedOpenAI {}
+
+#[async_trait]
+impl Embeddings for EmbedOpenAI {
+    fn provider(&self) -> Provider {
+        Provider::OpenAI
+    }
+
+    fn model_name(&self) -> String {
+        self.model.clone()
+    }
+
+    async fn embed_documents(&self, documents: Vec<String>) -> Result<Vec<Vec<f32>>, LlmError> {
+        Ok(EmbedOpenAI::embed_documents(self, documents).await?)
+    }
+}
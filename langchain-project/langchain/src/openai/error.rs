@@ -1,8 +1,13 @@
+use crate::llm::error::ApiError;
 use std::env;
 
 #[allow(dead_code)]
 #[derive(Debug, thiserror::Error)]
 pub enum OpenAIError {
+    /// An error answered by the API, with its status, code and body
+    #[error("{0}")]
+    ApiError(ApiError),
+
     #[error("API Connection Error: Issue connecting to services. Check network settings, proxy configuration, SSL certificates, or firewall rules. {0}")]
     APIConnectionError(String),
 
@@ -47,6 +52,12 @@ pub enum OpenAIError {
     
     #[error("Failed to get response content")]
     ResponseContentError,
+
+    #[error("Batch error: {0}")]
+    BatchError(String),
+
+    #[error("Failed to open file: {0}")]
+    FileError(String),
     
     #[error("{message}")]
     GenericError {
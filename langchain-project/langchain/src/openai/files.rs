@@ -0,0 +1,142 @@
+use crate::openai::error::OpenAIError;
+use crate::openai::libs::{DeletedFile, FileList, FileObject};
+use crate::openai::requests::{
+    delete_request, download_request, get_request, upload_request,
+};
+use crate::openai::utils::GetApiKey;
+use crate::openai::OPENAI_FILES_URL;
+use std::path::Path;
+use std::time::Duration;
+use crate::llm::http::{default_client, WithHttpClient};
+use reqwest::Client;
+
+/// Client of the Files API
+///
+/// # Example
+/// ```ignore
+/// let files = OpenAIFiles::new();
+/// let file = files.upload("requests.jsonl", jsonl.into_bytes(), "batch").await?;
+/// let content = files.content(&file.id).await?;
+/// files.delete(&file.id).await?;
+/// ```
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct OpenAIFiles {
+    pub api_key: String,
+    pub base_url: String,
+    pub timeout: Duration,
+    pub client: Client,
+}
+
+#[allow(dead_code)]
+impl OpenAIFiles {
+    pub fn new() -> Self {
+        let api_key: String = match Self::get_api_key() {
+            Ok(api_key) => api_key,
+            Err(_) => "not_key".to_string()
+        };
+
+        Self {
+            api_key,
+            base_url: OPENAI_FILES_URL.to_string(),
+            timeout: Duration::from_secs(300), // default: 5 minutes
+            client: default_client(),
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = api_key.to_string();
+        self
+    }
+
+    /// Overrides the files endpoint (default: `OPENAI_FILES_URL`)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_timeout_sec(mut self, timeout: u64) -> Self {
+        self.timeout = Duration::from_secs(timeout);
+        self
+    }
+
+    /// Uploads `file_data` under `file_name` for the given purpose, e.g. `batch`
+    pub async fn upload(
+        &self,
+        file_name: &str,
+        file_data: Vec<u8>,
+        purpose: &str,
+    ) -> Result<FileObject, OpenAIError> {
+        let response = upload_request(
+            &self.client,
+            &self.base_url,
+            &self.api_key,
+            file_name,
+            &file_data,
+            purpose,
+            self.timeout,
+        ).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Uploads a local file, keeping its name
+    pub async fn upload_file(&self, file_path: &str, purpose: &str) -> Result<FileObject, OpenAIError> {
+        let file_data = tokio::fs::read(file_path)
+            .await
+            .map_err(|e| OpenAIError::FileError(format!("{}: {}", file_path, e)))?;
+        let file_name = Path::new(file_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(file_path);
+
+        self.upload(file_name, file_data, purpose).await
+    }
+
+    /// Files of the organization, only those with `purpose` when given
+    pub async fn list(&self, purpose: Option<&str>) -> Result<Vec<FileObject>, OpenAIError> {
+        let url = match purpose {
+            Some(purpose) => format!("{}?purpose={}", self.base_url, purpose),
+            None => self.base_url.clone(),
+        };
+        let response = get_request(&self.client, &url, &self.api_key, self.timeout).await?;
+        let file_list: FileList = serde_json::from_value(response)?;
+        Ok(file_list.data)
+    }
+
+    pub async fn retrieve(&self, file_id: &str) -> Result<FileObject, OpenAIError> {
+        let url = format!("{}/{}", self.base_url, file_id);
+        let response = get_request(&self.client, &url, &self.api_key, self.timeout).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    pub async fn delete(&self, file_id: &str) -> Result<DeletedFile, OpenAIError> {
+        let url = format!("{}/{}", self.base_url, file_id);
+        let response = delete_request(&self.client, &url, &self.api_key, self.timeout).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Downloads the content of a file
+    pub async fn content(&self, file_id: &str) -> Result<Vec<u8>, OpenAIError> {
+        let url = format!("{}/{}/content", self.base_url, file_id);
+        download_request(&self.client, &url, &self.api_key, self.timeout).await
+    }
+}
+
+impl WithHttpClient for OpenAIFiles {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn with_config_base_url(self, base_url: &str) -> Self {
+        self.with_base_url(base_url)
+    }
+}
+
+impl Default for OpenAIFiles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GetApiKey for OpenAIFiles {}
@@ -1,3 +1,4 @@
+use crate::llm::libs::TokenUsage;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap; 
 use serde_json::Value;
@@ -87,7 +88,7 @@ pub struct ResponseRequest {
     /// **Optional.** How the model should select which tool (or tools) to use when 
     /// generating a response.
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_choice: Option<String>,
+    pub tool_choice: Option<ToolChoice>,
 
     /// **Optional.** An array of tools the model may call while generating a response.
     /// You can specify which tool to use by setting the tool_choice parameter.
@@ -371,6 +372,12 @@ pub struct Reasoning {
     /// One of concise or detailed.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub generate_summary: Option<String>,
+
+    /// **Optional.** Replaces `generate_summary` on newer models. Streams the
+    /// reasoning summary as `response.reasoning_summary_text.delta` events.
+    /// One of auto, concise or detailed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
 }
 
 /// A message input to the model with a role indicating instruction following hierarchy. 
@@ -495,6 +502,7 @@ pub struct ResponseObject {
     ///
     /// Keys are strings with a maximum length of 64 characters. 
     /// Values are strings with a maximum length of 512 characters.
+    #[serde(default)]
     pub metadata: HashMap<String, Value>,
 
     /// Model ID used to generate the response, like gpt-4o or o1. 
@@ -542,7 +550,7 @@ pub struct ResponseObject {
     pub text: Option<TextInfo>,
 
     /// How the model should select which tool (or tools) to use when generating a response.
-    pub tool_choice: Option<String>,
+    pub tool_choice: Option<Value>,
 
     /// An array of tools the model may call while generating a response. 
     /// You can specify which tool to use by setting the tool_choice parameter.
@@ -575,6 +583,10 @@ pub struct ResponseObject {
 pub enum OutputItem {
     Reasoning(ReasoningOutput),
     Message(MessageOutput),
+    FunctionCall(FunctionCallOutput),
+    /// Built-in tool calls (web search, file search, computer use) 
+    #[serde(other)]
+    Other,
 }
 
 // Specific struct for the "reasoning" type output
@@ -597,10 +609,25 @@ pub struct MessageOutput {
     // The 'type' field is handled by the enum tag
 }
 
+// Specific struct for the "function_call" type output
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct FunctionCallOutput {
+    pub id: Option<String>,
+    pub call_id: String,
+    pub name: String,
+    /// JSON encoded arguments, empty until the call is complete when streaming
+    pub arguments: String,
+    pub status: Option<String>,
+    // The 'type' field is handled by the enum tag
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Deserialize, Clone)]
 pub struct ContentItem {
+    #[serde(default)]
     pub annotations: Vec<Value>,
+    #[serde(default)]
     pub text: String,
     #[serde(rename = "type")]
     pub content_type: String,
@@ -609,8 +636,9 @@ pub struct ContentItem {
 #[allow(dead_code)]
 #[derive(Debug, Deserialize, Clone)]
 pub struct ReasoningInfo {
-    pub effort: String,
+    pub effort: Option<String>,
     pub generate_summary: Option<Value>,
+    pub summary: Option<Value>,
 }
 
 #[allow(dead_code)]
@@ -647,6 +675,29 @@ pub struct Usage {
     pub total_tokens: Option<u64>,
 }
 
+#[allow(dead_code)]
+impl Usage {
+    /// Normalized usage, with the cached input and reasoning tokens
+    pub fn token_usage(&self) -> TokenUsage {
+        let count = |value: u64| u32::try_from(value).unwrap_or(u32::MAX);
+
+        let mut token_usage = TokenUsage::new(
+            count(self.input_tokens.unwrap_or(0)),
+            count(self.output_tokens.unwrap_or(0)),
+        );
+        if let Some(total) = self.total_tokens {
+            token_usage.total_tokens = count(total);
+        }
+        if let Some(details) = &self.input_tokens_details {
+            token_usage.cache_read_input_tokens = count(details.cached_tokens);
+        }
+        if let Some(details) = &self.output_tokens_details {
+            token_usage.reasoning_tokens = count(details.reasoning_tokens);
+        }
+        token_usage
+    }
+}
+
 /// A detailed breakdown of the input tokens.
 #[allow(dead_code)]
 #[derive(Debug, Deserialize, Clone)]
@@ -661,4 +712,163 @@ pub struct InputTokensDetails {
 pub struct OutputTokensDetails {
     /// The number of reasoning tokens.
     pub reasoning_tokens: u64,
-}
\ No newline at end of file
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Streaming ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Semantic events emitted when a response is created with `stream` enabled.
+///
+/// Every event carries a `type` field that names it, e.g. 
+/// `response.output_text.delta`. Events not listed here are mapped to `Unknown`.
+/// 
+/// [Docs OpenAI - Streaming events](https://platform.openai.com/docs/api-reference/responses-streaming)
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum ResponseStreamEvent {
+    /// Emitted when a response is created.
+    #[serde(rename = "response.created")]
+    Created { response: ResponseObject },
+
+    /// Emitted when the response is in progress.
+    #[serde(rename = "response.in_progress")]
+    InProgress { response: ResponseObject },
+
+    /// Emitted when the model response is complete. Contains the full output.
+    #[serde(rename = "response.completed")]
+    Completed { response: ResponseObject },
+
+    /// Emitted when a response finishes as incomplete (e.g. `max_output_tokens` reached).
+    #[serde(rename = "response.incomplete")]
+    Incomplete { response: ResponseObject },
+
+    /// Emitted when a response fails. The stream yields it as an error.
+    #[serde(rename = "response.failed")]
+    Failed { response: ResponseObject },
+
+    /// Emitted when a new output item is added.
+    #[serde(rename = "response.output_item.added")]
+    OutputItemAdded { output_index: u32, item: OutputItem },
+
+    /// Emitted when an output item is marked done.
+    #[serde(rename = "response.output_item.done")]
+    OutputItemDone { output_index: u32, item: OutputItem },
+
+    /// Emitted when a new content part is added.
+    #[serde(rename = "response.content_part.added")]
+    ContentPartAdded {
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        part: Value,
+    },
+
+    /// Emitted when a content part is done.
+    #[serde(rename = "response.content_part.done")]
+    ContentPartDone {
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        part: Value,
+    },
+
+    /// Emitted when there is an additional text delta.
+    #[serde(rename = "response.output_text.delta")]
+    OutputTextDelta {
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        delta: String,
+    },
+
+    /// Emitted when text content is finalized.
+    #[serde(rename = "response.output_text.done")]
+    OutputTextDone {
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        text: String,
+    },
+
+    /// Emitted when there is a partial refusal text.
+    #[serde(rename = "response.refusal.delta")]
+    RefusalDelta {
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        delta: String,
+    },
+
+    /// Emitted when refusal text is finalized.
+    #[serde(rename = "response.refusal.done")]
+    RefusalDone {
+        item_id: String,
+        output_index: u32,
+        content_index: u32,
+        refusal: String,
+    },
+
+    /// Emitted when there is a partial function-call arguments delta.
+    #[serde(rename = "response.function_call_arguments.delta")]
+    FunctionCallArgumentsDelta {
+        item_id: String,
+        output_index: u32,
+        delta: String,
+    },
+
+    /// Emitted when function-call arguments are finalized.
+    #[serde(rename = "response.function_call_arguments.done")]
+    FunctionCallArgumentsDone {
+        item_id: String,
+        output_index: u32,
+        arguments: String,
+    },
+
+    /// Emitted when a new reasoning summary part is added.
+    #[serde(rename = "response.reasoning_summary_part.added")]
+    ReasoningSummaryPartAdded {
+        item_id: String,
+        output_index: u32,
+        summary_index: u32,
+        part: Value,
+    },
+
+    /// Emitted when a reasoning summary part is completed.
+    #[serde(rename = "response.reasoning_summary_part.done")]
+    ReasoningSummaryPartDone {
+        item_id: String,
+        output_index: u32,
+        summary_index: u32,
+        part: Value,
+    },
+
+    /// Emitted when a delta is added to a reasoning summary text.
+    #[serde(rename = "response.reasoning_summary_text.delta")]
+    ReasoningSummaryTextDelta {
+        item_id: String,
+        output_index: u32,
+        summary_index: u32,
+        delta: String,
+    },
+
+    /// Emitted when a reasoning summary text is completed.
+    #[serde(rename = "response.reasoning_summary_text.done")]
+    ReasoningSummaryTextDone {
+        item_id: String,
+        output_index: u32,
+        summary_index: u32,
+        text: String,
+    },
+
+    /// Emitted when an error occurs. The stream yields it as an error.
+    #[serde(rename = "error")]
+    Error {
+        code: Option<String>,
+        message: String,
+        param: Option<String>,
+    },
+
+    /// Any event type this client does not model yet.
+    #[serde(other)]
+    Unknown,
+}
@@ -1,4 +1,8 @@
+use crate::llm::libs::TokenUsage;
+use crate::openai::error::OpenAIError;
 use crate::openai::lib_response::ResponseRequest;
+use crate::openai::requests::error_from_details;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -21,6 +25,8 @@ pub struct ChatRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_completion_tokens:  Option<u32>, // For O1 models
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<String>, // For o-series models
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<Value>,
@@ -53,11 +59,20 @@ pub struct ResponseFormat {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EmbedRequest {
     pub model: String,
-    pub input: String,
+    pub input: EmbedInput,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dimensions: Option<u32>,
 }
 
+/// Text of an embedding request, one input or many in a single call
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum EmbedInput {
+    Text(String),
+    Texts(Vec<String>),
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EmbedResponse {
@@ -91,11 +106,16 @@ pub enum Role {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Message {
     pub role: Role,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub content: Vec<InputContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub recipient: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub end_turn: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -178,6 +198,30 @@ pub struct Usage {
     pub total_tokens: Option<u32>,
 }
 
+#[allow(dead_code)]
+impl Usage {
+    /// Normalized usage; the cached, audio and reasoning tokens are read
+    /// from the token details
+    pub fn token_usage(&self) -> TokenUsage {
+        let mut token_usage = TokenUsage::new(
+            self.prompt_tokens.unwrap_or(0),
+            self.completion_tokens.unwrap_or(0),
+        );
+        if let Some(total) = self.total_tokens {
+            token_usage.total_tokens = total;
+        }
+        if let Some(details) = &self.prompt_tokens_details {
+            token_usage.cache_read_input_tokens = details.cached_tokens;
+            token_usage.audio_input_tokens = details.audio_tokens;
+        }
+        if let Some(details) = &self.completion_tokens_details {
+            token_usage.reasoning_tokens = details.reasoning_tokens;
+            token_usage.audio_output_tokens = details.audio_tokens;
+        }
+        token_usage
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CompletionTokensDetails {
@@ -194,6 +238,193 @@ pub struct PromptTokensDetails {
     pub cached_tokens: u32,
 }
 
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Files ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// A file stored in the Files API
+///
+/// # Fields
+/// * `id` - File id, e.g. `file-abc123`
+/// * `bytes` - Size of the file
+/// * `filename` - Name given at upload
+/// * `purpose` - `batch`, `batch_output`, `fine-tune`, `assistants`, ...
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileObject {
+    pub id: String,
+    pub object: String,
+    pub bytes: u64,
+    pub created_at: u64,
+    pub filename: String,
+    pub purpose: String,
+    pub status: Option<String>,
+    pub expires_at: Option<u64>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileList {
+    pub object: String,
+    pub data: Vec<FileObject>,
+    pub has_more: Option<bool>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeletedFile {
+    pub id: String,
+    pub object: String,
+    pub deleted: bool,
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Batches ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// One line of the input file of a batch
+///
+/// # Fields
+/// * `custom_id` - Id chosen by the caller to match the result, unique in the batch
+/// * `method` - Always `POST`
+/// * `url` - Endpoint of the request, e.g. `/v1/chat/completions`
+/// * `body` - Request body, as sent to the endpoint
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchRequest {
+    pub custom_id: String,
+    pub method: String,
+    pub url: String,
+    pub body: Value,
+}
+
+/// Body of a create batch request
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateBatchRequest {
+    pub input_file_id: String,
+    pub endpoint: String,
+    pub completion_window: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+}
+
+/// A batch as returned by the API
+///
+/// # Fields
+/// * `id` - Batch id, e.g. `batch_abc123`
+/// * `status` - `validating`, `in_progress`, `finalizing`, `completed`,
+///   `failed`, `expired`, `cancelling` or `cancelled`
+/// * `output_file_id` - Results of the requests that succeeded
+/// * `error_file_id` - Results of the requests that failed
+/// * `errors` - Validation errors of the input file, when `failed`
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Batch {
+    pub id: String,
+    pub object: String,
+    pub endpoint: String,
+    pub errors: Option<Value>,
+    pub input_file_id: String,
+    pub completion_window: String,
+    pub status: String,
+    pub output_file_id: Option<String>,
+    pub error_file_id: Option<String>,
+    pub created_at: u64,
+    pub in_progress_at: Option<u64>,
+    pub expires_at: Option<u64>,
+    pub completed_at: Option<u64>,
+    pub failed_at: Option<u64>,
+    pub expired_at: Option<u64>,
+    pub cancelled_at: Option<u64>,
+    pub request_counts: Option<BatchRequestCounts>,
+    pub metadata: Option<Value>,
+}
+
+#[allow(dead_code)]
+impl Batch {
+    /// The batch will not change anymore
+    pub fn is_terminal(&self) -> bool {
+        matches!(self.status.as_str(), "completed" | "failed" | "expired" | "cancelled")
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BatchRequestCounts {
+    pub total: u32,
+    pub completed: u32,
+    pub failed: u32,
+}
+
+/// One line of the output or error file of a batch
+///
+/// # Fields
+/// * `custom_id` - Id of the input line
+/// * `response` - Answer of the endpoint, including error statuses
+/// * `error` - Set when the request could not be sent
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchResult {
+    pub id: String,
+    pub custom_id: String,
+    pub response: Option<BatchResponse>,
+    pub error: Option<BatchResultError>,
+}
+
+#[allow(dead_code)]
+impl BatchResult {
+    /// Body of a successful response, deserialized into `T`
+    ///
+    /// # Errors
+    /// * `BatchError` - The request could not be sent
+    /// * The API error of a response with an error status
+    pub fn body<T: DeserializeOwned>(&self) -> Result<T, OpenAIError> {
+        if let Some(error) = &self.error {
+            return Err(OpenAIError::BatchError(format!(
+                "Request {} failed: {}",
+                self.custom_id, error.message
+            )));
+        }
+
+        let response = match &self.response {
+            Some(response) => response,
+            None => {
+                return Err(OpenAIError::BatchError(format!(
+                    "Request {} has no response",
+                    self.custom_id
+                )));
+            }
+        };
+
+        if !(200..300).contains(&response.status_code) {
+            let details = serde_json::from_value::<ErrorDetails>(response.body["error"].clone());
+            return Err(match details {
+                Ok(details) => error_from_details(details),
+                Err(_) => OpenAIError::BatchError(format!(
+                    "Request {} failed with status {}: {}",
+                    self.custom_id,
+                    response.status_code,
+                    response.body["error"]["message"].as_str().unwrap_or_default(),
+                )),
+            });
+        }
+
+        Ok(serde_json::from_value(response.body.clone())?)
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchResponse {
+    pub status_code: u16,
+    pub request_id: Option<String>,
+    pub body: Value,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BatchResultError {
+    pub code: Option<String>,
+    pub message: String,
+}
+
 // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Errors ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
 
 /// Represents an error response structure from the API
@@ -1,64 +1,47 @@
 use reqwest::{Client, Response};
 use log::{warn, error};
 use async_stream::stream;
-use futures::StreamExt;
-use crate::openai::{
-    OPENAI_EMBED_URL, RETRY_BASE_DELAY,
-    DEBUG_PRE, DEBUG_POST,
-};
+use futures::{pin_mut, StreamExt};
+use crate::llm::sse::sse_events;
+use crate::llm::retry::{body_tokens, send_with_retry, RateLimiter, RetryPolicy};
+use crate::openai::{DEBUG_PRE, DEBUG_POST};
 use crate::openai::error::OpenAIError;
 use crate::openai::libs::{
     MainRequest, ChatRequest, EmbedRequest, 
-    ErrorResponse, ChatResponse,
+    ErrorDetails, ChatResponse,
 };
+use crate::openai::lib_response::{ResponseRequest, ResponseStreamEvent};
 use crate::openai::utils::print_pre;
+use serde_json::Value;
+use crate::llm::error::ApiError;
+use crate::llm::libs::Provider;
 use std::time::Duration;
-use tokio::time::sleep;
 
+/// Sends a request to the chat completions or responses endpoint
+///
+/// Failed requests are retried as `retry_policy` says, after waiting for
+/// the `rate_limiter`, if any.
 pub async fn request_chat(
+    client: &Client,
     request: &MainRequest,
     api_endpoint: &str,
     api_key: &str,
     timeout: Duration,
-    max_retries: u32,
+    retry_policy: &RetryPolicy,
+    rate_limiter: Option<&RateLimiter>,
 ) -> Result<String, OpenAIError> {
-    // Creates an HTTPS-capable client using rustls TLS implementation.
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-    
     print_pre(&request, DEBUG_PRE);
     
     // Serializes the request struct into a JSON byte vector
     let request_body = serde_json::to_vec(request)?;
 
-    let mut response: Response = make_request(
-        &client,
-        api_endpoint,
-        api_key, 
-        &request_body, 
-        timeout,
+    let response: Response = send_with_retry(
+        retry_policy,
+        rate_limiter,
+        body_tokens(&request_body),
+        || make_request(client, api_endpoint, api_key, &request_body, timeout),
     ).await?;
 
-    for attempt in 1..=max_retries {
-        if response.status().is_success() || response.status().as_u16() == 401 {
-            // 401 - Invalid Authentication
-            break;
-        }
-
-        warn!("Server error (attempt {}/{}): {}", attempt, max_retries, response.status());
-
-        sleep(RETRY_BASE_DELAY).await;
-        
-        response = make_request(
-            &client,
-            api_endpoint,
-            api_key,
-            &request_body,
-            timeout,
-        ).await?;
-    }
-
     // Checks if the response status is not successful (i.e., not in the 200-299 range).
     if !response.status().is_success() {
         let openai_error: OpenAIError = manage_error(response).await;
@@ -72,26 +55,35 @@ pub async fn request_chat(
     Ok(response_string)
 }
 
+/// Sends a request to the embeddings endpoint
+///
+/// Failed requests are retried as `retry_policy` says, after waiting for
+/// the `rate_limiter`, if any.
 pub async fn request_embed(
+    client: &Client,
     request: &EmbedRequest,
+    api_endpoint: &str,
     api_key: &str,
+    timeout: Duration,
+    retry_policy: &RetryPolicy,
+    rate_limiter: Option<&RateLimiter>,
 ) -> Result<String, OpenAIError> {
-    let client = Client::builder()
-        .use_rustls_tls()
-        .build()?;
-    let response: serde_json::Value;
-    
     print_pre(&request, DEBUG_PRE);
 
-    response = client
-        .post(OPENAI_EMBED_URL)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(request)
-        .send()
-        .await?
-        .json::<serde_json::Value>()
-        .await?;
+    let request_body = serde_json::to_vec(request)?;
+
+    let response: Response = send_with_retry(
+        retry_policy,
+        rate_limiter,
+        body_tokens(&request_body),
+        || make_request(client, api_endpoint, api_key, &request_body, timeout),
+    ).await?;
+
+    if !response.status().is_success() {
+        return Err(manage_error(response).await);
+    }
+
+    let response = response.json::<serde_json::Value>().await?;
 
     print_pre(&response, DEBUG_POST);
 
@@ -99,70 +91,336 @@ pub async fn request_embed(
     Ok(response_string)
 }
 
+/// Streams a chat completion request and yields each decoded chunk
+///
+/// # Arguments
+///
+/// * `client` - Client shared by the requests of a provider struct
+/// * `api_endpoint` - The chat completions endpoint URL
+/// * `api_key` - API key used for Bearer token authentication
+/// * `request` - The chat request, with `stream` enabled
+/// * `retry_policy` - When to retry a failed request, before any chunk arrives
+/// * `rate_limiter` - Optional limit of requests and tokens per minute
+///
+/// # Errors
+///
+/// Failed requests, non-2xx statuses, transport errors and chunks that
+/// cannot be parsed are yielded as `Err` items. A parse error does not end
+/// the stream.
 pub fn strem_chat(
+    client: Client,
     api_endpoint: String,
     api_key: String,
     request: ChatRequest,
-) -> impl futures::Stream<Item = ChatResponse> {
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+) -> impl futures::Stream<Item = Result<ChatResponse, OpenAIError>> {
     stream! {
-        let client = Client::new();
-
-        let response: Response = match client
-            .post(&api_endpoint)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await {
+        let request_body = match serde_json::to_vec(&request) {
+            Ok(request_body) => request_body,
+            Err(e) => {
+                yield Err(OpenAIError::JsonError(e));
+                return;
+            }
+        };
+
+        let response: Response = match send_with_retry(
+            &retry_policy,
+            rate_limiter.as_ref(),
+            body_tokens(&request_body),
+            || client
+                .post(&api_endpoint)
+                .header("Authorization", format!("Bearer {}", api_key))
+                .header("Content-Type", "application/json")
+                .body(request_body.clone())
+                .send(),
+        ).await {
                 Ok(response) => response,
                 Err(e) => {
                     error!("Error Error sending request: {}", e);
-                    return
+                    yield Err(OpenAIError::RequestError(e));
+                    return;
                 }
             };
 
-        if response.status().is_success() {
-            let mut stream = response.bytes_stream();
-
-            while let Some(chunk) = stream.next().await {
-                match chunk {
-                    Ok(bytes) => {
-                        let str_chunk = String::from_utf8_lossy(&bytes);
-                        let parts: Vec<&str> = str_chunk.split("\n\n").collect();
-                        for part in parts {
-                            if !part.is_empty() && part.ends_with("[DONE]") {
-                                break;
-                            }
-
-                            if !part.is_empty() && part.starts_with("data:") {
-                                let json_part = part.trim_start_matches("data:");
-                                if !json_part.contains("delta") {
-                                    continue;
-                                }
-                            
-                                match serde_json::from_str::<ChatResponse>(json_part) {
-                                    Ok(stream_response) => {
-                                        yield stream_response;
-                                    },
-                                    Err(e) => {
-                                        println!("Error parsing chunk: {:?}", json_part);
-                                        warn!("Error Error parsing chunk: {}", e);
-                                    }
-                                }    
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        warn!("Error Error reading chunk: {}", e);
+        if !response.status().is_success() {
+            yield Err(manage_error(response).await);
+            return;
+        }
+
+        let events = sse_events(response.bytes_stream());
+        pin_mut!(events);
+
+        while let Some(event) = events.next().await {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Error Error reading chunk: {}", e);
+                    yield Err(OpenAIError::RequestError(e));
+                    return;
+                }
+            };
+
+            if event.is_done() {
+                break;
+            }
+
+            match serde_json::from_str::<ChatResponse>(&event.data) {
+                Ok(stream_response) => {
+                    if stream_response.error.is_some() {
+                        yield Err(OpenAIError::ApiError(ApiError::from_body(Provider::OpenAI, &event.data)));
+                    } else {
+                        yield Ok(stream_response);
                     }
+                },
+                Err(e) => {
+                    warn!("Error Error parsing chunk: {}", e);
+                    yield Err(OpenAIError::JsonError(e));
+                }
+            }
+        }
+    }
+}
+
+/// Streams a Responses API request and yields each semantic event
+///
+/// # Arguments
+///
+/// * `client` - Client shared by the requests of a provider struct
+/// * `api_endpoint` - The responses endpoint URL
+/// * `api_key` - API key used for Bearer token authentication
+/// * `request` - The response request, with `stream` enabled
+/// * `retry_policy` - When to retry a failed request, before any chunk arrives
+/// * `rate_limiter` - Optional limit of requests and tokens per minute
+///
+/// # Errors
+///
+/// Failed requests, non-2xx statuses and transport errors are yielded as
+/// `Err` items and end the stream, as do `error` and `response.failed`
+/// events. Events that cannot be parsed are yielded as `Err` items without
+/// ending the stream.
+pub fn strem_response(
+    client: Client,
+    api_endpoint: String,
+    api_key: String,
+    request: ResponseRequest,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+) -> impl futures::Stream<Item = Result<ResponseStreamEvent, OpenAIError>> {
+    stream! {
+        let request_body = match serde_json::to_vec(&request) {
+            Ok(request_body) => request_body,
+            Err(e) => {
+                yield Err(OpenAIError::JsonError(e));
+                return;
+            }
+        };
+
+        let response: Response = match send_with_retry(
+            &retry_policy,
+            rate_limiter.as_ref(),
+            body_tokens(&request_body),
+            || client
+                .post(&api_endpoint)
+                .header("Authorization", format!("Bearer {}", api_key))
+                .header("Content-Type", "application/json")
+                .body(request_body.clone())
+                .send(),
+        ).await {
+                Ok(response) => response,
+                Err(e) => {
+                    error!("Error sending request: {}", e);
+                    yield Err(OpenAIError::RequestError(e));
+                    return;
+                }
+            };
+
+        if !response.status().is_success() {
+            yield Err(manage_error(response).await);
+            return;
+        }
+
+        let events = sse_events(response.bytes_stream());
+        pin_mut!(events);
+
+        while let Some(event) = events.next().await {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Error reading chunk: {}", e);
+                    yield Err(OpenAIError::RequestError(e));
+                    return;
+                }
+            };
+
+            if event.is_done() {
+                break;
+            }
+
+            match serde_json::from_str::<ResponseStreamEvent>(&event.data) {
+                Ok(ResponseStreamEvent::Error { .. }) => {
+                    yield Err(OpenAIError::ApiError(ApiError::from_body(Provider::OpenAI, &event.data)));
+                    return;
+                }
+                Ok(ResponseStreamEvent::Failed { response }) => {
+                    // The error object is nested in the failed response
+                    let error = match response.error {
+                        Some(error) => ApiError::from_body(Provider::OpenAI, &error.to_string()),
+                        None => ApiError::new(Provider::OpenAI, "Response failed"),
+                    };
+                    yield Err(OpenAIError::ApiError(error));
+                    return;
+                }
+                Ok(stream_event) => yield Ok(stream_event),
+                Err(e) => {
+                    warn!("Error parsing chunk: {}", e);
+                    yield Err(OpenAIError::JsonError(e));
                 }
             }
-        } else {
-            error!("Error Request failed with status code: {}", response.status());
         }
     }
 }
 
+/// Sends a GET request and returns the JSON response
+///
+/// # Arguments
+///
+/// * `client` - Client shared by the requests of a provider struct
+/// * `url` - The endpoint URL, e.g. a file or a batch
+/// * `api_key` - API key used for Bearer token authentication
+/// * `timeout` - The timeout of the request
+pub async fn get_request(
+    client: &Client,
+    url: &str,
+    api_key: &str,
+    timeout: Duration,
+) -> Result<Value, OpenAIError> {
+    let response = download_request(client, url, api_key, timeout).await?;
+    let response_data: Value = serde_json::from_slice(&response)?;
+    print_pre(&response_data, DEBUG_POST);
+    Ok(response_data)
+}
+
+/// Sends a GET request and returns the raw body, e.g. the content of a file
+pub async fn download_request(
+    client: &Client,
+    url: &str,
+    api_key: &str,
+    timeout: Duration,
+) -> Result<Vec<u8>, OpenAIError> {
+    let response = client
+        .get(url)
+        .timeout(timeout)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(manage_error(response).await);
+    }
+
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// Sends a POST request with a JSON body and returns the JSON response,
+/// without retries
+pub async fn post_request(
+    client: &Client,
+    url: &str,
+    api_key: &str,
+    request_body: &[u8],
+    timeout: Duration,
+) -> Result<Value, OpenAIError> {
+    let response = make_request(client, url, api_key, request_body, timeout).await?;
+
+    if !response.status().is_success() {
+        return Err(manage_error(response).await);
+    }
+
+    let response_data = response.json::<Value>().await?;
+    print_pre(&response_data, DEBUG_POST);
+    Ok(response_data)
+}
+
+/// Sends a DELETE request and returns the JSON response
+pub async fn delete_request(
+    client: &Client,
+    url: &str,
+    api_key: &str,
+    timeout: Duration,
+) -> Result<Value, OpenAIError> {
+    let response = client
+        .delete(url)
+        .timeout(timeout)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(manage_error(response).await);
+    }
+
+    let response_data = response.json::<Value>().await?;
+    print_pre(&response_data, DEBUG_POST);
+    Ok(response_data)
+}
+
+/// Uploads a file as `multipart/form-data` with its `purpose` field
+///
+/// # Arguments
+///
+/// * `client` - Client shared by the requests of a provider struct
+/// * `url` - The files endpoint URL
+/// * `api_key` - API key used for Bearer token authentication
+/// * `file_name` - Name stored with the file
+/// * `file_data` - Content of the file
+/// * `purpose` - Intended use, e.g. `batch`
+/// * `timeout` - The timeout of the request
+pub async fn upload_request(
+    client: &Client,
+    url: &str,
+    api_key: &str,
+    file_name: &str,
+    file_data: &[u8],
+    purpose: &str,
+    timeout: Duration,
+) -> Result<Value, OpenAIError> {
+    let boundary = format!("langchain-{}", std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default());
+
+    let mut body: Vec<u8> = Vec::new();
+    body.extend_from_slice(format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"purpose\"\r\n\r\n{}\r\n",
+        boundary, purpose,
+    ).as_bytes());
+    body.extend_from_slice(format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\
+        Content-Type: application/octet-stream\r\n\r\n",
+        boundary, file_name.replace('"', ""),
+    ).as_bytes());
+    body.extend_from_slice(file_data);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    let response = client
+        .post(url)
+        .timeout(timeout)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .header("Content-Type", format!("multipart/form-data; boundary={}", boundary))
+        .body(body)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(manage_error(response).await);
+    }
+
+    let response_data = response.json::<Value>().await?;
+    print_pre(&response_data, DEBUG_POST);
+    Ok(response_data)
+}
+
 pub async fn make_request(
     client: &Client,
     url: &str,
@@ -180,51 +438,44 @@ pub async fn make_request(
         .await?)
 }
 
+/// Reads the status, request id and error body of a failed response
 pub async fn manage_error(
     response: Response,
 ) -> OpenAIError {
     error!("Response code: {}", response.status());
+    OpenAIError::ApiError(ApiError::read(Provider::OpenAI, response).await)
+}
 
-    match response.json::<ErrorResponse>().await {
-        Ok(error_detail) => {
-            match error_detail.error.code.as_str() {
-                "invalid_api_key" => OpenAIError::AuthenticationError(
-                    error_detail.error.message
-                ),
-                "invalid_request_error" => OpenAIError::BadRequestError(
-                    error_detail.error.message
-                ),
-                "rate_limit_error" => OpenAIError::RateLimitError(
-                    error_detail.error.message
-                ),
-                "tokens_exceeded_error" => OpenAIError::RateLimitError(
-                    error_detail.error.message
-                ),
-                "authentication_error" => OpenAIError::AuthenticationError(
-                    error_detail.error.message
-                ),
-                "not_found_error" => OpenAIError::NotFoundError(
-                    error_detail.error.message
-                ),
-                "server_error" => OpenAIError::InternalServerError(
-                    error_detail.error.message
-                ),
-                "permission_error" => OpenAIError::PermissionDeniedError(
-                    error_detail.error.message
-                ), 
-                _ => OpenAIError::GenericError {
-                    code: error_detail.error.code,
-                    message: error_detail.error.message,
-                    detail: "ERROR-req-9822".to_string(),
-                },
-            }
-        }
-        Err(e) => {
-            OpenAIError::GenericError {
-                code: "None".to_string(),
-                message: format!("Error: {}", e),
-                detail: "ERROR-req-9823".to_string(),
-            }
-        }
+/// Maps the `error` object of an API error body, or of a failed batch
+/// request, to an `OpenAIError`
+pub fn error_from_details(error: ErrorDetails) -> OpenAIError {
+    match error.code.as_str() {
+        "invalid_api_key" => OpenAIError::AuthenticationError(
+            error.message
+        ),
+        "invalid_request_error" => OpenAIError::BadRequestError(
+            error.message
+        ),
+        "rate_limit_error" => OpenAIError::RateLimitError(
+            error.message
+        ),
+        "tokens_exceeded_error" => OpenAIError::RateLimitError(
+            error.message
+        ),
+        "authentication_error" => OpenAIError::AuthenticationError(
+            error.message
+        ),
+        "not_found_error" => OpenAIError::NotFoundError(
+            error.message
+        ),
+        "server_error" => OpenAIError::InternalServerError(
+            error.message
+        ),
+        "permission_error" => OpenAIError::PermissionDeniedError(
+            error.message
+        ),
+        _ => OpenAIError::ApiError(
+            ApiError::new(Provider::OpenAI, &error.message).with_code(&error.code)
+        ),
     }
-}
\ No newline at end of file
+}
@@ -1,29 +1,32 @@
 use futures::pin_mut;
 use futures::StreamExt;
 use async_stream::stream;
-use crate::openai::requests::{request_chat, strem_chat};
+use crate::openai::requests::{request_chat, strem_response};
 use crate::openai::utils::GetApiKey;
-// use crate::openai::libs::{
-//     ChatRequest, ResponseFormat,
-//     Message, Role, ChatResponse, ImageUrl,
-// };
-
 use crate::openai::libs::MainRequest;
 use crate::openai::lib_response::{
-    ResponseRequest, InputContent, ResponseObject,
+    ResponseRequest, InputContent, InputItemList, Item, Reasoning,
+    ResponseObject, ResponseStreamEvent, ToolChoice,
 };
 use crate::openai::OPENAI_RESPONSE_URL;
 use crate::openai::error::OpenAIError;
+use crate::llm::retry::{RateLimiter, RetryPolicy};
+use serde_json::json;
 use std::time::Duration;
 use log::error;
+use crate::llm::http::{default_client, WithHttpClient};
+use reqwest::Client;
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct ChatOpenAI {
     pub api_key: String,
+    pub base_url: String,
     pub request: ResponseRequest,
     pub timeout: Duration,
-    pub max_retries: u32,
+    pub retry_policy: RetryPolicy,
+    pub rate_limiter: Option<RateLimiter>,
+    pub client: Client,
 }
 
 #[allow(dead_code)]
@@ -54,274 +57,230 @@ impl ChatOpenAI {
             truncation: None,
             user: None,
         };
-        
+
         Self {
-            api_key: api_key,
-            request: request,
+            api_key,
+            base_url: OPENAI_RESPONSE_URL.to_string(),
+            request,
             timeout: Duration::from_secs(300), // default: 5 minutes
-            max_retries: 3,         // default: 3 times
+            retry_policy: RetryPolicy::default(), // default: 3 retries
+            rate_limiter: None,
+            client: default_client(),
         }
     }
 
     pub async fn invoke(
-        self,
+        mut self,
     ) -> Result<ResponseObject, OpenAIError> {
+        self.request.stream = None;
         let body_request = MainRequest::Responses(self.request.clone());
 
         let response: String = match request_chat(
+            &self.client,
             &body_request,
-            OPENAI_RESPONSE_URL,
+            &self.base_url,
             &self.api_key,
             self.timeout,
-            self.max_retries,
+            &self.retry_policy,
+            self.rate_limiter.as_ref(),
         ).await {
             Ok(response) => response,
             Err(openai_error) => {
                 return Err(openai_error);
             }
         };
-        
+
         let chat_response: ResponseObject = match serde_json::from_str(&response) {
             Ok(response_form) => response_form,
             Err(e) => {
-                error!("Failed to parse response: {}. ERROR-req-0023", e);
-                return Err(OpenAIError::ResponseContentError);
+                error!("Failed to parse response: {}", e);
+                return Err(e.into());
             }
         };
 
-        // let format_response = ChatResponse {
-        //     choices: chat_response.choices,
-        //     created: chat_response.created,
-        //     id: chat_response.id,
-        //     model: chat_response.model,
-        //     object: chat_response.object,
-        //     system_fingerprint: chat_response.system_fingerprint,
-        //     usage: chat_response.usage,
-        //     chat_history: self.request.messages,
-        //     service_tier: None,
-        //     error: None,
-        // };
         Ok(chat_response)
     }
 
-    // pub fn stream_response(
-    //     mut self,
-    // ) -> impl futures::Stream<Item = ChatResponse> {
-    //     stream! {     
-    //         self.request.stream = Some(true);
-    //         let endpoint_string = OPENAI_RESPONSE_URL.to_string();
-            
-    //         let body_request = MainRequest::Responses(self.request.clone());
-
-    //         let stream = strem_chat(
-    //             endpoint_string.clone(),
-    //             self.api_key.clone(),
-    //             body_request,
-    //         );
-
-    //         pin_mut!(stream);
-
-    //         while let Some(chat_response) = stream.next().await {
-    //             yield chat_response;
-    //         }
-    //     }
-    // }
+    /// Streams the response as semantic events (`response.output_text.delta`,
+    /// `response.function_call_arguments.delta`, `response.completed`...).
+    /// The `Completed` event carries the same `ResponseObject` that `invoke` returns.
+    pub fn stream_response(
+        mut self,
+    ) -> impl futures::Stream<Item = Result<ResponseStreamEvent, OpenAIError>> {
+        stream! {
+            self.request.stream = Some(true);
+            let endpoint_string = self.base_url.clone();
+
+            let stream = strem_response(
+                self.client.clone(),
+                endpoint_string,
+                self.api_key.clone(),
+                self.request.clone(),
+                self.retry_policy.clone(),
+                self.rate_limiter.clone(),
+            );
+
+            pin_mut!(stream);
+
+            while let Some(stream_event) = stream.next().await {
+                yield stream_event;
+            }
+        }
+    }
 
     pub fn with_prompt(mut self, prompt: &str) -> Self {
         self.request.input = InputContent::String(prompt.to_string());
         self
     }
 
-    // pub fn with_temperature(mut self, temperature: f32) -> Self {
-    //     if temperature < 0.0 || temperature > 2.0 {
-    //         println!(
-    //             "[ERROR] Temperature must be between 0.0 and 2.0. Actual temperature is {}", 
-    //             self.request.temperature.unwrap_or(0.0)
-    //         );
-    //         self
-    //     } else {
-    //         self.request.temperature = Some(temperature);
-    //         self
-    //     }
-    // }
-    
-    // pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
-    //     self.request.max_completion_tokens = Some(max_tokens);
-    //     self
-    // }
-
-    // pub fn with_timeout_sec(mut self, timeout: u64) -> Self {
-    //     self.timeout = Duration::from_secs(timeout);
-    //     self
-    // }
-
-    // pub fn with_frequency_penalty(mut self, frequency_penalty: f32) -> Self {
-    //     if frequency_penalty < -2.0 || frequency_penalty > 2.0 {
-    //         println!(
-    //             "[ERROR] Frequency penalty must be between -2.0 and 2.0. Actual frequency penalty is {}",
-    //             self.request.frequency_penalty.unwrap_or(0.0)
-    //         );
-    //         self
-    //     } else {
-    //         self.request.frequency_penalty = Some(frequency_penalty);
-    //         self
-    //     }
-    // }
-
-    // pub fn with_presence_penalty(mut self, presence_penalty: f32) -> Self {
-    //     if presence_penalty < -2.0 || presence_penalty > 2.0 {
-    //         println!(
-    //             "[ERROR] Presence penalty must be between -2.0 and 2.0. Actual presence penalty is {}",
-    //             self.request.presence_penalty.unwrap_or(0.0)
-    //         );
-    //         self
-    //     } else {
-    //         self.request.presence_penalty = Some(presence_penalty);
-    //         self
-    //     }
-    // }
-
-    // pub fn with_image_url(mut self, image_url: &str) -> Self {
-    //     self.request.input = InputContent::ImageUrl(InputItemList {
-    //         image_url: Some(image_url.to_string()),
-    //         text: None,
-    //     });
-
-    //     self
-    // }
-
-    // pub fn with_top_p(mut self, top_p: f32) -> Self {
-    //     if top_p < 0.0 || top_p > 1.0 {
-    //         println!(
-    //             "[ERROR] Top p must be between 0.0 and 1.0. Actual top p is {}",
-    //             self.request.top_p.unwrap_or(0.0)
-    //         );
-    //         self
-    //     } else {
-    //         self.request.top_p = Some(top_p);
-    //         self
-    //     }
-    // }
-
-    // pub fn with_n_completion(mut self, n_completion: u32) -> Self {
-    //     self.request.n_completion = Some(n_completion);
-    //     self
-    // }
-
-    // pub fn with_stop(mut self, stop: Vec<String>) -> Self {
-    //     self.request.stop = Some(stop);
-    //     self
-    // }
-
-    // pub fn with_system_prompt(mut self, system_prompt: &str) -> Self {
-    //     let content = vec![InputContent {
-    //         content_type: "text".to_string(),
-    //         text: Some(system_prompt.to_string()),
-    //         source: None,
-    //         image_url: None,
-    //     }];
-
-    //     let new_message = Message {
-    //         role: match self.request.model.as_str() {
-    //             "o1-mini" => Role::User,
-    //             _ => Role::Developer,
-    //         },
-    //         content: content.clone(),
-    //         recipient: None,
-    //         end_turn: None,
-    //     };
-
-    //     if let Some(messages) = &mut self.request.messages {
-    //         messages.insert(0, new_message);
-    //     } else {
-    //         self.request.messages = Some(vec![new_message]);
-    //     }
-
-    //     self
-    // }
-
-    // pub fn with_assistant_response(mut self,  assistant_response: &str) -> Self {
-    //     let content = vec![InputContent {
-    //         content_type: "text".to_string(),
-    //         text: Some(assistant_response.to_string()),
-    //         source: None,
-    //         image_url: None,
-    //     }];
-
-    //     let new_message = Message {
-    //         role: Role::Assistant,
-    //         content: content.clone(),
-    //         recipient: None,
-    //         end_turn: None,
-    //     };
-
-    //     if let Some(messages) = &mut self.request.messages {
-    //         messages.push(new_message);
-    //     } else {
-    //         self.request.messages = Some(vec![new_message]);
-    //     }
-
-    //     self
-    // }
-
-    // pub fn with_chat_history(mut self, history: Vec<Message>) -> Self {
-    //     self.request.messages = Some(history);
-    //     self
-    // }
-
-    // pub fn with_json_schema(mut self, json_schema: serde_json::Value) -> Self {
-    //     let response_format = ResponseFormat {
-    //         response_type: "json_schema".to_string(),
-    //         json_schema: Some(json_schema),
-    //     };
-
-    //     self.request.response_format = Some(response_format);
-    //     self
-    // }
-
-    // pub fn with_tools(mut self, tools_data: Vec<serde_json::Value>) -> Self {
-    //     self.request.tools = Some(tools_data);
-    //     self
-    // }
-
-    // pub fn with_tool_choice(mut self, tool_choice: serde_json::Value) -> Self {
-    //     self.request.tool_choice = Some(tool_choice);
-    //     self
-    // }
-
-    // pub fn with_max_retries(mut self, max_retries: u32) -> Self {
-    //     self.max_retries = max_retries;
-    //     self
-    // }
-
-    // pub fn with_api_key(mut self, api_key: &str) -> Self {
-    //     self.api_key = api_key.to_string();
-    //     self
-    // }
-
-    // pub fn with_json_format(mut self) -> Self {
-    //     let text_format = TextFormat {
-    //         format: json!({
-    //             "type": "json_object"
-    //         })
-    //     };
-    //     self.request.text = Some(text_format);
-    //     self
-    // }
-
-    // pub fn with_store(mut self, store: bool) -> Self {
-    //     self.request.store = Some(store);
-    //     self
-    // }
-
-    // pub fn with_reasoning(mut self, effort: &str) -> Self {
-    //     let reasoning = Reasoning {
-    //         effort: effort.to_string(),
-    //     };
-
-    //     self.request.reasoning = Some(reasoning);
-    //     self
-    // }
+    pub fn with_instructions(mut self, instructions: &str) -> Self {
+        self.request.instructions = Some(instructions.to_string());
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        if !(0.0..=2.0).contains(&temperature) {
+            println!(
+                "[ERROR] Temperature must be between 0.0 and 2.0. Actual temperature is {}",
+                self.request.temperature.unwrap_or(0.0)
+            );
+            self
+        } else {
+            self.request.temperature = Some(temperature);
+            self
+        }
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.request.max_output_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        if !(0.0..=1.0).contains(&top_p) {
+            println!(
+                "[ERROR] Top p must be between 0.0 and 1.0. Actual top p is {}",
+                self.request.top_p.unwrap_or(0.0)
+            );
+            self
+        } else {
+            self.request.top_p = Some(top_p);
+            self
+        }
+    }
+
+    pub fn with_timeout_sec(mut self, timeout: u64) -> Self {
+        self.timeout = Duration::from_secs(timeout);
+        self
+    }
+
+    /// Function tools use the flat Responses format:
+    /// `{"type": "function", "name": ..., "parameters": ...}`
+    pub fn with_tools(mut self, tools_data: Vec<serde_json::Value>) -> Self {
+        self.request.tools = Some(tools_data);
+        self
+    }
+
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.request.tool_choice = Some(tool_choice);
+        self
+    }
+
+    pub fn with_parallel_tool_calls(mut self, parallel_tool_calls: bool) -> Self {
+        self.request.parallel_tool_calls = Some(parallel_tool_calls);
+        self
+    }
+
+    /// Sends the output of a `function_call` back to the model. Use it together
+    /// with `with_previous_response_id` so the model can see the original call.
+    pub fn with_function_output(mut self, call_id: &str, output: &str) -> Self {
+        let item = InputItemList::Item(Item::FunctionToolOutput(json!({
+            "type": "function_call_output",
+            "call_id": call_id,
+            "output": output,
+        })));
+
+        if let InputContent::ItemList(items) = &mut self.request.input {
+            items.push(item);
+        } else {
+            self.request.input = InputContent::ItemList(vec![item]);
+        }
+
+        self
+    }
+
+    /// Reasoning effort for o-series models: low, medium or high
+    pub fn with_reasoning(mut self, effort: &str) -> Self {
+        let summary = self.request.reasoning.take().and_then(|reasoning| reasoning.summary);
+        self.request.reasoning = Some(Reasoning {
+            effort: Some(effort.to_string()),
+            generate_summary: None,
+            summary,
+        });
+        self
+    }
+
+    /// Reasoning summary for o-series models: auto, concise or detailed
+    pub fn with_reasoning_summary(mut self, summary: &str) -> Self {
+        let effort = self.request.reasoning.take().and_then(|reasoning| reasoning.effort);
+        self.request.reasoning = Some(Reasoning {
+            effort,
+            generate_summary: None,
+            summary: Some(summary.to_string()),
+        });
+        self
+    }
+
+    pub fn with_store(mut self, store: bool) -> Self {
+        self.request.store = Some(store);
+        self
+    }
+
+    /// Continues the conversation of a stored response
+    pub fn with_previous_response_id(mut self, previous_response_id: &str) -> Self {
+        self.request.previous_response_id = Some(previous_response_id.to_string());
+        self
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.retry_policy.max_retries = max_retries;
+        self
+    }
+
+    /// Replaces the retry policy, e.g. to change the backoff delays
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Limits the requests and tokens per minute; clone the same limiter
+    /// into every client that shares the account
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = api_key.to_string();
+        self
+    }
+
+    /// Overrides the responses endpoint (default: `OPENAI_RESPONSE_URL`)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.to_string();
+        self
+    }
+}
+
+impl WithHttpClient for ChatOpenAI {
+    fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn with_config_base_url(self, base_url: &str) -> Self {
+        self.with_base_url(base_url)
+    }
 }
 
-impl GetApiKey for ChatOpenAI {}
\ No newline at end of file
+impl GetApiKey for ChatOpenAI {}
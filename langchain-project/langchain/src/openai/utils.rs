@@ -1,5 +1,12 @@
 use std::env;
 use crate::openai::error::OpenAIError;
+use crate::openai::libs::{
+    ChatResponse, ImageUrl, InputContent, Message, Role as OpenAIRole,
+};
+use crate::llm::libs::{
+    ChatMessage, ContentPart, ModelResponse, Role, StopReason,
+    ToolCall, ToolChoice, ToolDefinition,
+};
 use serde_json::{json, Value};
 use schemars::schema::RootSchema;
 use log::{info, error};
@@ -13,7 +20,7 @@ use log::{info, error};
 /// * If the OPENAI_API_KEY environment variable is not set
 ///
 /// # Examples
-/// ```
+/// ```ignore
 /// let api_key = get_api_key();
 /// println!("API key: {}", api_key);
 /// ```
@@ -104,4 +111,170 @@ pub fn generate_schema(
         format_response["schema"]["additionalProperties"] = a_resp;
         return Ok(format_response);
     }
-}
\ No newline at end of file
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ ChatModel ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Converts normalized messages into chat completions messages
+///
+/// System messages are sent with the developer role, except for `o1-mini`
+/// which only accepts user messages (same rule as `with_system_prompt`).
+///
+pub fn to_openai_messages(messages: &[ChatMessage], model: &str) -> Vec<Message> {
+    messages
+        .iter()
+        .map(|message| {
+            let role = match message.role {
+                Role::System => match model {
+                    "o1-mini" => OpenAIRole::User,
+                    _ => OpenAIRole::Developer,
+                },
+                Role::User => OpenAIRole::User,
+                Role::Assistant => OpenAIRole::Assistant,
+                Role::Tool => OpenAIRole::Tool,
+            };
+
+            let tool_calls = if message.tool_calls.is_empty() {
+                None
+            } else {
+                Some(message.tool_calls.iter().map(to_openai_tool_call).collect())
+            };
+
+            Message {
+                role,
+                content: to_openai_content(&message.content),
+                recipient: None,
+                end_turn: None,
+                tool_calls,
+                tool_call_id: message.tool_call_id.clone(),
+            }
+        })
+        .collect()
+}
+
+fn to_openai_content(content: &[ContentPart]) -> Vec<InputContent> {
+    content
+        .iter()
+        .filter_map(|part| match part {
+            ContentPart::Text { text } => Some(InputContent {
+                content_type: "text".to_string(),
+                text: Some(text.clone()),
+                source: None,
+                image_url: None,
+            }),
+            ContentPart::Image { mime_type, data } => Some(InputContent {
+                content_type: "image_url".to_string(),
+                text: None,
+                source: None,
+                image_url: Some(ImageUrl {
+                    url: format!("data:{};base64,{}", mime_type, data),
+                }),
+            }),
+            ContentPart::Thinking { .. } | ContentPart::RedactedThinking { .. } => None,
+        })
+        .collect()
+}
+
+/// Formats a tool call as the `tool_calls` entry of an assistant message
+pub fn to_openai_tool_call(tool_call: &ToolCall) -> Value {
+    json!({
+        "id": tool_call.id,
+        "type": "function",
+        "function": {
+            "name": tool_call.name,
+            "arguments": tool_call.arguments.to_string(),
+        }
+    })
+}
+
+/// Parses the `tool_calls` entries returned by a chat completions API
+///
+/// The arguments are sent by the API as a JSON string; when they are not
+/// valid JSON the raw string is kept.
+///
+pub fn from_openai_tool_calls(tool_calls: &[Value]) -> Vec<ToolCall> {
+    tool_calls
+        .iter()
+        .map(|tool_call| {
+            let arguments = match tool_call["function"]["arguments"].as_str() {
+                Some(arguments) => serde_json::from_str(arguments)
+                    .unwrap_or(Value::String(arguments.to_string())),
+                None => tool_call["function"]["arguments"].clone(),
+            };
+
+            ToolCall {
+                id: tool_call["id"].as_str().unwrap_or_default().to_string(),
+                name: tool_call["function"]["name"].as_str().unwrap_or_default().to_string(),
+                arguments,
+            }
+        })
+        .collect()
+}
+
+pub fn to_openai_tools(tools: &[ToolDefinition]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|tool| json!({
+            "type": "function",
+            "function": {
+                "name": tool.name,
+                "description": tool.description,
+                "parameters": tool.parameters,
+            }
+        }))
+        .collect()
+}
+
+pub fn to_openai_tool_choice(tool_choice: &ToolChoice) -> Value {
+    match tool_choice {
+        ToolChoice::Auto => json!("auto"),
+        ToolChoice::Required => json!("required"),
+        ToolChoice::None => json!("none"),
+        ToolChoice::Tool(name) => json!({
+            "type": "function",
+            "function": { "name": name }
+        }),
+    }
+}
+
+pub fn to_stop_reason(finish_reason: String) -> StopReason {
+    match finish_reason.as_str() {
+        "stop" => StopReason::EndTurn,
+        "length" => StopReason::MaxTokens,
+        "tool_calls" | "function_call" => StopReason::ToolUse,
+        "content_filter" => StopReason::ContentFilter,
+        _ => StopReason::Other(finish_reason),
+    }
+}
+
+/// Converts the first choice of a chat completions response into a `ModelResponse`
+///
+/// # Returns
+/// * `None` if the response has no message
+///
+pub fn to_model_response(response: ChatResponse) -> Option<ModelResponse> {
+    let choice = response.choices?.into_iter().next()?;
+    let message = choice.message?;
+
+    let content = match message.content {
+        Some(text) if !text.is_empty() => vec![ContentPart::Text { text }],
+        _ => Vec::new(),
+    };
+    let tool_calls = from_openai_tool_calls(&message.tool_calls.unwrap_or_default());
+
+    let usage = response.usage.map(|usage| usage.token_usage());
+
+    Some(ModelResponse {
+        message: ChatMessage {
+            role: Role::Assistant,
+            content,
+            tool_calls,
+            tool_call_id: None,
+            name: None,
+        },
+        stop_reason: choice.finish_reason.map(to_stop_reason),
+        usage,
+        model: response.model,
+        id: response.id,
+    })
+}
@@ -0,0 +1,216 @@
+use crate::langsmith::client::LangsmithClient;
+use crate::langsmith::libs::{CommitResponse, Manifest};
+use crate::llm::libs::{ChatMessage, Role};
+use crate::prompts::error::PromptError;
+use crate::prompts::template::{merge_variables, PromptTemplate, TemplateFormat};
+use serde_json::{Map, Value};
+
+/// One entry of a `ChatPromptTemplate`
+///
+/// * `Message` - A message whose text is rendered from a template
+/// * `Placeholder` - Messages passed in a variable, e.g. the chat history,
+///   as a JSON array of serialized `ChatMessage`
+/// * `FewShot` - A user/assistant exchange per example, rendered with
+///   `example_prompt`
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub enum MessageTemplate {
+    Message {
+        role: Role,
+        template: PromptTemplate,
+    },
+    Placeholder {
+        name: String,
+        optional: bool,
+    },
+    FewShot {
+        examples: Vec<Value>,
+        example_prompt: Box<ChatPromptTemplate>,
+    },
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ ChatPromptTemplate ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// A conversation template rendered to the `ChatMessage` list taken by
+/// every `ChatModel`
+///
+/// # Example
+/// ```ignore
+/// let prompt = ChatPromptTemplate::from_messages(&[
+///     (Role::System, "You are a {style} assistant."),
+///     (Role::User, "{question}"),
+/// ])?
+/// .with_partial("style", "concise");
+///
+/// let messages = prompt.format_messages(&json!({"question": "What is Rust?"}))?;
+/// let response = llm.generate(&messages, &ChatOptions::default()).await?;
+/// ```
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct ChatPromptTemplate {
+    pub messages: Vec<MessageTemplate>,
+    pub partial_variables: Map<String, Value>,
+}
+
+#[allow(dead_code)]
+impl ChatPromptTemplate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Template of f-string messages
+    ///
+    /// # Errors
+    /// * `InvalidTemplate` - A message is not a valid f-string template
+    pub fn from_messages(messages: &[(Role, &str)]) -> Result<Self, PromptError> {
+        let mut prompt = Self::new();
+        for (role, template) in messages {
+            prompt = prompt.with_message(*role, PromptTemplate::new(template)?);
+        }
+        Ok(prompt)
+    }
+
+    /// Template of a chat prompt commit from the LangSmith hub
+    ///
+    /// A plain `PromptTemplate` commit becomes a single user message.
+    ///
+    /// # Errors
+    /// * `UnsupportedManifest` - The commit has message types other than
+    ///   system, human, AI and placeholder
+    /// * `UnsupportedFormat` - A template is neither f-string nor mustache
+    pub fn from_manifest(manifest: &Manifest) -> Result<Self, PromptError> {
+        let kwargs = &manifest.kwargs;
+        let mut prompt = Self::new();
+
+        if kwargs.messages.is_empty() {
+            let Some(template) = &kwargs.template else {
+                return Err(PromptError::UnsupportedManifest(manifest_name(&manifest.id)));
+            };
+            let template_format = TemplateFormat::from_name(kwargs.template_format.as_deref().unwrap_or_default())?;
+            prompt = prompt.with_message(Role::User, PromptTemplate::parse(template, template_format)?);
+        }
+
+        for message in &kwargs.messages {
+            let name = manifest_name(&message.id);
+            let role = match name.as_str() {
+                "SystemMessagePromptTemplate" => Role::System,
+                "HumanMessagePromptTemplate" => Role::User,
+                "AIMessagePromptTemplate" => Role::Assistant,
+                "MessagesPlaceholder" => {
+                    let Some(variable_name) = &message.kwargs.variable_name else {
+                        return Err(PromptError::UnsupportedManifest(name));
+                    };
+                    prompt = prompt.with_placeholder(variable_name, message.kwargs.optional.unwrap_or(false));
+                    continue;
+                }
+                _ => return Err(PromptError::UnsupportedManifest(name)),
+            };
+
+            let Some(template) = &message.kwargs.prompt else {
+                return Err(PromptError::UnsupportedManifest(name));
+            };
+            prompt = prompt.with_message(role, PromptTemplate::from_kwargs(&template.kwargs)?);
+        }
+
+        for (name, value) in kwargs.partial_variables.iter().flatten() {
+            prompt = prompt.with_partial(name, value.clone());
+        }
+        Ok(prompt)
+    }
+
+    /// Pulls a prompt from the LangSmith hub, e.g. `("rlm", "rag-prompt", "latest")`
+    ///
+    /// # Errors
+    /// * `LangsmithError` - No API key or the request failed
+    /// * `JsonError` - The response is not a commit
+    pub async fn pull(owner: &str, repo: &str, commit: &str) -> Result<Self, PromptError> {
+        let response = LangsmithClient::new()?.get_commit(owner, repo, commit).invoke().await?;
+        let commit: CommitResponse = serde_json::from_value(response)?;
+        Self::from_manifest(&commit.manifest)
+    }
+
+    pub fn with_message(mut self, role: Role, template: PromptTemplate) -> Self {
+        self.messages.push(MessageTemplate::Message { role, template });
+        self
+    }
+
+    /// Inserts the messages of a variable; a missing `optional` variable
+    /// inserts nothing
+    pub fn with_placeholder(mut self, name: &str, optional: bool) -> Self {
+        self.messages.push(MessageTemplate::Placeholder {
+            name: name.to_string(),
+            optional,
+        });
+        self
+    }
+
+    pub fn with_few_shot(mut self, examples: Vec<Value>, example_prompt: ChatPromptTemplate) -> Self {
+        self.messages.push(MessageTemplate::FewShot {
+            examples,
+            example_prompt: Box::new(example_prompt),
+        });
+        self
+    }
+
+    /// Sets a variable once for every `format_messages` call
+    pub fn with_partial(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.partial_variables.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Variables `format_messages` requires, in order of appearance
+    pub fn input_variables(&self) -> Vec<String> {
+        let mut variables: Vec<String> = Vec::new();
+        for message in &self.messages {
+            let names = match message {
+                MessageTemplate::Message { template, .. } => template.input_variables.clone(),
+                MessageTemplate::Placeholder { name, optional: false } => vec![name.clone()],
+                _ => Vec::new(),
+            };
+            for name in names {
+                if !self.partial_variables.contains_key(&name) && !variables.contains(&name) {
+                    variables.push(name);
+                }
+            }
+        }
+        variables
+    }
+
+    /// Renders the messages with the variables of a JSON object
+    ///
+    /// # Errors
+    /// * `InvalidVariables` - `variables` is not an object
+    /// * `MissingVariable` - A template variable or a required placeholder has no value
+    /// * `JsonError` - A placeholder value is not a list of messages
+    pub fn format_messages(&self, variables: &Value) -> Result<Vec<ChatMessage>, PromptError> {
+        let values = Value::Object(merge_variables(&self.partial_variables, variables)?);
+
+        let mut messages = Vec::new();
+        for message in &self.messages {
+            match message {
+                MessageTemplate::Message { role, template } => {
+                    messages.push(ChatMessage::new(*role, &template.format(&values)?));
+                }
+                MessageTemplate::Placeholder { name, optional } => match values.get(name) {
+                    Some(Value::Null) | None if *optional => {}
+                    Some(Value::Null) | None => return Err(PromptError::MissingVariable(name.clone())),
+                    Some(value) => {
+                        let history: Vec<ChatMessage> = serde_json::from_value(value.clone())?;
+                        messages.extend(history);
+                    }
+                },
+                MessageTemplate::FewShot { examples, example_prompt } => {
+                    for example in examples {
+                        messages.extend(example_prompt.format_messages(example)?);
+                    }
+                }
+            }
+        }
+        Ok(messages)
+    }
+}
+
+/// Class name at the end of a LangChain serialized id
+fn manifest_name(id: &[String]) -> String {
+    id.last().cloned().unwrap_or_default()
+}
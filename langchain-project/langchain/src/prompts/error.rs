@@ -0,0 +1,26 @@
+use crate::langsmith::error::LangsmithError;
+
+#[allow(dead_code)]
+#[derive(Debug, thiserror::Error)]
+pub enum PromptError {
+    #[error("Missing value for the prompt variable {0}")]
+    MissingVariable(String),
+
+    #[error("Prompt variables must be a JSON object, got {0}")]
+    InvalidVariables(String),
+
+    #[error("Invalid template: {0}")]
+    InvalidTemplate(String),
+
+    #[error("Unsupported template format {0}")]
+    UnsupportedFormat(String),
+
+    #[error("Unsupported prompt manifest: {0}")]
+    UnsupportedManifest(String),
+
+    #[error("Error in converting to json {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Langsmith error: {0}")]
+    LangsmithError(#[from] LangsmithError),
+}
@@ -0,0 +1,446 @@
+use crate::langsmith::libs::PromptKwargs;
+use crate::prompts::error::PromptError;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Syntax of the variables in a template
+///
+/// * `FString` - `{name}`, with `{{` and `}}` for literal braces
+/// * `Mustache` - `{{name}}`, `{{user.name}}`, sections `{{#items}}...{{/items}}`
+///   and inverted sections `{{^items}}...{{/items}}`. Values are not HTML escaped.
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TemplateFormat {
+    #[default]
+    FString,
+    Mustache,
+}
+
+#[allow(dead_code)]
+impl TemplateFormat {
+    /// Format from its LangChain name, `f-string` or `mustache`
+    pub fn from_name(name: &str) -> Result<Self, PromptError> {
+        match name {
+            "" | "f-string" => Ok(Self::FString),
+            "mustache" => Ok(Self::Mustache),
+            other => Err(PromptError::UnsupportedFormat(other.to_string())),
+        }
+    }
+}
+
+/// Parsed piece of a template
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Variable(String),
+    Section {
+        name: String,
+        inverted: bool,
+        children: Vec<Segment>,
+    },
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ PromptTemplate ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// A text prompt with variables, parsed once and rendered with `format`
+///
+/// # Fields
+/// * `template` - Source text of the template
+/// * `template_format` - Variable syntax
+/// * `input_variables` - Variables `format` requires, without the partial ones
+/// * `partial_variables` - Values set in advance with `with_partial`
+///
+/// # Example
+/// ```ignore
+/// let prompt = PromptTemplate::new("Translate to {language}: {text}")?
+///     .with_partial("language", "French");
+/// let text = prompt.format(&json!({"text": "Good morning"}))?;
+/// ```
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    pub template: String,
+    pub template_format: TemplateFormat,
+    pub input_variables: Vec<String>,
+    pub partial_variables: Map<String, Value>,
+    segments: Vec<Segment>,
+}
+
+#[allow(dead_code)]
+impl PromptTemplate {
+    /// Parses an f-string template
+    pub fn new(template: &str) -> Result<Self, PromptError> {
+        Self::parse(template, TemplateFormat::FString)
+    }
+
+    /// Parses a mustache template
+    pub fn mustache(template: &str) -> Result<Self, PromptError> {
+        Self::parse(template, TemplateFormat::Mustache)
+    }
+
+    /// Parses a template and collects its input variables
+    ///
+    /// # Errors
+    /// * `InvalidTemplate` - Unbalanced braces, an invalid variable name or
+    ///   an unclosed mustache section
+    pub fn parse(template: &str, template_format: TemplateFormat) -> Result<Self, PromptError> {
+        let segments = match template_format {
+            TemplateFormat::FString => parse_fstring(template)?,
+            TemplateFormat::Mustache => parse_mustache(template)?,
+        };
+
+        Ok(Self {
+            template: template.to_string(),
+            template_format,
+            input_variables: root_variables(&segments),
+            partial_variables: Map::new(),
+            segments,
+        })
+    }
+
+    /// Template of a prompt pulled from the LangSmith hub
+    pub fn from_kwargs(kwargs: &PromptKwargs) -> Result<Self, PromptError> {
+        let template_format = TemplateFormat::from_name(&kwargs.template_format)?;
+        Self::parse(&kwargs.template, template_format)
+    }
+
+    /// Sets a variable once for every `format` call
+    pub fn with_partial(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.input_variables.retain(|variable| variable != name);
+        self.partial_variables.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// Renders the template with the variables of a JSON object
+    ///
+    /// Strings are inserted as is and other values as JSON; a `null` value
+    /// renders as an empty string. Values given here replace partial ones.
+    ///
+    /// # Errors
+    /// * `InvalidVariables` - `variables` is not an object
+    /// * `MissingVariable` - An input variable has no value
+    pub fn format(&self, variables: &Value) -> Result<String, PromptError> {
+        let values = merge_variables(&self.partial_variables, variables)?;
+        if let Some(missing) = self.input_variables.iter().find(|name| !values.contains_key(*name)) {
+            return Err(PromptError::MissingVariable(missing.clone()));
+        }
+
+        let root = Value::Object(values);
+        let mut output = String::new();
+        render(&self.segments, &mut vec![&root], &mut output);
+        Ok(output)
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ FewShotPromptTemplate ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// A prompt made of an optional prefix, one block per example and a suffix
+///
+/// Each example is a JSON object rendered with `example_prompt`; the prefix
+/// and suffix are rendered with the variables given to `format`.
+///
+/// # Fields
+/// * `examples` - Variables of each example
+/// * `example_prompt` - Template of one example
+/// * `prefix` - Instructions before the examples
+/// * `suffix` - Text after the examples, usually with the user input
+/// * `example_separator` - Text between the blocks (default: a blank line)
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct FewShotPromptTemplate {
+    pub examples: Vec<Value>,
+    pub example_prompt: PromptTemplate,
+    pub prefix: Option<PromptTemplate>,
+    pub suffix: PromptTemplate,
+    pub example_separator: String,
+}
+
+#[allow(dead_code)]
+impl FewShotPromptTemplate {
+    pub fn new(examples: Vec<Value>, example_prompt: PromptTemplate, suffix: PromptTemplate) -> Self {
+        Self {
+            examples,
+            example_prompt,
+            prefix: None,
+            suffix,
+            example_separator: "\n\n".to_string(),
+        }
+    }
+
+    pub fn with_prefix(mut self, prefix: PromptTemplate) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    pub fn with_example_separator(mut self, example_separator: &str) -> Self {
+        self.example_separator = example_separator.to_string();
+        self
+    }
+
+    /// Variables of the prefix and suffix
+    pub fn input_variables(&self) -> Vec<String> {
+        let mut variables = Vec::new();
+        for prompt in self.prefix.iter().chain([&self.suffix]) {
+            for variable in &prompt.input_variables {
+                if !variables.contains(variable) {
+                    variables.push(variable.clone());
+                }
+            }
+        }
+        variables
+    }
+
+    /// # Errors
+    /// * `MissingVariable` - An example or the input lacks a variable
+    pub fn format(&self, variables: &Value) -> Result<String, PromptError> {
+        let mut blocks = Vec::with_capacity(self.examples.len() + 2);
+        if let Some(prefix) = &self.prefix {
+            blocks.push(prefix.format(variables)?);
+        }
+        for example in &self.examples {
+            blocks.push(self.example_prompt.format(example)?);
+        }
+        blocks.push(self.suffix.format(variables)?);
+
+        Ok(blocks.join(&self.example_separator))
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Helpers ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Partial values overridden by the variables of a JSON object
+pub fn merge_variables(
+    partials: &Map<String, Value>,
+    variables: &Value,
+) -> Result<Map<String, Value>, PromptError> {
+    let mut values = partials.clone();
+    match variables {
+        Value::Object(variables) => values.extend(variables.clone()),
+        Value::Null => {}
+        other => return Err(PromptError::InvalidVariables(other.to_string())),
+    }
+    Ok(values)
+}
+
+fn parse_fstring(template: &str) -> Result<Vec<Segment>, PromptError> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(PromptError::InvalidTemplate(format!("unclosed {{{}", name))),
+                    }
+                }
+                let name = name.trim();
+                if !is_identifier(name) {
+                    return Err(PromptError::InvalidTemplate(format!("invalid variable {{{}}}", name)));
+                }
+                push_text(&mut segments, &mut text);
+                segments.push(Segment::Variable(name.to_string()));
+            }
+            '}' => return Err(PromptError::InvalidTemplate("single } outside a variable, use }}".to_string())),
+            c => text.push(c),
+        }
+    }
+
+    push_text(&mut segments, &mut text);
+    Ok(segments)
+}
+
+fn parse_mustache(template: &str) -> Result<Vec<Segment>, PromptError> {
+    let mut segments: Vec<Segment> = Vec::new();
+    // Open sections as (name, inverted, segments before the section)
+    let mut open: Vec<(String, bool, Vec<Segment>)> = Vec::new();
+    let mut text_start = 0;
+
+    while let Some(offset) = template[text_start..].find("{{") {
+        let tag_start = text_start + offset;
+        let (tag, tag_end) = if template[tag_start..].starts_with("{{{") {
+            let close = find_from(template, "}}}", tag_start + 3)?;
+            (format!("&{}", &template[tag_start + 3..close]), close + 3)
+        } else {
+            let close = find_from(template, "}}", tag_start + 2)?;
+            (template[tag_start + 2..close].trim().to_string(), close + 2)
+        };
+
+        let (kind, name) = match tag.chars().next() {
+            Some(kind @ ('#' | '^' | '/' | '!' | '&' | '>' | '=')) => (kind, tag[1..].trim().to_string()),
+            _ => (' ', tag.clone()),
+        };
+
+        // A section or comment tag alone on its line removes the whole line
+        let (text_end, next) = match kind {
+            '#' | '^' | '/' | '!' => standalone_line(template, tag_start, tag_end)
+                .filter(|(line_start, _)| *line_start >= text_start)
+                .unwrap_or((tag_start, tag_end)),
+            _ => (tag_start, tag_end),
+        };
+        let mut text = template[text_start..text_end].to_string();
+        push_text(&mut segments, &mut text);
+        text_start = next;
+
+        match kind {
+            '#' | '^' => open.push((name, kind == '^', std::mem::take(&mut segments))),
+            '/' => {
+                let Some((open_name, inverted, parent)) = open.pop() else {
+                    return Err(PromptError::InvalidTemplate(format!("{{{{/{}}}}} closes no section", name)));
+                };
+                if open_name != name {
+                    return Err(PromptError::InvalidTemplate(format!(
+                        "{{{{/{}}}}} closes the section {}",
+                        name, open_name
+                    )));
+                }
+                let children = std::mem::replace(&mut segments, parent);
+                segments.push(Segment::Section { name, inverted, children });
+            }
+            '!' => {}
+            '>' | '=' => {
+                return Err(PromptError::InvalidTemplate(format!("unsupported mustache tag {{{{{}}}}}", tag)));
+            }
+            _ => {
+                if name.is_empty() {
+                    return Err(PromptError::InvalidTemplate("empty mustache tag".to_string()));
+                }
+                segments.push(Segment::Variable(name));
+            }
+        }
+    }
+
+    if let Some((name, _, _)) = open.last() {
+        return Err(PromptError::InvalidTemplate(format!("unclosed section {}", name)));
+    }
+
+    let mut text = template[text_start..].to_string();
+    push_text(&mut segments, &mut text);
+    Ok(segments)
+}
+
+fn find_from(template: &str, pattern: &str, from: usize) -> Result<usize, PromptError> {
+    template[from..]
+        .find(pattern)
+        .map(|offset| from + offset)
+        .ok_or_else(|| PromptError::InvalidTemplate(format!("unclosed tag at byte {}", from)))
+}
+
+/// Start of the line and start of the next one when the tag is the only
+/// thing on its line besides whitespace
+fn standalone_line(template: &str, tag_start: usize, tag_end: usize) -> Option<(usize, usize)> {
+    let line_start = template[..tag_start].rfind('\n').map(|index| index + 1).unwrap_or(0);
+    if !template[line_start..tag_start].trim().is_empty() {
+        return None;
+    }
+
+    let line_end = template[tag_end..].find('\n').map(|index| tag_end + index + 1);
+    let after = &template[tag_end..line_end.unwrap_or(template.len())];
+    if !after.trim().is_empty() {
+        return None;
+    }
+    Some((line_start, line_end.unwrap_or(template.len())))
+}
+
+fn push_text(segments: &mut Vec<Segment>, text: &mut String) {
+    if !text.is_empty() {
+        segments.push(Segment::Text(std::mem::take(text)));
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Top level names used by the template, in order of appearance; names
+/// inside sections may refer to the section item and are not included
+fn root_variables(segments: &[Segment]) -> Vec<String> {
+    let mut variables: Vec<String> = Vec::new();
+    for segment in segments {
+        let path = match segment {
+            Segment::Variable(path) | Segment::Section { name: path, .. } => path,
+            Segment::Text(_) => continue,
+        };
+        let root = path.split('.').next().unwrap_or_default();
+        if !root.is_empty() && !variables.iter().any(|variable| variable == root) {
+            variables.push(root.to_string());
+        }
+    }
+    variables
+}
+
+fn render(segments: &[Segment], context: &mut Vec<&Value>, output: &mut String) {
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => output.push_str(text),
+            Segment::Variable(path) => match lookup(context, path) {
+                Some(Value::String(text)) => output.push_str(text),
+                Some(Value::Null) | None => {}
+                Some(value) => output.push_str(&value.to_string()),
+            },
+            Segment::Section { name, inverted, children } => {
+                let value = lookup(context, name);
+                if *inverted {
+                    if !value.is_some_and(is_truthy) {
+                        render(children, context, output);
+                    }
+                    continue;
+                }
+                match value {
+                    Some(Value::Array(items)) => {
+                        for item in items {
+                            context.push(item);
+                            render(children, context, output);
+                            context.pop();
+                        }
+                    }
+                    Some(value) if is_truthy(value) => {
+                        context.push(value);
+                        render(children, context, output);
+                        context.pop();
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Value of a dotted path, searching the innermost section first; `.` is
+/// the current section item
+fn lookup<'a>(context: &[&'a Value], path: &str) -> Option<&'a Value> {
+    if path == "." {
+        return context.last().copied();
+    }
+
+    let mut keys = path.split('.');
+    let first = keys.next()?;
+    let mut value = context.iter().rev().find_map(|scope| scope.get(first))?;
+    for key in keys {
+        value = value.get(key)?;
+    }
+    Some(value)
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => false,
+        Value::Array(items) => !items.is_empty(),
+        Value::String(text) => !text.is_empty(),
+        _ => true,
+    }
+}
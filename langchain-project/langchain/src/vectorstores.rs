@@ -0,0 +1,5 @@
+pub mod error;
+pub mod libs;
+pub mod memory;
+#[cfg(feature = "mongodb")]
+pub mod mongo;
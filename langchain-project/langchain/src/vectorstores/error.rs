@@ -0,0 +1,31 @@
+use crate::llm::error::LlmError;
+
+#[allow(dead_code)]
+#[derive(Debug, thiserror::Error)]
+pub enum VectorStoreError {
+    #[error("Embedding error: {0}")]
+    EmbeddingError(#[from] LlmError),
+
+    #[error("Expected {expected} embeddings, got {found}")]
+    EmbeddingCount { expected: usize, found: usize },
+
+    #[error("Expected vectors of {expected} dimensions, got {found}")]
+    DimensionMismatch { expected: usize, found: usize },
+
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Error in converting to json {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Index {0} is not ready")]
+    IndexNotReady(String),
+
+    #[cfg(feature = "mongodb")]
+    #[error("MongoDB error: {0}")]
+    MongoError(#[from] mongodb::error::Error),
+
+    #[cfg(feature = "mongodb")]
+    #[error("BSON error: {0}")]
+    BsonError(String),
+}
@@ -0,0 +1,255 @@
+use crate::documents::libs::Document;
+use crate::llm::embed::cosine_similarity;
+use crate::vectorstores::error::VectorStoreError;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+
+/// A search result with its similarity to the query, higher is closer
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScoredDocument {
+    pub document: Document,
+    pub score: f32,
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Filter ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Condition on the metadata of a document
+///
+/// Comparisons (`Gt`, `Gte`, `Lt`, `Lte`) apply to two numbers or two
+/// strings, e.g. ISO dates; any other pair does not match. A missing key
+/// only matches `Ne` and `Not`.
+///
+/// # Example
+/// ```ignore
+/// let filter = Filter::and(vec![
+///     Filter::eq("source", "handbook.pdf"),
+///     Filter::gte("page", 10),
+/// ]);
+/// ```
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Eq(String, Value),
+    Ne(String, Value),
+    In(String, Vec<Value>),
+    Gt(String, Value),
+    Gte(String, Value),
+    Lt(String, Value),
+    Lte(String, Value),
+    Exists(String),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+}
+
+#[allow(dead_code)]
+impl Filter {
+    pub fn eq(key: &str, value: impl Into<Value>) -> Self {
+        Self::Eq(key.to_string(), value.into())
+    }
+
+    pub fn ne(key: &str, value: impl Into<Value>) -> Self {
+        Self::Ne(key.to_string(), value.into())
+    }
+
+    pub fn is_in(key: &str, values: Vec<Value>) -> Self {
+        Self::In(key.to_string(), values)
+    }
+
+    pub fn gt(key: &str, value: impl Into<Value>) -> Self {
+        Self::Gt(key.to_string(), value.into())
+    }
+
+    pub fn gte(key: &str, value: impl Into<Value>) -> Self {
+        Self::Gte(key.to_string(), value.into())
+    }
+
+    pub fn lt(key: &str, value: impl Into<Value>) -> Self {
+        Self::Lt(key.to_string(), value.into())
+    }
+
+    pub fn lte(key: &str, value: impl Into<Value>) -> Self {
+        Self::Lte(key.to_string(), value.into())
+    }
+
+    pub fn exists(key: &str) -> Self {
+        Self::Exists(key.to_string())
+    }
+
+    pub fn and(filters: Vec<Filter>) -> Self {
+        Self::And(filters)
+    }
+
+    pub fn or(filters: Vec<Filter>) -> Self {
+        Self::Or(filters)
+    }
+
+    pub fn negate(filter: Filter) -> Self {
+        Self::Not(Box::new(filter))
+    }
+
+    /// Whether the metadata satisfies the condition
+    pub fn matches(&self, metadata: &Map<String, Value>) -> bool {
+        match self {
+            Self::Eq(key, value) => metadata.get(key) == Some(value),
+            Self::Ne(key, value) => metadata.get(key) != Some(value),
+            Self::In(key, values) => metadata.get(key).is_some_and(|found| values.contains(found)),
+            Self::Gt(key, value) => compare(metadata.get(key), value) == Some(Ordering::Greater),
+            Self::Gte(key, value) => matches!(
+                compare(metadata.get(key), value),
+                Some(Ordering::Greater | Ordering::Equal)
+            ),
+            Self::Lt(key, value) => compare(metadata.get(key), value) == Some(Ordering::Less),
+            Self::Lte(key, value) => matches!(
+                compare(metadata.get(key), value),
+                Some(Ordering::Less | Ordering::Equal)
+            ),
+            Self::Exists(key) => metadata.contains_key(key),
+            Self::And(filters) => filters.iter().all(|filter| filter.matches(metadata)),
+            Self::Or(filters) => filters.iter().any(|filter| filter.matches(metadata)),
+            Self::Not(filter) => !filter.matches(metadata),
+        }
+    }
+}
+
+fn compare(found: Option<&Value>, value: &Value) -> Option<Ordering> {
+    match (found?, value) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ SearchOptions ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Settings of a similarity or MMR search
+///
+/// # Fields
+/// * `k` - Documents returned (default: 4)
+/// * `filter` - Only documents whose metadata matches are considered
+/// * `fetch_k` - Candidates ranked by MMR or a reranker (default: 20)
+/// * `lambda_mult` - MMR trade-off between relevance (1.0) and diversity (0.0), default: 0.5
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchOptions {
+    pub k: usize,
+    pub filter: Option<Filter>,
+    pub fetch_k: usize,
+    pub lambda_mult: f32,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            k: 4,
+            filter: None,
+            fetch_k: 20,
+            lambda_mult: 0.5,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl SearchOptions {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn with_fetch_k(mut self, fetch_k: usize) -> Self {
+        self.fetch_k = fetch_k;
+        self
+    }
+
+    pub fn with_lambda_mult(mut self, lambda_mult: f32) -> Self {
+        self.lambda_mult = lambda_mult.clamp(0.0, 1.0);
+        self
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ VectorStore ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Common interface of the vector stores
+///
+/// A store embeds the documents with the `Embeddings` client it was created
+/// with, so the queries must be embedded by the same model.
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Embeds and stores the documents, replacing those with the same id,
+    /// and returns their ids in input order
+    async fn add_documents(&self, documents: Vec<Document>) -> Result<Vec<String>, VectorStoreError>;
+
+    /// Removes the documents with these ids; unknown ids are ignored
+    async fn delete(&self, ids: &[String]) -> Result<(), VectorStoreError>;
+
+    /// The `k` documents closest to the query, closest first
+    async fn similarity_search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<ScoredDocument>, VectorStoreError>;
+
+    /// `k` documents picked among the `fetch_k` closest to the query with
+    /// maximal marginal relevance, so that near duplicates are skipped
+    async fn max_marginal_relevance_search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<ScoredDocument>, VectorStoreError>;
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Helpers ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Indexes of `k` candidates picked with maximal marginal relevance, in the
+/// order picked
+///
+/// Each step takes the candidate maximizing
+/// `lambda_mult * sim(query, c) - (1 - lambda_mult) * max sim(c, picked)`.
+pub fn maximal_marginal_relevance(
+    query: &[f32],
+    candidates: &[Vec<f32>],
+    k: usize,
+    lambda_mult: f32,
+) -> Vec<usize> {
+    let relevance: Vec<f32> = candidates
+        .iter()
+        .map(|candidate| cosine_similarity(query, candidate))
+        .collect();
+
+    let mut picked: Vec<usize> = Vec::new();
+    // Highest similarity of each candidate to the picked ones
+    let mut redundancy = vec![f32::NEG_INFINITY; candidates.len()];
+
+    while picked.len() < k.min(candidates.len()) {
+        let mut best: Option<(usize, f32)> = None;
+        for (index, candidate_relevance) in relevance.iter().enumerate() {
+            if picked.contains(&index) {
+                continue;
+            }
+            let penalty = if picked.is_empty() { 0.0 } else { redundancy[index] };
+            let score = lambda_mult * candidate_relevance - (1.0 - lambda_mult) * penalty;
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((index, score));
+            }
+        }
+
+        let Some((index, _)) = best else { break };
+        picked.push(index);
+        for (other, other_redundancy) in redundancy.iter_mut().enumerate() {
+            let similarity = cosine_similarity(&candidates[index], &candidates[other]);
+            *other_redundancy = other_redundancy.max(similarity);
+        }
+    }
+
+    picked
+}
@@ -0,0 +1,290 @@
+use crate::documents::libs::{contents, Document};
+use crate::llm::embed::{cosine_similarity, Embeddings};
+use crate::vectorstores::error::VectorStoreError;
+use crate::vectorstores::libs::{
+    maximal_marginal_relevance, ScoredDocument, SearchOptions, VectorStore,
+};
+use async_trait::async_trait;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A document with its embedding, as kept in memory and in the store file
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredDocument {
+    pub document: Document,
+    pub embedding: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoreFile {
+    model: String,
+    documents: Vec<StoredDocument>,
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ InMemoryVectorStore ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// A vector store kept in memory and searched by brute force
+///
+/// Good for up to some tens of thousands of documents. `save` writes the
+/// documents and their embeddings to a JSON file and `load` reads them
+/// back, so a small RAG app embeds its corpus once and needs no database.
+///
+/// # Example
+/// ```ignore
+/// let embeddings = Arc::new(EmbedOpenAI::new("text-embedding-3-small"));
+/// let store = InMemoryVectorStore::new(embeddings);
+/// store.add_documents(vec![Document::new("Rust is fast").with_metadata("topic", "rust")]).await?;
+///
+/// let results = store.similarity_search("Which language is fast?", &SearchOptions::new(1)).await?;
+/// store.save("store.json").await?;
+/// ```
+pub struct InMemoryVectorStore {
+    embeddings: Arc<dyn Embeddings>,
+    documents: RwLock<Vec<StoredDocument>>,
+}
+
+#[allow(dead_code)]
+impl InMemoryVectorStore {
+    pub fn new(embeddings: Arc<dyn Embeddings>) -> Self {
+        Self {
+            embeddings,
+            documents: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Reads a store written by `save`
+    ///
+    /// The embeddings client must use the model the file was built with,
+    /// otherwise the queries are compared with unrelated vectors; a
+    /// different model name is logged as a warning.
+    ///
+    /// # Errors
+    /// * `IoError` - The file cannot be read
+    /// * `JsonError` - The file is not a saved store
+    pub async fn load(
+        path: impl AsRef<Path>,
+        embeddings: Arc<dyn Embeddings>,
+    ) -> Result<Self, VectorStoreError> {
+        let data = tokio::fs::read(path.as_ref()).await?;
+        let file: StoreFile = serde_json::from_slice(&data)?;
+
+        if file.model != embeddings.model_name() {
+            warn!(
+                "Vector store {} was built with {}, not {}",
+                path.as_ref().display(),
+                file.model,
+                embeddings.model_name()
+            );
+        }
+
+        Ok(Self {
+            embeddings,
+            documents: RwLock::new(file.documents),
+        })
+    }
+
+    /// Writes the documents and their embeddings to a JSON file
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<(), VectorStoreError> {
+        let data = {
+            let file = StoreFile {
+                model: self.embeddings.model_name(),
+                documents: self.read().clone(),
+            };
+            serde_json::to_vec(&file)?
+        };
+
+        tokio::fs::write(path, data).await?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Document> {
+        self.read()
+            .iter()
+            .find(|stored| stored.document.id.as_deref() == Some(id))
+            .map(|stored| stored.document.clone())
+    }
+
+    /// Adds documents with embeddings computed elsewhere, e.g. by a batch job
+    ///
+    /// # Errors
+    /// * `EmbeddingCount` - Not one embedding per document
+    /// * `DimensionMismatch` - The vectors differ in size from the stored ones
+    pub fn add_embedded_documents(
+        &self,
+        documents: Vec<Document>,
+        embeddings: Vec<Vec<f32>>,
+    ) -> Result<Vec<String>, VectorStoreError> {
+        if documents.len() != embeddings.len() {
+            return Err(VectorStoreError::EmbeddingCount {
+                expected: documents.len(),
+                found: embeddings.len(),
+            });
+        }
+
+        let mut stored = self.write();
+        let mut dimensions = stored.first().map(|stored| stored.embedding.len());
+        for embedding in &embeddings {
+            let expected = *dimensions.get_or_insert(embedding.len());
+            if embedding.len() != expected {
+                return Err(VectorStoreError::DimensionMismatch {
+                    expected,
+                    found: embedding.len(),
+                });
+            }
+        }
+
+        let prefix = id_prefix();
+        let mut ids = Vec::with_capacity(documents.len());
+        for (index, (mut document, embedding)) in documents.into_iter().zip(embeddings).enumerate() {
+            let id = document
+                .id
+                .clone()
+                .unwrap_or_else(|| format!("{}-{}", prefix, index));
+            document.id = Some(id.clone());
+
+            let entry = StoredDocument { document, embedding };
+            match stored.iter().position(|stored| stored.document.id.as_deref() == Some(&id)) {
+                Some(position) => stored[position] = entry,
+                None => stored.push(entry),
+            }
+            ids.push(id);
+        }
+
+        Ok(ids)
+    }
+
+    /// The `k` documents closest to an embedding, closest first
+    pub fn similarity_search_by_vector(
+        &self,
+        embedding: &[f32],
+        options: &SearchOptions,
+    ) -> Result<Vec<ScoredDocument>, VectorStoreError> {
+        let candidates = self.closest(embedding, options.k, options)?;
+        Ok(candidates
+            .into_iter()
+            .map(|(stored, score)| ScoredDocument { document: stored.document, score })
+            .collect())
+    }
+
+    /// Documents matching the filter, closest to the embedding first
+    fn closest(
+        &self,
+        embedding: &[f32],
+        k: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<(StoredDocument, f32)>, VectorStoreError> {
+        let stored = self.read();
+        if let Some(first) = stored.first() {
+            if first.embedding.len() != embedding.len() {
+                return Err(VectorStoreError::DimensionMismatch {
+                    expected: first.embedding.len(),
+                    found: embedding.len(),
+                });
+            }
+        }
+
+        let mut scored: Vec<(&StoredDocument, f32)> = stored
+            .iter()
+            .filter(|stored| match &options.filter {
+                Some(filter) => filter.matches(&stored.document.metadata),
+                None => true,
+            })
+            .map(|stored| (stored, cosine_similarity(embedding, &stored.embedding)))
+            .collect();
+
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(scored
+            .into_iter()
+            .take(k)
+            .map(|(stored, score)| (stored.clone(), score))
+            .collect())
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Vec<StoredDocument>> {
+        self.documents.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Vec<StoredDocument>> {
+        self.documents.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl fmt::Debug for InMemoryVectorStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InMemoryVectorStore")
+            .field("model", &self.embeddings.model_name())
+            .field("documents", &self.len())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl VectorStore for InMemoryVectorStore {
+    async fn add_documents(&self, documents: Vec<Document>) -> Result<Vec<String>, VectorStoreError> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let embeddings = self.embeddings.embed_documents(contents(&documents)).await?;
+        self.add_embedded_documents(documents, embeddings)
+    }
+
+    async fn delete(&self, ids: &[String]) -> Result<(), VectorStoreError> {
+        self.write().retain(|stored| match &stored.document.id {
+            Some(id) => !ids.contains(id),
+            None => true,
+        });
+        Ok(())
+    }
+
+    async fn similarity_search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<ScoredDocument>, VectorStoreError> {
+        let embedding = self.embeddings.embed_query(query).await?;
+        self.similarity_search_by_vector(&embedding, options)
+    }
+
+    async fn max_marginal_relevance_search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<ScoredDocument>, VectorStoreError> {
+        let embedding = self.embeddings.embed_query(query).await?;
+        let candidates = self.closest(&embedding, options.fetch_k.max(options.k), options)?;
+
+        let vectors: Vec<Vec<f32>> = candidates.iter().map(|(stored, _)| stored.embedding.clone()).collect();
+        let picked = maximal_marginal_relevance(&embedding, &vectors, options.k, options.lambda_mult);
+
+        Ok(picked
+            .into_iter()
+            .map(|index| {
+                let (stored, score) = &candidates[index];
+                ScoredDocument { document: stored.document.clone(), score: *score }
+            })
+            .collect())
+    }
+}
+
+/// Prefix of the ids generated in one `add_documents` call
+fn id_prefix() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    format!("{:x}", nanos)
+}
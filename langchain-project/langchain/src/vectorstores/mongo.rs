@@ -0,0 +1,481 @@
+use crate::documents::libs::{contents, Document};
+use crate::llm::embed::Embeddings;
+use crate::vectorstores::error::VectorStoreError;
+use crate::vectorstores::libs::{
+    maximal_marginal_relevance, Filter, ScoredDocument, SearchOptions, VectorStore,
+};
+use async_trait::async_trait;
+use futures::{stream, StreamExt, TryStreamExt};
+use log::info;
+use mongodb::bson::{doc, Bson, Document as BsonDocument};
+use mongodb::{Client, Collection, SearchIndexModel, SearchIndexType};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// Field of the stored documents holding the metadata; filters apply to its keys
+pub static MONGO_METADATA_KEY: &str = "metadata";
+
+/// Candidates accepted by `$vectorSearch` in `numCandidates`
+const MONGO_MAX_CANDIDATES: usize = 10_000;
+
+/// Replacements in flight at once when upserting documents
+pub const MONGO_UPSERT_CONCURRENCY: usize = 16;
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ MongoCollection ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Operations on the collection used by `MongoVectorStore`
+///
+/// Implemented by `mongodb::Collection<bson::Document>`. Another
+/// implementation can stand in for the server, e.g. to run `$vectorSearch`
+/// locally in tests, since only Atlas runs vector search pipelines.
+#[async_trait]
+pub trait MongoCollection: Send + Sync {
+    async fn list_search_indexes(&self) -> Result<Vec<BsonDocument>, VectorStoreError>;
+
+    /// Creates the index and returns its name
+    async fn create_search_index(&self, model: SearchIndexModel) -> Result<String, VectorStoreError>;
+
+    /// Replaces each document with the same `_id`, inserting it when missing
+    async fn upsert_many(&self, documents: Vec<BsonDocument>) -> Result<(), VectorStoreError>;
+
+    async fn delete_many(&self, ids: &[String]) -> Result<(), VectorStoreError>;
+
+    async fn aggregate(&self, pipeline: Vec<BsonDocument>) -> Result<Vec<BsonDocument>, VectorStoreError>;
+}
+
+#[async_trait]
+impl MongoCollection for Collection<BsonDocument> {
+    async fn list_search_indexes(&self) -> Result<Vec<BsonDocument>, VectorStoreError> {
+        let cursor = Collection::list_search_indexes(self).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn create_search_index(&self, model: SearchIndexModel) -> Result<String, VectorStoreError> {
+        Ok(Collection::create_search_index(self, model).await?)
+    }
+
+    /// Runs up to `MONGO_UPSERT_CONCURRENCY` replacements at once; the
+    /// `bulkWrite` command that would batch them needs MongoDB 8.0
+    async fn upsert_many(&self, documents: Vec<BsonDocument>) -> Result<(), VectorStoreError> {
+        stream::iter(documents)
+            .map(|document| async move {
+                let id = document.get("_id").cloned().unwrap_or(Bson::Null);
+                self.replace_one(doc! { "_id": id }, document).upsert(true).await
+            })
+            .buffer_unordered(MONGO_UPSERT_CONCURRENCY)
+            .try_for_each(|_| async { Ok(()) })
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_many(&self, ids: &[String]) -> Result<(), VectorStoreError> {
+        Collection::delete_many(self, doc! { "_id": { "$in": ids } }).await?;
+        Ok(())
+    }
+
+    async fn aggregate(&self, pipeline: Vec<BsonDocument>) -> Result<Vec<BsonDocument>, VectorStoreError> {
+        let cursor = Collection::aggregate(self, pipeline).await?;
+        Ok(cursor.try_collect().await?)
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ MongoVectorStore ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Similarity function of the vector search index
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorSimilarity {
+    Cosine,
+    DotProduct,
+    Euclidean,
+}
+
+impl VectorSimilarity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cosine => "cosine",
+            Self::DotProduct => "dotProduct",
+            Self::Euclidean => "euclidean",
+        }
+    }
+}
+
+/// A vector store on a MongoDB Atlas collection, searched with `$vectorSearch`
+///
+/// Documents are stored as `{_id, text, embedding, metadata}`; the text and
+/// embedding field names can be changed to match an existing collection.
+/// Metadata keys used in filters must be declared with `with_filter_fields`
+/// before `create_index`. Scores are the `vectorSearchScore` of Atlas, e.g.
+/// `(1 + cosine) / 2` for the cosine similarity.
+///
+/// # Example
+/// ```ignore
+/// let embeddings = Arc::new(EmbedOpenAI::new("text-embedding-3-small"));
+/// let store = MongoVectorStore::from_uri(&uri, "news", "articles", embeddings)
+///     .await?
+///     .with_filter_fields(&["category"]);
+///
+/// store.create_index(1536).await?;
+/// store.add_documents(documents).await?;
+///
+/// let options = SearchOptions::new(3).with_filter(Filter::eq("category", "SPORTS"));
+/// let results = store.similarity_search("World cup final", &options).await?;
+/// ```
+#[derive(Clone)]
+pub struct MongoVectorStore {
+    pub collection: Arc<dyn MongoCollection>,
+    pub embeddings: Arc<dyn Embeddings>,
+    pub index_name: String,
+    pub text_key: String,
+    pub embedding_key: String,
+    pub similarity: VectorSimilarity,
+    pub filter_fields: Vec<String>,
+    pub candidates_factor: usize,
+    pub poll_interval: Duration,
+    pub index_timeout: Duration,
+}
+
+#[allow(dead_code)]
+impl MongoVectorStore {
+    pub fn new(collection: Arc<dyn MongoCollection>, embeddings: Arc<dyn Embeddings>) -> Self {
+        Self {
+            collection,
+            embeddings,
+            index_name: "vector_index".to_string(),
+            text_key: "text".to_string(),
+            embedding_key: "embedding".to_string(),
+            similarity: VectorSimilarity::Cosine,
+            filter_fields: Vec::new(),
+            candidates_factor: 10,
+            poll_interval: Duration::from_secs(5),
+            index_timeout: Duration::from_secs(300), // default: 5 minutes
+        }
+    }
+
+    /// Connects to the cluster and uses a collection of `database`
+    pub async fn from_uri(
+        uri: &str,
+        database: &str,
+        collection: &str,
+        embeddings: Arc<dyn Embeddings>,
+    ) -> Result<Self, VectorStoreError> {
+        let client = Client::with_uri_str(uri).await?;
+        let collection: Collection<BsonDocument> = client.database(database).collection(collection);
+        Ok(Self::new(Arc::new(collection), embeddings))
+    }
+
+    /// Creates the vector search index unless it exists, then waits until
+    /// it is queryable
+    ///
+    /// # Errors
+    /// * `MongoError` - The index cannot be listed or created
+    /// * `IndexNotReady` - The index is not queryable after `index_timeout`
+    pub async fn create_index(&self, dimensions: usize) -> Result<(), VectorStoreError> {
+        let indexes = self.collection.list_search_indexes().await?;
+        let exists = indexes
+            .iter()
+            .any(|index| index.get_str("name").ok() == Some(self.index_name.as_str()));
+
+        if exists {
+            info!("Vector search index {} exists", self.index_name);
+        } else {
+            let model = SearchIndexModel::builder()
+                .definition(self.index_definition(dimensions))
+                .name(self.index_name.clone())
+                .index_type(SearchIndexType::VectorSearch)
+                .build();
+            let name = self.collection.create_search_index(model).await?;
+            info!("Vector search index {} is building", name);
+        }
+
+        self.wait_index_ready().await
+    }
+
+    /// Definition of the vector search index: the embedding field and the
+    /// metadata filter fields
+    pub fn index_definition(&self, dimensions: usize) -> BsonDocument {
+        let mut fields = vec![doc! {
+            "type": "vector",
+            "path": &self.embedding_key,
+            "numDimensions": dimensions as i64,
+            "similarity": self.similarity.as_str(),
+        }];
+        for field in &self.filter_fields {
+            fields.push(doc! {
+                "type": "filter",
+                "path": format!("{}.{}", MONGO_METADATA_KEY, field),
+            });
+        }
+
+        doc! { "fields": fields }
+    }
+
+    /// Polls the search indexes every `poll_interval` until the index is queryable
+    pub async fn wait_index_ready(&self) -> Result<(), VectorStoreError> {
+        let deadline = Instant::now() + self.index_timeout;
+
+        loop {
+            let indexes = self.collection.list_search_indexes().await?;
+            let queryable = indexes.iter().any(|index| {
+                index.get_str("name").ok() == Some(self.index_name.as_str())
+                    && index.get_bool("queryable").unwrap_or(false)
+            });
+            if queryable {
+                return Ok(());
+            }
+
+            if Instant::now() >= deadline {
+                return Err(VectorStoreError::IndexNotReady(self.index_name.clone()));
+            }
+            sleep(self.poll_interval).await;
+        }
+    }
+
+    /// The `k` documents closest to an embedding, closest first
+    pub async fn similarity_search_by_vector(
+        &self,
+        embedding: &[f32],
+        options: &SearchOptions,
+    ) -> Result<Vec<ScoredDocument>, VectorStoreError> {
+        let results = self.vector_search(embedding, options.k, options, false).await?;
+        Ok(results.into_iter().map(|(document, _)| document).collect())
+    }
+
+    /// `$vectorSearch` pipeline returning the text, metadata and score, plus
+    /// the embedding when `with_embedding` is set
+    pub fn search_pipeline(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+        options: &SearchOptions,
+        with_embedding: bool,
+    ) -> Result<Vec<BsonDocument>, VectorStoreError> {
+        let num_candidates = (limit * self.candidates_factor).clamp(limit, MONGO_MAX_CANDIDATES);
+
+        let mut vector_search = doc! {
+            "index": &self.index_name,
+            "path": &self.embedding_key,
+            "queryVector": embedding.to_vec(),
+            "numCandidates": num_candidates as i64,
+            "limit": limit as i64,
+        };
+        if let Some(filter) = &options.filter {
+            vector_search.insert("filter", filter_to_mql(filter)?);
+        }
+
+        let mut project = doc! {
+            &self.text_key: 1,
+            MONGO_METADATA_KEY: 1,
+            "score": { "$meta": "vectorSearchScore" },
+        };
+        if with_embedding {
+            project.insert(&self.embedding_key, 1);
+        }
+
+        Ok(vec![
+            doc! { "$vectorSearch": vector_search },
+            doc! { "$project": project },
+        ])
+    }
+
+    async fn vector_search(
+        &self,
+        embedding: &[f32],
+        limit: usize,
+        options: &SearchOptions,
+        with_embedding: bool,
+    ) -> Result<Vec<(ScoredDocument, Vec<f32>)>, VectorStoreError> {
+        let pipeline = self.search_pipeline(embedding, limit, options, with_embedding)?;
+        let results = self.collection.aggregate(pipeline).await?;
+        Ok(results.iter().map(|result| self.scored_document(result)).collect())
+    }
+
+    fn scored_document(&self, result: &BsonDocument) -> (ScoredDocument, Vec<f32>) {
+        let id = match result.get("_id") {
+            Some(Bson::String(id)) => Some(id.clone()),
+            Some(Bson::ObjectId(id)) => Some(id.to_hex()),
+            Some(other) => Some(other.to_string()),
+            None => None,
+        };
+
+        let metadata = match result.get(MONGO_METADATA_KEY) {
+            Some(metadata) => match metadata.clone().into_relaxed_extjson() {
+                Value::Object(metadata) => metadata,
+                _ => Map::new(),
+            },
+            None => Map::new(),
+        };
+
+        let embedding = match result.get_array(&self.embedding_key) {
+            Ok(values) => values.iter().filter_map(Bson::as_f64).map(|value| value as f32).collect(),
+            Err(_) => Vec::new(),
+        };
+
+        let document = Document {
+            id,
+            content: result.get_str(&self.text_key).unwrap_or_default().to_string(),
+            metadata,
+        };
+        let score = result.get_f64("score").unwrap_or_default() as f32;
+
+        (ScoredDocument { document, score }, embedding)
+    }
+
+    pub fn with_index_name(mut self, index_name: &str) -> Self {
+        self.index_name = index_name.to_string();
+        self
+    }
+
+    pub fn with_text_key(mut self, text_key: &str) -> Self {
+        self.text_key = text_key.to_string();
+        self
+    }
+
+    pub fn with_embedding_key(mut self, embedding_key: &str) -> Self {
+        self.embedding_key = embedding_key.to_string();
+        self
+    }
+
+    pub fn with_similarity(mut self, similarity: VectorSimilarity) -> Self {
+        self.similarity = similarity;
+        self
+    }
+
+    /// Metadata keys indexed as filter fields, required by `$vectorSearch` pre-filters
+    pub fn with_filter_fields(mut self, filter_fields: &[&str]) -> Self {
+        self.filter_fields = filter_fields.iter().map(|field| field.to_string()).collect();
+        self
+    }
+
+    /// Candidates considered per result returned (default: 10)
+    pub fn with_candidates_factor(mut self, candidates_factor: usize) -> Self {
+        self.candidates_factor = candidates_factor.max(1);
+        self
+    }
+
+    /// How often and how long `create_index` waits for the index (default: 5 s and 5 min)
+    pub fn with_poll_interval(mut self, poll_interval: Duration, index_timeout: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self.index_timeout = index_timeout;
+        self
+    }
+}
+
+impl fmt::Debug for MongoVectorStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MongoVectorStore")
+            .field("model", &self.embeddings.model_name())
+            .field("index_name", &self.index_name)
+            .field("text_key", &self.text_key)
+            .field("embedding_key", &self.embedding_key)
+            .field("similarity", &self.similarity)
+            .field("filter_fields", &self.filter_fields)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl VectorStore for MongoVectorStore {
+    async fn add_documents(&self, documents: Vec<Document>) -> Result<Vec<String>, VectorStoreError> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let embeddings = self.embeddings.embed_documents(contents(&documents)).await?;
+        if embeddings.len() != documents.len() {
+            return Err(VectorStoreError::EmbeddingCount {
+                expected: documents.len(),
+                found: embeddings.len(),
+            });
+        }
+
+        let mut ids = Vec::with_capacity(documents.len());
+        let mut stored = Vec::with_capacity(documents.len());
+        for (document, embedding) in documents.into_iter().zip(embeddings) {
+            let id = match document.id {
+                Some(id) => id,
+                None => mongodb::bson::oid::ObjectId::new().to_hex(),
+            };
+            let metadata = mongodb::bson::to_document(&document.metadata)
+                .map_err(|e| VectorStoreError::BsonError(e.to_string()))?;
+
+            stored.push(doc! {
+                "_id": &id,
+                &self.text_key: document.content,
+                &self.embedding_key: embedding,
+                MONGO_METADATA_KEY: metadata,
+            });
+            ids.push(id);
+        }
+        self.collection.upsert_many(stored).await?;
+
+        Ok(ids)
+    }
+
+    async fn delete(&self, ids: &[String]) -> Result<(), VectorStoreError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        self.collection.delete_many(ids).await
+    }
+
+    async fn similarity_search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<ScoredDocument>, VectorStoreError> {
+        let embedding = self.embeddings.embed_query(query).await?;
+        self.similarity_search_by_vector(&embedding, options).await
+    }
+
+    async fn max_marginal_relevance_search(
+        &self,
+        query: &str,
+        options: &SearchOptions,
+    ) -> Result<Vec<ScoredDocument>, VectorStoreError> {
+        let embedding = self.embeddings.embed_query(query).await?;
+        let candidates = self
+            .vector_search(&embedding, options.fetch_k.max(options.k), options, true)
+            .await?;
+
+        let vectors: Vec<Vec<f32>> = candidates.iter().map(|(_, vector)| vector.clone()).collect();
+        let picked = maximal_marginal_relevance(&embedding, &vectors, options.k, options.lambda_mult);
+
+        Ok(picked.into_iter().map(|index| candidates[index].0.clone()).collect())
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Filters ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Translates a metadata filter to the MQL of a `$vectorSearch` pre-filter
+pub fn filter_to_mql(filter: &Filter) -> Result<BsonDocument, VectorStoreError> {
+    let field = |key: &str| format!("{}.{}", MONGO_METADATA_KEY, key);
+    let operator = |key: &str, operator: &str, value: &Value| -> Result<BsonDocument, VectorStoreError> {
+        Ok(doc! { field(key): { operator: to_bson(value)? } })
+    };
+
+    match filter {
+        Filter::Eq(key, value) => operator(key, "$eq", value),
+        Filter::Ne(key, value) => operator(key, "$ne", value),
+        Filter::In(key, values) => operator(key, "$in", &Value::Array(values.clone())),
+        Filter::Gt(key, value) => operator(key, "$gt", value),
+        Filter::Gte(key, value) => operator(key, "$gte", value),
+        Filter::Lt(key, value) => operator(key, "$lt", value),
+        Filter::Lte(key, value) => operator(key, "$lte", value),
+        Filter::Exists(key) => Ok(doc! { field(key): { "$exists": true } }),
+        Filter::And(filters) => Ok(doc! { "$and": filters_to_mql(filters)? }),
+        Filter::Or(filters) => Ok(doc! { "$or": filters_to_mql(filters)? }),
+        Filter::Not(filter) => Ok(doc! { "$nor": [filter_to_mql(filter)?] }),
+    }
+}
+
+fn filters_to_mql(filters: &[Filter]) -> Result<Vec<BsonDocument>, VectorStoreError> {
+    filters.iter().map(filter_to_mql).collect()
+}
+
+fn to_bson(value: &Value) -> Result<Bson, VectorStoreError> {
+    mongodb::bson::to_bson(value).map_err(|e| VectorStoreError::BsonError(e.to_string()))
+}
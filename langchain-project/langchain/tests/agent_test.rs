@@ -0,0 +1,623 @@
+mod common;
+
+use common::{Fixture, MockServer};
+use langchain::agents::error::AgentError;
+use langchain::agents::financials_agents::create_research_agent;
+use langchain::agents::libs::{
+    extract_json, Agent, AgentType, Thinking, ToolToFinalOutputResult, ToolUseBehavior,
+};
+use langchain::agents::tools::FunctionTool;
+use langchain::llm::libs::ChatMessage;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+static OPENAI_MODEL: &str = "gpt-4o-mini";
+static OPENAI_UPSTREAM: &str = "https://api.openai.com";
+static COMPLETIONS_PATH: &str = "/v1/chat/completions";
+
+static ANTHROPIC_MODEL: &str = "claude-3-5-sonnet-20241022";
+static ANTHROPIC_UPSTREAM: &str = "https://api.anthropic.com";
+static MESSAGES_PATH: &str = "/v1/messages";
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct WeatherArgs {
+    /// The city and state, e.g. San Francisco, CA
+    location: String,
+    /// Temperature unit, celsius or fahrenheit
+    unit: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct StockArgs {
+    /// The stock ticker symbol, e.g. AAPL for Apple Inc.
+    ticker: String,
+}
+
+fn weather_tool(calls: Arc<Mutex<Vec<String>>>) -> FunctionTool {
+    FunctionTool::new(
+        "get_current_weather",
+        "Get the current weather in a given location",
+        move |args: WeatherArgs| {
+            let calls = calls.clone();
+            async move {
+                calls.lock().unwrap().push(args.location.clone());
+                Ok::<_, String>(json!({
+                    "temperature": 22,
+                    "unit": args.unit.unwrap_or("celsius".to_string()),
+                    "description": "Sunny",
+                }))
+            }
+        },
+    )
+}
+
+fn stock_tool() -> FunctionTool {
+    FunctionTool::new(
+        "get_stock_price",
+        "Retrieves the current stock price for a given ticker symbol.",
+        |args: StockArgs| async move {
+            match args.ticker.as_str() {
+                "TSLA" => Ok("248.50".to_string()),
+                ticker => Err(format!("Unknown ticker {}", ticker)),
+            }
+        },
+    )
+}
+
+async fn openai_agent(server: &MockServer) -> Agent {
+    let mut agent = Agent::new(
+        "WeatherAgent".to_string(),
+        "You are a weather assistant.".to_string(),
+    ).await;
+    agent.model = Some(OPENAI_MODEL.to_string());
+    agent.with_base_url(&format!("{}{}", server.url(), COMPLETIONS_PATH))
+}
+
+async fn anthropic_agent(server: &MockServer) -> Agent {
+    let mut agent = Agent::new(
+        "StockAgent".to_string(),
+        "You are a stock market assistant.".to_string(),
+    ).await;
+    agent.agent_type = AgentType::Anthropic;
+    agent.model = Some(ANTHROPIC_MODEL.to_string());
+    agent
+        .with_base_url(&format!("{}{}", server.url(), MESSAGES_PATH))
+        .with_tool(stock_tool())
+}
+
+#[tokio::test]
+async fn agent_openai_tool_loop() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/functions.json"));
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/functions_answer.json"));
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let agent = openai_agent(&server).await.with_tool(weather_tool(calls.clone()));
+
+    let result = match agent.run_messages(vec![
+        ChatMessage::user("What is the weather like in Boston today?"),
+    ]).await {
+        Ok(result) => result,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert!(result.final_output.contains("22 degrees"));
+    assert_eq!(result.turns, 2);
+    assert_eq!(result.usage.total_tokens, 114 + 152);
+    assert_eq!(*calls.lock().unwrap(), vec!["Boston, MA".to_string()]);
+
+    let requests = server.requests_to(COMPLETIONS_PATH);
+    assert_eq!(requests.len(), 2);
+
+    let first = requests[0].json();
+    let parameters = &first["tools"][0]["function"]["parameters"];
+    assert_eq!(first["tools"][0]["function"]["name"], "get_current_weather");
+    assert_eq!(parameters["type"], "object");
+    assert_eq!(parameters["properties"]["location"]["type"], "string");
+    assert_eq!(parameters["required"], json!(["location"]));
+    assert!(parameters.get("$schema").is_none());
+
+    let second = requests[1].json();
+    assert_eq!(second["messages"][3]["role"], "tool");
+    assert_eq!(second["messages"][3]["tool_call_id"], "call_62136354");
+    let output: serde_json::Value = serde_json::from_str(
+        second["messages"][3]["content"][0]["text"].as_str().unwrap_or_default()
+    ).unwrap_or_default();
+    assert_eq!(output["temperature"], 22);
+}
+
+#[tokio::test]
+async fn agent_anthropic_tool_loop() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function.json"));
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function_answer.json"));
+
+    let agent = anthropic_agent(&server).await;
+
+    let response = match agent.run("How much is Tesla stock trading for?").await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert!(response.contains("248.50"));
+
+    let requests = server.requests_to(MESSAGES_PATH);
+    assert_eq!(requests.len(), 2);
+
+    let first = requests[0].json();
+    assert_eq!(first["system"], "You are a stock market assistant.");
+    assert_eq!(first["tools"][0]["input_schema"]["properties"]["ticker"]["type"], "string");
+
+    let second = requests[1].json();
+    assert_eq!(second["messages"][2]["content"][0]["type"], "tool_result");
+    assert_eq!(second["messages"][2]["content"][0]["content"], "248.50");
+}
+
+#[tokio::test]
+async fn agent_anthropic_thinking() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/thinking_tool_use.json"));
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function_answer.json"));
+
+    let mut agent = anthropic_agent(&server).await;
+    agent.model_settings(1.0, 1024, Some(Thinking::Enabled(4096))).await;
+
+    let result = match agent.run_messages(vec![
+        ChatMessage::user("How much is Tesla stock trading for?"),
+    ]).await {
+        Ok(result) => result,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert!(result.final_output.contains("248.50"));
+
+    let requests = server.requests_to(MESSAGES_PATH);
+    let first = requests[0].json();
+    assert_eq!(first["thinking"]["budget_tokens"], 4096);
+    assert_eq!(first["max_tokens"], 4096 + 1024);
+    assert!(first.get("temperature").is_none());
+
+    // Thinking blocks stay at the start of the assistant turn that called the tool
+    let second = requests[1].json();
+    let assistant = &second["messages"][1]["content"];
+    assert_eq!(assistant[0]["type"], "thinking");
+    assert_eq!(assistant[1]["type"], "redacted_thinking");
+    assert_eq!(assistant[2]["type"], "text");
+    assert_eq!(assistant[3]["type"], "tool_use");
+}
+
+#[tokio::test]
+async fn agent_openai_reasoning_effort() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/functions_answer.json"));
+
+    let mut agent = openai_agent(&server).await;
+    agent.model_settings(1.0, 4096, Some(Thinking::Enabled(8192))).await;
+
+    if let Err(e) = agent.run("What is the weather like in Boston today?").await {
+        panic!("Error: {}", e);
+    }
+
+    let body = server.requests_to(COMPLETIONS_PATH)[0].json();
+    assert_eq!(body["reasoning_effort"], "medium");
+}
+
+#[tokio::test]
+async fn agent_stop_on_first_tool() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/functions.json"));
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let agent = openai_agent(&server).await
+        .with_tool(weather_tool(calls))
+        .with_tool_use_behavior(ToolUseBehavior::StopOnFirstTool);
+
+    let response = match agent.run("What is the weather like in Boston today?").await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    let output: serde_json::Value = serde_json::from_str(&response).unwrap_or_default();
+    assert_eq!(output["description"], "Sunny");
+    assert_eq!(server.requests_to(COMPLETIONS_PATH).len(), 1);
+}
+
+#[tokio::test]
+async fn agent_stop_at_tools() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function.json"));
+
+    let agent = anthropic_agent(&server).await
+        .with_tool_use_behavior(ToolUseBehavior::StopAtTools(vec!["get_stock_price".to_string()]));
+
+    let result = match agent.run_messages(vec![
+        ChatMessage::user("How much is Tesla stock trading for?"),
+    ]).await {
+        Ok(result) => result,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(result.final_output, "248.50");
+    assert_eq!(result.turns, 1);
+    assert_eq!(result.usage.input_tokens, 512);
+    assert_eq!(server.requests_to(MESSAGES_PATH).len(), 1);
+}
+
+#[tokio::test]
+async fn agent_custom_tool_behavior() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/functions.json"));
+
+    let failing_tool = FunctionTool::new(
+        "get_current_weather",
+        "Get the current weather in a given location",
+        |_args: WeatherArgs| async move { Err::<String, _>("Service unavailable") },
+    );
+
+    let agent = openai_agent(&server).await
+        .with_tool(failing_tool)
+        .with_tool_use_behavior(ToolUseBehavior::custom(|results| {
+            match results.iter().find(|result| result.is_error) {
+                Some(result) => ToolToFinalOutputResult::new_error(result.output.clone()),
+                None => ToolToFinalOutputResult::Ignore,
+            }
+        }));
+
+    let response = match agent.run("What is the weather like in Boston today?").await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(response, "Error: Service unavailable");
+}
+
+#[tokio::test]
+async fn agent_max_turns() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    // The last fixture is served again, so the model keeps calling the tool
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/functions.json"));
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let agent = openai_agent(&server).await
+        .with_tool(weather_tool(calls.clone()))
+        .with_max_turns(3);
+
+    match agent.run("What is the weather like in Boston today?").await {
+        Err(AgentError::MaxTurnsExceeded(turns)) => assert_eq!(turns, 3),
+        other => panic!("Expected MaxTurnsExceeded, got {:?}", other),
+    }
+
+    assert_eq!(calls.lock().unwrap().len(), 3);
+    assert_eq!(server.requests_to(COMPLETIONS_PATH).len(), 3);
+}
+
+#[tokio::test]
+async fn agent_handoff() {
+    let openai_server = MockServer::start(OPENAI_UPSTREAM).await;
+    openai_server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/agent_handoff.json"));
+
+    let anthropic_server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    anthropic_server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function.json"));
+    anthropic_server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function_answer.json"));
+
+    let stock_agent = anthropic_agent(&anthropic_server).await
+        .with_handoff_description("Answers questions about stock prices.");
+
+    let mut triage_agent = Agent::new(
+        "TriageAgent".to_string(),
+        "Route the user to the right agent.".to_string(),
+    ).await;
+    triage_agent.model = Some(OPENAI_MODEL.to_string());
+    let triage_agent = triage_agent
+        .with_base_url(&format!("{}{}", openai_server.url(), COMPLETIONS_PATH))
+        .with_handoff(stock_agent);
+
+    let result = match triage_agent.run_messages(vec![
+        ChatMessage::user("How much is Tesla stock trading for?"),
+    ]).await {
+        Ok(result) => result,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert!(result.final_output.contains("248.50"));
+    assert_eq!(result.agents, vec!["TriageAgent".to_string(), "StockAgent".to_string()]);
+    assert_eq!(result.last_agent, "StockAgent");
+    assert_eq!(result.turns, 3);
+
+    let triage_request = openai_server.requests_to(COMPLETIONS_PATH)[0].json();
+    let handoff_tool = &triage_request["tools"][0]["function"];
+    assert_eq!(handoff_tool["name"], "transfer_to_stock_agent");
+    assert!(handoff_tool["description"].as_str().unwrap_or_default().contains("stock prices"));
+
+    // The stock agent continues the conversation with its own prompt and tools
+    let stock_request = anthropic_server.requests_to(MESSAGES_PATH)[0].json();
+    assert_eq!(stock_request["system"], "You are a stock market assistant.");
+    assert_eq!(stock_request["tools"][0]["name"], "get_stock_price");
+    assert_eq!(stock_request["messages"][0]["content"][0]["text"], "How much is Tesla stock trading for?");
+    // The triage agent's handoff call is replayed as text
+    assert_eq!(stock_request["messages"][1]["content"][0]["text"], "Called transfer_to_stock_agent with {}");
+    assert_eq!(stock_request["messages"][2]["content"][0]["type"], "text");
+
+    // Its own tool turns keep their blocks
+    let answer_request = anthropic_server.requests_to(MESSAGES_PATH)[1].json();
+    assert_eq!(answer_request["messages"][3]["content"][1]["type"], "tool_use");
+    assert_eq!(answer_request["messages"][4]["content"][0]["type"], "tool_result");
+}
+
+#[tokio::test]
+async fn agent_handoff_to_agent_without_tools() {
+    let openai_server = MockServer::start(OPENAI_UPSTREAM).await;
+    openai_server.mock("POST", COMPLETIONS_PATH, tool_call_completion("call_1", "get_stock_price", json!({"ticker": "TSLA"})));
+    openai_server.mock("POST", COMPLETIONS_PATH, tool_call_completion("call_2", "transfer_to_advisor_agent", json!({})));
+
+    let anthropic_server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    anthropic_server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/simple_shot.json"));
+
+    let mut advisor_agent = Agent::new(
+        "AdvisorAgent".to_string(),
+        "You are a financial advisor.".to_string(),
+    ).await;
+    advisor_agent.agent_type = AgentType::Anthropic;
+    advisor_agent.model = Some(ANTHROPIC_MODEL.to_string());
+    let advisor_agent = advisor_agent
+        .with_base_url(&format!("{}{}", anthropic_server.url(), MESSAGES_PATH))
+        .with_handoff_description("Gives investment advice.");
+
+    let mut triage_agent = Agent::new(
+        "TriageAgent".to_string(),
+        "Look up the price, then hand off to the advisor.".to_string(),
+    ).await;
+    triage_agent.model = Some(OPENAI_MODEL.to_string());
+    let triage_agent = triage_agent
+        .with_base_url(&format!("{}{}", openai_server.url(), COMPLETIONS_PATH))
+        .with_tool(stock_tool())
+        .with_handoff(advisor_agent);
+
+    let result = match triage_agent.run_messages(vec![
+        ChatMessage::user("Should I buy Tesla stock?"),
+    ]).await {
+        Ok(result) => result,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert_eq!(result.last_agent, "AdvisorAgent");
+    // The run result keeps the tool turns as they happened
+    assert_eq!(result.messages[1].tool_calls[0].name, "get_stock_price");
+
+    // Anthropic rejects tool blocks in a request without tools
+    let advisor_request = anthropic_server.requests_to(MESSAGES_PATH)[0].json();
+    assert!(advisor_request.get("tools").is_none());
+    let blocks: Vec<&str> = advisor_request["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|message| message["content"].as_array().unwrap())
+        .filter_map(|block| block["type"].as_str())
+        .collect();
+    assert!(blocks.iter().all(|block| *block == "text"), "{:?}", blocks);
+    assert!(advisor_request.to_string().contains("Result of get_stock_price: 248.50"));
+}
+
+fn completion(message: serde_json::Value, finish_reason: &str) -> Fixture {
+    Fixture::inline(200, json!({
+        "id": "chatcmpl-research",
+        "object": "chat.completion",
+        "created": 1743872331,
+        "model": OPENAI_MODEL,
+        "choices": [{"index": 0, "message": message, "finish_reason": finish_reason}],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 10, "total_tokens": 20}
+    }))
+}
+
+fn tool_call_completion(id: &str, name: &str, arguments: serde_json::Value) -> Fixture {
+    completion(json!({
+        "role": "assistant",
+        "content": null,
+        "tool_calls": [{
+            "id": id,
+            "type": "function",
+            "function": {"name": name, "arguments": arguments.to_string()}
+        }]
+    }), "tool_calls")
+}
+
+fn set_base_url(agent: &mut Agent, base_url: &str) {
+    agent.base_url = Some(base_url.to_string());
+    agent.model = Some(OPENAI_MODEL.to_string());
+    for handoff in agent.handoffs.iter_mut().flatten() {
+        set_base_url(handoff, base_url);
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SearchArgs {
+    /// The search term
+    query: String,
+}
+
+#[tokio::test]
+async fn agent_financial_research_flow() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    let mocks = [
+        tool_call_completion("call_1", "transfer_to_financial_search_agent", json!({})),
+        tool_call_completion("call_2", "web_search", json!({"query": "Tesla Q1 2025 deliveries"})),
+        tool_call_completion("call_3", "transfer_to_fundamentals_analyst_agent", json!({})),
+        tool_call_completion("call_4", "transfer_to_risk_analyst_agent", json!({})),
+        tool_call_completion("call_5", "transfer_to_financial_advisor_agent", json!({})),
+        completion(json!({
+            "role": "assistant",
+            "content": "Tesla's deliveries fell to the lowest level since 2022."
+        }), "stop"),
+    ];
+    for fixture in mocks {
+        server.mock("POST", COMPLETIONS_PATH, fixture);
+    }
+
+    let searches = Arc::new(Mutex::new(Vec::new()));
+    let searches_tool = searches.clone();
+    let web_search = FunctionTool::new(
+        "web_search",
+        "Searches the web",
+        move |args: SearchArgs| {
+            let searches = searches_tool.clone();
+            async move {
+                searches.lock().unwrap().push(args.query);
+                Ok::<_, String>("Tesla delivered 336,681 vehicles in Q1 2025.")
+            }
+        },
+    );
+
+    let mut research_agent = create_research_agent(vec![web_search]).await;
+    set_base_url(&mut research_agent, &format!("{}{}", server.url(), COMPLETIONS_PATH));
+
+    let result = match research_agent.run_messages(vec![
+        ChatMessage::user("Analyze Tesla's most recent quarter"),
+    ]).await {
+        Ok(result) => result,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert!(result.final_output.contains("lowest level since 2022"));
+    assert_eq!(result.agents, vec![
+        "FinancialPlannerAgent",
+        "FinancialSearchAgent",
+        "FundamentalsAnalystAgent",
+        "RiskAnalystAgent",
+        "FinancialAdvisorAgent",
+    ]);
+    assert_eq!(result.turns, 6);
+    assert_eq!(result.usage.total_tokens, 120);
+    assert_eq!(*searches.lock().unwrap(), vec!["Tesla Q1 2025 deliveries".to_string()]);
+
+    // The search agent sees the web search tool and its own handoff only
+    let requests = server.requests_to(COMPLETIONS_PATH);
+    let search_request = requests[1].json();
+    let tool_names: Vec<&str> = search_request["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|tool| tool["function"]["name"].as_str())
+        .collect();
+    assert_eq!(tool_names, vec!["web_search", "transfer_to_fundamentals_analyst_agent"]);
+
+    // The advisor gets the whole conversation, including the search results
+    let advisor_request = requests[5].json();
+    assert!(advisor_request.to_string().contains("336,681 vehicles"));
+}
+
+#[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+struct WeatherReport {
+    /// The city the report is about
+    city: String,
+    /// Temperature in celsius
+    temperature: f64,
+    /// Short description, e.g. Sunny
+    conditions: Vec<String>,
+}
+
+fn text_completion(content: &str) -> Fixture {
+    completion(json!({"role": "assistant", "content": content}), "stop")
+}
+
+#[tokio::test]
+async fn agent_typed_output() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, text_completion(
+        r#"{"city": "Boston", "temperature": 22.5, "conditions": ["Sunny", "Windy"]}"#,
+    ));
+
+    let agent = openai_agent(&server).await.with_output_type::<WeatherReport>();
+
+    let report = match agent.run("What is the weather like in Boston today?").await {
+        Ok(report) => report,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(report, WeatherReport {
+        city: "Boston".to_string(),
+        temperature: 22.5,
+        conditions: vec!["Sunny".to_string(), "Windy".to_string()],
+    });
+
+    let body = server.requests_to(COMPLETIONS_PATH)[0].json();
+    let json_schema = &body["response_format"]["json_schema"];
+    assert_eq!(body["response_format"]["type"], "json_schema");
+    assert_eq!(json_schema["name"], "WeatherReport");
+    assert_eq!(json_schema["schema"]["properties"]["temperature"]["type"], "number");
+    assert_eq!(json_schema["schema"]["required"], json!(["city", "conditions", "temperature"]));
+    assert_eq!(json_schema["schema"]["additionalProperties"], false);
+}
+
+#[tokio::test]
+async fn agent_array_output() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, text_completion(
+        "```json\n[{\"city\": \"Boston\", \"temperature\": 22.5, \"conditions\": [\"Sunny\"]}, \
+        {\"city\": \"Lima\", \"temperature\": 18.0, \"conditions\": []}]\n```",
+    ));
+
+    let agent = openai_agent(&server).await.with_output_type::<Vec<WeatherReport>>();
+
+    let reports = match agent.run("What is the weather like in Boston and Lima?").await {
+        Ok(reports) => reports,
+        Err(e) => panic!("Error: {}", e),
+    };
+    let cities: Vec<&str> = reports.iter().map(|report| report.city.as_str()).collect();
+    assert_eq!(cities, vec!["Boston", "Lima"]);
+}
+
+#[test]
+fn extract_json_from_answers() {
+    assert_eq!(extract_json(r#"{"a": 1}"#), r#"{"a": 1}"#);
+    assert_eq!(extract_json("```json\n{\"a\": {\"b\": 2}}\n```"), r#"{"a": {"b": 2}}"#);
+    assert_eq!(extract_json("Here it is: [{\"a\": 1}, {\"a\": 2}]. Done"), r#"[{"a": 1}, {"a": 2}]"#);
+    assert_eq!(extract_json("See [1]: {\"a\": [1, 2]}"), r#"{"a": [1, 2]}"#);
+    assert_eq!(extract_json("```\n[1, 2]\n```\nThe list"), "[1, 2]");
+    assert_eq!(extract_json("  no json here "), "no json here");
+}
+
+#[tokio::test]
+async fn agent_typed_output_retry() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, text_completion(r#"{"city": "Boston", "temperature": "warm"}"#));
+    server.mock("POST", COMPLETIONS_PATH, text_completion(
+        "```json\n{\"city\": \"Boston\", \"temperature\": 22.0, \"conditions\": [\"Sunny\"]}\n```",
+    ));
+
+    let agent = openai_agent(&server).await.with_output_type::<WeatherReport>();
+
+    let result = match agent.run_messages(vec![
+        ChatMessage::user("What is the weather like in Boston today?"),
+    ]).await {
+        Ok(result) => result,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(result.final_output.temperature, 22.0);
+    assert_eq!(result.turns, 2);
+
+    // The validation error is sent back to the model
+    let second = server.requests_to(COMPLETIONS_PATH)[1].json();
+    let feedback = second["messages"][3]["content"][0]["text"].as_str().unwrap_or_default();
+    assert_eq!(second["messages"][3]["role"], "user");
+    assert!(feedback.contains("invalid type: string \"warm\""));
+}
+
+#[tokio::test]
+async fn agent_typed_output_invalid() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    // The last fixture is served again, so every retry fails
+    server.mock("POST", COMPLETIONS_PATH, text_completion("It is sunny in Boston."));
+
+    let agent = openai_agent(&server).await
+        .with_output_type::<WeatherReport>()
+        .with_max_output_retries(1);
+
+    match agent.run("What is the weather like in Boston today?").await {
+        Err(AgentError::InvalidOutput(message)) => assert!(message.contains("expected value")),
+        other => panic!("Expected InvalidOutput, got {:?}", other),
+    }
+
+    assert_eq!(server.requests_to(COMPLETIONS_PATH).len(), 2);
+}
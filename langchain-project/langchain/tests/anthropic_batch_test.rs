@@ -0,0 +1,139 @@
+mod common;
+
+use common::{Fixture, MockServer};
+use futures::StreamExt;
+use langchain::anthropic::batch::AnthropicBatch;
+use langchain::anthropic::chat::ChatAnthropic;
+use langchain::anthropic::error::AnthropicError;
+use langchain::anthropic::libs::BatchOutcome;
+use std::time::Duration;
+
+static ANTHROPIC_MODEL: &str = "claude-3-5-haiku-20241022";
+static ANTHROPIC_UPSTREAM: &str = "https://api.anthropic.com";
+static BATCHES_PATH: &str = "/v1/messages/batches";
+static BATCH_ID: &str = "msgbatch_01HkcTjaV5uDC8jWR4ZsDV8d";
+
+fn batch_client(server: &MockServer) -> AnthropicBatch {
+    AnthropicBatch::new()
+        .with_base_url(&format!("{}{}", server.url(), BATCHES_PATH))
+        .with_api_key("test_key")
+        .with_poll_interval(Duration::from_millis(10), Duration::from_millis(20))
+}
+
+fn batch_path(suffix: &str) -> String {
+    format!("{}/{}{}", BATCHES_PATH, BATCH_ID, suffix)
+}
+
+#[tokio::test]
+async fn anthropic_batch_create_and_wait() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", BATCHES_PATH, Fixture::json("anthropic/batch_created.json"));
+    server.mock("GET", &batch_path(""), Fixture::json("anthropic/batch_created.json"));
+    server.mock("GET", &batch_path(""), Fixture::json("anthropic/batch_created.json"));
+    server.mock("GET", &batch_path(""), Fixture::json("anthropic/batch_ended.json"));
+    server.mock("GET", &batch_path("/results"), Fixture::jsonl("anthropic/batch_results.jsonl"));
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL)
+        .with_system_prompt("Classify the review as positive or negative.")
+        .with_max_tokens(16)
+        .with_stream(true);
+
+    let batch = batch_client(&server)
+        .with_request("review-1", llm.clone().with_prompt("Great product, works as described."))
+        .with_request("review-2", llm.clone().with_prompt("It broke after a day."))
+        .with_request("review-3", llm.with_prompt("Arrived on time."));
+
+    let message_batch = match batch.create().await {
+        Ok(message_batch) => message_batch,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert_eq!(message_batch.id, BATCH_ID);
+    assert_eq!(message_batch.request_counts.processing, 3);
+
+    let message_batch = match batch.wait(&message_batch.id).await {
+        Ok(message_batch) => message_batch,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert!(message_batch.is_ended());
+    assert_eq!(message_batch.request_counts.succeeded, 2);
+    assert_eq!(message_batch.request_counts.errored, 1);
+    assert_eq!(server.requests_to(&batch_path("")).len(), 3);
+
+    let mut responses = match batch.responses(&message_batch).await {
+        Ok(responses) => responses,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert_eq!(responses.len(), 3);
+
+    let positive = responses.remove("review-1").unwrap().expect("review-1 failed");
+    let contents = positive.content.unwrap_or_default();
+    assert_eq!(contents[0].text.as_deref(), Some("positive"));
+
+    match responses.remove("review-3").unwrap() {
+        Err(AnthropicError::BadRequestError(message)) => assert!(message.contains("max_tokens")),
+        other => panic!("Expected a bad request error, got {:?}", other),
+    }
+
+    let request = &server.requests_to(BATCHES_PATH)[0];
+    assert_eq!(request.header("x-api-key"), Some("test_key"));
+
+    let body = request.json();
+    let requests = body["requests"].as_array().expect("Missing requests");
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0]["custom_id"], "review-1");
+    assert_eq!(requests[0]["params"]["model"], ANTHROPIC_MODEL);
+    assert_eq!(requests[0]["params"]["max_tokens"], 16);
+    assert_eq!(requests[0]["params"]["stream"], false);
+    assert_eq!(requests[0]["params"]["system"], "Classify the review as positive or negative.");
+    assert_eq!(requests[1]["params"]["messages"][0]["content"][0]["text"], "It broke after a day.");
+}
+
+#[tokio::test]
+async fn anthropic_batch_results_stream() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("GET", &batch_path(""), Fixture::json("anthropic/batch_ended.json"));
+    server.mock(
+        "GET",
+        &batch_path("/results"),
+        Fixture::jsonl("anthropic/batch_results.jsonl").chunked(64),
+    );
+
+    let batch = batch_client(&server);
+    let message_batch = match batch.retrieve(BATCH_ID).await {
+        Ok(message_batch) => message_batch,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    let results: Vec<_> = batch.results(&message_batch).collect().await;
+    assert_eq!(results.len(), 3);
+
+    let custom_ids: Vec<String> = results
+        .iter()
+        .map(|result| result.as_ref().expect("Invalid result").custom_id.clone())
+        .collect();
+    assert_eq!(custom_ids, vec!["review-2", "review-1", "review-3"]);
+
+    match &results[2].as_ref().unwrap().result {
+        BatchOutcome::Errored { error } => assert_eq!(error.error.error_type, "invalid_request_error"),
+        other => panic!("Expected an errored result, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn anthropic_batch_cancel() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", &batch_path("/cancel"), Fixture::json("anthropic/batch_canceling.json"));
+
+    let batch = batch_client(&server);
+    let message_batch = match batch.cancel(BATCH_ID).await {
+        Ok(message_batch) => message_batch,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(message_batch.processing_status, "canceling");
+    assert!(message_batch.cancel_initiated_at.is_some());
+
+    // Results are only available once processing ended
+    let results: Vec<_> = batch.results(&message_batch).collect().await;
+    assert!(matches!(results[0], Err(AnthropicError::BatchNotEnded(_))));
+}
@@ -1,68 +1,49 @@
-use langchain::anthropic::ChatAnthropic;
+mod common;
+
+use common::{Fixture, MockServer, read_base64};
+use futures::StreamExt;
+use langchain::anthropic::chat::ChatAnthropic;
+use langchain::anthropic::embed::EmbedVoyage;
+use langchain::anthropic::libs::{
+    CacheControl, CacheTtl, ChatResponse, ContentDelta, StreamEvent, SystemBlock,
+};
+use langchain::llm::chat::ChatModel;
+use langchain::llm::embed::Embeddings;
+use langchain::llm::error::LlmError;
+use langchain::llm::libs::{ChatMessage, ChatOptions, StopReason, ToolChoice, ToolDefinition};
 use serde_json::json;
-use std::fs::File;
-use std::io::Read;
-use base64::{Engine as _, engine::general_purpose::STANDARD};
 
 static ANTHROPIC_MODEL: &str = "claude-3-5-sonnet-20241022";
+static ANTHROPIC_UPSTREAM: &str = "https://api.anthropic.com";
+static MESSAGES_PATH: &str = "/v1/messages";
 
-#[tokio::test]
-async fn anthropic_simple_shot() {
-    let llm = match ChatAnthropic::new(ANTHROPIC_MODEL) {
-        Ok(llm) => llm,
-        Err(e) => panic!("Error: {}", e),
-    };
-
-    let llm = llm.with_max_tokens(1024);
-    let llm = llm.with_temperature(0.9);
-    let llm = llm.with_max_tokens(2048);
-    let llm = llm.with_timeout_sec(30);
-    
-    let llm = llm.with_system_prompt("You are a helpful assistant.");
-    let prompt = "Only say Simple test";
-
-    let response = match llm.invoke(prompt).await {
-        Ok(response) => response,
-        Err(e) => panic!("Error: {}", e),
-    };
+fn messages_url(server: &MockServer) -> String {
+    format!("{}{}", server.url(), MESSAGES_PATH)
+}
 
-    if let Some(candidates) = response.content {
-        for candidate in candidates {
-            match candidate.text {
-                Some(message) => {
-                    let text_l = message.to_lowercase();
-                    let possible_values = vec![
-                        "simple test",
-                        "simple test\n",
-                        "simple test.\n",
-                        "simple test."
-                    ];
-
-                    // Count how many matches we have
-                    let match_count = possible_values.iter()
-                        .filter(|&&val| val == text_l)
-                        .count();
-                    assert_eq!(
-                        match_count, 
-                        1, 
-                        "Text '{}' did not match any of the expected values", 
-                        text_l
-                    );
-                }
-                None => panic!("No response candidates available"),
+fn response_text(response: &ChatResponse) -> String {
+    let mut result = String::new();
+    if let Some(contents) = &response.content {
+        for content in contents {
+            if let Some(text) = &content.text {
+                result.push_str(text);
             }
         }
-    };
+    }
+    result
 }
 
-#[tokio::test]
-async fn anthropic_function() {
-    let llm = match ChatAnthropic::new(ANTHROPIC_MODEL) {
-        Ok(llm) => llm,
-        Err(e) => panic!("Error: {}", e),
-    };
-    
-    let tool_data = json!({
+fn assert_one_of(text: &str, possible_values: &[&str]) {
+    let text_l = text.to_lowercase();
+    assert!(
+        possible_values.contains(&text_l.as_str()),
+        "Text '{}' did not match any of the expected values",
+        text_l
+    );
+}
+
+fn stock_tool() -> serde_json::Value {
+    json!({
         "name": "get_stock_price",
         "description": "Retrieves the current stock price for a given ticker symbol. \
                         The ticker symbol must be valid for a publicly traded company \
@@ -73,77 +54,232 @@ async fn anthropic_function() {
                         It will not provide any other information \
                         about the stock or company.",
         "input_schema": {
-        "type": "object",
-        "properties": {
-            "ticker": {
-            "type": "string",
-            "description": "The stock ticker symbol, e.g. AAPL for Apple Inc."
-            }
-        },
-        "required": ["ticker"]
+            "type": "object",
+            "properties": {
+                "ticker": {
+                    "type": "string",
+                    "description": "The stock ticker symbol, e.g. AAPL for Apple Inc."
+                }
+            },
+            "required": ["ticker"]
         }
-    });
+    })
+}
 
-    let tools = vec![tool_data];
-    let tool_choice = json!({"type": "auto"});
-    let llm = llm.with_tools(tools, tool_choice);
+#[tokio::test]
+async fn anthropic_simple_shot() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/simple_shot.json"));
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL)
+        .with_base_url(&messages_url(&server))
+        .with_api_key("test_key")
+        .with_temperature(0.9)
+        .with_max_tokens(2048)
+        .with_timeout_sec(30)
+        .with_system_prompt("You are a helpful assistant.");
+    let prompt = "Only say Simple test";
 
-    let prompt = "How much is Tesla stock trading for? Before answering, explain your reasoning step-by-step in tags.";
-    
     let response = match llm.invoke(prompt).await {
         Ok(response) => response,
         Err(e) => panic!("Error: {}", e),
     };
 
-    if let Some(contents) = &response.content {
-        let function_content = &contents[1];
-        assert_eq!(function_content.name, Some("get_stock_price".to_string()));
-        assert_eq!(function_content.content_type, Some("tool_use".to_string()));
-        if let Some(input) = &function_content.input {
-            assert_eq!(input["ticker"], "TSLA");
-        } else {
-            panic!("Input should not be None");
-        }
-    } else {
-        panic!("Content should not be None");
-    }
+    assert_one_of(
+        &response_text(&response),
+        &["simple test", "simple test\n", "simple test.\n", "simple test."],
+    );
+
+    let request = &server.requests_to(MESSAGES_PATH)[0];
+    assert_eq!(request.header("x-api-key"), Some("test_key"));
+    assert_eq!(request.header("anthropic-version"), Some("2023-06-01"));
+
+    let body = request.json();
+    assert_eq!(body["model"], ANTHROPIC_MODEL);
+    assert_eq!(body["system"], "You are a helpful assistant.");
+    assert_eq!(body["max_tokens"], 2048);
+    assert_eq!(body["messages"][0]["content"][0]["text"], prompt);
 }
 
 #[tokio::test]
-async fn anthropic_images() {
-    let llm = match ChatAnthropic::new(ANTHROPIC_MODEL) {
-        Ok(llm) => llm,
+async fn anthropic_function() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function.json"));
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function_answer.json"));
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL)
+        .with_base_url(&messages_url(&server))
+        .with_tools(vec![stock_tool()], Some(json!({"type": "auto"})));
+
+    let prompt = "How much is Tesla stock trading for? Before answering, explain your reasoning step-by-step in tags.";
+
+    let response = match llm.clone().invoke(prompt).await {
+        Ok(response) => response,
         Err(e) => panic!("Error: {}", e),
     };
 
-    // Read first image into a byte vector
-    let mut file_01 = match File::open("tests/files/image01.jpg") {
-        Ok(file) => file, 
-        Err(e) => panic!("Error: {}", e)
+    assert_eq!(response.stop_reason, Some("tool_use".to_string()));
+
+    let contents = match &response.content {
+        Some(contents) => contents.clone(),
+        None => panic!("Content should not be None"),
     };
-    let mut buffer_01 = Vec::new();
-    match file_01.read_to_end(&mut buffer_01) {
-        Ok(_) => (),
-        Err(e) => panic!("Error: {}", e)
+
+    let function_content = &contents[1];
+    assert_eq!(function_content.name, Some("get_stock_price".to_string()));
+    assert_eq!(function_content.content_type, "tool_use".to_string());
+    match &function_content.input {
+        Some(input) => assert_eq!(input["ticker"], "TSLA"),
+        None => panic!("Input should not be None"),
+    }
+
+    let tool_id = function_content.id.clone().expect("Missing tool id");
+    let chat_history = response.chat_history.clone().unwrap_or_default();
+
+    let response = match llm
+        .with_chat_history(chat_history)
+        .with_assistant_content(contents)
+        .with_tool_result(&tool_id, "248.50")
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
     };
 
-    // Read second image into a byte vector
-    let mut file_02 = match File::open("tests/files/image03.png") {
-        Ok(file) => file,
-        Err(e) => panic!("Error: {}", e)
+    assert!(response_text(&response).contains("248.50"));
+
+    let requests = server.requests_to(MESSAGES_PATH);
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].json()["tools"][0]["name"], "get_stock_price");
+
+    let body = requests[1].json();
+    assert_eq!(body["messages"][1]["role"], "assistant");
+    assert_eq!(body["messages"][2]["content"][0]["type"], "tool_result");
+    assert_eq!(body["messages"][2]["content"][0]["tool_use_id"], tool_id);
+}
+
+#[tokio::test]
+async fn anthropic_thinking_tool_use() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/thinking_tool_use.json"));
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function_answer.json"));
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL)
+        .with_base_url(&messages_url(&server))
+        .with_thinking(2048)
+        .with_tools(vec![stock_tool()], Some(json!({"type": "auto"})));
+
+    let response = match llm.clone().invoke("How much is Tesla stock trading for?").await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
     };
-    let mut buffer_02 = Vec::new();
-    match file_02.read_to_end(&mut buffer_02) {
-        Ok(_) => (),
-        Err(e) => panic!("Error: {}", e)
+
+    let contents = response.content.clone().expect("Missing content");
+    assert_eq!(contents[0].content_type, "thinking");
+    assert!(contents[0].thinking.as_deref().unwrap_or_default().contains("TSLA"));
+    assert!(contents[0].signature.is_some());
+    assert_eq!(contents[1].content_type, "redacted_thinking");
+    assert!(contents[1].data.is_some());
+
+    let tool_id = contents[3].id.clone().expect("Missing tool id");
+    let chat_history = response.chat_history.clone().unwrap_or_default();
+
+    let response = match llm
+        .with_chat_history(chat_history)
+        .with_assistant_content(contents)
+        .with_tool_result(&tool_id, "248.50")
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert!(response_text(&response).contains("248.50"));
+
+    let requests = server.requests_to(MESSAGES_PATH);
+    let first = requests[0].json();
+    assert_eq!(first["thinking"], json!({"type": "enabled", "budget_tokens": 2048}));
+    assert_eq!(first["max_tokens"], 2048 + 1024);
+
+    // The thinking blocks go back unchanged with the tool result
+    let second = requests[1].json();
+    let assistant = &second["messages"][1]["content"];
+    assert_eq!(assistant[0]["type"], "thinking");
+    assert!(assistant[0]["signature"].as_str().unwrap_or_default().starts_with("EuYBCkQYAiJA"));
+    assert_eq!(assistant[1]["type"], "redacted_thinking");
+    assert!(assistant[1]["data"].as_str().is_some());
+    assert!(assistant[1].get("text").is_none());
+    assert_eq!(second["messages"][2]["content"][0]["tool_use_id"], tool_id);
+}
+
+#[test]
+fn anthropic_thinking_budget_limits() {
+    // The API rejects budgets under 1024 tokens
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL).with_thinking(100);
+    let thinking = llm.request.thinking.as_ref().unwrap();
+    assert_eq!(thinking.budget_tokens, Some(1024));
+    assert_eq!(llm.request.max_tokens, Some(2048));
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL)
+        .with_max_tokens(u32::MAX - 1)
+        .with_thinking(u32::MAX);
+    assert_eq!(llm.request.max_tokens, Some(u32::MAX));
+
+    // The order of the builder calls does not matter
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL)
+        .with_thinking(4096)
+        .with_max_tokens(2000);
+    assert_eq!(llm.request.max_tokens, Some(6096));
+}
+
+#[tokio::test]
+async fn anthropic_prompt_cache() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/prompt_cache.json"));
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL)
+        .with_base_url(&messages_url(&server))
+        .with_system_blocks(vec![
+            SystemBlock::text("You are a financial assistant."),
+            SystemBlock::text("Earnings calendar: ...")
+                .with_cache_control(CacheControl::ephemeral().with_ttl(CacheTtl::OneHour)),
+        ])
+        .with_tools(vec![stock_tool()], None)
+        .with_tools_cache(CacheControl::ephemeral());
+
+    let response = match llm.invoke("When does Tesla report earnings?").await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
     };
 
-    // Convert to base64
-    let base64_string_01 = STANDARD.encode(&buffer_01);
-    let base64_string_02 = STANDARD.encode(&buffer_02);
+    assert!(response_text(&response).contains("TSLA"));
 
-    let llm = llm.with_image_jpeg(&base64_string_01);
-    let llm = llm.with_image_png(&base64_string_02);
+    // Cached tokens are part of the normalized input tokens
+    let usage = response.usage.expect("Missing usage").token_usage();
+    assert_eq!(usage.input_tokens, 21 + 188 + 2154);
+    assert_eq!(usage.cache_creation_input_tokens, 188);
+    assert_eq!(usage.cache_read_input_tokens, 2154);
+    assert_eq!(usage.total_tokens, 21 + 188 + 2154 + 24);
+
+    let body = server.requests_to(MESSAGES_PATH)[0].json();
+    assert_eq!(body["system"][0], json!({"type": "text", "text": "You are a financial assistant."}));
+    assert_eq!(body["system"][1]["cache_control"], json!({"type": "ephemeral", "ttl": "1h"}));
+    assert_eq!(body["tools"][0]["cache_control"], json!({"type": "ephemeral"}));
+    assert!(body["messages"][0]["content"][0].get("cache_control").is_none());
+}
+
+#[tokio::test]
+async fn anthropic_images() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/images.json"));
+
+    let base64_string_01 = read_base64("tests/files/image01.jpg");
+    let base64_string_02 = read_base64("tests/files/image03.png");
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL)
+        .with_base_url(&messages_url(&server))
+        .with_image_base64(&base64_string_01, "image/jpeg")
+        .with_image_base64(&base64_string_02, "image/png");
 
     let prompt = "Compare the two pictures provided. \
         Which of the images shows an office with people working, \
@@ -154,41 +290,21 @@ async fn anthropic_images() {
         Err(e) => panic!("Error: {}", e),
     };
 
-    if let Some(candidates) = response.content {
-        for candidate in candidates {
-            match candidate.text {
-                Some(message) => {
-                    let text_l = message.to_lowercase();
-                    let possible_values = vec![
-                        "first",
-                        "first\n",
-                        "first.\n",
-                        "first."
-                    ];
-
-                    // Count how many matches we have
-                    let match_count = possible_values.iter()
-                        .filter(|&&val| val == text_l)
-                        .count();
-                    assert_eq!(
-                        match_count, 
-                        1, 
-                        "Text '{}' did not match any of the expected values", 
-                        text_l
-                    );
-                }
-                None => panic!("No response candidates available"),
-            }
-        }
-    };
+    assert_one_of(&response_text(&response), &["first", "first\n", "first.\n", "first."]);
+
+    let body = server.requests_to(MESSAGES_PATH)[0].json();
+    assert_eq!(body["messages"][0]["content"][0]["source"]["media_type"], "image/jpeg");
+    assert_eq!(body["messages"][1]["content"][0]["source"]["media_type"], "image/png");
+    assert_eq!(body["messages"][1]["content"][0]["source"]["data"], base64_string_02);
 }
 
 #[tokio::test]
 async fn anthropic_multiple_turns() {
-    let llm = match ChatAnthropic::new(ANTHROPIC_MODEL) {
-        Ok(llm) => llm,
-        Err(e) => panic!("Error: {}", e),
-    };
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/multiple_turns_1.json"));
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/multiple_turns_2.json"));
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL).with_base_url(&messages_url(&server));
 
     let prompt = "Please answer the following question with only \"yes\" or \"no\": Is the sky blue during a clear day?";
 
@@ -197,30 +313,17 @@ async fn anthropic_multiple_turns() {
         Err(e) => panic!("Error: {}", e),
     };
 
-    let mut response_model = String::new();
-
-    if let Some(candidates) = response.content {
-        for candidate in candidates {
-            match candidate.text {
-                Some(text) => {
-                    response_model = text.to_string();
-                }
-                None => panic!("No response candidates available"),
-            }
-
-        }
-    }
+    let response_model = response_text(&response);
+    assert_one_of(&response_model, &["yes", "yes\n", "yes.\n", "yes."]);
 
     let chat_history = match response.chat_history {
         Some(chat_history) => chat_history,
-        None => {
-            println!("No chat history");
-            Vec::new()
-        }
+        None => panic!("No chat history"),
     };
 
-    let llm = llm.with_chat_history(chat_history);
-    let llm = llm.with_assistant_response(&response_model);
+    let llm = llm
+        .with_chat_history(chat_history)
+        .with_assistant_response(&response_model);
 
     let prompt = "And during the night? Answer only with \"yes\" or \"no\".";
 
@@ -229,31 +332,320 @@ async fn anthropic_multiple_turns() {
         Err(e) => panic!("Error: {}", e),
     };
 
-    if let Some(candidates) = response.content {
-        for candidate in candidates {
-            match candidate.text {
-                Some(message) => {
-                    let text_l = message.to_lowercase();
-                    let possible_values = vec![
-                        "no",
-                        "no\n",
-                        "no.\n",
-                        "no."
-                    ];
-
-                    // Count how many matches we have
-                    let match_count = possible_values.iter()
-                        .filter(|&&val| val == text_l)
-                        .count();
-                    assert_eq!(
-                        match_count, 
-                        1, 
-                        "Text '{}' did not match any of the expected values", 
-                        text_l
-                    );
-                }
-                None => panic!("No response candidates available"),
+    assert_one_of(&response_text(&response), &["no", "no\n", "no.\n", "no."]);
+
+    let body = server.requests_to(MESSAGES_PATH)[1].json();
+    assert_eq!(body["messages"].as_array().unwrap().len(), 3);
+    assert_eq!(body["messages"][1]["content"][0]["text"], response_model);
+}
+
+#[tokio::test]
+async fn anthropic_request_error() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock(
+        "POST",
+        MESSAGES_PATH,
+        Fixture::json("anthropic/error_401.json").with_status(401),
+    );
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL)
+        .with_base_url(&messages_url(&server))
+        .with_max_retries(0);
+
+    assert!(llm.invoke("Hello").await.is_err());
+    assert_eq!(server.requests_to(MESSAGES_PATH).len(), 1);
+}
+
+#[tokio::test]
+async fn anthropic_stream_events() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::sse("anthropic/stream_tool_use.sse").chunked(11));
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL).with_base_url(&messages_url(&server));
+
+    let stream = llm.stream_response("What is the weather like in San Francisco?".to_string());
+    futures::pin_mut!(stream);
+
+    let mut text = String::new();
+    let mut partial_json = String::new();
+    let mut event_types = Vec::new();
+    while let Some(event) = stream.next().await {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => panic!("Error: {}", e),
+        };
+        match &event {
+            StreamEvent::ContentBlockDelta { delta: ContentDelta::TextDelta { text: delta }, .. } => {
+                text.push_str(delta);
+            }
+            StreamEvent::ContentBlockDelta { delta: ContentDelta::InputJsonDelta { partial_json: delta }, .. } => {
+                partial_json.push_str(delta);
+            }
+            StreamEvent::MessageDelta { delta, usage } => {
+                assert_eq!(delta.stop_reason.as_deref(), Some("tool_use"));
+                assert_eq!(usage.as_ref().and_then(|usage| usage.output_tokens), Some(89));
             }
+            _ => (),
+        }
+        event_types.push(event);
+    }
+
+    assert_eq!(text, "Okay, let's check the weather for San Francisco, CA:");
+    assert_eq!(partial_json, "{\"location\": \"San Francisco, CA\", \"unit\": \"fahrenheit\"}");
+    assert!(matches!(event_types.first(), Some(StreamEvent::MessageStart { .. })));
+    assert!(matches!(event_types.last(), Some(StreamEvent::MessageStop)));
+    assert!(event_types.iter().any(|event| matches!(event, StreamEvent::Ping)));
+
+    let body = server.requests_to(MESSAGES_PATH)[0].json();
+    assert_eq!(body["stream"], true);
+}
+
+#[tokio::test]
+async fn anthropic_stream_assembled() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::sse("anthropic/stream_tool_use.sse").chunked(3));
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL).with_base_url(&messages_url(&server));
+
+    let response = match llm.invoke_stream("What is the weather like in San Francisco?").await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(response.id.as_deref(), Some("msg_014p7gG3wDgGV9EUtLvnow3U"));
+    assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+    assert_eq!(response_text(&response), "Okay, let's check the weather for San Francisco, CA:");
+
+    let usage = response.usage.expect("Missing usage");
+    assert_eq!(usage.input_tokens, Some(472));
+    assert_eq!(usage.output_tokens, Some(89));
+
+    let contents = response.content.expect("Missing content");
+    assert_eq!(contents[1].content_type, "tool_use");
+    assert_eq!(contents[1].id.as_deref(), Some("toolu_01T1x1fJ34qAmk2tNTrN7Up6"));
+    assert_eq!(
+        contents[1].input,
+        Some(json!({"location": "San Francisco, CA", "unit": "fahrenheit"}))
+    );
+
+    let chat_history = response.chat_history.expect("Missing chat history");
+    assert_eq!(chat_history.len(), 1);
+}
+
+#[tokio::test]
+async fn anthropic_stream_thinking() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::sse("anthropic/stream_thinking.sse"));
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL)
+        .with_base_url(&messages_url(&server))
+        .with_stream(true);
+
+    let response = match llm.invoke("What is 27 * 453?").await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    let contents = response.content.clone().expect("Missing content");
+    assert_eq!(contents[0].content_type, "thinking");
+    assert!(contents[0].thinking.as_deref().unwrap_or_default().ends_with("= 12231"));
+    assert!(contents[0].signature.is_some());
+    assert_eq!(response_text(&response), "27 * 453 = 12,231");
+    assert_eq!(response.stop_reason.as_deref(), Some("end_turn"));
+}
+
+#[tokio::test]
+async fn anthropic_stream_error() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::sse("anthropic/stream_error.sse"));
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL).with_base_url(&messages_url(&server));
+
+    let stream = llm.clone().stream_response("Hello".to_string());
+    futures::pin_mut!(stream);
+
+    let mut items: Vec<_> = stream.collect().await;
+    assert_eq!(items.len(), 4);
+    match LlmError::from(items.pop().unwrap().unwrap_err()) {
+        LlmError::ServerError(error) => {
+            assert_eq!(error.code.as_deref(), Some("overloaded_error"));
+            assert_eq!(error.message, "Overloaded");
         }
+        other => panic!("Expected a server error, got {:?}", other),
+    }
+
+    assert!(llm.invoke_stream("Hello").await.is_err());
+}
+
+#[tokio::test]
+async fn anthropic_stream_error_status() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock(
+        "POST",
+        MESSAGES_PATH,
+        Fixture::json("anthropic/error_401.json").with_status(401),
+    );
+
+    let llm = ChatAnthropic::new(ANTHROPIC_MODEL).with_base_url(&messages_url(&server));
+
+    let stream = llm.stream_response("Hello".to_string());
+    futures::pin_mut!(stream);
+
+    let items: Vec<_> = stream.collect().await;
+    assert_eq!(items.len(), 1);
+    match &items[0] {
+        Err(e) => assert!(e.to_string().starts_with("Authentication Error")),
+        Ok(_) => panic!("Expected an error item"),
+    }
+}
+
+#[tokio::test]
+async fn anthropic_chat_model_tools() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function.json"));
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function_answer.json"));
+
+    let llm: Box<dyn ChatModel> = Box::new(
+        ChatAnthropic::new(ANTHROPIC_MODEL).with_base_url(&messages_url(&server))
+    );
+
+    let tool = stock_tool();
+    let tools = vec![ToolDefinition {
+        name: "get_stock_price".to_string(),
+        description: tool["description"].as_str().unwrap_or_default().to_string(),
+        parameters: tool["input_schema"].clone(),
+    }];
+    let options = ChatOptions::new()
+        .with_tools(tools)
+        .with_tool_choice(ToolChoice::Auto)
+        .with_max_tokens(512);
+
+    let mut messages = vec![
+        ChatMessage::system("You are a financial assistant."),
+        ChatMessage::user("How much is Tesla stock trading for?"),
+    ];
+
+    let response = match llm.generate(&messages, &options).await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(response.stop_reason, Some(StopReason::ToolUse));
+    let tool_call = response.tool_calls()[0].clone();
+    assert_eq!(tool_call.id, "toolu_01A09q90qw90lq917835lq9");
+    assert_eq!(tool_call.arguments["ticker"], "TSLA");
+
+    messages.push(response.message.clone());
+    messages.push(ChatMessage::tool_result(&tool_call.id, &tool_call.name, "248.50"));
+
+    let response = match llm.generate(&messages, &options).await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(response.stop_reason, Some(StopReason::EndTurn));
+    assert!(response.text().contains("248.50"));
+
+    let requests = server.requests_to(MESSAGES_PATH);
+    let first = requests[0].json();
+    assert_eq!(first["system"], "You are a financial assistant.");
+    assert_eq!(first["tools"][0]["input_schema"]["required"][0], "ticker");
+    assert_eq!(first["tool_choice"]["type"], "auto");
+    assert_eq!(first["max_tokens"], 512);
+
+    let second = requests[1].json();
+    assert_eq!(second["messages"][1]["content"][1]["type"], "tool_use");
+    assert_eq!(second["messages"][2]["content"][0]["tool_use_id"], tool_call.id);
+}
+
+#[tokio::test]
+async fn anthropic_chat_model_prompt_cache() {
+    let server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/prompt_cache.json"));
+
+    let llm: Box<dyn ChatModel> = Box::new(
+        ChatAnthropic::new(ANTHROPIC_MODEL)
+            .with_base_url(&messages_url(&server))
+            .with_prompt_cache(CacheControl::ephemeral())
+    );
+
+    let tool = stock_tool();
+    let tools = vec![ToolDefinition {
+        name: "get_stock_price".to_string(),
+        description: tool["description"].as_str().unwrap_or_default().to_string(),
+        parameters: tool["input_schema"].clone(),
+    }];
+    let options = ChatOptions::new().with_tools(tools);
+
+    let messages = vec![
+        ChatMessage::system("You are a financial assistant."),
+        ChatMessage::user("When does Tesla report earnings?"),
+    ];
+
+    let response = match llm.generate(&messages, &options).await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
     };
+
+    let usage = response.usage.expect("Missing usage");
+    assert_eq!(usage.input_tokens, 21 + 188 + 2154);
+    assert_eq!(usage.cache_read_input_tokens, 2154);
+    assert!(usage.cache_hit_rate() > 0.9);
+
+    let body = server.requests_to(MESSAGES_PATH)[0].json();
+    let cache_control = json!({"type": "ephemeral"});
+    assert_eq!(body["system"][0]["text"], "You are a financial assistant.");
+    assert_eq!(body["system"][0]["cache_control"], cache_control);
+    assert_eq!(body["tools"][0]["cache_control"], cache_control);
+    assert_eq!(body["messages"][0]["content"][0]["cache_control"], cache_control);
+}
+
+fn voyage_body(vectors: &[[f64; 2]]) -> serde_json::Value {
+    let data: Vec<serde_json::Value> = vectors
+        .iter()
+        .enumerate()
+        .map(|(index, vector)| json!({"object": "embedding", "index": index, "embedding": vector}))
+        .collect();
+
+    json!({
+        "object": "list",
+        "data": data,
+        "model": "voyage-3-lite",
+        "usage": {"total_tokens": 6}
+    })
+}
+
+#[tokio::test]
+async fn voyage_embeddings_trait() {
+    let server = MockServer::start("https://api.voyageai.com").await;
+    let embeddings_path = "/v1/embeddings";
+    server.mock("POST", embeddings_path, Fixture::inline(200, voyage_body(&[[0.1, 0.2], [0.3, 0.4]])));
+    server.mock("POST", embeddings_path, Fixture::inline(200, voyage_body(&[[0.5, 0.6]])));
+
+    let embeddings: Box<dyn Embeddings> = Box::new(
+        EmbedVoyage::new("voyage-3-lite")
+            .with_base_url(&format!("{}{}", server.url(), embeddings_path))
+            .with_api_key("test_key"),
+    );
+    assert_eq!(embeddings.model_name(), "voyage-3-lite");
+
+    let documents = vec!["dog".to_string(), "cat".to_string()];
+    let vectors = match embeddings.embed_documents(documents).await {
+        Ok(vectors) => vectors,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert_eq!(vectors, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+
+    let query = match embeddings.embed_query("pets").await {
+        Ok(query) => query,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert_eq!(query, vec![0.5, 0.6]);
+
+    let requests = server.requests_to(embeddings_path);
+    assert_eq!(requests[0].header("authorization"), Some("Bearer test_key"));
+    assert_eq!(requests[0].json()["input"], json!(["dog", "cat"]));
+    assert_eq!(requests[0].json()["input_type"], "document");
+    assert_eq!(requests[1].json()["input"], json!(["pets"]));
+    assert_eq!(requests[1].json()["input_type"], "query");
 }
@@ -0,0 +1,440 @@
+//! Offline fixture server for the integration tests.
+//!
+//! `MockServer` is a small HTTP/1.1 server bound to 127.0.0.1 that answers
+//! each registered route with a canned response loaded from
+//! `tests/files/fixtures`. Point a client at it with `with_base_url` and the
+//! tests run without network or API keys.
+//!
+//! Record mode: with `LANGCHAIN_RECORD_FIXTURES=1` the server forwards every
+//! request to the real API given to `MockServer::start` and overwrites the
+//! fixture files with the live responses. The API keys are taken from the
+//! usual environment variables by the clients.
+#![allow(dead_code)]
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use langchain::llm::embed::Embeddings;
+use langchain::llm::error::LlmError;
+use langchain::llm::libs::Provider;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+pub const FIXTURES_DIR: &str = "tests/files/fixtures";
+pub const RECORD_ENV: &str = "LANGCHAIN_RECORD_FIXTURES";
+
+/// Placeholder replaced by the server url in fixture bodies and headers
+pub const BASE_URL_TEMPLATE: &str = "{{base_url}}";
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Fixtures ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    pub status: u16,
+    pub content_type: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Fixture file, relative to `FIXTURES_DIR`, rewritten in record mode
+    pub file: Option<String>,
+    /// Sends the body in pieces of this size to exercise chunk boundaries
+    pub chunk_size: Option<usize>,
+    /// Waits before answering, to simulate a slow model
+    pub delay: Option<Duration>,
+}
+
+impl Fixture {
+    fn from_file(name: &str, content_type: &str) -> Self {
+        let path = format!("{}/{}", FIXTURES_DIR, name);
+        let body = match fs::read(&path) {
+            Ok(body) => body,
+            Err(_) if record_mode() => Vec::new(),
+            Err(e) => panic!("Missing fixture {}: {}", path, e),
+        };
+
+        Self {
+            status: 200,
+            content_type: content_type.to_string(),
+            headers: Vec::new(),
+            body,
+            file: Some(name.to_string()),
+            chunk_size: None,
+            delay: None,
+        }
+    }
+
+    /// JSON response loaded from `tests/files/fixtures/<name>`
+    pub fn json(name: &str) -> Self {
+        Self::from_file(name, "application/json")
+    }
+
+    /// Server-sent events stream loaded from `tests/files/fixtures/<name>`
+    pub fn sse(name: &str) -> Self {
+        Self::from_file(name, "text/event-stream")
+    }
+
+    /// JSON Lines file loaded from `tests/files/fixtures/<name>`
+    pub fn jsonl(name: &str) -> Self {
+        Self::from_file(name, "application/binary")
+    }
+
+    /// JSON response built in the test, never recorded
+    pub fn inline(status: u16, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json".to_string(),
+            headers: Vec::new(),
+            body: body.to_string().into_bytes(),
+            file: None,
+            chunk_size: None,
+            delay: None,
+        }
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn chunked(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size.max(1));
+        self
+    }
+
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Requests ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// A request received by the mock server
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(|value| value.as_str())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_slice(&self.body).unwrap_or(serde_json::Value::Null)
+    }
+
+    fn path_and_query(&self) -> String {
+        match &self.query {
+            Some(query) => format!("{}?{}", self.path, query),
+            None => self.path.clone(),
+        }
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Server ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+type Routes = Arc<Mutex<HashMap<(String, String), VecDeque<Fixture>>>>;
+
+#[derive(Clone)]
+struct State {
+    url: String,
+    upstream: String,
+    routes: Routes,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+pub struct MockServer {
+    state: State,
+}
+
+pub fn record_mode() -> bool {
+    std::env::var(RECORD_ENV).map(|value| value == "1").unwrap_or(false)
+}
+
+impl MockServer {
+    /// Starts a server on a random local port
+    ///
+    /// # Arguments
+    /// * `upstream` - Real API root used in record mode, e.g.
+    ///   `https://api.anthropic.com`
+    pub async fn start(upstream: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Cannot bind mock server");
+        let address = listener.local_addr().expect("No local address");
+
+        let state = State {
+            url: format!("http://{}", address),
+            upstream: upstream.trim_end_matches('/').to_string(),
+            routes: Arc::new(Mutex::new(HashMap::new())),
+            requests: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    handle_connection(stream, state).await;
+                });
+            }
+        });
+
+        Self { state }
+    }
+
+    pub fn url(&self) -> String {
+        self.state.url.clone()
+    }
+
+    /// Queues a response for `method` and `path` (query string excluded)
+    ///
+    /// Responses are served in the order they were added; the last one is
+    /// repeated for any further request on the same route.
+    pub fn mock(&self, method: &str, path: &str, fixture: Fixture) {
+        let mut routes = self.state.routes.lock().unwrap();
+        routes
+            .entry((method.to_uppercase(), path.to_string()))
+            .or_default()
+            .push_back(fixture);
+    }
+
+    pub fn received_requests(&self) -> Vec<RecordedRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// Requests received on `path`, in order
+    pub fn requests_to(&self, path: &str) -> Vec<RecordedRequest> {
+        self.received_requests()
+            .into_iter()
+            .filter(|request| request.path == path)
+            .collect()
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, state: State) {
+    let request = match read_request(&mut stream).await {
+        Some(request) => request,
+        None => return,
+    };
+
+    state.requests.lock().unwrap().push(request.clone());
+
+    let fixture = {
+        let mut routes = state.routes.lock().unwrap();
+        routes
+            .get_mut(&(request.method.clone(), request.path.clone()))
+            .and_then(|queue| {
+                if queue.len() > 1 {
+                    queue.pop_front()
+                } else {
+                    queue.front().cloned()
+                }
+            })
+    };
+
+    let fixture = match fixture {
+        Some(fixture) if record_mode() => record(&state, &request, fixture).await,
+        Some(fixture) => fixture,
+        None => Fixture::inline(404, serde_json::json!({
+            "error": {
+                "message": format!("No fixture for {} {}", request.method, request.path)
+            }
+        })),
+    };
+
+    write_response(&mut stream, &state.url, fixture).await;
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<RecordedRequest> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 8192];
+
+    let header_end = loop {
+        if let Some(position) = find(&buffer, b"\r\n\r\n") {
+            break position;
+        }
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?.to_string();
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let content_length: usize = headers
+        .get("content-length")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0);
+
+    let mut body = buffer[header_end + 4..].to_vec();
+    while body.len() < content_length {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), Some(query.to_string())),
+        None => (target, None),
+    };
+
+    Some(RecordedRequest { method, path, query, headers, body })
+}
+
+async fn write_response(stream: &mut TcpStream, base_url: &str, fixture: Fixture) {
+    if let Some(delay) = fixture.delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    let body = String::from_utf8_lossy(&fixture.body)
+        .replace(BASE_URL_TEMPLATE, base_url)
+        .into_bytes();
+
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        fixture.status,
+        reason(fixture.status),
+        fixture.content_type,
+        body.len(),
+    );
+    for (name, value) in &fixture.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value.replace(BASE_URL_TEMPLATE, base_url)));
+    }
+    head.push_str("\r\n");
+
+    if stream.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+
+    match fixture.chunk_size {
+        Some(size) => {
+            for piece in body.chunks(size) {
+                if stream.write_all(piece).await.is_err() {
+                    return;
+                }
+                let _ = stream.flush().await;
+                tokio::time::sleep(Duration::from_millis(2)).await;
+            }
+        }
+        None => {
+            let _ = stream.write_all(&body).await;
+        }
+    }
+
+    let _ = stream.flush().await;
+    let _ = stream.shutdown().await;
+}
+
+/// Forwards the request to the real API and saves the response as fixture
+async fn record(state: &State, request: &RecordedRequest, mut fixture: Fixture) -> Fixture {
+    let url = format!("{}{}", state.upstream, request.path_and_query());
+    let method = reqwest::Method::from_bytes(request.method.as_bytes())
+        .expect("Invalid method");
+
+    let mut builder = reqwest::Client::new()
+        .request(method, &url)
+        .body(request.body.clone());
+
+    for (name, value) in &request.headers {
+        if !["host", "content-length", "connection"].contains(&name.as_str()) {
+            builder = builder.header(name, value);
+        }
+    }
+
+    let response = builder.send().await.expect("Upstream request failed");
+    fixture.status = response.status().as_u16();
+    if let Some(content_type) = response.headers().get("content-type") {
+        fixture.content_type = content_type.to_str().unwrap_or_default().to_string();
+    }
+    fixture.body = response.bytes().await.expect("Cannot read upstream body").to_vec();
+
+    if let Some(file) = &fixture.file {
+        let path = format!("{}/{}", FIXTURES_DIR, file);
+        if let Some(parent) = std::path::Path::new(&path).parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        fs::write(&path, &fixture.body).expect("Cannot write fixture");
+    }
+
+    fixture
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        529 => "Overloaded",
+        _ => "Unknown",
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Embeddings ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Counts the words of a small vocabulary, so similar texts get close vectors
+pub struct WordCountEmbeddings;
+
+pub static VOCABULARY: [&str; 4] = ["cat", "dog", "fish", "car"];
+
+#[async_trait::async_trait]
+impl Embeddings for WordCountEmbeddings {
+    fn provider(&self) -> Provider {
+        Provider::OpenAI
+    }
+
+    fn model_name(&self) -> String {
+        "word-count".to_string()
+    }
+
+    async fn embed_documents(&self, documents: Vec<String>) -> Result<Vec<Vec<f32>>, LlmError> {
+        Ok(documents
+            .iter()
+            .map(|document| {
+                VOCABULARY
+                    .iter()
+                    .map(|word| document.split_whitespace().filter(|token| token == word).count() as f32)
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+// ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Helpers ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+
+/// Reads a test file and returns it base64 encoded
+pub fn read_base64(path: &str) -> String {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+    let buffer = fs::read(path).unwrap_or_else(|e| panic!("Error reading {}: {}", path, e));
+    STANDARD.encode(buffer)
+}
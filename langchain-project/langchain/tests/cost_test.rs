@@ -0,0 +1,197 @@
+mod common;
+
+use common::{Fixture, MockServer};
+use langchain::llm::chat::ChatModel;
+use langchain::llm::cost::{CostReport, CostTracker, ModelPrice, PriceTable};
+use langchain::llm::libs::TokenUsage;
+use langchain::openai::chat::ChatOpenAI;
+use serde_json::json;
+use std::sync::Arc;
+
+static OPENAI_MODEL: &str = "gpt-4o-mini";
+static COMPLETIONS_PATH: &str = "/v1/chat/completions";
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+}
+
+fn completion(model: &str, prompt_tokens: u32, cached_tokens: u32, completion_tokens: u32) -> Fixture {
+    Fixture::inline(200, json!({
+        "id": "chatcmpl-cost",
+        "object": "chat.completion",
+        "created": 1743872331,
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "Paris"},
+            "finish_reason": "stop"
+        }],
+        "usage": {
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens,
+            "prompt_tokens_details": {"cached_tokens": cached_tokens, "audio_tokens": 0},
+            "completion_tokens_details": {
+                "reasoning_tokens": 0,
+                "audio_tokens": 0,
+                "accepted_prediction_tokens": 0,
+                "rejected_prediction_tokens": 0
+            }
+        }
+    }))
+}
+
+#[test]
+fn provider_usage_is_normalized() {
+    let openai: langchain::openai::libs::Usage = serde_json::from_value(json!({
+        "prompt_tokens": 1200,
+        "completion_tokens": 300,
+        "total_tokens": 1500,
+        "prompt_tokens_details": {"cached_tokens": 1024, "audio_tokens": 50},
+        "completion_tokens_details": {
+            "reasoning_tokens": 200,
+            "audio_tokens": 40,
+            "accepted_prediction_tokens": 0,
+            "rejected_prediction_tokens": 0
+        }
+    })).unwrap();
+    let usage = openai.token_usage();
+    assert_eq!((usage.input_tokens, usage.output_tokens, usage.total_tokens), (1200, 300, 1500));
+    assert_eq!((usage.cache_read_input_tokens, usage.reasoning_tokens), (1024, 200));
+    assert_eq!((usage.audio_input_tokens, usage.audio_output_tokens), (50, 40));
+
+    let responses: langchain::openai::lib_response::Usage = serde_json::from_value(json!({
+        "input_tokens": 36,
+        "input_tokens_details": {"cached_tokens": 12},
+        "output_tokens": 87,
+        "output_tokens_details": {"reasoning_tokens": 64},
+        "total_tokens": 123
+    })).unwrap();
+    assert_eq!(
+        responses.token_usage(),
+        TokenUsage::new(36, 87).with_cache(0, 12).with_reasoning(64),
+    );
+
+    // Gemini reports thinking apart from the candidates
+    let gemini: langchain::gemini::libs::UsageMetadata = serde_json::from_value(json!({
+        "promptTokenCount": 1290,
+        "candidatesTokenCount": 20,
+        "thoughtsTokenCount": 100,
+        "totalTokenCount": 1410,
+        "cachedContentTokenCount": 1000,
+        "promptTokensDetails": [
+            {"modality": "TEXT", "tokenCount": 32},
+            {"modality": "IMAGE", "tokenCount": 258},
+            {"modality": "AUDIO", "tokenCount": 1000}
+        ]
+    })).unwrap();
+    let usage = gemini.token_usage();
+    assert_eq!((usage.input_tokens, usage.output_tokens, usage.total_tokens), (1290, 120, 1410));
+    assert_eq!((usage.cache_read_input_tokens, usage.reasoning_tokens), (1000, 100));
+    assert_eq!((usage.image_input_tokens, usage.audio_input_tokens), (258, 1000));
+
+    let anthropic: langchain::anthropic::libs::Usage = serde_json::from_value(json!({
+        "input_tokens": 20,
+        "output_tokens": 10,
+        "cache_creation_input_tokens": 2000,
+        "cache_read_input_tokens": 500
+    })).unwrap();
+    assert_eq!(anthropic.token_usage(), TokenUsage::new(2520, 10).with_cache(2000, 500));
+}
+
+#[test]
+fn model_price_bills_each_token_kind() {
+    let price = ModelPrice::new(2.0, 8.0)
+        .with_cache(2.5, 0.5)
+        .with_audio(40.0, 80.0);
+    let usage = TokenUsage::new(1_000_000, 500_000)
+        .with_cache(100_000, 400_000)
+        .with_reasoning(200_000)
+        .with_media(50_000, 10_000, 20_000);
+
+    let cost = price.cost(&usage);
+    // 440k plain and 50k image tokens at 2.0, 10k audio tokens at 40.0
+    assert_close(cost.input, 0.98 + 0.4);
+    assert_close(cost.cache_write, 0.25);
+    assert_close(cost.cache_read, 0.2);
+    // 480k text and reasoning tokens at 8.0, 20k audio tokens at 80.0
+    assert_close(cost.output, 3.84 + 1.6);
+    assert_close(cost.total, 1.38 + 0.25 + 0.2 + 5.44);
+
+    let prices = PriceTable::from_json(r#"{
+        "prices": {
+            "gpt-4o": {"input": 2.5, "output": 10.0},
+            "gpt-4o-mini": {"input": 0.15, "output": 0.6, "cache_read": 0.075}
+        }
+    }"#).unwrap();
+    assert_eq!(prices.currency, "USD");
+    assert_eq!(prices.price("gpt-4o-mini-2024-07-18").unwrap().input, 0.15);
+    assert_eq!(prices.price("gpt-4o-2024-08-06").unwrap().input, 2.5);
+    assert!(prices.price("o3-mini").is_none());
+}
+
+#[tokio::test]
+async fn tracker_aggregates_by_session_and_model() {
+    let server = MockServer::start("https://api.openai.com").await;
+    server.mock("POST", COMPLETIONS_PATH, completion("gpt-4o-mini-2024-07-18", 2000, 1000, 100));
+    server.mock("POST", COMPLETIONS_PATH, completion("gpt-4o-mini-2024-07-18", 1000, 0, 50));
+    server.mock("POST", COMPLETIONS_PATH, completion("o3-mini", 100, 0, 10));
+
+    let prices = PriceTable::new()
+        .with_price(OPENAI_MODEL, ModelPrice::new(0.15, 0.6).with_cache(0.15, 0.075));
+    let tracker = CostTracker::new(prices);
+    let llm: Arc<dyn ChatModel> = Arc::new(
+        ChatOpenAI::new(OPENAI_MODEL)
+            .with_base_url(&format!("{}{}", server.url(), COMPLETIONS_PATH))
+            .with_api_key("test_key"),
+    );
+
+    let acme = tracker.track(llm.clone(), "acme");
+    acme.generate_text("Capital of France?").await.unwrap();
+    acme.generate_text("Capital of Spain?").await.unwrap();
+    tracker.track(llm, "globex").generate_text("Capital of Peru?").await.unwrap();
+
+    let acme = tracker.session("acme");
+    assert_eq!(acme.requests, 2);
+    assert_eq!((acme.usage.input_tokens, acme.usage.cache_read_input_tokens), (3000, 1000));
+    // 2000 uncached tokens at 0.15, 1000 cached at 0.075 and 150 output at 0.6
+    assert_close(acme.cost.total, 0.0003 + 0.000075 + 0.00009);
+
+    let globex = tracker.session("globex");
+    assert_eq!((globex.requests, globex.usage.total_tokens), (1, 110));
+    assert_eq!(globex.cost.total, 0.0);
+
+    let report = tracker.report();
+    assert_eq!(report.unpriced_models, vec!["o3-mini".to_string()]);
+    assert_eq!(report.total.requests, 3);
+    assert_eq!(report.models["gpt-4o-mini-2024-07-18"].requests, 2);
+    assert_eq!(report.sessions["acme"].models.len(), 1);
+
+    let json = tracker.to_json();
+    assert_eq!(json["currency"], "USD");
+    assert_eq!(json["sessions"]["globex"]["models"]["o3-mini"]["usage"]["output_tokens"], 10);
+
+    let path = std::env::temp_dir().join(format!("cost-report-{}.json", std::process::id()));
+    tracker.export_json(&path).unwrap();
+    let exported: CostReport = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(exported.sessions.keys().collect::<Vec<_>>(), vec!["acme", "globex"]);
+    assert_eq!(exported.total.usage, report.total.usage);
+    assert_close(exported.total.cost.total, report.total.cost.total);
+    std::fs::remove_file(&path).unwrap();
+
+    tracker.clear_session("acme");
+    assert_eq!(tracker.total().requests, 1);
+}
+
+#[test]
+fn tracker_totals_exceed_u32() {
+    let tracker = CostTracker::new(PriceTable::new().with_price(OPENAI_MODEL, ModelPrice::new(0.15, 0.6)));
+    for _ in 0..3 {
+        tracker.record("acme", OPENAI_MODEL, &TokenUsage::new(2_000_000_000, 1_000_000));
+    }
+
+    let report = tracker.report();
+    assert_eq!(report.total.usage.input_tokens, 6_000_000_000);
+    assert_eq!(report.sessions["acme"].total.usage.total_tokens, 6_003_000_000);
+    assert_eq!(tracker.model(OPENAI_MODEL).usage.output_tokens, 3_000_000);
+}
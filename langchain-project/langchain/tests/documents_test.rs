@@ -0,0 +1,146 @@
+use langchain::documents::error::DocumentError;
+use langchain::documents::libs::{contents, Document};
+use langchain::documents::loaders::{
+    CsvLoader, DocumentLoader, JsonLoader, MarkdownLoader, PdfLoader, TextLoader,
+};
+use langchain::documents::splitters::{
+    MarkdownHeaderTextSplitter, RecursiveCharacterTextSplitter, TextSplitter, TokenTextSplitter,
+};
+use serde_json::json;
+
+#[tokio::test]
+async fn text_loader_and_recursive_splitter() -> Result<(), Box<dyn std::error::Error>> {
+    let loader = TextLoader::new("tests/files/apolo11.txt");
+    let splitter = RecursiveCharacterTextSplitter::new(500, 100);
+
+    let documents = loader.load().await?;
+    assert_eq!(documents.len(), 1);
+    assert_eq!(documents[0].metadata["source"], json!("tests/files/apolo11.txt"));
+
+    let chunks = loader.load_and_split(&splitter).await?;
+    assert!(chunks.len() > 1);
+    for chunk in &chunks {
+        assert!(chunk.content.chars().count() <= 500);
+        assert_eq!(chunk.metadata["source"], json!("tests/files/apolo11.txt"));
+    }
+    assert_eq!(contents(&chunks).len(), chunks.len());
+
+    Ok(())
+}
+
+#[test]
+fn recursive_splitter_overlap() {
+    let splitter = RecursiveCharacterTextSplitter::new(20, 10);
+    let chunks = splitter.split_text("one two three four five six seven eight");
+
+    assert_eq!(chunks, vec![
+        "one two three four",
+        "three four five six",
+        "five six seven eight",
+    ]);
+}
+
+#[test]
+fn recursive_splitter_long_word() {
+    let splitter = RecursiveCharacterTextSplitter::new(4, 0);
+    let chunks = splitter.split_text("ab abcdefghij\n\ncd");
+
+    assert_eq!(chunks, vec!["ab", "abcd", "efgh", "ij", "cd"]);
+}
+
+#[test]
+fn token_splitter_keeps_sentences() {
+    let splitter = TokenTextSplitter::new(8, 0);
+    let text = "The cat sat on the mat today. The dog ran in the park all day.";
+    let chunks = splitter.split_text(text);
+
+    assert_eq!(chunks, vec!["The cat sat on the mat today", "The dog ran in the park all day."]);
+}
+
+#[tokio::test]
+async fn markdown_loader_and_header_splitter() -> Result<(), Box<dyn std::error::Error>> {
+    let documents = MarkdownLoader::new("tests/files/rag_guide.md").load().await?;
+    assert_eq!(documents.len(), 1);
+    assert_eq!(documents[0].metadata["title"], json!("RAG Guide"));
+    assert_eq!(documents[0].metadata["author"], json!("Docs Team"));
+    assert!(documents[0].content.starts_with("# RAG Guide"));
+
+    let chunks = MarkdownHeaderTextSplitter::default().split_documents(&documents);
+    assert_eq!(chunks.len(), 4);
+
+    assert_eq!(chunks[1].metadata["h1"], json!("RAG Guide"));
+    assert_eq!(chunks[1].metadata["h2"], json!("Setup"));
+    assert!(chunks[1].content.contains("# not a header"));
+
+    assert_eq!(chunks[2].metadata["h2"], json!("Setup"));
+    assert_eq!(chunks[2].metadata["h3"], json!("Keys"));
+
+    // A new h2 closes the h3 above it
+    assert_eq!(chunks[3].metadata["h2"], json!("Usage"));
+    assert!(chunks[3].metadata.get("h3").is_none());
+    assert_eq!(chunks[3].metadata["source"], json!("tests/files/rag_guide.md"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn csv_loader_rows() -> Result<(), Box<dyn std::error::Error>> {
+    let documents = CsvLoader::new("tests/files/sample.csv")
+        .with_content_columns(&["Open", "Close"])
+        .with_metadata_columns(&["Date"])
+        .load()
+        .await?;
+
+    assert_eq!(documents.len(), 5);
+    assert_eq!(documents[0].content, "Open: 150.00\nClose: 154.00");
+    assert_eq!(documents[0].metadata["Date"], json!("2023-10-01"));
+    assert_eq!(documents[1].metadata["row"], json!(1));
+
+    let result = CsvLoader::new("tests/files/sample.csv")
+        .with_metadata_columns(&["Ticker"])
+        .load()
+        .await;
+    assert!(matches!(result, Err(DocumentError::MissingField { record: 0, .. })));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn json_loader_records() -> Result<(), Box<dyn std::error::Error>> {
+    let documents = JsonLoader::new("tests/files/articles.jsonl")
+        .with_content_pointer("/text")
+        .with_metadata_pointers(&["/id", "/meta/category"])
+        .load()
+        .await?;
+
+    assert_eq!(documents.len(), 2);
+    assert_eq!(documents[1], Document::new("Croissants are made from a laminated dough.")
+        .with_metadata("source", "tests/files/articles.jsonl")
+        .with_metadata("seq", 1)
+        .with_metadata("id", "a2")
+        .with_metadata("category", "food"));
+
+    let result = JsonLoader::new("tests/files/articles_missing.json")
+        .with_content_pointer("/text")
+        .load()
+        .await;
+    assert!(matches!(result, Err(DocumentError::MissingField { record: 1, .. })));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn pdf_loader_pages() -> Result<(), Box<dyn std::error::Error>> {
+    let pages = PdfLoader::new("tests/files/sample_marketing_campaign.pdf").load().await?;
+    assert!(!pages.is_empty());
+    assert_eq!(pages[0].metadata["page"], json!(1));
+
+    let merged = PdfLoader::new("tests/files/sample_marketing_campaign.pdf")
+        .with_single_document(true)
+        .load()
+        .await?;
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].metadata["total_pages"], pages[0].metadata["total_pages"]);
+
+    Ok(())
+}
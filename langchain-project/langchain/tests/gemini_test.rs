@@ -1,103 +1,105 @@
-use langchain::gemini::ChatGemini;
-use std::fs::File;
-use std::io::{Write, Read};
-use base64::{Engine as _, engine::general_purpose::STANDARD};
+mod common;
+
+use common::{Fixture, MockServer, read_base64};
+use futures::StreamExt;
+use langchain::gemini::chat::ChatGemini;
+use langchain::gemini::embed::EmbedGemini;
+use langchain::gemini::libs::{ChatResponse, TaskType};
+use langchain::llm::chat::ChatModel;
+use langchain::llm::libs::{ChatMessage, ChatOptions, ToolChoice, ToolDefinition};
 use serde_json::json;
+use std::fs::File;
+use std::io::Write;
 use std::path::Path;
 
-static GEMINI_MODEL: &str = "gemini-2.0-flash-exp";
+static GEMINI_MODEL: &str = "gemini-2.0-flash";
 static GEMINI_MODEL_THINK: &str = "gemini-2.0-flash-thinking-exp";
 static GEMINI_MODEL_CACHE: &str = "gemini-1.5-flash-001";
 
-#[tokio::test]
-async fn gemini_simple_shot() {
-    let llm = match ChatGemini::new(GEMINI_MODEL) {
-        Ok(llm) => llm,
-        Err(e) => panic!("Error: {}", e),
-    };
-    let llm = llm.with_temperature(0.9);
-    let llm = llm.with_max_tokens(2048);
-    let llm = llm.with_system_prompt("You are a helpful assistant.");
-    let prompt = "Only say Simple test";
+static GEMINI_UPSTREAM: &str = "https://generativelanguage.googleapis.com/v1beta";
+static GEMINI_UPLOAD_UPSTREAM: &str = "https://generativelanguage.googleapis.com/upload/v1beta";
 
-    let response = match llm.invoke(prompt).await {
-        Ok(response) => response,
-        Err(e) => panic!("Error: {}", e),
-    };
-    
-    if let Some(candidates) = response.candidates {
+fn generate_path(model: &str) -> String {
+    format!("/models/{}:generateContent", model)
+}
+
+fn response_text(response: &ChatResponse) -> String {
+    let mut result = String::new();
+    if let Some(candidates) = &response.candidates {
         for candidate in candidates {
-            if let Some(content) = candidate.content {
-                for part in content.parts {
-                    if let Some(text) = part.text {
-                        let text_l = text.to_lowercase();
-                        let possible_values = vec![
-                            "simple test",
-                            "simple test\n",
-                            "simple test.\n",
-                            "simple test."
-                        ];
-
-                        // Count how many matches we have
-                        let match_count = possible_values.iter()
-                            .filter(|&&val| val == text_l)
-                            .count();
-                        assert_eq!(
-                            match_count, 
-                            1, 
-                            "Text '{}' did not match any of the expected values", 
-                            text_l
-                        );
+            if let Some(content) = &candidate.content {
+                for part in &content.parts {
+                    if let Some(text) = &part.text {
+                        result.push_str(text);
                     }
                 }
             }
         }
-    };
+    }
+    result
+}
+
+fn assert_one_of(text: &str, possible_values: &[&str]) {
+    let text_l = text.to_lowercase();
+    assert!(
+        possible_values.contains(&text_l.as_str()),
+        "Text '{}' did not match any of the expected values",
+        text_l
+    );
+}
+
+/// Upload and cache calls read the key from the environment
+fn ensure_api_key() {
+    if std::env::var("GEMINI_API_KEY").is_err() {
+        std::env::set_var("GEMINI_API_KEY", "test_key");
+    }
 }
 
 #[tokio::test]
-async fn gemini_compare_images() {
-    let llm = match ChatGemini::new(GEMINI_MODEL) {
-        Ok(llm) => llm,
-        Err(e) => panic!("Error: {}", e),
-    };
+async fn gemini_simple_shot() {
+    let server = MockServer::start(GEMINI_UPSTREAM).await;
+    server.mock("POST", &generate_path(GEMINI_MODEL), Fixture::json("gemini/simple_shot.json"));
+
+    let llm = ChatGemini::new(GEMINI_MODEL)
+        .with_base_url(&server.url())
+        .with_temperature(0.9)
+        .with_max_tokens(2048)
+        .with_system_prompt("You are a helpful assistant.");
+    let prompt = "Only say Simple test";
 
-    // Read first image into a byte vector
-    let mut file_01 = match File::open("tests/files/image01.jpg") {
-        Ok(file) => file,
+    let response = match llm.invoke(prompt).await {
+        Ok(response) => response,
         Err(e) => panic!("Error: {}", e),
     };
 
-    let mut buffer_01 = Vec::new();
-    match file_01.read_to_end(&mut buffer_01) {
-        Ok(_) => {},
-        Err(e) => panic!("Error: {}", e),
-    }
-    
-    // Read second image into a byte vector
-    let mut file_02 = match File::open("tests/files/image03.png") {
-        Ok(file) => file,
-        Err(e) => panic!("Error: {}", e),
-    };
+    assert_one_of(
+        &response_text(&response),
+        &["simple test", "simple test\n", "simple test.\n", "simple test."],
+    );
 
-    let mut buffer_02 = Vec::new();
-    match file_02.read_to_end(&mut buffer_02) {
-        Ok(_) => {},
-        Err(e) => panic!("Error: {}", e),
-    }
+    let requests = server.requests_to(&generate_path(GEMINI_MODEL));
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].query.as_deref().unwrap_or_default().starts_with("key="));
+
+    let body = requests[0].json();
+    assert_eq!(body["systemInstruction"]["parts"][0]["text"], "You are a helpful assistant.");
+    assert_eq!(body["generationConfig"]["maxOutputTokens"], 2048);
+    assert_eq!(body["contents"][0]["parts"][0]["text"], prompt);
+}
 
-    // Convert to base64
-    let base64_string_01 = STANDARD.encode(&buffer_01);
-    let base64_string_02 = STANDARD.encode(&buffer_02);
+#[tokio::test]
+async fn gemini_compare_images() {
+    let server = MockServer::start(GEMINI_UPSTREAM).await;
+    server.mock("POST", &generate_path(GEMINI_MODEL), Fixture::json("gemini/compare_images.json"));
+
+    let base64_string_01 = read_base64("tests/files/image01.jpg");
+    let base64_string_02 = read_base64("tests/files/image03.png");
+
+    let llm = ChatGemini::new(GEMINI_MODEL)
+        .with_base_url(&server.url())
+        .with_inline_data(&base64_string_01, "image/jpeg")
+        .with_inline_data(&base64_string_02, "image/png");
 
-    let llm = llm.with_image(
-        &base64_string_01,
-        "image/jpeg",
-    );
-    let llm = llm.with_image(
-        &base64_string_02,
-        "image/png",
-    );
     let prompt = "Compare the two pictures provided. \
             Which of the images shows an office with people working, \
             the first or the second? Just answer: FIRST or SECOND.";
@@ -107,45 +109,39 @@ async fn gemini_compare_images() {
         Err(e) => panic!("Error: {}", e),
     };
 
-    if let Some(candidates) = response.candidates {
-        for candidate in candidates {
-            if let Some(content) = candidate.content {
-                for part in content.parts {
-                    if let Some(text) = part.text {
-                        let text_l = text.to_lowercase();
-                        let possible_values = vec![
-                            "first\n",
-                            "first",
-                        ];
-
-                        // Count how many matches we have
-                        let match_count = possible_values.iter()
-                            .filter(|&&val| val == text_l)
-                            .count();
-                        assert_eq!(
-                            match_count, 
-                            1, 
-                            "Text '{}' did not match any of the expected values", 
-                            text_l
-                        );
-                    }
-                }
-            }
-        }
-    };
+    assert_one_of(&response_text(&response), &["first\n", "first"]);
+
+    let body = server.requests_to(&generate_path(GEMINI_MODEL))[0].json();
+    assert_eq!(body["contents"][0]["parts"][0]["inline_data"]["mime_type"], "image/jpeg");
+    assert_eq!(body["contents"][1]["parts"][0]["inline_data"]["mime_type"], "image/png");
 }
 
 #[tokio::test]
 async fn gemini_upload_image() {
-    let llm = match ChatGemini::new(GEMINI_MODEL) {
-        Ok(llm) => llm,
-        Err(e) => panic!("Error: {}", e),
-    };
+    ensure_api_key();
 
-    let file_path = "tests/files/image03.png";
-    let file_mime = "image/png";
+    let server = MockServer::start(GEMINI_UPSTREAM).await;
+    let upload_server = MockServer::start(GEMINI_UPLOAD_UPSTREAM).await;
 
-    let llm = match llm.media_upload(file_path, file_mime).await {
+    upload_server.mock(
+        "POST",
+        "/files",
+        Fixture::json("gemini/upload_start.json")
+            .with_header("x-goog-upload-url", "{{base_url}}/files/session"),
+    );
+    upload_server.mock("POST", "/files/session", Fixture::json("gemini/upload_finalize.json"));
+    server.mock("POST", &generate_path(GEMINI_MODEL), Fixture::json("gemini/upload_image.json"));
+
+    let llm = ChatGemini::new(GEMINI_MODEL)
+        .with_base_url(&server.url())
+        .with_upload_url(&upload_server.url());
+
+    let llm = match llm.media_upload(
+        Some("tests/files/image03.png"),
+        None,
+        "image03",
+        "image/png",
+    ).await {
         Ok(llm) => llm,
         Err(e) => panic!("Error: {}", e),
     };
@@ -158,42 +154,32 @@ async fn gemini_upload_image() {
         Err(e) => panic!("Error: {}", e),
     };
 
-    if let Some(candidates) = response.candidates {
-        for candidate in candidates {
-            if let Some(content) = candidate.content {
-                for part in content.parts {
-                    if let Some(text) = part.text {
-                        let text_l = text.to_lowercase();
-                        let possible_values = vec![
-                            "fantastic\n",
-                            "fantastic",
-                            "fantastic.\n",
-                            "fantastic.",
-                        ];
-
-                        // Count how many matches we have
-                        let match_count = possible_values.iter()
-                            .filter(|&&val| val == text_l)
-                            .count();
-                        assert_eq!(
-                            match_count, 
-                            1, 
-                            "Text '{}' did not match any of the expected values", 
-                            text_l
-                        );
-                    }
-                }
-            }
-        }
-    };
+    assert_one_of(
+        &response_text(&response),
+        &["fantastic\n", "fantastic", "fantastic.\n", "fantastic."],
+    );
+
+    let start = &upload_server.requests_to("/files")[0];
+    assert_eq!(start.header("x-goog-upload-command"), Some("start"));
+    assert_eq!(start.header("x-goog-upload-header-content-type"), Some("image/png"));
+
+    let upload = &upload_server.requests_to("/files/session")[0];
+    assert_eq!(upload.body, std::fs::read("tests/files/image03.png").unwrap());
+
+    let body = server.requests_to(&generate_path(GEMINI_MODEL))[0].json();
+    assert_eq!(
+        body["contents"][0]["parts"][0]["file_data"]["file_uri"],
+        "https://generativelanguage.googleapis.com/v1beta/files/8xk2p0c1m9qe"
+    );
 }
 
 #[tokio::test]
 async fn gemini_multiple_turns() {
-    let llm = match ChatGemini::new(GEMINI_MODEL) {
-        Ok(llm) => llm,
-        Err(e) => panic!("Error: {}", e),
-    };
+    let server = MockServer::start(GEMINI_UPSTREAM).await;
+    server.mock("POST", &generate_path(GEMINI_MODEL), Fixture::json("gemini/multiple_turns_1.json"));
+    server.mock("POST", &generate_path(GEMINI_MODEL), Fixture::json("gemini/multiple_turns_2.json"));
+
+    let llm = ChatGemini::new(GEMINI_MODEL).with_base_url(&server.url());
 
     let prompt = "Please answer the following question with only \"yes\" or \"no\": Is the sky blue during a clear day?";
 
@@ -202,30 +188,24 @@ async fn gemini_multiple_turns() {
         Err(e) => panic!("Error: {}", e),
     };
 
-    let mut response_model = String::new();
-
-    if let Some(candidates) = response.candidates {
-        for candidate in candidates {
-            if let Some(content) = candidate.content {
-                for part in content.parts {
-                    if let Some(text) = part.text {
-                        response_model = text.to_string();
-                    }
-                }
-            }
-        }
-    };
+    assert_one_of(&response_text(&response), &["yes\n", "yes"]);
 
     let chat_history = match response.chat_history {
         Some(chat_history) => chat_history,
-        None => {
-            println!("No chat history");
-            Vec::new()
-        }
+        None => panic!("No chat history"),
     };
 
-    let llm = llm.with_chat_history(chat_history);
-    let llm = llm.with_assistant_response(&response_model);
+    let assistant_parts = match response.candidates {
+        Some(candidates) => match &candidates[0].content {
+            Some(content) => content.parts.clone(),
+            None => panic!("No content in candidate"),
+        },
+        None => panic!("No response candidates available"),
+    };
+
+    let llm = llm
+        .with_chat_history(chat_history)
+        .with_assistant_response(assistant_parts);
 
     let prompt = "And during the night? Answer only with \"yes\" or \"no\".";
 
@@ -234,74 +214,50 @@ async fn gemini_multiple_turns() {
         Err(e) => panic!("Error: {}", e),
     };
 
-    println!("\n#### Multiple Turn 2 ####");
-    if let Some(candidates) = response.candidates {
-        for candidate in candidates {
-            if let Some(content) = candidate.content {
-                for part in content.parts {
-                    if let Some(text) = part.text {
-                        let text_l = text.to_lowercase();
-                        let possible_values = vec![
-                            "no\n",
-                            "mo"
-                        ];
-
-                        // Count how many matches we have
-                        let match_count = possible_values.iter()
-                            .filter(|&&val| val == text_l)
-                            .count();
-                        assert_eq!(
-                            match_count, 
-                            1, 
-                            "Text '{}' did not match any of the expected values", 
-                            text_l
-                        );
-                    }
-                }
-            }
-        }
-    };
+    assert_one_of(&response_text(&response), &["no\n", "no"]);
+
+    let requests = server.requests_to(&generate_path(GEMINI_MODEL));
+    assert_eq!(requests.len(), 2);
+
+    let body = requests[1].json();
+    assert_eq!(body["contents"].as_array().unwrap().len(), 3);
+    assert_eq!(body["contents"][1]["role"], "model");
 }
 
 #[tokio::test]
 async fn gemini_upload_cache() {
-    let llm = match ChatGemini::new(GEMINI_MODEL_CACHE) {
-        Ok(llm) => llm,
-        Err(e) => panic!("Error: {}", e),
-    };
+    ensure_api_key();
 
-    // Read first file into a byte vector
-    let mut file_01 = match File::open("tests/files/apolo11.txt") {
-        Ok(file) => file,
-        Err(e) => panic!("Error: {}", e),
-    };
-    let mut buffer_01 = Vec::new();
-    match file_01.read_to_end(&mut buffer_01) {
-        Ok(_) => {},
-        Err(e) => panic!("Error: {}", e),
-    }
-    
-    // Convert to base64
-    let base64_string_01 = STANDARD.encode(&buffer_01);
+    let server = MockServer::start(GEMINI_UPSTREAM).await;
+    server.mock("POST", "/cachedContents", Fixture::json("gemini/cache_create.json"));
+    server.mock("POST", &generate_path(GEMINI_MODEL_CACHE), Fixture::json("gemini/cached_answer.json"));
 
+    let llm = ChatGemini::new(GEMINI_MODEL_CACHE).with_base_url(&server.url());
+
+    let base64_string_01 = read_base64("tests/files/apolo11.txt");
     let system_instruction = "You are an expert at analyzing transcripts.";
-    
+
     let cache_url = match llm.clone().cache_upload(
-            base64_string_01,
-            "text/plain",
-            system_instruction
-        ).await {
-            Ok(cache_url) => cache_url,
-            Err(e) => panic!("Error: {}", e),
+        base64_string_01,
+        "text/plain",
+        system_instruction,
+        300,
+    ).await {
+        Ok(cache_url) => cache_url,
+        Err(e) => panic!("Error: {}", e),
     };
 
     assert!(
-        cache_url.starts_with("cachedContents/"), 
-        "Cache url '{}' doesn't start with 'cachedContents/'", 
+        cache_url.starts_with("cachedContents/"),
+        "Cache url '{}' doesn't start with 'cachedContents/'",
         cache_url
     );
 
-    let llm = llm.with_cached_content(cache_url);
+    let cache_body = server.requests_to("/cachedContents")[0].json();
+    assert_eq!(cache_body["model"], format!("models/{}", GEMINI_MODEL_CACHE));
+    assert_eq!(cache_body["ttl"], "300s");
+
+    let llm = llm.with_cached_content(cache_url.clone());
     let prompt = "Is this a transcript of Apollo 11? Answer only with yes or no.";
 
     let response = match llm.invoke(prompt).await {
@@ -309,28 +265,16 @@ async fn gemini_upload_cache() {
         Err(e) => panic!("Error: {}", e),
     };
 
-    if let Some(candidates) = response.candidates {
-        for candidate in candidates {
-            if let Some(content) = candidate.content {
-                for part in content.parts {
-                    if let Some(text) = part.text {
-                        let _text = text.to_lowercase().replace("\n", "");
-                        let _text = _text.trim();
-                        assert_eq!(_text, "yes");
-                    }
-                }
-            }
-        }
-    };
+    assert_eq!(response_text(&response).to_lowercase().trim(), "yes");
+
+    let body = server.requests_to(&generate_path(GEMINI_MODEL_CACHE))[0].json();
+    assert_eq!(body["cachedContent"], cache_url);
 }
 
 #[tokio::test]
 async fn gemini_response_schema() {
-
-    let llm = match ChatGemini::new(GEMINI_MODEL) {
-        Ok(llm) => llm,
-        Err(e) => panic!("Error: {}", e),
-    };
+    let server = MockServer::start(GEMINI_UPSTREAM).await;
+    server.mock("POST", &generate_path(GEMINI_MODEL), Fixture::json("gemini/response_schema.json"));
 
     let response_schema = json!({
         "type":"object",
@@ -344,7 +288,10 @@ async fn gemini_response_schema() {
             }
         }
     });
-    let llm = llm.with_response_schema(response_schema);
+
+    let llm = ChatGemini::new(GEMINI_MODEL)
+        .with_base_url(&server.url())
+        .with_response_schema(response_schema.clone());
     let prompt = "Please answer the following question with only yes or no: Is the sky blue during a clear day?";
 
     let response = match llm.invoke(prompt).await {
@@ -352,30 +299,26 @@ async fn gemini_response_schema() {
         Err(e) => panic!("Error: {}", e),
     };
 
-    if let Some(candidates) = response.candidates {
-        for candidate in candidates {
-            if let Some(content) = candidate.content {
-                for part in content.parts {
-                    if let Some(text) = part.text {
-                        let json_data = match serde_json::from_str::<serde_json::Value>(&text) {
-                            Ok(json) => json,
-                            Err(e) => panic!("Error: {}", e),
-                        };
-                        assert_eq!(json_data["response"], "yes");
-                    }
-                }
-            }
-        }
+    let json_data = match serde_json::from_str::<serde_json::Value>(&response_text(&response)) {
+        Ok(json) => json,
+        Err(e) => panic!("Error: {}", e),
     };
+    assert_eq!(json_data["response"], "yes");
+
+    let body = server.requests_to(&generate_path(GEMINI_MODEL))[0].json();
+    assert_eq!(body["generationConfig"]["responseSchema"], response_schema);
+    assert_eq!(body["generationConfig"]["responseMimeType"], "application/json");
 }
 
 #[tokio::test]
 async fn gemini_think_mode() {
+    let server = MockServer::start(GEMINI_UPSTREAM).await;
+    server.mock("POST", &generate_path(GEMINI_MODEL_THINK), Fixture::json("gemini/think_mode.json"));
 
-    let llm = match ChatGemini::new(GEMINI_MODEL_THINK) {
-        Ok(llm) => llm,
-        Err(e) => panic!("Error: {}", e),
-    };
+    let llm = ChatGemini::new(GEMINI_MODEL_THINK)
+        .with_base_url(&server.url())
+        .with_temperature(1.0)
+        .with_top_k(64);
 
     let prompt = "What is the geometric monthly fecal coliform mean of a \
                   distribution system with the following FC counts: \
@@ -383,27 +326,13 @@ async fn gemini_think_mode() {
                   into a NPDES DMR, therefore, round to the \
                   nearest whole number.";
 
-    let llm = llm.with_temperature(1.0);
-    let llm = llm.with_top_k(64);
-    
     let response = match llm.invoke(prompt).await {
         Ok(response) => response,
         Err(e) => panic!("Error: {}", e),
     };
 
-    let mut result = String::from("");
-
-    if let Some(candidates) = response.candidates {
-        for candidate in candidates {
-            if let Some(content) = candidate.content {
-                for part in content.parts {
-                    if let Some(text) = part.text {
-                        result.push_str(&text);
-                    }
-                }
-            }
-        }
-    };
+    let result = response_text(&response);
+    assert!(result.contains("18"), "Unexpected answer: {}", result);
 
     // Save result to file.md
     let file_path = "tests/output/test-gemini-think-mode.md";
@@ -420,7 +349,189 @@ async fn gemini_think_mode() {
     // Check if the file exists
     let path = Path::new(file_path);
     assert!(path.exists(), "File was not created!");
+}
+
+#[tokio::test]
+async fn gemini_stream() {
+    let server = MockServer::start(GEMINI_UPSTREAM).await;
+    server.mock(
+        "POST",
+        &format!("/models/{}:streamGenerateContent", GEMINI_MODEL),
+        Fixture::sse("gemini/stream.sse").chunked(7),
+    );
+
+    let llm = ChatGemini::new(GEMINI_MODEL).with_base_url(&server.url());
 
-    // Check if the file has content
-    assert!(!result.is_empty(), "File is empty!");
-}
\ No newline at end of file
+    let stream = llm.stream_response("Why is the sky blue?".to_string());
+    futures::pin_mut!(stream);
+
+    let mut chunks = 0;
+    let mut result = String::new();
+    while let Some(response) = stream.next().await {
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => panic!("Error: {}", e),
+        };
+        chunks += 1;
+        result.push_str(&response_text(&response));
+    }
+
+    assert_eq!(chunks, 3);
+    assert_eq!(result, "The sky  is blue because of Rayleigh scattering.\n");
+
+    let requests = server.requests_to(&format!("/models/{}:streamGenerateContent", GEMINI_MODEL));
+    assert!(requests[0].query.as_deref().unwrap_or_default().starts_with("alt=sse"));
+}
+
+#[tokio::test]
+async fn gemini_stream_error() {
+    let server = MockServer::start(GEMINI_UPSTREAM).await;
+    server.mock(
+        "POST",
+        &format!("/models/{}:streamGenerateContent", GEMINI_MODEL),
+        Fixture::json("gemini/error_400.json").with_status(400),
+    );
+
+    let llm = ChatGemini::new(GEMINI_MODEL).with_base_url(&server.url());
+
+    let stream = llm.stream_response("Why is the sky blue?".to_string());
+    futures::pin_mut!(stream);
+
+    let items: Vec<_> = stream.collect().await;
+    assert_eq!(items.len(), 1);
+    match &items[0] {
+        Err(e) => assert!(e.to_string().contains("API key not valid")),
+        Ok(_) => panic!("Expected an error item"),
+    }
+}
+
+#[tokio::test]
+async fn gemini_request_error() {
+    let server = MockServer::start(GEMINI_UPSTREAM).await;
+    server.mock(
+        "POST",
+        &generate_path(GEMINI_MODEL),
+        Fixture::json("gemini/error_400.json").with_status(400),
+    );
+
+    let llm = ChatGemini::new(GEMINI_MODEL)
+        .with_base_url(&server.url())
+        .with_max_retries(0);
+
+    assert!(llm.invoke("Hello").await.is_err());
+}
+
+#[tokio::test]
+async fn gemini_chat_model_tools() {
+    let server = MockServer::start(GEMINI_UPSTREAM).await;
+    server.mock("POST", &generate_path(GEMINI_MODEL), Fixture::json("gemini/function_call.json"));
+    server.mock("POST", &generate_path(GEMINI_MODEL), Fixture::json("gemini/function_answer.json"));
+
+    let llm: Box<dyn ChatModel> = Box::new(
+        ChatGemini::new(GEMINI_MODEL).with_base_url(&server.url())
+    );
+
+    let tools = vec![ToolDefinition {
+        name: "find_theaters".to_string(),
+        description: "Find theaters based on location and optionally movie title".to_string(),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "location": {"type": "string"},
+                "movie": {"type": "string"}
+            },
+            "required": ["location"]
+        }),
+    }];
+    let options = ChatOptions::new()
+        .with_tools(tools)
+        .with_tool_choice(ToolChoice::Auto);
+
+    let mut messages = vec![
+        ChatMessage::system("You help people find movies."),
+        ChatMessage::user("Which theaters in Mountain View show the Barbie movie?"),
+    ];
+
+    let response = match llm.generate(&messages, &options).await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert!(response.has_tool_calls());
+    let tool_call = response.tool_calls()[0].clone();
+    assert_eq!(tool_call.name, "find_theaters");
+    assert_eq!(tool_call.arguments["movie"], "Barbie");
+
+    messages.push(response.message.clone());
+    messages.push(ChatMessage::tool_result(
+        &tool_call.id,
+        &tool_call.name,
+        r#"{"theaters": ["AMC Mountain View 16", "Regal Edwards 14"]}"#,
+    ));
+
+    let response = match llm.generate(&messages, &options).await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert!(response.text().contains("AMC Mountain View 16"));
+    assert_eq!(response.usage.map(|usage| usage.input_tokens), Some(180));
+
+    let requests = server.requests_to(&generate_path(GEMINI_MODEL));
+    let first = requests[0].json();
+    assert_eq!(first["tools"][0]["functionDeclarations"][0]["name"], "find_theaters");
+    assert_eq!(first["systemInstruction"]["parts"][0]["text"], "You help people find movies.");
+
+    let second = requests[1].json();
+    assert_eq!(second["contents"][1]["parts"][0]["functionCall"]["name"], "find_theaters");
+    assert_eq!(
+        second["contents"][2]["parts"][0]["functionResponse"]["response"]["content"]["theaters"][0],
+        "AMC Mountain View 16"
+    );
+}
+
+#[tokio::test]
+async fn gemini_embed_documents() {
+    let server = MockServer::start(GEMINI_UPSTREAM).await;
+    let batch_path = "/models/text-embedding-004:batchEmbedContents";
+    server.mock("POST", batch_path, Fixture::inline(200, json!({
+        "embeddings": [{"values": [0.1, 0.2]}, {"values": [0.3, 0.4]}]
+    })));
+    server.mock("POST", batch_path, Fixture::inline(200, json!({
+        "embeddings": [{"values": [0.5, 0.6]}]
+    })));
+
+    let embed = EmbedGemini::new("text-embedding-004")
+        .with_base_url(&server.url())
+        .with_api_key("test_key")
+        .with_task_type(TaskType::RetrievalDocument)
+        .with_output_dimensionality(2)
+        .with_batch_size(2, 10_000)
+        .with_max_concurrency(1);
+
+    let documents = vec![
+        "The quick brown fox".to_string(),
+        "jumps over".to_string(),
+        "the lazy dog".to_string(),
+    ];
+    let vectors = match embed.embed_documents(documents).await {
+        Ok(vectors) => vectors,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(vectors, vec![vec![0.1, 0.2], vec![0.3, 0.4], vec![0.5, 0.6]]);
+
+    let requests = server.requests_to(batch_path);
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].query.as_deref(), Some("key=test_key"));
+
+    let first = requests[0].json();
+    assert_eq!(first["requests"].as_array().map(|requests| requests.len()), Some(2));
+    assert_eq!(first["requests"][0]["model"], "models/text-embedding-004");
+    assert_eq!(first["requests"][0]["content"]["parts"][0]["text"], "The quick brown fox");
+    assert_eq!(first["requests"][1]["task_type"], "RETRIEVAL_DOCUMENT");
+    assert_eq!(first["requests"][1]["output_dimensionality"], 2);
+
+    let second = requests[1].json();
+    assert_eq!(second["requests"][0]["content"]["parts"][0]["text"], "the lazy dog");
+}
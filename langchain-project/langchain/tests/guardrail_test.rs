@@ -0,0 +1,208 @@
+mod common;
+
+use common::{Fixture, MockServer};
+use langchain::agents::error::AgentError;
+use langchain::agents::guardrails::{AgentGuardrail, FunctionGuardrail, GuardrailResult};
+use langchain::agents::libs::Agent;
+use langchain::agents::tools::FunctionTool;
+use langchain::llm::libs::ChatMessage;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+static OPENAI_MODEL: &str = "gpt-4o-mini";
+static OPENAI_UPSTREAM: &str = "https://api.openai.com";
+static COMPLETIONS_PATH: &str = "/v1/chat/completions";
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct WeatherArgs {
+    /// The city and state, e.g. San Francisco, CA
+    location: String,
+}
+
+fn completion(content: &str) -> Fixture {
+    Fixture::inline(200, json!({
+        "id": "chatcmpl-guardrail",
+        "object": "chat.completion",
+        "created": 1743872331,
+        "model": OPENAI_MODEL,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": content},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 10, "total_tokens": 20}
+    }))
+}
+
+fn homework_guardrail() -> FunctionGuardrail {
+    FunctionGuardrail::new("no_homework", |text: String| async move {
+        if text.contains("homework") {
+            GuardrailResult::trip(json!({"reason": "Homework request"}))
+        } else {
+            GuardrailResult::pass(Value::Null)
+        }
+    })
+}
+
+async fn openai_agent(server: &MockServer) -> Agent {
+    let mut agent = Agent::new(
+        "WeatherAgent".to_string(),
+        "You are a weather assistant.".to_string(),
+    ).await;
+    agent.model = Some(OPENAI_MODEL.to_string());
+    agent.with_base_url(&format!("{}{}", server.url(), COMPLETIONS_PATH))
+}
+
+#[tokio::test]
+async fn guardrail_input_trips_before_model() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock(
+        "POST",
+        COMPLETIONS_PATH,
+        completion("Sure, here is your homework.").delayed(Duration::from_secs(5)),
+    );
+
+    let agent = openai_agent(&server).await.with_input_guardrail(homework_guardrail());
+
+    let start = Instant::now();
+    match agent.run("Can you do my math homework?").await {
+        Err(AgentError::InputGuardrailTripwireTriggered(result)) => {
+            assert_eq!(result.guardrail_name, "no_homework");
+            assert_eq!(result.output_info["reason"], "Homework request");
+        }
+        other => panic!("Expected InputGuardrailTripwireTriggered, got {:?}", other),
+    }
+
+    // The run does not wait for the pending model call
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn guardrail_results_recorded() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, completion("It is sunny in Boston."));
+
+    let length_guardrail = FunctionGuardrail::new("max_length", |text: String| async move {
+        GuardrailResult::pass(json!({"length": text.len()}))
+    });
+
+    let agent = openai_agent(&server).await
+        .with_input_guardrail(homework_guardrail())
+        .with_output_guardrail(length_guardrail);
+
+    let result = match agent.run_messages(vec![
+        ChatMessage::user("What is the weather like in Boston today?"),
+    ]).await {
+        Ok(result) => result,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(result.final_output, "It is sunny in Boston.");
+    assert_eq!(result.input_guardrail_results.len(), 1);
+    assert_eq!(result.input_guardrail_results[0].guardrail_name, "no_homework");
+    assert!(!result.input_guardrail_results[0].tripwire_triggered);
+    assert_eq!(result.output_guardrail_results.len(), 1);
+    assert_eq!(result.output_guardrail_results[0].output_info["length"], 22);
+}
+
+#[tokio::test]
+async fn guardrail_slow_input_blocks_tools() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/functions.json"));
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let calls_tool = calls.clone();
+    let weather_tool = FunctionTool::new(
+        "get_current_weather",
+        "Get the current weather in a given location",
+        move |args: WeatherArgs| {
+            let calls = calls_tool.clone();
+            async move {
+                calls.lock().unwrap().push(args.location);
+                Ok::<_, String>("22 degrees and sunny")
+            }
+        },
+    );
+
+    let slow_guardrail = FunctionGuardrail::new("slow_check", |_text: String| async move {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        GuardrailResult::trip(json!({"reason": "Blocked"}))
+    });
+
+    let agent = openai_agent(&server).await
+        .with_tool(weather_tool)
+        .with_input_guardrail(slow_guardrail);
+
+    match agent.run("What is the weather like in Boston today?").await {
+        Err(AgentError::InputGuardrailTripwireTriggered(result)) => {
+            assert_eq!(result.guardrail_name, "slow_check");
+        }
+        other => panic!("Expected InputGuardrailTripwireTriggered, got {:?}", other),
+    }
+
+    // The model answered first, but its tool calls never run
+    assert_eq!(server.requests_to(COMPLETIONS_PATH).len(), 1);
+    assert!(calls.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn guardrail_output_trips() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, completion("Call 555-0100 for the forecast."));
+
+    let phone_guardrail = FunctionGuardrail::new("no_phone_numbers", |text: String| async move {
+        if text.chars().any(|c| c.is_ascii_digit()) {
+            GuardrailResult::trip(json!({"reason": "Contains a phone number"}))
+        } else {
+            GuardrailResult::pass(Value::Null)
+        }
+    });
+
+    let agent = openai_agent(&server).await.with_output_guardrail(phone_guardrail);
+
+    match agent.run("What is the weather like in Boston today?").await {
+        Err(AgentError::OutputGuardrailTripwireTriggered(result)) => {
+            assert_eq!(result.guardrail_name, "no_phone_numbers");
+            assert_eq!(result.output_info["reason"], "Contains a phone number");
+        }
+        other => panic!("Expected OutputGuardrailTripwireTriggered, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn guardrail_agent_check() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, completion("Boston is sunny today."));
+
+    let guardrail_server = MockServer::start(OPENAI_UPSTREAM).await;
+    guardrail_server.mock(
+        "POST",
+        COMPLETIONS_PATH,
+        completion("```json\n{\"tripwire_triggered\": true, \"reasoning\": \"Asks for homework\"}\n```"),
+    );
+
+    let mut checker = Agent::new(
+        "HomeworkChecker".to_string(),
+        "Check if the user is asking you to do their homework.".to_string(),
+    ).await;
+    checker.model = Some(OPENAI_MODEL.to_string());
+    let checker = checker.with_base_url(&format!("{}{}", guardrail_server.url(), COMPLETIONS_PATH));
+
+    let agent = openai_agent(&server).await.with_input_guardrail(AgentGuardrail::new(checker));
+
+    match agent.run("Write my essay about the weather").await {
+        Err(AgentError::InputGuardrailTripwireTriggered(result)) => {
+            assert_eq!(result.guardrail_name, "HomeworkChecker");
+            assert_eq!(result.output_info["reasoning"], "Asks for homework");
+        }
+        other => panic!("Expected InputGuardrailTripwireTriggered, got {:?}", other),
+    }
+
+    let request = guardrail_server.requests_to(COMPLETIONS_PATH)[0].json();
+    let system = request["messages"][0].to_string();
+    assert!(system.contains("tripwire_triggered"));
+    assert!(request.to_string().contains("Write my essay about the weather"));
+}
@@ -0,0 +1,275 @@
+mod common;
+
+use async_trait::async_trait;
+use common::{Fixture, MockServer};
+use langchain::agents::error::AgentError;
+use langchain::agents::guardrails::{FunctionGuardrail, GuardrailResult};
+use langchain::agents::hooks::AgentHooks;
+use langchain::agents::libs::{Agent, AgentType};
+use langchain::agents::tools::{FunctionTool, ToolResult};
+use langchain::agents::trace::SpanKind;
+use langchain::llm::libs::{ChatMessage, ModelResponse, ToolCall};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+static OPENAI_MODEL: &str = "gpt-4o-mini";
+static OPENAI_UPSTREAM: &str = "https://api.openai.com";
+static COMPLETIONS_PATH: &str = "/v1/chat/completions";
+
+static ANTHROPIC_MODEL: &str = "claude-3-5-sonnet-20241022";
+static ANTHROPIC_UPSTREAM: &str = "https://api.anthropic.com";
+static MESSAGES_PATH: &str = "/v1/messages";
+
+#[derive(Debug, Default)]
+struct RecordingHooks {
+    events: Mutex<Vec<String>>,
+}
+
+impl RecordingHooks {
+    fn push(&self, event: String) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+#[async_trait]
+impl AgentHooks for RecordingHooks {
+    async fn on_agent_start(&self, agent_name: &str) {
+        self.push(format!("agent_start {}", agent_name));
+    }
+
+    async fn on_agent_end(&self, agent_name: &str, output: &str) {
+        self.push(format!("agent_end {} {}", agent_name, output.contains("248.50")));
+    }
+
+    async fn on_llm_request(&self, agent_name: &str, messages: &[ChatMessage]) {
+        self.push(format!("llm_request {} {}", agent_name, messages.len()));
+    }
+
+    async fn on_llm_response(&self, agent_name: &str, response: &ModelResponse) {
+        self.push(format!("llm_response {} {}", agent_name, response.has_tool_calls()));
+    }
+
+    async fn on_tool_start(&self, agent_name: &str, tool_call: &ToolCall) {
+        self.push(format!("tool_start {} {} {}", agent_name, tool_call.name, tool_call.arguments));
+    }
+
+    async fn on_tool_end(&self, agent_name: &str, result: &ToolResult) {
+        self.push(format!("tool_end {} {} {}", agent_name, result.name, result.output));
+    }
+
+    async fn on_handoff(&self, from_agent: &str, to_agent: &str) {
+        self.push(format!("handoff {} {}", from_agent, to_agent));
+    }
+
+    async fn on_guardrail_tripped(&self, agent_name: &str, result: &GuardrailResult) {
+        self.push(format!("guardrail_tripped {} {}", agent_name, result.guardrail_name));
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct StockArgs {
+    /// The stock ticker symbol, e.g. AAPL for Apple Inc.
+    ticker: String,
+}
+
+fn stock_tool() -> FunctionTool {
+    FunctionTool::new(
+        "get_stock_price",
+        "Retrieves the current stock price for a given ticker symbol.",
+        |args: StockArgs| async move {
+            match args.ticker.as_str() {
+                "TSLA" => Ok("248.50".to_string()),
+                ticker => Err(format!("Unknown ticker {}", ticker)),
+            }
+        },
+    )
+}
+
+async fn triage_agent(openai_server: &MockServer, anthropic_server: &MockServer) -> Agent {
+    let mut stock_agent = Agent::new(
+        "StockAgent".to_string(),
+        "You are a stock market assistant.".to_string(),
+    ).await;
+    stock_agent.agent_type = AgentType::Anthropic;
+    stock_agent.model = Some(ANTHROPIC_MODEL.to_string());
+    let stock_agent = stock_agent
+        .with_base_url(&format!("{}{}", anthropic_server.url(), MESSAGES_PATH))
+        .with_tool(stock_tool());
+
+    let mut triage_agent = Agent::new(
+        "TriageAgent".to_string(),
+        "Route the user to the right agent.".to_string(),
+    ).await;
+    triage_agent.model = Some(OPENAI_MODEL.to_string());
+    triage_agent
+        .with_base_url(&format!("{}{}", openai_server.url(), COMPLETIONS_PATH))
+        .with_handoff(stock_agent)
+}
+
+#[tokio::test]
+async fn hooks_lifecycle_events() {
+    let openai_server = MockServer::start(OPENAI_UPSTREAM).await;
+    openai_server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/agent_handoff.json"));
+
+    let anthropic_server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    anthropic_server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function.json"));
+    anthropic_server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function_answer.json"));
+
+    let hooks = Arc::new(RecordingHooks::default());
+    let agent = triage_agent(&openai_server, &anthropic_server).await
+        .with_hooks(hooks.clone());
+
+    if let Err(e) = agent.run("How much is Tesla stock trading for?").await {
+        panic!("Error: {}", e);
+    }
+
+    let events = hooks.events.lock().unwrap().clone();
+    assert_eq!(events, vec![
+        "agent_start TriageAgent",
+        "llm_request TriageAgent 2",
+        "llm_response TriageAgent true",
+        "handoff TriageAgent StockAgent",
+        "agent_start StockAgent",
+        "llm_request StockAgent 4",
+        "llm_response StockAgent true",
+        r#"tool_start StockAgent get_stock_price {"ticker":"TSLA"}"#,
+        "tool_end StockAgent get_stock_price 248.50",
+        "llm_request StockAgent 6",
+        "llm_response StockAgent false",
+        "agent_end StockAgent true",
+    ]);
+}
+
+#[tokio::test]
+async fn hooks_run_trace() {
+    let openai_server = MockServer::start(OPENAI_UPSTREAM).await;
+    openai_server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/agent_handoff.json"));
+
+    let anthropic_server = MockServer::start(ANTHROPIC_UPSTREAM).await;
+    anthropic_server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function.json"));
+    anthropic_server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/function_answer.json"));
+
+    let no_secrets = FunctionGuardrail::new("no_secrets", |text: String| async move {
+        GuardrailResult::pass(json!({"length": text.len()}))
+    });
+    let agent = triage_agent(&openai_server, &anthropic_server).await
+        .with_input_guardrail(no_secrets);
+
+    let result = match agent.run_messages(vec![
+        ChatMessage::user("How much is Tesla stock trading for?"),
+    ]).await {
+        Ok(result) => result,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    let trace = &result.trace;
+    let agents: Vec<&str> = trace.spans.iter().map(|span| span.name.as_str()).collect();
+    assert_eq!(agents, vec!["TriageAgent", "StockAgent"]);
+    assert!(trace.spans.iter().all(|span| span.kind == SpanKind::Agent));
+
+    let triage_steps: Vec<SpanKind> = trace.spans[0].children.iter().map(|span| span.kind).collect();
+    assert!(triage_steps.contains(&SpanKind::Guardrail));
+    assert!(triage_steps.ends_with(&[SpanKind::Generation, SpanKind::Handoff]));
+
+    let stock_steps: Vec<SpanKind> = trace.spans[1].children.iter().map(|span| span.kind).collect();
+    assert_eq!(stock_steps, vec![SpanKind::Generation, SpanKind::Tool, SpanKind::Generation]);
+
+    let generations = trace.find(SpanKind::Generation);
+    assert_eq!(generations.len(), 3);
+    assert_eq!(generations[0].name, OPENAI_MODEL);
+    assert_eq!(generations[1].name, ANTHROPIC_MODEL);
+    assert_eq!(trace.usage, result.usage);
+    assert_eq!(
+        trace.spans[0].usage.total_tokens + trace.spans[1].usage.total_tokens,
+        result.usage.total_tokens
+    );
+
+    assert_eq!(trace.find(SpanKind::Tool)[0].name, "get_stock_price");
+    assert!(trace.spans[1].start >= trace.spans[0].start + trace.spans[0].duration);
+    assert!(trace.duration >= trace.spans[1].start + trace.spans[1].duration);
+
+    let printed = trace.to_string();
+    assert!(printed.contains("Agent StockAgent"));
+    assert!(printed.contains("Tool get_stock_price"));
+}
+
+#[tokio::test]
+async fn hooks_guardrail_tripped() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/functions_answer.json"));
+
+    let hooks = Arc::new(RecordingHooks::default());
+    let mut agent = Agent::new(
+        "WeatherAgent".to_string(),
+        "You are a weather assistant.".to_string(),
+    ).await;
+    agent.model = Some(OPENAI_MODEL.to_string());
+    let agent = agent
+        .with_base_url(&format!("{}{}", server.url(), COMPLETIONS_PATH))
+        .with_hooks(hooks.clone())
+        .with_output_guardrail(FunctionGuardrail::new("no_weather", |_text: String| async move {
+            GuardrailResult::trip(json!({"reason": "No forecasts allowed"}))
+        }));
+
+    match agent.run("What is the weather like in Boston today?").await {
+        Err(AgentError::OutputGuardrailTripwireTriggered(_)) => (),
+        other => panic!("Expected OutputGuardrailTripwireTriggered, got {:?}", other),
+    }
+
+    let events = hooks.events.lock().unwrap().clone();
+    assert_eq!(events.last().map(String::as_str), Some("guardrail_tripped WeatherAgent no_weather"));
+    assert!(!events.iter().any(|event| event.starts_with("agent_end")));
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct DelayArgs {
+    /// Milliseconds to wait before answering
+    millis: u64,
+}
+
+#[tokio::test]
+async fn hooks_tool_spans_time_each_tool() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    let tool_call = |id: &str, millis: u64| json!({
+        "id": id,
+        "type": "function",
+        "function": {"name": "wait", "arguments": json!({"millis": millis}).to_string()}
+    });
+    server.mock("POST", COMPLETIONS_PATH, Fixture::inline(200, json!({
+        "id": "chatcmpl-tools",
+        "object": "chat.completion",
+        "created": 1743872331,
+        "model": OPENAI_MODEL,
+        "choices": [{
+            "index": 0,
+            "message": {
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [tool_call("call_slow", 300), tool_call("call_fast", 0)]
+            },
+            "finish_reason": "tool_calls"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 10, "total_tokens": 20}
+    })));
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/simple_shot.json"));
+
+    let wait = FunctionTool::new("wait", "Waits a while", |args: DelayArgs| async move {
+        tokio::time::sleep(std::time::Duration::from_millis(args.millis)).await;
+        Ok::<_, String>(args.millis.to_string())
+    });
+    let mut agent = Agent::new("WaitAgent".to_string(), "Wait when asked.".to_string()).await;
+    agent.model = Some(OPENAI_MODEL.to_string());
+    let agent = agent
+        .with_base_url(&format!("{}{}", server.url(), COMPLETIONS_PATH))
+        .with_tool(wait);
+
+    let result = agent.run_messages(vec![ChatMessage::user("Wait twice")]).await.unwrap();
+
+    // The tools run together; the fast one must not report the slow one's time
+    let tools = result.trace.find(SpanKind::Tool);
+    assert_eq!(tools.len(), 2);
+    assert!(tools[0].duration >= std::time::Duration::from_millis(300));
+    assert!(tools[1].duration < std::time::Duration::from_millis(150), "{:?}", tools[1].duration);
+}
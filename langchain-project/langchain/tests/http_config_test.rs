@@ -0,0 +1,100 @@
+mod common;
+
+use common::{Fixture, MockServer};
+use langchain::anthropic::chat::ChatAnthropic;
+use langchain::gemini::chat::ChatGemini;
+use langchain::llm::error::HttpError;
+use langchain::llm::http::{HttpConfig, HttpVersion, WithHttpClient};
+use langchain::openai::chat::ChatOpenAI;
+use std::time::Duration;
+
+static COMPLETIONS_PATH: &str = "/v1/chat/completions";
+static MESSAGES_PATH: &str = "/v1/messages";
+static GEMINI_MODEL: &str = "gemini-2.0-flash";
+
+#[tokio::test]
+async fn http_config_headers_reach_the_server() {
+    let server = MockServer::start("https://api.openai.com").await;
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/simple_shot.json"));
+
+    let config = HttpConfig::new()
+        .with_header("x-gateway-key", "gateway_secret")
+        .with_header("authorization", "Bearer gateway_token")
+        .with_user_agent("lambda-batch/1.0")
+        .with_connect_timeout(Duration::from_secs(2))
+        .with_pool_max_idle_per_host(8)
+        .with_base_url(&format!("{}{}", server.url(), COMPLETIONS_PATH));
+
+    let llm = ChatOpenAI::new("gpt-4o-mini")
+        .with_api_key("test_key")
+        .with_http_config(&config)
+        .unwrap();
+    llm.invoke("Only say Simple test").await.unwrap();
+
+    let requests = server.requests_to(COMPLETIONS_PATH);
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].header("x-gateway-key"), Some("gateway_secret"));
+    assert_eq!(requests[0].header("user-agent"), Some("lambda-batch/1.0"));
+    // The provider's own headers take precedence
+    assert_eq!(requests[0].header("authorization"), Some("Bearer test_key"));
+}
+
+#[tokio::test]
+async fn one_client_is_shared_by_providers() {
+    let server = MockServer::start("https://api.anthropic.com").await;
+    server.mock("POST", MESSAGES_PATH, Fixture::json("anthropic/simple_shot.json"));
+    let gemini_server = MockServer::start("https://generativelanguage.googleapis.com/v1beta").await;
+    let generate_path = format!("/models/{}:generateContent", GEMINI_MODEL);
+    gemini_server.mock("POST", &generate_path, Fixture::json("gemini/simple_shot.json"));
+
+    let client = HttpConfig::new()
+        .with_header("x-tenant", "acme")
+        .with_http_version(HttpVersion::Http1Only)
+        .build_client()
+        .unwrap();
+
+    ChatAnthropic::new("claude-3-5-haiku-latest")
+        .with_base_url(&format!("{}{}", server.url(), MESSAGES_PATH))
+        .with_api_key("test_key")
+        .with_http_client(client.clone())
+        .invoke("Only say Simple test")
+        .await
+        .unwrap();
+
+    // The base URL of the config replaces the Gemini API root, not the endpoint
+    let config = HttpConfig::new()
+        .with_header("x-tenant", "acme")
+        .with_base_url(&gemini_server.url());
+    ChatGemini::new(GEMINI_MODEL)
+        .with_http_config(&config)
+        .unwrap()
+        .invoke("Only say Simple test")
+        .await
+        .unwrap();
+
+    assert_eq!(server.requests_to(MESSAGES_PATH)[0].header("x-tenant"), Some("acme"));
+    assert_eq!(gemini_server.requests_to(&generate_path)[0].header("x-tenant"), Some("acme"));
+    assert!(server.requests_to(MESSAGES_PATH)[0]
+        .header("user-agent")
+        .unwrap()
+        .starts_with("langchain-rust/"));
+}
+
+#[test]
+fn invalid_http_config_is_rejected() {
+    let error = HttpConfig::new()
+        .with_header("x bad header", "value")
+        .build_client()
+        .unwrap_err();
+    assert!(matches!(error, HttpError::InvalidHeader(name) if name == "x bad header"));
+
+    let error = HttpConfig::new()
+        .with_header("x-gateway-key", "line\nbreak")
+        .build_client()
+        .unwrap_err();
+    assert!(matches!(error, HttpError::InvalidHeader(_)));
+
+    let result = ChatOpenAI::new("gpt-4o-mini")
+        .with_http_config(&HttpConfig::new().with_proxy("http://[::1"));
+    assert!(matches!(result, Err(HttpError::ClientError(_))));
+}
@@ -0,0 +1,181 @@
+mod common;
+
+use common::{Fixture, MockServer};
+use langchain::anthropic::chat::ChatAnthropic;
+use langchain::compatible::chat::ChatCompatible;
+use langchain::gemini::chat::ChatGemini;
+use langchain::gemini::error::GeminiError;
+use langchain::llm::chat::ChatModel;
+use langchain::llm::error::{ApiError, ApiErrorKind, LlmError};
+use langchain::llm::libs::{ChatMessage, ChatOptions, Provider};
+use langchain::openai::chat::ChatOpenAI;
+use serde_json::json;
+
+static COMPLETIONS_PATH: &str = "/v1/chat/completions";
+static MESSAGES_PATH: &str = "/v1/messages";
+static GEMINI_MODEL: &str = "gemini-2.0-flash";
+
+async fn generate(llm: &dyn ChatModel) -> LlmError {
+    llm.generate(&[ChatMessage::user("Hello")], &ChatOptions::new())
+        .await
+        .expect_err("Expected an error")
+}
+
+fn openai(server: &MockServer) -> ChatOpenAI {
+    ChatOpenAI::new("gpt-4o-mini")
+        .with_base_url(&format!("{}{}", server.url(), COMPLETIONS_PATH))
+        .with_api_key("test_key")
+        .with_max_retries(0)
+}
+
+fn anthropic(server: &MockServer) -> ChatAnthropic {
+    ChatAnthropic::new("claude-3-5-haiku-latest")
+        .with_base_url(&format!("{}{}", server.url(), MESSAGES_PATH))
+        .with_api_key("test_key")
+        .with_max_retries(0)
+}
+
+#[tokio::test]
+async fn openai_errors_keep_status_code_and_request_id() {
+    let server = MockServer::start("https://api.openai.com").await;
+    server.mock(
+        "POST",
+        COMPLETIONS_PATH,
+        Fixture::json("openai/error_401.json")
+            .with_status(401)
+            .with_header("x-request-id", "req_123"),
+    );
+    server.mock("POST", COMPLETIONS_PATH, Fixture::inline(400, json!({
+        "error": {
+            "message": "This model's maximum context length is 128000 tokens.",
+            "type": "invalid_request_error",
+            "param": "messages",
+            "code": "context_length_exceeded"
+        }
+    })));
+
+    let error = generate(&openai(&server)).await;
+    let api_error = match &error {
+        LlmError::Authentication(api_error) => api_error,
+        other => panic!("Expected an authentication error, got {:?}", other),
+    };
+    assert_eq!(api_error.provider, Provider::OpenAI);
+    assert_eq!(api_error.status, Some(401));
+    assert_eq!(api_error.code.as_deref(), Some("invalid_api_key"));
+    assert_eq!(api_error.request_id.as_deref(), Some("req_123"));
+    assert!(api_error.body.contains("Incorrect API key provided"));
+    assert!(error.to_string().starts_with("Authentication Error (401): Incorrect API key"));
+    assert!(!error.is_retryable());
+
+    let error = generate(&openai(&server)).await;
+    assert!(matches!(error, LlmError::ContextLengthExceeded(_)));
+    assert_eq!(error.status(), Some(400));
+}
+
+#[tokio::test]
+async fn anthropic_errors_are_typed() {
+    let server = MockServer::start("https://api.anthropic.com").await;
+    server.mock(
+        "POST",
+        MESSAGES_PATH,
+        Fixture::inline(429, json!({
+            "type": "error",
+            "error": {"type": "rate_limit_error", "message": "Number of request tokens has exceeded your rate limit."}
+        }))
+        .with_header("request-id", "req_018EeWyXxfu5pfWkrYcMdjWG"),
+    );
+    server.mock("POST", MESSAGES_PATH, Fixture::inline(529, json!({
+        "type": "error",
+        "error": {"type": "overloaded_error", "message": "Overloaded"}
+    })));
+    server.mock("POST", MESSAGES_PATH, Fixture::inline(400, json!({
+        "type": "error",
+        "error": {"type": "invalid_request_error", "message": "prompt is too long: 208310 tokens > 200000 maximum"}
+    })));
+
+    let error = generate(&anthropic(&server)).await;
+    match &error {
+        LlmError::RateLimit(api_error) => {
+            assert_eq!(api_error.code.as_deref(), Some("rate_limit_error"));
+            assert_eq!(api_error.request_id.as_deref(), Some("req_018EeWyXxfu5pfWkrYcMdjWG"));
+        }
+        other => panic!("Expected a rate limit error, got {:?}", other),
+    }
+    assert!(error.is_retryable());
+
+    assert!(matches!(generate(&anthropic(&server)).await, LlmError::ServerError(_)));
+    assert!(matches!(generate(&anthropic(&server)).await, LlmError::ContextLengthExceeded(_)));
+}
+
+#[tokio::test]
+async fn gemini_errors_are_not_collapsed() {
+    let server = MockServer::start("https://generativelanguage.googleapis.com/v1beta").await;
+    let path = format!("/models/{}:generateContent", GEMINI_MODEL);
+    server.mock("POST", &path, Fixture::json("gemini/error_400.json").with_status(400));
+    server.mock("POST", &path, Fixture::inline(200, json!({
+        "promptFeedback": {"blockReason": "PROHIBITED_CONTENT"},
+        "usageMetadata": {"promptTokenCount": 8, "totalTokenCount": 8}
+    })));
+
+    let llm = ChatGemini::new(GEMINI_MODEL)
+        .with_base_url(&server.url())
+        .with_max_retries(0);
+    match llm.clone().invoke("Hello").await {
+        Err(GeminiError::ApiError(api_error)) => {
+            assert_eq!(api_error.status, Some(400));
+            assert_eq!(api_error.code.as_deref(), Some("INVALID_ARGUMENT"));
+            assert_eq!(api_error.kind(), ApiErrorKind::Authentication);
+        }
+        other => panic!("Expected an API error, got {:?}", other),
+    }
+
+    // A blocked prompt is answered with a 200 and no candidates
+    match generate(&llm).await {
+        LlmError::ContentFiltered(api_error) => {
+            assert_eq!(api_error.code.as_deref(), Some("PROHIBITED_CONTENT"));
+        }
+        other => panic!("Expected a content filtered error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn compatible_errors_keep_detail_bodies() {
+    let server = MockServer::start("https://api.x.ai/v1").await;
+    server.mock("POST", "/chat/completions", Fixture::inline(404, json!({
+        "detail": "Model grok-9 does not exist"
+    })));
+
+    let llm = ChatCompatible::new(&server.url(), "grok-9")
+        .with_api_key("test_key")
+        .with_max_retries(0);
+    match generate(&llm).await {
+        LlmError::InvalidRequest(api_error) => {
+            assert_eq!(api_error.provider, Provider::Compatible);
+            assert_eq!(api_error.status, Some(404));
+            assert_eq!(api_error.message, "Model grok-9 does not exist");
+        }
+        other => panic!("Expected an invalid request error, got {:?}", other),
+    }
+}
+
+#[test]
+fn api_error_kinds() {
+    let kind = |status: u16, body: &str| {
+        ApiError::from_response(Provider::OpenAI, status, &Default::default(), body).kind()
+    };
+
+    assert_eq!(kind(503, "upstream connect error"), ApiErrorKind::ServerError);
+    assert_eq!(kind(504, ""), ApiErrorKind::Timeout);
+    assert_eq!(
+        kind(400, r#"{"error": {"message": "Your request was rejected by the content filter", "code": "content_filter"}}"#),
+        ApiErrorKind::ContentFiltered,
+    );
+    assert_eq!(
+        kind(429, r#"{"error": {"code": 429, "message": "Quota exceeded", "status": "RESOURCE_EXHAUSTED"}}"#),
+        ApiErrorKind::RateLimit,
+    );
+
+    let error = ApiError::from_response(Provider::Gemini, 500, &Default::default(), "");
+    assert_eq!(error.message, "HTTP status 500");
+    assert_eq!(error.to_string(), "Server Error (500): HTTP status 500");
+}
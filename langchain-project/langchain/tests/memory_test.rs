@@ -0,0 +1,172 @@
+mod common;
+
+use common::{Fixture, MockServer};
+use langchain::chains::conversation::ConversationChain;
+use langchain::llm::libs::{ChatMessage, Role};
+use langchain::memory::libs::{
+    BufferMemory, ChatMemory, SummaryMemory, TokenBufferMemory, WindowMemory, SUMMARY_MESSAGE_NAME,
+};
+use langchain::memory::stores::{ChatMessageStore, InMemoryChatStore, JsonFileChatStore};
+use langchain::openai::chat::ChatOpenAI;
+use serde_json::json;
+use std::sync::Arc;
+
+static OPENAI_MODEL: &str = "gpt-4o-mini";
+static COMPLETIONS_PATH: &str = "/v1/chat/completions";
+
+fn completion(answer: &str) -> Fixture {
+    Fixture::inline(200, json!({
+        "id": "chatcmpl-memory",
+        "object": "chat.completion",
+        "created": 1743872331,
+        "model": OPENAI_MODEL,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": answer},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 20, "completion_tokens": 5, "total_tokens": 25}
+    }))
+}
+
+fn chat(server: &MockServer) -> Arc<ChatOpenAI> {
+    Arc::new(
+        ChatOpenAI::new(OPENAI_MODEL)
+            .with_base_url(&format!("{}{}", server.url(), COMPLETIONS_PATH))
+            .with_api_key("test_key"),
+    )
+}
+
+fn texts(messages: &[ChatMessage]) -> Vec<String> {
+    messages.iter().map(|message| message.text()).collect()
+}
+
+/// Request texts, as (role, text) pairs
+fn request_texts(request: &serde_json::Value) -> Vec<(String, String)> {
+    request["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| {
+            let role = message["role"].as_str().unwrap_or_default().to_string();
+            let text = message["content"][0]["text"].as_str().unwrap_or_default().to_string();
+            (role, text)
+        })
+        .collect()
+}
+
+fn temp_file(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("{}-{}.json", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+async fn three_turns(memory: &dyn ChatMemory) {
+    for (question, answer) in [("one", "1"), ("two", "2"), ("three", "3")] {
+        memory
+            .save_messages("s1", &[ChatMessage::user(question), ChatMessage::assistant(answer)])
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn memory_strategies() {
+    let store = Arc::new(InMemoryChatStore::new());
+
+    let buffer = BufferMemory::new(store.clone());
+    three_turns(&buffer).await;
+    assert_eq!(texts(&buffer.load_messages("s1").await.unwrap()), vec!["one", "1", "two", "2", "three", "3"]);
+    assert!(buffer.load_messages("s2").await.unwrap().is_empty());
+
+    let window = WindowMemory::new(store.clone(), 2);
+    assert_eq!(texts(&window.load_messages("s1").await.unwrap()), vec!["two", "2", "three", "3"]);
+
+    // The turns are estimated at 2, 2 and 3 tokens
+    let tokens = TokenBufferMemory::new(store.clone(), 5);
+    assert_eq!(texts(&tokens.load_messages("s1").await.unwrap()), vec!["two", "2", "three", "3"]);
+    let tokens = TokenBufferMemory::new(store.clone(), 2);
+    assert!(tokens.load_messages("s1").await.unwrap().is_empty());
+
+    window.clear("s1").await.unwrap();
+    assert!(store.messages("s1").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn json_file_store_survives_restart() {
+    let path = temp_file("chat_history");
+
+    let store = JsonFileChatStore::new(&path);
+    store.add_messages("alice", &[ChatMessage::user("Hi, I'm Alice")]).await.unwrap();
+    store.add_messages("bob", &[ChatMessage::user("Hi, I'm Bob")]).await.unwrap();
+    store.add_messages("alice", &[ChatMessage::assistant("Hello Alice")]).await.unwrap();
+    drop(store);
+
+    let store = JsonFileChatStore::new(&path);
+    let messages = store.messages("alice").await.unwrap();
+    assert_eq!(texts(&messages), vec!["Hi, I'm Alice", "Hello Alice"]);
+    assert_eq!(messages[1].role, Role::Assistant);
+
+    store.clear("alice").await.unwrap();
+    assert!(store.messages("alice").await.unwrap().is_empty());
+    assert_eq!(store.messages("bob").await.unwrap().len(), 1);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn summary_memory_compacts_history() {
+    let server = MockServer::start("https://api.openai.com").await;
+    server.mock("POST", COMPLETIONS_PATH, completion("The user counted to two."));
+
+    let store = Arc::new(InMemoryChatStore::new());
+    let memory = SummaryMemory::new(store.clone(), chat(&server), 4);
+    three_turns(&memory).await;
+
+    // The two oldest turns were summarized, the last one is kept verbatim
+    let messages = memory.load_messages("s1").await.unwrap();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[0].role, Role::System);
+    assert_eq!(messages[0].name.as_deref(), Some(SUMMARY_MESSAGE_NAME));
+    assert_eq!(texts(&messages[1..]), vec!["three", "3"]);
+    assert_eq!(memory.summary("s1").await.unwrap().as_deref(), Some("The user counted to two."));
+
+    let requests = server.requests_to(COMPLETIONS_PATH);
+    assert_eq!(requests.len(), 1);
+    let prompt = &request_texts(&requests[0].json())[0].1;
+    assert!(prompt.contains("Current summary:\n(empty)"));
+    assert!(prompt.contains("User: one\nAssistant: 1\nUser: two\nAssistant: 2"));
+}
+
+#[tokio::test]
+async fn conversation_chain_replays_history() {
+    let server = MockServer::start("https://api.openai.com").await;
+    server.mock("POST", COMPLETIONS_PATH, completion("Nice to meet you, Ada."));
+    server.mock("POST", COMPLETIONS_PATH, completion("Your name is Ada."));
+
+    let path = temp_file("conversation");
+    let memory = Arc::new(BufferMemory::new(Arc::new(JsonFileChatStore::new(&path))));
+    let chain = ConversationChain::new(chat(&server), memory)
+        .with_system_prompt("You are a friendly assistant.");
+    let response = chain.invoke("chat-1", "My name is Ada").await.unwrap();
+    assert_eq!(response.text(), "Nice to meet you, Ada.");
+
+    // A new chain on the same file continues the conversation
+    let memory = Arc::new(BufferMemory::new(Arc::new(JsonFileChatStore::new(&path))));
+    let chain = ConversationChain::new(chat(&server), memory)
+        .with_system_prompt("You are a friendly assistant.");
+    chain.invoke("chat-1", "What is my name?").await.unwrap();
+
+    let requests = server.requests_to(COMPLETIONS_PATH);
+    let roles_and_texts = request_texts(&requests[1].json());
+    assert_eq!(roles_and_texts, vec![
+        ("developer".to_string(), "You are a friendly assistant.".to_string()),
+        ("user".to_string(), "My name is Ada".to_string()),
+        ("assistant".to_string(), "Nice to meet you, Ada.".to_string()),
+        ("user".to_string(), "What is my name?".to_string()),
+    ]);
+    assert_eq!(chain.history("chat-1").await.unwrap().len(), 4);
+    assert!(chain.history("chat-2").await.unwrap().is_empty());
+
+    std::fs::remove_file(&path).unwrap();
+}
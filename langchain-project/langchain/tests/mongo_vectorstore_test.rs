@@ -0,0 +1,256 @@
+#![cfg(feature = "mongodb")]
+
+mod common;
+
+use async_trait::async_trait;
+use common::WordCountEmbeddings;
+use langchain::documents::libs::Document;
+use langchain::llm::embed::cosine_similarity;
+use langchain::vectorstores::error::VectorStoreError;
+use langchain::vectorstores::libs::{Filter, SearchOptions, VectorStore};
+use langchain::vectorstores::mongo::{MongoCollection, MongoVectorStore};
+use mongodb::bson::{doc, Bson, Document as BsonDocument};
+use mongodb::SearchIndexModel;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Stands in for mongod: keeps the documents in memory and answers
+/// `$vectorSearch` with the cosine similarity, ignoring the pre-filter
+#[derive(Default)]
+struct LocalCollection {
+    documents: Mutex<Vec<BsonDocument>>,
+    indexes: Mutex<Vec<BsonDocument>>,
+    pipelines: Mutex<Vec<Vec<BsonDocument>>>,
+    upserts: Mutex<Vec<usize>>,
+    polls_until_queryable: Mutex<u32>,
+}
+
+#[async_trait]
+impl MongoCollection for LocalCollection {
+    async fn list_search_indexes(&self) -> Result<Vec<BsonDocument>, VectorStoreError> {
+        let mut polls = self.polls_until_queryable.lock().unwrap();
+        let mut indexes = self.indexes.lock().unwrap();
+        if *polls == 0 {
+            for index in indexes.iter_mut() {
+                index.insert("queryable", true);
+            }
+        } else {
+            *polls -= 1;
+        }
+        Ok(indexes.clone())
+    }
+
+    async fn create_search_index(&self, model: SearchIndexModel) -> Result<String, VectorStoreError> {
+        let name = model.name.unwrap_or_default();
+        self.indexes.lock().unwrap().push(doc! {
+            "name": &name,
+            "type": "vectorSearch",
+            "queryable": false,
+            "latestDefinition": model.definition,
+        });
+        *self.polls_until_queryable.lock().unwrap() = 2;
+        Ok(name)
+    }
+
+    async fn upsert_many(&self, upserted: Vec<BsonDocument>) -> Result<(), VectorStoreError> {
+        self.upserts.lock().unwrap().push(upserted.len());
+        let mut documents = self.documents.lock().unwrap();
+        for document in upserted {
+            let id = document.get_str("_id").unwrap().to_string();
+            documents.retain(|stored| stored.get_str("_id").ok() != Some(id.as_str()));
+            documents.push(document);
+        }
+        Ok(())
+    }
+
+    async fn delete_many(&self, ids: &[String]) -> Result<(), VectorStoreError> {
+        self.documents
+            .lock()
+            .unwrap()
+            .retain(|stored| !ids.iter().any(|id| stored.get_str("_id").ok() == Some(id.as_str())));
+        Ok(())
+    }
+
+    async fn aggregate(&self, pipeline: Vec<BsonDocument>) -> Result<Vec<BsonDocument>, VectorStoreError> {
+        self.pipelines.lock().unwrap().push(pipeline.clone());
+
+        let search = pipeline[0].get_document("$vectorSearch").unwrap();
+        let project = pipeline[1].get_document("$project").unwrap();
+        let path = search.get_str("path").unwrap();
+        let limit = search.get_i64("limit").unwrap() as usize;
+        let query = vector(search.get_array("queryVector").unwrap());
+
+        let mut results: Vec<(f32, BsonDocument)> = self
+            .documents
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|stored| {
+                let score = (1.0 + cosine_similarity(&query, &vector(stored.get_array(path).unwrap()))) / 2.0;
+                let mut result = doc! { "_id": stored.get("_id").unwrap().clone(), "score": score as f64 };
+                for (key, _) in project.iter().filter(|(key, _)| *key != "score") {
+                    if let Some(value) = stored.get(key) {
+                        result.insert(key, value.clone());
+                    }
+                }
+                (score, result)
+            })
+            .collect();
+
+        results.sort_by(|a, b| b.0.total_cmp(&a.0));
+        Ok(results.into_iter().take(limit).map(|(_, result)| result).collect())
+    }
+}
+
+fn vector(values: &[Bson]) -> Vec<f32> {
+    values.iter().filter_map(Bson::as_f64).map(|value| value as f32).collect()
+}
+
+fn pets() -> Vec<Document> {
+    vec![
+        Document::new("cat cat").with_id("1").with_metadata("kind", "pet").with_metadata("legs", 4),
+        Document::new("cat cat dog").with_id("2").with_metadata("kind", "pet").with_metadata("legs", 4),
+        Document::new("fish").with_id("3").with_metadata("kind", "pet").with_metadata("legs", 0),
+        Document::new("car car cat").with_id("4").with_metadata("kind", "vehicle"),
+    ]
+}
+
+fn local_store(collection: &Arc<LocalCollection>) -> MongoVectorStore {
+    MongoVectorStore::new(collection.clone(), Arc::new(WordCountEmbeddings))
+        .with_filter_fields(&["kind", "legs"])
+        .with_poll_interval(Duration::from_millis(1), Duration::from_secs(1))
+}
+
+#[tokio::test]
+async fn mongo_create_index() {
+    let collection = Arc::new(LocalCollection::default());
+    let store = local_store(&collection);
+
+    store.create_index(4).await.unwrap();
+    // A second call finds the index and does not create it again
+    store.create_index(4).await.unwrap();
+
+    let indexes = collection.indexes.lock().unwrap().clone();
+    assert_eq!(indexes.len(), 1);
+    assert_eq!(indexes[0].get_str("name").unwrap(), "vector_index");
+    assert!(indexes[0].get_bool("queryable").unwrap());
+
+    let fields = indexes[0].get_document("latestDefinition").unwrap().get_array("fields").unwrap();
+    let vector_field = fields[0].as_document().unwrap();
+    assert_eq!(vector_field.get_str("type").unwrap(), "vector");
+    assert_eq!(vector_field.get_str("path").unwrap(), "embedding");
+    assert_eq!(vector_field.get_i64("numDimensions").unwrap(), 4);
+    assert_eq!(vector_field.get_str("similarity").unwrap(), "cosine");
+    assert_eq!(fields[1].as_document().unwrap().get_str("path").unwrap(), "metadata.kind");
+    assert_eq!(fields[2].as_document().unwrap().get_str("type").unwrap(), "filter");
+}
+
+#[tokio::test]
+async fn mongo_create_index_timeout() {
+    let collection = Arc::new(LocalCollection::default());
+    let store = local_store(&collection).with_poll_interval(Duration::from_millis(1), Duration::ZERO);
+
+    match store.create_index(4).await {
+        Err(VectorStoreError::IndexNotReady(name)) => assert_eq!(name, "vector_index"),
+        other => panic!("Expected IndexNotReady, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn mongo_add_documents_and_search() {
+    let collection = Arc::new(LocalCollection::default());
+    let store = local_store(&collection);
+
+    let ids = store.add_documents(pets()).await.unwrap();
+    assert_eq!(ids, vec!["1", "2", "3", "4"]);
+
+    let generated = store.add_documents(vec![Document::new("dog")]).await.unwrap();
+    assert_eq!(generated[0].len(), 24);
+    // One call per `add_documents`, not one per document
+    assert_eq!(*collection.upserts.lock().unwrap(), vec![4, 1]);
+
+    let stored = collection.documents.lock().unwrap()[0].clone();
+    assert_eq!(stored.get_str("_id").unwrap(), "1");
+    assert_eq!(stored.get_str("text").unwrap(), "cat cat");
+    assert_eq!(vector(stored.get_array("embedding").unwrap()), vec![2.0, 0.0, 0.0, 0.0]);
+    assert_eq!(stored.get_document("metadata").unwrap().get_str("kind").unwrap(), "pet");
+
+    let options = SearchOptions::new(2).with_filter(Filter::and(vec![
+        Filter::eq("kind", "pet"),
+        Filter::negate(Filter::lt("legs", 1)),
+    ]));
+    let results = store.similarity_search("cat", &options).await.unwrap();
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].document.id.as_deref(), Some("1"));
+    assert_eq!(results[0].document.content, "cat cat");
+    assert_eq!(results[0].document.metadata["legs"], 4);
+    assert!((results[0].score - 1.0).abs() < 1e-6);
+    assert!(results[1].score < results[0].score);
+
+    let pipeline = collection.pipelines.lock().unwrap()[0].clone();
+    let search = pipeline[0].get_document("$vectorSearch").unwrap();
+    assert_eq!(search.get_str("index").unwrap(), "vector_index");
+    assert_eq!(search.get_i64("limit").unwrap(), 2);
+    assert_eq!(search.get_i64("numCandidates").unwrap(), 20);
+    assert_eq!(
+        search.get_document("filter").unwrap(),
+        &doc! {
+            "$and": [
+                { "metadata.kind": { "$eq": "pet" } },
+                { "$nor": [{ "metadata.legs": { "$lt": 1_i64 } }] },
+            ]
+        }
+    );
+
+    let project = pipeline[1].get_document("$project").unwrap();
+    assert!(project.get("embedding").is_none());
+    assert_eq!(project.get_document("score").unwrap(), &doc! { "$meta": "vectorSearchScore" });
+}
+
+#[tokio::test]
+async fn mongo_max_marginal_relevance_search_and_delete() {
+    let collection = Arc::new(LocalCollection::default());
+    let store = local_store(&collection).with_text_key("body").with_embedding_key("vector");
+
+    store.add_documents(pets()).await.unwrap();
+    store.add_documents(vec![Document::new("cat cat").with_id("5")]).await.unwrap();
+
+    let options = SearchOptions::new(2).with_fetch_k(4).with_lambda_mult(0.3);
+    let results = store.max_marginal_relevance_search("cat", &options).await.unwrap();
+    let ids: Vec<_> = results.iter().map(|result| result.document.id.clone().unwrap()).collect();
+    assert_eq!(ids, vec!["1", "4"]);
+    assert_eq!(results[1].document.content, "car car cat");
+
+    let pipeline = collection.pipelines.lock().unwrap()[0].clone();
+    let search = pipeline[0].get_document("$vectorSearch").unwrap();
+    assert_eq!(search.get_str("path").unwrap(), "vector");
+    assert_eq!(search.get_i64("limit").unwrap(), 4);
+    assert_eq!(pipeline[1].get_document("$project").unwrap().get_i32("vector").unwrap(), 1);
+
+    store.delete(&["1".to_string(), "5".to_string()]).await.unwrap();
+    assert_eq!(collection.documents.lock().unwrap().len(), 3);
+}
+
+/// Upserts and deletes on a real server; vector search needs Atlas
+///
+/// Run with `MONGODB_URI=mongodb://localhost:27017 cargo test --features mongodb -- --ignored`
+#[tokio::test]
+#[ignore]
+async fn mongo_local_upsert_and_delete() {
+    let uri = std::env::var("MONGODB_URI").expect("MONGODB_URI must be set");
+    let client = mongodb::Client::with_uri_str(&uri).await.unwrap();
+    let collection = client.database("langchain_test").collection::<BsonDocument>("vectorstore");
+    collection.drop().await.unwrap();
+
+    let store = MongoVectorStore::new(Arc::new(collection.clone()), Arc::new(WordCountEmbeddings));
+    store.add_documents(pets()).await.unwrap();
+    store.add_documents(vec![Document::new("dog dog").with_id("1")]).await.unwrap();
+
+    assert_eq!(collection.count_documents(doc! {}).await.unwrap(), 4);
+    let stored = collection.find_one(doc! { "_id": "1" }).await.unwrap().unwrap();
+    assert_eq!(stored.get_str("text").unwrap(), "dog dog");
+
+    store.delete(&["1".to_string(), "2".to_string()]).await.unwrap();
+    assert_eq!(collection.count_documents(doc! {}).await.unwrap(), 2);
+}
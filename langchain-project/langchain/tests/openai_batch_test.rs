@@ -0,0 +1,217 @@
+mod common;
+
+use common::{Fixture, MockServer};
+use langchain::openai::batch::{OpenAIBatch, EMBEDDINGS_ENDPOINT};
+use langchain::openai::chat::ChatOpenAI;
+use langchain::openai::embed::EmbedOpenAI;
+use langchain::openai::error::OpenAIError;
+use langchain::openai::files::OpenAIFiles;
+use serde_json::json;
+use std::time::Duration;
+
+static OPENAI_MODEL: &str = "gpt-4o-mini";
+static OPENAI_UPSTREAM: &str = "https://api.openai.com";
+static FILES_PATH: &str = "/v1/files";
+static BATCHES_PATH: &str = "/v1/batches";
+static INPUT_FILE_ID: &str = "file-6F2ksmvXxt4VdoqmHRw6kL";
+static OUTPUT_FILE_ID: &str = "file-WTiyDq8mMcKyRkDTCHwqbJ";
+static ERROR_FILE_ID: &str = "file-3vZcGpPQWk4BDxTWpRmA2n";
+static BATCH_ID: &str = "batch_67126a53c2b88190a4dbc5a1c7f8a6d4";
+
+fn files_client(server: &MockServer) -> OpenAIFiles {
+    OpenAIFiles::new()
+        .with_base_url(&format!("{}{}", server.url(), FILES_PATH))
+        .with_api_key("test_key")
+}
+
+fn batch_client(server: &MockServer) -> OpenAIBatch {
+    OpenAIBatch::new()
+        .with_base_url(&format!("{}{}", server.url(), BATCHES_PATH))
+        .with_files_url(&format!("{}{}", server.url(), FILES_PATH))
+        .with_api_key("test_key")
+        .with_poll_interval(Duration::from_millis(10), Duration::from_millis(20))
+}
+
+fn file_path(file_id: &str, suffix: &str) -> String {
+    format!("{}/{}{}", FILES_PATH, file_id, suffix)
+}
+
+#[tokio::test]
+async fn openai_files() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", FILES_PATH, Fixture::json("openai/file_uploaded.json"));
+    server.mock("GET", FILES_PATH, Fixture::json("openai/file_list.json"));
+    server.mock("GET", &file_path(INPUT_FILE_ID, ""), Fixture::json("openai/file_uploaded.json"));
+    server.mock("GET", &file_path(OUTPUT_FILE_ID, "/content"), Fixture::jsonl("openai/batch_output.jsonl"));
+    server.mock("DELETE", &file_path(INPUT_FILE_ID, ""), Fixture::json("openai/file_deleted.json"));
+
+    let files = files_client(&server);
+
+    let file = match files.upload("batch_input.jsonl", b"{\"custom_id\":\"1\"}\n".to_vec(), "batch").await {
+        Ok(file) => file,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert_eq!(file.id, INPUT_FILE_ID);
+    assert_eq!(file.purpose, "batch");
+
+    let upload = &server.requests_to(FILES_PATH)[0];
+    assert_eq!(upload.header("authorization"), Some("Bearer test_key"));
+    assert!(upload.header("content-type").unwrap_or_default().starts_with("multipart/form-data; boundary="));
+    let body = String::from_utf8_lossy(&upload.body);
+    assert!(body.contains("name=\"purpose\"\r\n\r\nbatch\r\n"));
+    assert!(body.contains("filename=\"batch_input.jsonl\""));
+    assert!(body.contains("{\"custom_id\":\"1\"}\n"));
+
+    let list = match files.list(Some("batch")).await {
+        Ok(list) => list,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[1].purpose, "batch_output");
+    assert_eq!(server.requests_to(FILES_PATH)[1].query.as_deref(), Some("purpose=batch"));
+
+    let file = match files.retrieve(INPUT_FILE_ID).await {
+        Ok(file) => file,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert_eq!(file.bytes, 1028);
+
+    let content = match files.content(OUTPUT_FILE_ID).await {
+        Ok(content) => content,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert_eq!(String::from_utf8_lossy(&content).lines().count(), 2);
+
+    let deleted = match files.delete(INPUT_FILE_ID).await {
+        Ok(deleted) => deleted,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert!(deleted.deleted);
+}
+
+#[tokio::test]
+async fn openai_batch_chat() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", FILES_PATH, Fixture::json("openai/file_uploaded.json"));
+    server.mock("POST", BATCHES_PATH, Fixture::json("openai/batch_created.json"));
+    let batch_path = format!("{}/{}", BATCHES_PATH, BATCH_ID);
+    server.mock("GET", &batch_path, Fixture::json("openai/batch_in_progress.json"));
+    server.mock("GET", &batch_path, Fixture::json("openai/batch_completed.json"));
+    server.mock("GET", &file_path(OUTPUT_FILE_ID, "/content"), Fixture::jsonl("openai/batch_output.jsonl"));
+    server.mock("GET", &file_path(ERROR_FILE_ID, "/content"), Fixture::jsonl("openai/batch_errors.jsonl"));
+
+    let llm = ChatOpenAI::new(OPENAI_MODEL)
+        .with_system_prompt("Classify the review as positive or negative.")
+        .with_max_tokens(16);
+
+    let batch = batch_client(&server)
+        .with_metadata(json!({"job": "nightly-reviews"}))
+        .with_chat_request("review-1", llm.clone().with_prompt("Great product, works as described."))
+        .with_chat_request("review-2", llm.clone().with_prompt("It broke after a day."))
+        .with_chat_request("review-3", llm.with_max_tokens(0).with_prompt("Arrived on time."));
+
+    let created = match batch.create().await {
+        Ok(created) => created,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert_eq!(created.status, "validating");
+
+    let finished = match batch.wait(&created.id).await {
+        Ok(finished) => finished,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert_eq!(finished.status, "completed");
+    assert_eq!(finished.request_counts.clone().unwrap_or_default().failed, 1);
+    assert_eq!(server.requests_to(&batch_path).len(), 2);
+
+    let mut responses = match batch.chat_responses(&finished).await {
+        Ok(responses) => responses,
+        Err(e) => panic!("Error: {}", e),
+    };
+    assert_eq!(responses.len(), 3);
+
+    let positive = responses.remove("review-1").unwrap().expect("review-1 failed");
+    let choices = positive.choices.unwrap_or_default();
+    let message = choices[0].message.clone().expect("Missing message");
+    assert_eq!(message.content.as_deref(), Some("positive"));
+
+    match responses.remove("review-3").unwrap() {
+        Err(OpenAIError::BadRequestError(message)) => assert!(message.contains("max_completion_tokens")),
+        other => panic!("Expected a bad request error, got {:?}", other),
+    }
+
+    // The input file has one request per line
+    let upload = String::from_utf8_lossy(&server.requests_to(FILES_PATH)[0].body).to_string();
+    let lines: Vec<serde_json::Value> = upload
+        .lines()
+        .filter(|line| line.starts_with("{\"custom_id\""))
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["method"], "POST");
+    assert_eq!(lines[0]["url"], "/v1/chat/completions");
+    assert_eq!(lines[0]["body"]["model"], OPENAI_MODEL);
+    assert_eq!(lines[0]["body"]["max_completion_tokens"], 16);
+    assert!(lines[0]["body"].get("stream").is_none());
+    assert_eq!(lines[1]["custom_id"], "review-2");
+
+    let body = server.requests_to(BATCHES_PATH)[0].json();
+    assert_eq!(body["input_file_id"], INPUT_FILE_ID);
+    assert_eq!(body["endpoint"], "/v1/chat/completions");
+    assert_eq!(body["completion_window"], "24h");
+    assert_eq!(body["metadata"]["job"], "nightly-reviews");
+}
+
+#[tokio::test]
+async fn openai_batch_embed_requests() {
+    let embed = EmbedOpenAI::new("text-embedding-3-small").with_dimensions(256);
+
+    let batch = OpenAIBatch::new()
+        .with_embed_request("doc-1", embed.clone().with_input("The quick brown fox"))
+        .with_embed_request("doc-2", embed.with_input("jumps over the lazy dog"));
+
+    let jsonl = batch.to_jsonl().expect("Invalid requests");
+    let lines: Vec<serde_json::Value> = jsonl
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["url"], EMBEDDINGS_ENDPOINT);
+    assert_eq!(lines[1]["body"]["input"], "jumps over the lazy dog");
+    assert_eq!(lines[1]["body"]["dimensions"], 256);
+
+    // A batch targets a single endpoint
+    let mixed = batch.with_chat_request("chat-1", ChatOpenAI::new(OPENAI_MODEL).with_prompt("Hi"));
+    match mixed.create().await {
+        Err(OpenAIError::BatchError(message)) => assert!(message.contains("same endpoint")),
+        other => panic!("Expected a batch error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn openai_batch_failures() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", FILES_PATH, Fixture::json("openai/file_uploaded.json"));
+    server.mock("POST", BATCHES_PATH, Fixture::inline(400, json!({
+        "error": {"message": "Invalid completion window", "type": "invalid_request_error", "param": null, "code": null}
+    })));
+    server.mock("DELETE", &file_path(INPUT_FILE_ID, ""), Fixture::json("openai/file_deleted.json"));
+    let batch_path = format!("{}/{}", BATCHES_PATH, BATCH_ID);
+    server.mock("GET", &batch_path, Fixture::json("openai/batch_completed.json"));
+    server.mock("GET", &file_path(OUTPUT_FILE_ID, "/content"), Fixture::jsonl("openai/batch_output.jsonl"));
+    server.mock("GET", &file_path(ERROR_FILE_ID, "/content"), Fixture::inline(200, json!({"unexpected": true})));
+
+    let batch = batch_client(&server)
+        .with_chat_request("review-1", ChatOpenAI::new(OPENAI_MODEL).with_prompt("Great product"));
+
+    // The uploaded input file is not left behind on the account
+    assert!(batch.create().await.is_err());
+    assert_eq!(server.requests_to(&file_path(INPUT_FILE_ID, "")).len(), 1);
+
+    // A line that is not a batch result is reported, not dropped
+    let finished = batch.retrieve(BATCH_ID).await.unwrap();
+    let results = batch.results(&finished).await.unwrap();
+    assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 2);
+    assert!(matches!(results.last(), Some(Err(OpenAIError::JsonError(_)))));
+    assert!(matches!(batch.chat_responses(&finished).await, Err(OpenAIError::JsonError(_))));
+}
@@ -0,0 +1,282 @@
+mod common;
+
+use common::{Fixture, MockServer};
+use futures::StreamExt;
+use langchain::openai::lib_response::{
+    FunctionTool, OutputItem, ResponseObject, ResponseStreamEvent, ToolChoice,
+};
+use langchain::llm::error::LlmError;
+use langchain::openai::response::ChatOpenAI;
+use serde_json::json;
+
+static OPENAI_MODEL: &str = "gpt-4o-mini";
+static OPENAI_MODEL_REASONING: &str = "o4-mini";
+static OPENAI_UPSTREAM: &str = "https://api.openai.com";
+static RESPONSES_PATH: &str = "/v1/responses";
+
+fn responses_url(server: &MockServer) -> String {
+    format!("{}{}", server.url(), RESPONSES_PATH)
+}
+
+fn response_text(response: &ResponseObject) -> String {
+    let mut result = String::new();
+    for output_item in &response.output {
+        if let OutputItem::Message(message) = output_item {
+            for content in &message.content {
+                result.push_str(&content.text);
+            }
+        }
+    }
+    result
+}
+
+fn weather_tool() -> serde_json::Value {
+    json!({
+        "type": "function",
+        "name": "get_weather",
+        "description": "Get current temperature for a given location.",
+        "parameters": {
+            "type": "object",
+            "properties": {
+                "location": {
+                    "type": "string",
+                    "description": "City and country e.g. Bogotá, Colombia"
+                }
+            },
+            "required": ["location"],
+            "additionalProperties": false
+        },
+        "strict": true
+    })
+}
+
+#[tokio::test]
+async fn openai_response_simple_shot() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", RESPONSES_PATH, Fixture::json("openai/responses_simple.json"));
+
+    let llm = ChatOpenAI::new(OPENAI_MODEL)
+        .with_base_url(&responses_url(&server))
+        .with_api_key("test_key")
+        .with_instructions("You are a helpful assistant.")
+        .with_temperature(0.9)
+        .with_top_p(0.4)
+        .with_max_tokens(2048)
+        .with_store(false);
+
+    let response = match llm.with_prompt("Only say Simple test").invoke().await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(response_text(&response), "Simple test");
+    assert_eq!(response.usage.and_then(|usage| usage.total_tokens), Some(15));
+
+    let request = &server.requests_to(RESPONSES_PATH)[0];
+    assert_eq!(request.header("authorization"), Some("Bearer test_key"));
+
+    let body = request.json();
+    assert_eq!(body["model"], OPENAI_MODEL);
+    assert_eq!(body["input"], "Only say Simple test");
+    assert_eq!(body["instructions"], "You are a helpful assistant.");
+    assert_eq!(body["max_output_tokens"], 2048);
+    assert_eq!(body["store"], false);
+    assert!(body.get("stream").is_none());
+}
+
+#[tokio::test]
+async fn openai_response_stream() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", RESPONSES_PATH, Fixture::sse("openai/responses_stream.sse").chunked(9));
+
+    let llm = ChatOpenAI::new(OPENAI_MODEL)
+        .with_base_url(&responses_url(&server))
+        .with_prompt("Only say Simple test");
+
+    let stream = llm.stream_response();
+    futures::pin_mut!(stream);
+
+    let mut result = String::new();
+    let mut completed = None;
+    while let Some(event) = stream.next().await {
+        match event {
+            Ok(ResponseStreamEvent::OutputTextDelta { delta, .. }) => result.push_str(&delta),
+            Ok(ResponseStreamEvent::Completed { response }) => completed = Some(response),
+            Ok(_) => (),
+            Err(e) => panic!("Error: {}", e),
+        }
+    }
+
+    assert_eq!(result, "Simple test");
+
+    let completed = completed.expect("Missing response.completed event");
+    assert_eq!(completed.status.as_deref(), Some("completed"));
+    assert_eq!(response_text(&completed), result);
+
+    let body = server.requests_to(RESPONSES_PATH)[0].json();
+    assert_eq!(body["stream"], true);
+}
+
+#[tokio::test]
+async fn openai_response_stream_function() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", RESPONSES_PATH, Fixture::sse("openai/responses_stream_function.sse"));
+    server.mock("POST", RESPONSES_PATH, Fixture::json("openai/responses_function_answer.json"));
+
+    let llm = ChatOpenAI::new(OPENAI_MODEL)
+        .with_base_url(&responses_url(&server))
+        .with_tools(vec![weather_tool()])
+        .with_tool_choice(ToolChoice::FunctionTool(FunctionTool {
+            name: "get_weather".to_string(),
+            type_: "function".to_string(),
+        }))
+        .with_parallel_tool_calls(false);
+
+    let stream = llm.clone()
+        .with_prompt("What is the weather like in Paris today?")
+        .stream_response();
+    futures::pin_mut!(stream);
+
+    let mut arguments = String::new();
+    let mut function_call = None;
+    let mut response_id = None;
+    while let Some(event) = stream.next().await {
+        match event {
+            Ok(ResponseStreamEvent::FunctionCallArgumentsDelta { delta, .. }) => {
+                arguments.push_str(&delta);
+            }
+            Ok(ResponseStreamEvent::FunctionCallArgumentsDone { arguments: done, .. }) => {
+                assert_eq!(arguments, done);
+            }
+            Ok(ResponseStreamEvent::OutputItemDone { item: OutputItem::FunctionCall(call), .. }) => {
+                function_call = Some(call);
+            }
+            Ok(ResponseStreamEvent::Completed { response }) => response_id = response.id,
+            Ok(_) => (),
+            Err(e) => panic!("Error: {}", e),
+        }
+    }
+
+    let function_call = function_call.expect("Missing function call");
+    assert_eq!(function_call.name, "get_weather");
+    assert_eq!(function_call.arguments, arguments);
+    let arguments: serde_json::Value = serde_json::from_str(&arguments).unwrap();
+    assert_eq!(arguments["location"], "Paris, France");
+
+    let response_id = response_id.expect("Missing response id");
+    let response = match llm
+        .with_previous_response_id(&response_id)
+        .with_function_output(&function_call.call_id, "14°C")
+        .invoke()
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert!(response_text(&response).contains("14°C"));
+
+    let requests = server.requests_to(RESPONSES_PATH);
+    let first = requests[0].json();
+    assert_eq!(first["tools"][0]["name"], "get_weather");
+    assert_eq!(first["tool_choice"]["name"], "get_weather");
+    assert_eq!(first["parallel_tool_calls"], false);
+
+    let second = requests[1].json();
+    assert_eq!(second["previous_response_id"], response_id);
+    assert_eq!(second["input"][0]["type"], "function_call_output");
+    assert_eq!(second["input"][0]["call_id"], function_call.call_id);
+    assert_eq!(second["input"][0]["output"], "14°C");
+}
+
+#[tokio::test]
+async fn openai_response_stream_reasoning() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", RESPONSES_PATH, Fixture::sse("openai/responses_stream_reasoning.sse"));
+
+    let llm = ChatOpenAI::new(OPENAI_MODEL_REASONING)
+        .with_base_url(&responses_url(&server))
+        .with_reasoning("low")
+        .with_reasoning_summary("auto")
+        .with_prompt("Are semicolons optional in Rust?");
+
+    let stream = llm.stream_response();
+    futures::pin_mut!(stream);
+
+    let mut summary = String::new();
+    let mut text = String::new();
+    let mut usage = None;
+    while let Some(event) = stream.next().await {
+        match event {
+            Ok(ResponseStreamEvent::ReasoningSummaryTextDelta { delta, .. }) => summary.push_str(&delta),
+            Ok(ResponseStreamEvent::ReasoningSummaryTextDone { text: done, .. }) => {
+                assert_eq!(summary, done);
+            }
+            Ok(ResponseStreamEvent::OutputTextDelta { delta, .. }) => text.push_str(&delta),
+            Ok(ResponseStreamEvent::Completed { response }) => usage = response.usage,
+            Ok(_) => (),
+            Err(e) => panic!("Error: {}", e),
+        }
+    }
+
+    assert!(summary.starts_with("**Answering a simple question**"));
+    assert!(text.starts_with("Not entirely."));
+
+    let reasoning_tokens = usage
+        .and_then(|usage| usage.output_tokens_details)
+        .map(|details| details.reasoning_tokens);
+    assert_eq!(reasoning_tokens, Some(192));
+
+    let body = server.requests_to(RESPONSES_PATH)[0].json();
+    assert_eq!(body["reasoning"]["effort"], "low");
+    assert_eq!(body["reasoning"]["summary"], "auto");
+}
+
+#[tokio::test]
+async fn openai_response_stream_error() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", RESPONSES_PATH, Fixture::sse("openai/responses_stream_error.sse"));
+
+    let llm = ChatOpenAI::new(OPENAI_MODEL)
+        .with_base_url(&responses_url(&server))
+        .with_prompt("Hello");
+
+    let stream = llm.stream_response();
+    futures::pin_mut!(stream);
+
+    let mut items: Vec<_> = stream.collect().await;
+    assert_eq!(items.len(), 3);
+    assert!(items[0].is_ok());
+    match LlmError::from(items.pop().unwrap().unwrap_err()) {
+        LlmError::ServerError(error) => {
+            assert_eq!(error.status, None);
+            assert_eq!(error.code.as_deref(), Some("server_error"));
+            assert!(error.message.starts_with("The server had an error"));
+        }
+        other => panic!("Expected a server error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn openai_response_stream_error_status() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock(
+        "POST",
+        RESPONSES_PATH,
+        Fixture::json("openai/error_401.json").with_status(401),
+    );
+
+    let llm = ChatOpenAI::new(OPENAI_MODEL)
+        .with_base_url(&responses_url(&server))
+        .with_prompt("Hello");
+
+    let stream = llm.stream_response();
+    futures::pin_mut!(stream);
+
+    let items: Vec<_> = stream.collect().await;
+    assert_eq!(items.len(), 1);
+    match &items[0] {
+        Err(e) => assert!(e.to_string().starts_with("Authentication Error")),
+        Ok(_) => panic!("Expected an error item"),
+    }
+}
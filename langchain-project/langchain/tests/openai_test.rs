@@ -1,75 +1,24 @@
-use langchain::openai::ChatOpenAI;
+mod common;
+
+use common::{Fixture, MockServer};
+use futures::StreamExt;
+use langchain::llm::chat::ChatModel;
+use langchain::llm::libs::{ChatMessage, ChatOptions, StopReason, ToolChoice, ToolDefinition};
+use langchain::openai::chat::ChatOpenAI;
+use langchain::openai::embed::EmbedOpenAI;
+use langchain::openai::error::OpenAIError;
 use serde_json::json;
 
 static OPENAI_MODEL: &str = "gpt-4o-mini";
+static OPENAI_UPSTREAM: &str = "https://api.openai.com";
+static COMPLETIONS_PATH: &str = "/v1/chat/completions";
 
-#[tokio::test]
-async fn openai_simple_shot() {
-    let llm = match ChatOpenAI::new(OPENAI_MODEL) {
-        Ok(llm) => llm,
-        Err(e) => panic!("Error: {}", e),
-    };
-    let llm = llm.with_temperature(0.9);
-    let llm = llm.with_max_completion_tokens(2048);
-    let llm = llm.with_timeout_sec(30);
-    let llm = llm.with_presence_penalty(1.5);
-    let llm = llm.with_frequency_penalty(1.5);
-    let llm = llm.with_n_completion(1);
-
-    let llm = llm.with_top_p(0.4); // Recommend altering top_p with temperature but not both.
-
-    let llm = llm.with_system_prompt("You are a helpful assistant.");
-    let prompt = "Only say Simple test";
-
-    let response = match llm.invoke(prompt).await {
-        Ok(response) => response,
-        Err(e) => panic!("Error: {}", e),
-    };
-
-    match response.choices {
-        Some(candidates) => {
-            for candidate in candidates {
-                #[allow(irrefutable_let_patterns)]
-                if let message = candidate.message {
-                    let text_l = message
-                        .content
-                        .expect("Cannot read message")
-                        .to_lowercase();
-
-                    let possible_values = vec![
-                        "simple test",
-                        "simple test\n",
-                        "simple test.\n",
-                        "simple test."
-                    ];
-
-                    // Count how many matches we have
-                    let match_count = possible_values.iter()
-                        .filter(|&&val| val == text_l)
-                        .count();
-                    assert_eq!(
-                        match_count, 
-                        1, 
-                        "Text '{}' did not match any of the expected values", 
-                        text_l
-                    );
-                }
-            }
-        }
-        None => panic!("No response choices available"),
-    };
+fn completions_url(server: &MockServer) -> String {
+    format!("{}{}", server.url(), COMPLETIONS_PATH)
 }
 
-#[tokio::test]
-async fn openai_functions() {
-    let llm = match ChatOpenAI::new(OPENAI_MODEL) {
-        Ok(llm) => llm,
-        Err(e) => panic!("Error: {}", e),
-    };
-
-    let llm = llm.with_system_prompt("Don't make assumptions about what values to plug into functions. Ask for clarification if a user request is ambiguous.");
-
-    let weather_function = json!( {
+fn weather_function() -> serde_json::Value {
+    json!({
         "type": "function",
         "function": {
             "name": "get_current_weather",
@@ -90,13 +39,74 @@ async fn openai_functions() {
                 "required": ["location"]
             }
         }
-    });
+    })
+}
+
+#[tokio::test]
+async fn openai_simple_shot() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/simple_shot.json"));
 
-    let llm = llm.with_tools(vec![weather_function]);
+    let llm = ChatOpenAI::new(OPENAI_MODEL)
+        .with_base_url(&completions_url(&server))
+        .with_api_key("test_key")
+        .with_temperature(0.9)
+        .with_max_tokens(2048)
+        .with_timeout_sec(30)
+        .with_presence_penalty(1.5)
+        .with_frequency_penalty(1.5)
+        .with_n_completion(1)
+        .with_top_p(0.4) // Recommend altering top_p with temperature but not both.
+        .with_system_prompt("You are a helpful assistant.");
+    let prompt = "Only say Simple test";
+
+    let response = match llm.invoke(prompt).await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    match response.choices {
+        Some(candidates) => {
+            for candidate in candidates {
+                let text_l = candidate.message
+                    .and_then(|message| message.content)
+                    .expect("Cannot read message")
+                    .to_lowercase();
+
+                let possible_values = ["simple test", "simple test\n", "simple test.\n", "simple test."];
+                assert!(
+                    possible_values.contains(&text_l.as_str()),
+                    "Text '{}' did not match any of the expected values",
+                    text_l
+                );
+            }
+        }
+        None => panic!("No response choices available"),
+    };
+
+    let request = &server.requests_to(COMPLETIONS_PATH)[0];
+    assert_eq!(request.header("authorization"), Some("Bearer test_key"));
+
+    let body = request.json();
+    assert_eq!(body["model"], OPENAI_MODEL);
+    assert_eq!(body["max_completion_tokens"], 2048);
+    assert_eq!(body["messages"][0]["role"], "developer");
+    assert_eq!(body["messages"][1]["content"][0]["text"], prompt);
+}
+
+#[tokio::test]
+async fn openai_functions() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/functions.json"));
 
     let tool_choice = json!({"type": "function", "function": {"name": "get_current_weather"}});
-    let llm = llm.with_tool_choice(tool_choice);
-    
+
+    let llm = ChatOpenAI::new(OPENAI_MODEL)
+        .with_base_url(&completions_url(&server))
+        .with_system_prompt("Don't make assumptions about what values to plug into functions. Ask for clarification if a user request is ambiguous.")
+        .with_tools(vec![weather_function()])
+        .with_tool_choice(tool_choice.clone());
+
     let prompt = "What is the weather like in Boston today?";
     let response = match llm.invoke(prompt).await {
         Ok(response) => response,
@@ -106,19 +116,257 @@ async fn openai_functions() {
     match response.choices {
         Some(candidates) => {
             for candidate in candidates {
-                #[allow(irrefutable_let_patterns)]
-                if let message = candidate.message {
-                    match message.tool_calls {
-                        Some(tool_calls) => {
-                            let value = tool_calls[0].clone();
-                            assert_eq!(value["function"]["name"], "get_current_weather");
-                            assert_eq!(value["function"]["arguments"], "{\"location\":\"Boston, MA\"}");
-                        },
-                        None => panic!("No tool_calls in message"),
-                    };                 
-                }
+                assert_eq!(candidate.finish_reason, Some("tool_calls".to_string()));
+                match candidate.message.and_then(|message| message.tool_calls) {
+                    Some(tool_calls) => {
+                        let value = tool_calls[0].clone();
+                        assert_eq!(value["function"]["name"], "get_current_weather");
+                        assert_eq!(value["function"]["arguments"], "{\"location\":\"Boston, MA\"}");
+                    },
+                    None => panic!("No tool_calls in message"),
+                };
             }
         }
         None => panic!("No response choices available"),
     };
+
+    let body = server.requests_to(COMPLETIONS_PATH)[0].json();
+    assert_eq!(body["tools"][0], weather_function());
+    assert_eq!(body["tool_choice"], tool_choice);
+}
+
+#[tokio::test]
+async fn openai_stream() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, Fixture::sse("openai/stream.sse").chunked(5));
+
+    let llm = ChatOpenAI::new(OPENAI_MODEL).with_base_url(&completions_url(&server));
+
+    let stream = llm.stream_response("Only say Simple test".to_string());
+    futures::pin_mut!(stream);
+
+    let mut result = String::new();
+    let mut finish_reason = None;
+    while let Some(response) = stream.next().await {
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => panic!("Error: {}", e),
+        };
+        for choice in response.choices.unwrap_or_default() {
+            if let Some(content) = choice.delta.and_then(|delta| delta.content) {
+                result.push_str(&content);
+            }
+            if choice.finish_reason.is_some() {
+                finish_reason = choice.finish_reason;
+            }
+        }
+    }
+
+    assert_eq!(result, "Simple test");
+    assert_eq!(finish_reason, Some("stop".to_string()));
+
+    let body = server.requests_to(COMPLETIONS_PATH)[0].json();
+    assert_eq!(body["stream"], true);
+}
+
+#[tokio::test]
+async fn openai_stream_malformed_chunk() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, Fixture::sse("openai/stream_malformed.sse"));
+
+    let llm = ChatOpenAI::new(OPENAI_MODEL).with_base_url(&completions_url(&server));
+
+    let stream = llm.stream_response("Only say Simple test".to_string());
+    futures::pin_mut!(stream);
+
+    let items: Vec<_> = stream.collect().await;
+    assert_eq!(items.len(), 4);
+    assert!(items[0].is_ok());
+    assert!(items[1].is_err(), "Malformed chunk should be an error item");
+    assert!(items[2].is_ok());
+    assert!(items[3].is_ok());
+}
+
+#[tokio::test]
+async fn openai_stream_error_status() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock(
+        "POST",
+        COMPLETIONS_PATH,
+        Fixture::json("openai/error_401.json").with_status(401),
+    );
+
+    let llm = ChatOpenAI::new(OPENAI_MODEL).with_base_url(&completions_url(&server));
+
+    let stream = llm.stream_response("Only say Simple test".to_string());
+    futures::pin_mut!(stream);
+
+    let items: Vec<_> = stream.collect().await;
+    assert_eq!(items.len(), 1);
+    match &items[0] {
+        Err(e) => assert!(e.to_string().starts_with("Authentication Error")),
+        Ok(_) => panic!("Expected an error item"),
+    }
+}
+
+#[tokio::test]
+async fn openai_request_error() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock(
+        "POST",
+        COMPLETIONS_PATH,
+        Fixture::json("openai/error_401.json").with_status(401),
+    );
+
+    let llm = ChatOpenAI::new(OPENAI_MODEL)
+        .with_base_url(&completions_url(&server))
+        .with_max_retries(0);
+
+    assert!(llm.invoke("Hello").await.is_err());
+}
+
+#[tokio::test]
+async fn openai_chat_model_tools() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/functions.json"));
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/functions_answer.json"));
+
+    let llm: Box<dyn ChatModel> = Box::new(
+        ChatOpenAI::new(OPENAI_MODEL).with_base_url(&completions_url(&server))
+    );
+
+    let function = weather_function();
+    let tools = vec![ToolDefinition {
+        name: "get_current_weather".to_string(),
+        description: "Get the current weather in a given location".to_string(),
+        parameters: function["function"]["parameters"].clone(),
+    }];
+    let options = ChatOptions::new()
+        .with_tools(tools)
+        .with_tool_choice(ToolChoice::Tool("get_current_weather".to_string()));
+
+    let mut messages = vec![
+        ChatMessage::system("You are a weather assistant."),
+        ChatMessage::user("What is the weather like in Boston today?"),
+    ];
+
+    let response = match llm.generate(&messages, &options).await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(response.stop_reason, Some(StopReason::ToolUse));
+    let tool_call = response.tool_calls()[0].clone();
+    assert_eq!(tool_call.id, "call_62136354");
+    assert_eq!(tool_call.arguments["location"], "Boston, MA");
+
+    messages.push(response.message.clone());
+    messages.push(ChatMessage::tool_result(
+        &tool_call.id,
+        &tool_call.name,
+        r#"{"temperature": 22, "unit": "celsius", "description": "Sunny"}"#,
+    ));
+
+    let response = match llm.generate(&messages, &options).await {
+        Ok(response) => response,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert!(response.text().contains("22 degrees"));
+    assert_eq!(response.usage.map(|usage| usage.total_tokens), Some(152));
+
+    let requests = server.requests_to(COMPLETIONS_PATH);
+    let first = requests[0].json();
+    assert_eq!(first["tool_choice"]["function"]["name"], "get_current_weather");
+
+    let second = requests[1].json();
+    assert_eq!(second["messages"][2]["tool_calls"][0]["id"], tool_call.id);
+    assert_eq!(second["messages"][3]["role"], "tool");
+    assert_eq!(second["messages"][3]["tool_call_id"], tool_call.id);
+}
+
+fn embeddings_body(vectors: &[[f32; 2]]) -> serde_json::Value {
+    // Returned out of order on purpose, the index gives the position
+    let data: Vec<serde_json::Value> = vectors
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(index, vector)| json!({"object": "embedding", "index": index, "embedding": vector}))
+        .collect();
+
+    json!({
+        "object": "list",
+        "data": data,
+        "model": "text-embedding-3-small",
+        "usage": {"prompt_tokens": 8, "total_tokens": 8}
+    })
+}
+
+#[tokio::test]
+async fn openai_embed_documents() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    let embeddings_path = "/v1/embeddings";
+    server.mock("POST", embeddings_path, Fixture::inline(200, embeddings_body(&[[0.1, 0.2], [0.3, 0.4]])));
+    server.mock("POST", embeddings_path, Fixture::inline(200, embeddings_body(&[[0.5, 0.6]])));
+
+    // "dog" and "cat" fit in one request of 3 tokens, the long text does not
+    let documents = vec!["dog".to_string(), "cat".to_string(), "a much longer text".to_string()];
+
+    let embed = EmbedOpenAI::new("text-embedding-3-small")
+        .with_base_url(&format!("{}{}", server.url(), embeddings_path))
+        .with_api_key("test_key")
+        .with_dimensions(2)
+        .with_batch_size(10, 3)
+        .with_max_concurrency(1);
+
+    let vectors = match embed.embed_documents(documents).await {
+        Ok(vectors) => vectors,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(vectors, vec![vec![0.1, 0.2], vec![0.3, 0.4], vec![0.5, 0.6]]);
+
+    let requests = server.requests_to(embeddings_path);
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].header("authorization"), Some("Bearer test_key"));
+    assert_eq!(requests[0].json()["input"], json!(["dog", "cat"]));
+    assert_eq!(requests[0].json()["dimensions"], 2);
+    assert_eq!(requests[1].json()["input"], json!(["a much longer text"]));
+}
+
+#[tokio::test]
+async fn openai_embed_documents_concurrent() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    let embeddings_path = "/v1/embeddings";
+    server.mock("POST", embeddings_path, Fixture::inline(200, embeddings_body(&[[1.0, 0.0]])));
+
+    let documents: Vec<String> = (0..5).map(|index| format!("document {}", index)).collect();
+
+    let embed = EmbedOpenAI::new("text-embedding-3-small")
+        .with_base_url(&format!("{}{}", server.url(), embeddings_path))
+        .with_batch_size(1, 1000)
+        .with_max_concurrency(3);
+
+    let vectors = match embed.embed_documents(documents).await {
+        Ok(vectors) => vectors,
+        Err(e) => panic!("Error: {}", e),
+    };
+
+    assert_eq!(vectors.len(), 5);
+    assert_eq!(server.requests_to(embeddings_path).len(), 5);
+}
+
+#[tokio::test]
+async fn openai_embed_documents_short_response() {
+    let server = MockServer::start(OPENAI_UPSTREAM).await;
+    let embeddings_path = "/v1/embeddings";
+    server.mock("POST", embeddings_path, Fixture::inline(200, embeddings_body(&[[0.1, 0.2]])));
+
+    let documents = vec!["dog".to_string(), "cat".to_string()];
+    let embed = EmbedOpenAI::new("text-embedding-3-small")
+        .with_base_url(&format!("{}{}", server.url(), embeddings_path))
+        .with_api_key("test_key");
+
+    let result = embed.embed_documents(documents).await;
+    assert!(matches!(result, Err(OpenAIError::ResponseContentError)));
 }
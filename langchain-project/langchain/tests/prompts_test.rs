@@ -0,0 +1,133 @@
+use langchain::langsmith::libs::CommitResponse;
+use langchain::llm::libs::{ChatMessage, Role};
+use langchain::prompts::chat::ChatPromptTemplate;
+use langchain::prompts::error::PromptError;
+use langchain::prompts::template::{FewShotPromptTemplate, PromptTemplate};
+use serde_json::json;
+
+fn texts(messages: &[ChatMessage]) -> Vec<(Role, String)> {
+    messages.iter().map(|message| (message.role, message.text())).collect()
+}
+
+#[test]
+fn fstring_template() {
+    let prompt = PromptTemplate::new("Translate {text} to {language}. Answer as {{\"text\": ...}}")
+        .unwrap()
+        .with_partial("language", "French");
+    assert_eq!(prompt.input_variables, vec!["text"]);
+
+    let text = prompt.format(&json!({"text": "good morning"})).unwrap();
+    assert_eq!(text, "Translate good morning to French. Answer as {\"text\": ...}");
+
+    // Values given to format replace the partial ones
+    let text = prompt.format(&json!({"text": 42, "language": "German"})).unwrap();
+    assert_eq!(text, "Translate 42 to German. Answer as {\"text\": ...}");
+
+    assert!(matches!(prompt.format(&json!({})), Err(PromptError::MissingVariable(name)) if name == "text"));
+    assert!(matches!(prompt.format(&json!(["text"])), Err(PromptError::InvalidVariables(_))));
+    assert!(matches!(PromptTemplate::new("Hello {name"), Err(PromptError::InvalidTemplate(_))));
+    assert!(matches!(PromptTemplate::new("Hello {user.name}"), Err(PromptError::InvalidTemplate(_))));
+    assert!(matches!(PromptTemplate::new("Hello }"), Err(PromptError::InvalidTemplate(_))));
+}
+
+#[test]
+fn mustache_template() {
+    let template = "Hello {{user.name}}!\n{{! not rendered }}\n{{#orders}}\n- {{id}}: {{{item}}} for {{user.name}}\n{{/orders}}\n{{^orders}}\nNo orders.\n{{/orders}}\nBye";
+    let prompt = PromptTemplate::mustache(template).unwrap();
+    assert_eq!(prompt.input_variables, vec!["user", "orders"]);
+
+    let variables = json!({
+        "user": {"name": "Ada"},
+        "orders": [{"id": 1, "item": "<book>"}, {"id": 2, "item": "pen"}]
+    });
+    assert_eq!(
+        prompt.format(&variables).unwrap(),
+        "Hello Ada!\n- 1: <book> for Ada\n- 2: pen for Ada\nBye"
+    );
+
+    let variables = json!({"user": {"name": "Bob"}, "orders": []});
+    assert_eq!(prompt.format(&variables).unwrap(), "Hello Bob!\nNo orders.\nBye");
+
+    let list = PromptTemplate::mustache("{{#tags}}[{{.}}]{{/tags}}").unwrap();
+    assert_eq!(list.format(&json!({"tags": ["a", "b"]})).unwrap(), "[a][b]");
+
+    assert!(matches!(PromptTemplate::mustache("{{#a}}x"), Err(PromptError::InvalidTemplate(_))));
+    assert!(matches!(PromptTemplate::mustache("{{#a}}x{{/b}}"), Err(PromptError::InvalidTemplate(_))));
+    assert!(matches!(PromptTemplate::mustache("{{> partial}}"), Err(PromptError::InvalidTemplate(_))));
+}
+
+#[test]
+fn few_shot_template() {
+    let prompt = FewShotPromptTemplate::new(
+        vec![
+            json!({"word": "happy", "antonym": "sad"}),
+            json!({"word": "tall", "antonym": "short"}),
+        ],
+        PromptTemplate::new("Word: {word}\nAntonym: {antonym}").unwrap(),
+        PromptTemplate::new("Word: {input}\nAntonym:").unwrap(),
+    )
+    .with_prefix(PromptTemplate::new("Give the antonym of every word.").unwrap());
+    assert_eq!(prompt.input_variables(), vec!["input"]);
+
+    let text = prompt.format(&json!({"input": "big"})).unwrap();
+    assert_eq!(
+        text,
+        "Give the antonym of every word.\n\nWord: happy\nAntonym: sad\n\nWord: tall\nAntonym: short\n\nWord: big\nAntonym:"
+    );
+}
+
+#[test]
+fn chat_template_messages() {
+    let example_prompt = ChatPromptTemplate::from_messages(&[
+        (Role::User, "{input}"),
+        (Role::Assistant, "{output}"),
+    ])
+    .unwrap();
+
+    let prompt = ChatPromptTemplate::from_messages(&[(Role::System, "You answer in {language}.")])
+        .unwrap()
+        .with_few_shot(vec![json!({"input": "2+2", "output": "4"})], example_prompt)
+        .with_placeholder("history", false)
+        .with_message(Role::User, PromptTemplate::new("{question}").unwrap())
+        .with_partial("language", "English");
+    assert_eq!(prompt.input_variables(), vec!["history", "question"]);
+
+    let history = serde_json::to_value(vec![
+        ChatMessage::user("Hi"),
+        ChatMessage::assistant("Hello!"),
+    ])
+    .unwrap();
+    let messages = prompt
+        .format_messages(&json!({"history": history, "question": "3+3"}))
+        .unwrap();
+
+    assert_eq!(texts(&messages), vec![
+        (Role::System, "You answer in English.".to_string()),
+        (Role::User, "2+2".to_string()),
+        (Role::Assistant, "4".to_string()),
+        (Role::User, "Hi".to_string()),
+        (Role::Assistant, "Hello!".to_string()),
+        (Role::User, "3+3".to_string()),
+    ]);
+
+    let result = prompt.format_messages(&json!({"question": "3+3"}));
+    assert!(matches!(result, Err(PromptError::MissingVariable(name)) if name == "history"));
+}
+
+#[test]
+fn chat_template_from_langsmith_commit() {
+    let data = std::fs::read_to_string("tests/files/fixtures/langsmith/commit_chat_prompt.json").unwrap();
+    let commit: CommitResponse = serde_json::from_str(&data).unwrap();
+
+    let prompt = ChatPromptTemplate::from_manifest(&commit.manifest).unwrap();
+    assert_eq!(prompt.input_variables(), vec!["question", "context"]);
+
+    // The optional history placeholder is left out
+    let messages = prompt
+        .format_messages(&json!({"question": "What is RAG?", "context": "RAG adds retrieved text."}))
+        .unwrap();
+    assert_eq!(texts(&messages), vec![
+        (Role::System, "You are a friendly assistant for question-answering tasks.".to_string()),
+        (Role::User, "Question: What is RAG?\nContext: RAG adds retrieved text.\nAnswer:".to_string()),
+    ]);
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use common::{Fixture, MockServer, WordCountEmbeddings};
+use langchain::anthropic::embed::EmbedRankVoyage;
+use langchain::chains::retrieval_qa::{citations, RetrievalQA};
+use langchain::documents::libs::Document;
+use langchain::openai::chat::ChatOpenAI;
+use langchain::vectorstores::libs::{ScoredDocument, SearchOptions, VectorStore};
+use langchain::vectorstores::memory::InMemoryVectorStore;
+use serde_json::json;
+use std::sync::Arc;
+
+static OPENAI_MODEL: &str = "gpt-4o-mini";
+static COMPLETIONS_PATH: &str = "/v1/chat/completions";
+static RERANK_PATH: &str = "/v1/rerank";
+
+async fn pets_store() -> Arc<InMemoryVectorStore> {
+    let store = InMemoryVectorStore::new(Arc::new(WordCountEmbeddings));
+    store
+        .add_documents(vec![
+            Document::new("cat cat").with_id("1").with_metadata("source", "cats.md"),
+            Document::new("cat cat dog").with_id("2").with_metadata("source", "pets.md").with_metadata("page", 2),
+            Document::new("fish").with_id("3"),
+            Document::new("car car cat").with_id("4"),
+        ])
+        .await
+        .unwrap();
+    Arc::new(store)
+}
+
+fn chat(server: &MockServer, answer: &str) -> Arc<ChatOpenAI> {
+    server.mock("POST", COMPLETIONS_PATH, Fixture::inline(200, json!({
+        "id": "chatcmpl-qa",
+        "object": "chat.completion",
+        "created": 1743872331,
+        "model": OPENAI_MODEL,
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": answer},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 120, "completion_tokens": 12, "total_tokens": 132}
+    })));
+
+    Arc::new(
+        ChatOpenAI::new(OPENAI_MODEL)
+            .with_base_url(&format!("{}{}", server.url(), COMPLETIONS_PATH))
+            .with_api_key("test_key"),
+    )
+}
+
+fn ids(results: &[ScoredDocument]) -> Vec<&str> {
+    results
+        .iter()
+        .map(|result| result.document.id.as_deref().unwrap_or_default())
+        .collect()
+}
+
+#[tokio::test]
+async fn retrieval_qa_cites_sources() {
+    let server = MockServer::start("https://api.openai.com").await;
+    let chat = chat(&server, "Cats sleep a lot [2]. They live with dogs [1, 2] [7].");
+
+    let qa = RetrievalQA::new(pets_store().await, chat)
+        .with_search_options(SearchOptions::new(2));
+    let response = qa.invoke("cat").await.unwrap();
+
+    assert_eq!(ids(&response.context), vec!["1", "2"]);
+    assert_eq!(ids(&response.sources), vec!["2", "1"]);
+    assert_eq!(response.usage.unwrap().total_tokens, 132);
+
+    let request = server.requests_to(COMPLETIONS_PATH)[0].json();
+    let messages = request["messages"].as_array().unwrap();
+    assert!(messages[0]["content"][0]["text"].as_str().unwrap().contains("square brackets"));
+    assert_eq!(
+        messages[1]["content"][0]["text"],
+        "Sources:\n\n[1] (source: cats.md)\ncat cat\n\n[2] (source: pets.md, page: 2)\ncat cat dog\n\nQuestion: cat"
+    );
+}
+
+#[tokio::test]
+async fn retrieval_qa_reranks_candidates() {
+    let server = MockServer::start("https://api.voyageai.com").await;
+    let chat = chat(&server, "I don't know.");
+    server.mock("POST", RERANK_PATH, Fixture::inline(200, json!({
+        "object": "list",
+        "data": [
+            {"relevance_score": 0.91, "index": 2},
+            {"relevance_score": 0.35, "index": 0}
+        ],
+        "model": "rerank-2-lite",
+        "usage": {"total_tokens": 40}
+    })));
+
+    let reranker = EmbedRankVoyage::new("rerank-2-lite")
+        .with_base_url(&format!("{}{}", server.url(), RERANK_PATH))
+        .with_api_key("test_key");
+    let qa = RetrievalQA::new(pets_store().await, chat)
+        .with_reranker(Arc::new(reranker))
+        .with_search_options(SearchOptions::new(2).with_fetch_k(3));
+    let response = qa.invoke("cat").await.unwrap();
+
+    // The reranker picks among the 3 closest documents: 1, 2 and 4
+    assert_eq!(ids(&response.context), vec!["4", "1"]);
+    assert!((response.context[0].score - 0.91).abs() < 1e-6);
+    assert!(response.sources.is_empty());
+
+    let request = server.requests_to(RERANK_PATH)[0].json();
+    assert_eq!(request["query"], "cat");
+    assert_eq!(request["documents"], json!(["cat cat", "cat cat dog", "car car cat"]));
+    assert_eq!(request["top_k"], 2);
+}
+
+#[tokio::test]
+async fn retrieval_qa_context_budget() {
+    let server = MockServer::start("https://api.openai.com").await;
+    let chat = chat(&server, "Cats [1].");
+
+    let store = pets_store().await;
+    store
+        .add_documents(vec![Document::new(&"cat ".repeat(50)).with_id("5")])
+        .await
+        .unwrap();
+
+    // The long document is the closest, but does not fit in 5 tokens
+    let qa = RetrievalQA::new(store, chat)
+        .with_search_options(SearchOptions::new(3))
+        .with_max_context_tokens(5);
+    let response = qa.invoke("cat").await.unwrap();
+
+    assert_eq!(ids(&response.context), vec!["1", "2"]);
+    assert_eq!(ids(&response.sources), vec!["1"]);
+}
+
+#[test]
+fn retrieval_qa_citations() {
+    assert_eq!(citations("A [3]. B [1][3]. C [2, 4].", 3), vec![3, 1, 2]);
+    assert_eq!(citations("See [note] and [0] or [1 ,2", 2), Vec::<usize>::new());
+}
@@ -0,0 +1,200 @@
+mod common;
+
+use common::{Fixture, MockServer};
+use langchain::anthropic::chat::ChatAnthropic;
+use langchain::llm::retry::{parse_http_date, parse_reset, retry_after, RateLimiter, RetryPolicy};
+use futures::StreamExt;
+use langchain::openai::chat::ChatOpenAI;
+use langchain::openai::embed::EmbedOpenAI;
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde_json::json;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+static OPENAI_MODEL: &str = "gpt-4o-mini";
+static COMPLETIONS_PATH: &str = "/v1/chat/completions";
+static EMBEDDINGS_PATH: &str = "/v1/embeddings";
+
+fn rate_limited() -> Fixture {
+    Fixture::inline(429, json!({
+        "error": {
+            "message": "Rate limit reached for requests",
+            "type": "requests",
+            "code": "rate_limit_exceeded"
+        }
+    }))
+}
+
+fn server_error() -> Fixture {
+    Fixture::inline(500, json!({
+        "error": {"message": "The server had an error", "type": "server_error", "code": "server_error"}
+    }))
+}
+
+fn openai(server: &MockServer) -> ChatOpenAI {
+    ChatOpenAI::new(OPENAI_MODEL)
+        .with_base_url(&format!("{}{}", server.url(), COMPLETIONS_PATH))
+        .with_api_key("test_key")
+        .with_retry_policy(RetryPolicy::new(2).with_base_delay(Duration::from_millis(10)))
+}
+
+fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in pairs {
+        headers.insert(*name, HeaderValue::from_static(value));
+    }
+    headers
+}
+
+#[tokio::test]
+async fn retries_rate_limits_after_retry_after() {
+    let server = MockServer::start("https://api.openai.com").await;
+    server.mock("POST", COMPLETIONS_PATH, rate_limited().with_header("retry-after-ms", "50"));
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/simple_shot.json"));
+
+    let started = Instant::now();
+    let response = openai(&server).invoke("Only say Simple test").await.unwrap();
+
+    assert!(!response.choices.unwrap().is_empty());
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    assert_eq!(server.requests_to(COMPLETIONS_PATH).len(), 2);
+}
+
+#[tokio::test]
+async fn server_errors_stop_after_max_retries() {
+    let server = MockServer::start("https://api.openai.com").await;
+    server.mock("POST", COMPLETIONS_PATH, server_error());
+
+    let error = openai(&server).invoke("Hello").await.unwrap_err();
+
+    assert!(error.to_string().contains("The server had an error"));
+    assert_eq!(server.requests_to(COMPLETIONS_PATH).len(), 3);
+}
+
+#[tokio::test]
+async fn streams_and_embeddings_are_retried() {
+    let server = MockServer::start("https://api.openai.com").await;
+    server.mock("POST", COMPLETIONS_PATH, rate_limited().with_header("retry-after-ms", "10"));
+    server.mock("POST", COMPLETIONS_PATH, Fixture::sse("openai/stream.sse"));
+
+    let stream = openai(&server).stream_response("Only say Simple test".to_string());
+    futures::pin_mut!(stream);
+    while let Some(chunk) = stream.next().await {
+        chunk.unwrap();
+    }
+    assert_eq!(server.requests_to(COMPLETIONS_PATH).len(), 2);
+
+    server.mock("POST", EMBEDDINGS_PATH, rate_limited().with_header("retry-after-ms", "10"));
+    server.mock("POST", EMBEDDINGS_PATH, Fixture::inline(200, json!({
+        "object": "list",
+        "data": [{"object": "embedding", "index": 0, "embedding": [0.1, 0.2]}],
+        "model": "text-embedding-3-small",
+        "usage": {"prompt_tokens": 1, "total_tokens": 1}
+    })));
+
+    let limiter = RateLimiter::new().with_requests_per_minute(600);
+    let embed = EmbedOpenAI::new("text-embedding-3-small")
+        .with_base_url(&format!("{}{}", server.url(), EMBEDDINGS_PATH))
+        .with_api_key("test_key")
+        .with_retry_policy(RetryPolicy::new(2).with_base_delay(Duration::from_millis(10)))
+        .with_rate_limiter(limiter);
+
+    let vectors = embed.embed_documents(vec!["dog".to_string()]).await.unwrap();
+    assert_eq!(vectors, vec![vec![0.1, 0.2]]);
+    assert_eq!(server.requests_to(EMBEDDINGS_PATH).len(), 2);
+}
+
+#[tokio::test]
+async fn fatal_errors_are_not_retried() {
+    let server = MockServer::start("https://api.openai.com").await;
+    server.mock("POST", COMPLETIONS_PATH, Fixture::json("openai/error_401.json").with_status(401));
+
+    assert!(openai(&server).invoke("Hello").await.is_err());
+    assert_eq!(server.requests_to(COMPLETIONS_PATH).len(), 1);
+
+    // A Retry-After longer than the policy allows ends the retries
+    let server = MockServer::start("https://api.anthropic.com").await;
+    server.mock("POST", "/v1/messages", rate_limited().with_header("retry-after", "120"));
+
+    let llm = ChatAnthropic::new("claude-3-5-haiku-latest")
+        .with_base_url(&format!("{}/v1/messages", server.url()))
+        .with_api_key("test_key")
+        .with_retry_policy(RetryPolicy::new(3).with_max_delay(Duration::from_secs(10)));
+    assert!(llm.invoke("Hello").await.is_err());
+    assert_eq!(server.requests_to("/v1/messages").len(), 1);
+}
+
+#[test]
+fn backoff_and_retry_headers() {
+    let policy = RetryPolicy::new(5)
+        .with_jitter(false)
+        .with_max_delay(Duration::from_secs(5));
+    let delays: Vec<u64> = (1..=5).map(|attempt| policy.backoff(attempt).as_secs()).collect();
+    assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+
+    let jittered = RetryPolicy::new(5).backoff(3);
+    assert!(jittered >= Duration::from_secs(2) && jittered <= Duration::from_secs(4));
+
+    assert_eq!(parse_reset("6m0s"), Some(Duration::from_secs(360)));
+    assert_eq!(parse_reset("1h2m3.5s"), Some(Duration::from_secs_f64(3723.5)));
+    assert_eq!(parse_reset("20ms"), Some(Duration::from_millis(20)));
+    assert_eq!(parse_reset("soon"), None);
+
+    assert_eq!(retry_after(&headers(&[("retry-after", "7")])), Some(Duration::from_secs(7)));
+    assert_eq!(retry_after(&headers(&[("retry-after-ms", "250"), ("retry-after", "1")])), Some(Duration::from_millis(250)));
+    assert_eq!(
+        retry_after(&headers(&[
+            ("x-ratelimit-remaining-requests", "0"),
+            ("x-ratelimit-reset-requests", "2s"),
+            ("x-ratelimit-remaining-tokens", "1500"),
+            ("x-ratelimit-reset-tokens", "30s"),
+        ])),
+        Some(Duration::from_secs(2)),
+    );
+    assert_eq!(retry_after(&headers(&[("x-ratelimit-reset-requests", "2s")])), None);
+
+    // Values a Duration cannot hold are ignored instead of panicking
+    assert_eq!(retry_after(&headers(&[("retry-after", "inf")])), None);
+    assert_eq!(retry_after(&headers(&[("retry-after-ms", "1e30")])), None);
+    assert_eq!(retry_after(&headers(&[("retry-after", "NaN")])), None);
+    assert_eq!(parse_reset("1e300s"), None);
+    assert_eq!(
+        retry_after(&headers(&[("x-ratelimit-remaining-tokens", "0"), ("x-ratelimit-reset-tokens", "1e30")])),
+        None,
+    );
+
+    // Retry-After may be an HTTP date; a past one means now
+    assert_eq!(
+        parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT"),
+        Some(UNIX_EPOCH + Duration::from_secs(1_445_412_480)),
+    );
+    assert_eq!(parse_http_date("Thu, 29 Feb 2024 23:59:59 GMT"), Some(UNIX_EPOCH + Duration::from_secs(1_709_251_199)));
+    assert_eq!(parse_http_date("Wed, 21 Oct 2015 07:28:00 CET"), None);
+    assert_eq!(retry_after(&headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")])), Some(Duration::ZERO));
+    let delay = retry_after(&headers(&[("retry-after", "Fri, 31 Dec 9999 23:59:59 GMT")])).unwrap();
+    assert!(RetryPolicy::new(3).retry_delay(1, &headers(&[("retry-after", "Fri, 31 Dec 9999 23:59:59 GMT")])).is_none());
+    assert!(delay > Duration::from_secs(60));
+}
+
+#[tokio::test]
+async fn rate_limiter_is_shared_by_clones() {
+    // 100 tokens per second
+    let limiter = RateLimiter::new().with_tokens_per_minute(6000);
+    let clone = limiter.clone();
+
+    let started = Instant::now();
+    limiter.acquire(6000).await;
+    assert!(started.elapsed() < Duration::from_millis(100));
+
+    clone.acquire(50).await;
+    assert!(started.elapsed() >= Duration::from_millis(400));
+
+    // An exhausted limit reported by the server pauses every request
+    let started = Instant::now();
+    let limiter = RateLimiter::new().with_requests_per_minute(1000);
+    limiter.update_from_headers(&headers(&[
+        ("x-ratelimit-remaining-requests", "0"),
+        ("x-ratelimit-reset-requests", "200ms"),
+    ]));
+    limiter.clone().acquire(1).await;
+    assert!(started.elapsed() >= Duration::from_millis(200));
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "sqlite")]
+
+use langchain::llm::libs::{ChatMessage, Role};
+use langchain::memory::libs::{ChatMemory, WindowMemory};
+use langchain::memory::sqlite::SqliteChatStore;
+use langchain::memory::stores::ChatMessageStore;
+use std::sync::Arc;
+
+fn texts(messages: &[ChatMessage]) -> Vec<String> {
+    messages.iter().map(|message| message.text()).collect()
+}
+
+#[tokio::test]
+async fn sqlite_store_keeps_sessions_apart() {
+    let store = SqliteChatStore::open_in_memory().unwrap();
+    store
+        .add_messages("alice", &[ChatMessage::user("Hi, I'm Alice"), ChatMessage::assistant("Hello Alice")])
+        .await
+        .unwrap();
+    store.add_messages("bob", &[ChatMessage::user("Hi, I'm Bob")]).await.unwrap();
+
+    let messages = store.messages("alice").await.unwrap();
+    assert_eq!(texts(&messages), vec!["Hi, I'm Alice", "Hello Alice"]);
+    assert_eq!(messages[1].role, Role::Assistant);
+
+    store.replace_messages("alice", &[ChatMessage::user("Start over")]).await.unwrap();
+    assert_eq!(texts(&store.messages("alice").await.unwrap()), vec!["Start over"]);
+
+    store.clear("bob").await.unwrap();
+    assert!(store.messages("bob").await.unwrap().is_empty());
+    assert!(store.messages("carol").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn sqlite_store_survives_restart() {
+    let path = std::env::temp_dir().join(format!("chat_history-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let memory = WindowMemory::new(Arc::new(SqliteChatStore::open(&path).unwrap()), 1);
+    for (question, answer) in [("one", "1"), ("two", "2")] {
+        memory
+            .save_messages("s1", &[ChatMessage::user(question), ChatMessage::assistant(answer)])
+            .await
+            .unwrap();
+    }
+    drop(memory);
+
+    let store = Arc::new(SqliteChatStore::open(&path).unwrap());
+    assert_eq!(store.messages("s1").await.unwrap().len(), 4);
+    let memory = WindowMemory::new(store, 1);
+    assert_eq!(texts(&memory.load_messages("s1").await.unwrap()), vec!["two", "2"]);
+
+    std::fs::remove_file(&path).unwrap();
+}
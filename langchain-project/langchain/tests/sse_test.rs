@@ -0,0 +1,110 @@
+use futures::StreamExt;
+use langchain::llm::sse::{sse_events, SseDecoder, SseEvent};
+
+fn decode_all(chunks: &[&[u8]]) -> Vec<SseEvent> {
+    let mut decoder = SseDecoder::new();
+    let mut events = Vec::new();
+    for chunk in chunks {
+        events.extend(decoder.push(chunk));
+    }
+    events.extend(decoder.finish());
+    events
+}
+
+#[test]
+fn sse_event_split_across_chunks() {
+    let payload = b"data: {\"text\": \"hello\"}\n\ndata: {\"text\": \"world\"}\n\n";
+
+    // Every possible split point must give the same two events
+    for split in 0..payload.len() {
+        let events = decode_all(&[&payload[..split], &payload[split..]]);
+        assert_eq!(events.len(), 2, "split at {}", split);
+        assert_eq!(events[0].data, "{\"text\": \"hello\"}");
+        assert_eq!(events[1].data, "{\"text\": \"world\"}");
+    }
+}
+
+#[test]
+fn sse_one_byte_chunks() {
+    let payload = "event: ping\r\ndata: caf\u{e9} \u{1F600}\r\n\r\n".as_bytes();
+    let chunks: Vec<&[u8]> = payload.chunks(1).collect();
+
+    let events = decode_all(&chunks);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event.as_deref(), Some("ping"));
+    assert_eq!(events[0].data, "caf\u{e9} \u{1F600}");
+}
+
+#[test]
+fn sse_line_endings() {
+    let events = decode_all(&[b"data: a\r\n\r\ndata: b\r\rdata: c\n\n"]);
+    let data: Vec<&str> = events.iter().map(|event| event.data.as_str()).collect();
+    assert_eq!(data, vec!["a", "b", "c"]);
+}
+
+#[test]
+fn sse_multi_line_data_and_fields() {
+    let events = decode_all(&[
+        b": comment\n",
+        b"event: content_block_delta\n",
+        b"id: 42\n",
+        b"retry: 3000\n",
+        b"data: first\n",
+        b"data:second\n",
+        b"data\n",
+        b"unknown: ignored\n\n",
+    ]);
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event.as_deref(), Some("content_block_delta"));
+    assert_eq!(events[0].id.as_deref(), Some("42"));
+    assert_eq!(events[0].retry, Some(3000));
+    assert_eq!(events[0].data, "first\nsecond\n");
+}
+
+#[test]
+fn sse_event_type_resets_between_events() {
+    let events = decode_all(&[b"event: message_start\ndata: 1\n\ndata: 2\n\n"]);
+    assert_eq!(events[0].event.as_deref(), Some("message_start"));
+    assert_eq!(events[1].event, None);
+}
+
+#[test]
+fn sse_blocks_without_data_are_skipped() {
+    let events = decode_all(&[b": keep-alive\n\nevent: ping\n\nid: 7\n\ndata: x\n\n"]);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].data, "x");
+    assert_eq!(events[0].id.as_deref(), Some("7"));
+}
+
+#[test]
+fn sse_bom_and_missing_final_blank_line() {
+    let events = decode_all(&[b"\xEF\xBB", b"\xBFdata: first\n\ndata: last"]);
+    let data: Vec<&str> = events.iter().map(|event| event.data.as_str()).collect();
+    assert_eq!(data, vec!["first", "last"]);
+}
+
+#[test]
+fn sse_done_marker() {
+    let events = decode_all(&[b"data: [DONE]\n\n"]);
+    assert!(events[0].is_done());
+}
+
+#[tokio::test]
+async fn sse_stream_passes_transport_errors() {
+    let chunks: Vec<Result<&[u8], String>> = vec![
+        Ok(b"data: one\n\nda"),
+        Ok(b"ta: two\n\n"),
+        Err("connection reset".to_string()),
+        Ok(b"data: three\n\n"),
+    ];
+
+    let events: Vec<Result<SseEvent, String>> = sse_events(futures::stream::iter(chunks))
+        .collect()
+        .await;
+
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].as_ref().unwrap().data, "one");
+    assert_eq!(events[1].as_ref().unwrap().data, "two");
+    assert_eq!(events[2].as_ref().unwrap_err(), "connection reset");
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use common::WordCountEmbeddings;
+use langchain::documents::libs::Document;
+use langchain::vectorstores::error::VectorStoreError;
+use langchain::vectorstores::libs::{Filter, SearchOptions, VectorStore};
+use langchain::vectorstores::memory::InMemoryVectorStore;
+use std::sync::Arc;
+
+fn pets_store() -> InMemoryVectorStore {
+    InMemoryVectorStore::new(Arc::new(WordCountEmbeddings))
+}
+
+fn pets() -> Vec<Document> {
+    vec![
+        Document::new("cat cat").with_id("1").with_metadata("kind", "pet").with_metadata("legs", 4),
+        Document::new("cat cat dog").with_id("2").with_metadata("kind", "pet").with_metadata("legs", 4),
+        Document::new("fish").with_id("3").with_metadata("kind", "pet").with_metadata("legs", 0),
+        Document::new("car car cat").with_id("4").with_metadata("kind", "vehicle"),
+    ]
+}
+
+fn ids(results: &[langchain::vectorstores::libs::ScoredDocument]) -> Vec<&str> {
+    results
+        .iter()
+        .map(|result| result.document.id.as_deref().unwrap_or_default())
+        .collect()
+}
+
+#[tokio::test]
+async fn memory_similarity_search() {
+    let store = pets_store();
+    let added = store.add_documents(pets()).await.unwrap();
+    assert_eq!(added, vec!["1", "2", "3", "4"]);
+    assert_eq!(store.len(), 4);
+
+    let results = store.similarity_search("cat", &SearchOptions::new(3)).await.unwrap();
+    assert_eq!(ids(&results), vec!["1", "2", "4"]);
+    assert!((results[0].score - 1.0).abs() < 1e-6);
+    assert!(results[1].score > results[2].score);
+
+    let options = SearchOptions::new(3).with_filter(Filter::and(vec![
+        Filter::eq("kind", "pet"),
+        Filter::gt("legs", 0),
+    ]));
+    let results = store.similarity_search("car", &options).await.unwrap();
+    assert_eq!(ids(&results), vec!["1", "2"]);
+
+    let options = SearchOptions::new(4).with_filter(Filter::negate(Filter::exists("legs")));
+    let results = store.similarity_search("cat", &options).await.unwrap();
+    assert_eq!(ids(&results), vec!["4"]);
+}
+
+#[tokio::test]
+async fn memory_max_marginal_relevance_search() {
+    let store = pets_store();
+    store.add_documents(pets()).await.unwrap();
+    store.add_documents(vec![Document::new("cat cat").with_id("5")]).await.unwrap();
+
+    // Plain similarity returns the two identical documents
+    let results = store.similarity_search("cat", &SearchOptions::new(2)).await.unwrap();
+    assert_eq!(ids(&results), vec!["1", "5"]);
+
+    // Favoring diversity, MMR skips the duplicate and the closest variant
+    let options = SearchOptions::new(2).with_fetch_k(4).with_lambda_mult(0.3);
+    let results = store.max_marginal_relevance_search("cat", &options).await.unwrap();
+    assert_eq!(ids(&results), vec!["1", "4"]);
+    assert!(results[1].score < 1.0);
+
+}
+
+#[tokio::test]
+async fn memory_upsert_and_delete() {
+    let store = pets_store();
+    store.add_documents(pets()).await.unwrap();
+
+    let ids = store
+        .add_documents(vec![Document::new("dog dog").with_id("1"), Document::new("fish fish")])
+        .await
+        .unwrap();
+    assert_eq!(ids[0], "1");
+    assert_eq!(store.len(), 5);
+    assert_eq!(store.get("1").map(|document| document.content), Some("dog dog".to_string()));
+    assert_eq!(store.get(&ids[1]).map(|document| document.content), Some("fish fish".to_string()));
+
+    store.delete(&["1".to_string(), "unknown".to_string()]).await.unwrap();
+    assert_eq!(store.len(), 4);
+    assert!(store.get("1").is_none());
+}
+
+#[tokio::test]
+async fn memory_save_and_load() {
+    let path = std::env::temp_dir().join(format!("vectorstore_test_{}.json", std::process::id()));
+
+    let store = pets_store();
+    store.add_documents(pets()).await.unwrap();
+    store.save(&path).await.unwrap();
+
+    let loaded = InMemoryVectorStore::load(&path, Arc::new(WordCountEmbeddings)).await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.len(), 4);
+    assert_eq!(loaded.get("3"), store.get("3"));
+
+    let results = loaded.similarity_search("fish", &SearchOptions::new(1)).await.unwrap();
+    assert_eq!(ids(&results), vec!["3"]);
+    assert_eq!(results[0].document.metadata["legs"], 0);
+}
+
+#[tokio::test]
+async fn memory_dimension_mismatch() {
+    let store = pets_store();
+    store.add_documents(pets()).await.unwrap();
+
+    let error = store
+        .add_embedded_documents(vec![Document::new("bird")], vec![vec![1.0, 0.0]])
+        .unwrap_err();
+    assert!(matches!(error, VectorStoreError::DimensionMismatch { expected: 4, found: 2 }));
+
+    let error = store.similarity_search_by_vector(&[1.0], &SearchOptions::new(1)).unwrap_err();
+    assert!(matches!(error, VectorStoreError::DimensionMismatch { expected: 4, found: 1 }));
+}